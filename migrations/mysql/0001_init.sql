@@ -0,0 +1,92 @@
+-- session
+create table if not exists `session` (
+    `id` bigint not null primary key auto_increment,
+    `token` char(32) not null,
+    `product_id` bigint not null,
+    `user_id` bigint not null,
+    `expire` bigint not null,
+    index idx_session_token(`token`)
+);
+
+-- 公用user和product
+create table if not exists `user` (
+    `id` bigint not null primary key auto_increment,
+    `user_name` varchar(64) null,
+    `email` varchar(64) not null,
+    `passwd` char(64) not null,
+    -- 00 正常 -- 99 已注销
+    `status` char(2) not null,
+    `update_time` bigint null,
+    index idx_user_name(`user_name`),
+    index idx_user_email(`email`)
+);
+
+create table if not exists `product` (
+    `id` bigint not null primary key auto_increment,
+    `product` varchar(64) not null,
+    `desc` varchar(256) null,
+    -- 00 正常 -- 99 已下架
+    `status` char(2) not null,
+    `update_time` bigint null,
+    index idx_product(`product`)
+);
+
+create table if not exists `user_product` (
+    `id` bigint not null primary key auto_increment,
+    `product_id` bigint not null,
+    `user_id` bigint not null,
+    `avatar` varchar(256) null,
+    -- 00 正常 -- 99 已注销
+    `status` char(2) not null,
+    `update_time` bigint not null,
+    index idx_user_product(`product_id`, `user_id`)
+);
+
+-- hiqradio
+create table if not exists `hiqradio_recently` (
+    `id` bigint not null primary key auto_increment,
+    `user_id` bigint not null,
+    `stationuuid` varchar(40) not null,
+    `start_time` bigint not null,
+    `end_time` bigint null,
+    index idx_hiqradio_recently_user(`user_id`, `start_time`)
+);
+
+create table if not exists `hiqradio_fav_group` (
+    `id` bigint not null primary key auto_increment,
+    `user_id` bigint not null,
+    `create_time` bigint not null,
+    `name` varchar(255) not null,
+    `desc` varchar(1024) null,
+    `is_def` bigint not null,
+    index idx_hiqradio_fav_group_user(`user_id`, `name`)
+);
+
+create table if not exists `hiqradio_favorite` (
+    `id` bigint not null primary key auto_increment,
+    `user_id` bigint not null,
+    `stationuuid` varchar(40) not null,
+    `group_id` bigint not null,
+    `create_time` bigint not null,
+    index idx_hiqradio_favorite_user(`user_id`, `stationuuid`),
+    index idx_hiqradio_favorite_group(`group_id`)
+);
+
+insert into
+    `product`(`product`, `desc`, `status`, `update_time`)
+select
+    'hiqradio',
+    'hiqradio listen the whole world',
+    '00',
+    unix_timestamp()
+from
+    dual
+where
+    not exists (
+        select
+            1
+        from
+            `product`
+        where
+            `product` = 'hiqradio'
+    );
@@ -0,0 +1,12 @@
+-- hiqradio 删除/修改记录，同步时下发
+create table if not exists `hiqradio_tombstone` (
+    `id` bigint not null primary key auto_increment,
+    `user_id` bigint not null,
+    `kind` varchar(32) not null,
+    `group_name` varchar(255) not null,
+    `stationuuid` varchar(40) not null,
+    `new_name` varchar(255) not null,
+    `new_desc` varchar(1024) not null,
+    `create_time` bigint not null,
+    index idx_hiqradio_tombstone_user(`user_id`, `create_time`)
+);
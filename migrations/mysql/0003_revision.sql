@@ -0,0 +1,101 @@
+-- hiqradio 按用户递增的版本号，增量同步使用
+create table if not exists `hiqradio_revision` (
+    `user_id` bigint not null primary key,
+    `revision` bigint not null
+);
+
+-- ddl会隐式提交，每条ddl先检查是否已执行，中途失败后可以重新执行整个迁移
+set @ddl = if(
+    exists(select 1 from information_schema.columns
+        where table_schema = database() and table_name = 'hiqradio_recently' and column_name = 'revision'),
+    'select 1',
+    'alter table `hiqradio_recently` add column `revision` bigint not null default 0'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_recently' and index_name = 'idx_hiqradio_recently_revision'),
+    'select 1',
+    'alter table `hiqradio_recently` add index idx_hiqradio_recently_revision(`user_id`, `revision`)'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.columns
+        where table_schema = database() and table_name = 'hiqradio_fav_group' and column_name = 'revision'),
+    'select 1',
+    'alter table `hiqradio_fav_group` add column `revision` bigint not null default 0'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_fav_group' and index_name = 'idx_hiqradio_fav_group_revision'),
+    'select 1',
+    'alter table `hiqradio_fav_group` add index idx_hiqradio_fav_group_revision(`user_id`, `revision`)'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.columns
+        where table_schema = database() and table_name = 'hiqradio_favorite' and column_name = 'revision'),
+    'select 1',
+    'alter table `hiqradio_favorite` add column `revision` bigint not null default 0'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_favorite' and index_name = 'idx_hiqradio_favorite_revision'),
+    'select 1',
+    'alter table `hiqradio_favorite` add index idx_hiqradio_favorite_revision(`user_id`, `revision`)'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.columns
+        where table_schema = database() and table_name = 'hiqradio_tombstone' and column_name = 'revision'),
+    'select 1',
+    'alter table `hiqradio_tombstone` add column `revision` bigint not null default 0'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_tombstone' and index_name = 'idx_hiqradio_tombstone_revision'),
+    'select 1',
+    'alter table `hiqradio_tombstone` add index idx_hiqradio_tombstone_revision(`user_id`, `revision`)'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+-- 已有数据记为版本1
+update `hiqradio_recently` set `revision` = 1;
+update `hiqradio_fav_group` set `revision` = 1;
+update `hiqradio_favorite` set `revision` = 1;
+update `hiqradio_tombstone` set `revision` = 1;
+
+insert ignore into `hiqradio_revision`(`user_id`, `revision`)
+select `user_id`, 1 from `hiqradio_recently`
+union
+select `user_id`, 1 from `hiqradio_fav_group`
+union
+select `user_id`, 1 from `hiqradio_favorite`
+union
+select `user_id`, 1 from `hiqradio_tombstone`;
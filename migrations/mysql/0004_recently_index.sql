@@ -0,0 +1,11 @@
+-- 最近播放按电台过滤，按时间分页使用idx_hiqradio_recently_user
+-- ddl会隐式提交，每条ddl先检查是否已执行，中途失败后可以重新执行整个迁移
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_recently' and index_name = 'idx_hiqradio_recently_station'),
+    'select 1',
+    'alter table `hiqradio_recently` add index idx_hiqradio_recently_station(`user_id`, `stationuuid`, `start_time`)'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
@@ -0,0 +1,22 @@
+-- 电台目录，从radio-browser导出的数据导入
+create table if not exists `hiqradio_station` (
+    `stationuuid` varchar(40) not null primary key,
+    `name` varchar(512) not null,
+    `url` varchar(2048) not null,
+    `url_resolved` varchar(2048) not null,
+    `homepage` varchar(2048) not null,
+    `favicon` varchar(2048) not null,
+    `tags` varchar(2048) not null,
+    `country` varchar(128) not null,
+    `countrycode` varchar(8) not null,
+    `state` varchar(128) not null,
+    `language` varchar(256) not null,
+    `codec` varchar(32) not null,
+    `bitrate` bigint not null,
+    `hls` bigint not null,
+    `lastcheckok` bigint not null,
+    `votes` bigint not null,
+    `clickcount` bigint not null,
+    `deleted` bigint not null default 0,
+    `update_time` bigint not null
+);
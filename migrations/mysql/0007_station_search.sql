@@ -0,0 +1,11 @@
+-- 电台全文搜索
+-- ddl会隐式提交，每条ddl先检查是否已执行，中途失败后可以重新执行整个迁移
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_station' and index_name = 'hiqradio_station_search'),
+    'select 1',
+    'alter table `hiqradio_station` add fulltext index `hiqradio_station_search` (`name`, `tags`, `country`, `language`, `codec`)'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
@@ -0,0 +1,34 @@
+-- 分组和分组中收藏的顺序，已有数据按创建顺序
+-- ddl会隐式提交，每条ddl先检查是否已执行，中途失败后可以重新执行整个迁移
+set @ddl = if(
+    exists(select 1 from information_schema.columns
+        where table_schema = database() and table_name = 'hiqradio_fav_group' and column_name = 'position'),
+    'select 1',
+    'alter table `hiqradio_fav_group` add column `position` bigint not null default 0'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.columns
+        where table_schema = database() and table_name = 'hiqradio_favorite' and column_name = 'position'),
+    'select 1',
+    'alter table `hiqradio_favorite` add column `position` bigint not null default 0'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+update `hiqradio_fav_group` g join (
+    select a.`id`, count(b.`id`) as `pos` from `hiqradio_fav_group` a
+    left join `hiqradio_fav_group` b on b.`user_id` = a.`user_id` and b.`id` < a.`id`
+    group by a.`id`
+) t on t.`id` = g.`id`
+set g.`position` = t.`pos`;
+update `hiqradio_favorite` f join (
+    select a.`id`, count(b.`id`) as `pos` from `hiqradio_favorite` a
+    left join `hiqradio_favorite` b on b.`group_id` = a.`group_id` and b.`id` < a.`id`
+    group by a.`id`
+) t on t.`id` = f.`id`
+set f.`position` = t.`pos`;
@@ -0,0 +1,9 @@
+-- hiqradio 公开分享的收藏分组，分组删除后分享失效
+create table if not exists `hiqradio_share` (
+    `id` bigint not null primary key auto_increment,
+    `token` varchar(64) not null unique,
+    `user_id` bigint not null,
+    `group_id` bigint not null unique,
+    `create_time` bigint not null,
+    index idx_hiqradio_share_user(`user_id`)
+);
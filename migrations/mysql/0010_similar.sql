@@ -0,0 +1,8 @@
+-- hiqradio 相似电台，后台任务根据全部用户的收藏和最近播放定期重建
+create table if not exists `hiqradio_similar` (
+    `stationuuid` varchar(40) not null,
+    `similaruuid` varchar(40) not null,
+    `score` double not null,
+    `update_time` bigint not null,
+    primary key (`stationuuid`, `similaruuid`)
+);
@@ -0,0 +1,25 @@
+-- hiqradio 电台评分和评论，每个用户对每个电台一条，评论需审核后公开
+create table if not exists `hiqradio_review` (
+    `id` bigint not null primary key auto_increment,
+    `user_id` bigint not null,
+    `stationuuid` varchar(40) not null,
+    `rating` bigint not null,
+    `content` varchar(2048) not null,
+    -- pending 待审核 approved 已通过 rejected 已拒绝
+    `status` varchar(16) not null,
+    `reports` bigint not null default 0,
+    `create_time` bigint not null,
+    `update_time` bigint not null,
+    unique index idx_hiqradio_review_user(`user_id`, `stationuuid`),
+    index idx_hiqradio_review_station(`stationuuid`, `status`),
+    index idx_hiqradio_review_status(`status`)
+);
+
+-- 评论的举报，每个用户对每条评论只计一次
+create table if not exists `hiqradio_review_report` (
+    `review_id` bigint not null,
+    `user_id` bigint not null,
+    `reason` varchar(1024) not null,
+    `create_time` bigint not null,
+    primary key (`review_id`, `user_id`)
+);
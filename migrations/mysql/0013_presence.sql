@@ -0,0 +1,13 @@
+-- hiqradio 设备正在播放的电台，客户端定期心跳，过期后视为不在线
+create table if not exists `hiqradio_presence` (
+    `user_id` bigint not null,
+    `device_id` varchar(64) not null,
+    `device_name` varchar(64) not null,
+    `token` varchar(64) not null,
+    `stationuuid` varchar(40) not null,
+    `start_time` bigint not null,
+    `update_time` bigint not null,
+    `expire` bigint not null,
+    primary key (`user_id`, `device_id`),
+    index idx_hiqradio_presence_expire(`expire`)
+);
@@ -0,0 +1,31 @@
+-- 导入播放列表时按播放地址和名称查找电台，地址超过索引长度限制，只索引前缀
+-- ddl会隐式提交，每条ddl先检查是否已执行，中途失败后可以重新执行整个迁移
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_station' and index_name = 'idx_hiqradio_station_url'),
+    'select 1',
+    'alter table `hiqradio_station` add index idx_hiqradio_station_url(`url`(255))'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_station' and index_name = 'idx_hiqradio_station_url_resolved'),
+    'select 1',
+    'alter table `hiqradio_station` add index idx_hiqradio_station_url_resolved(`url_resolved`(255))'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
+
+set @ddl = if(
+    exists(select 1 from information_schema.statistics
+        where table_schema = database() and table_name = 'hiqradio_station' and index_name = 'idx_hiqradio_station_name'),
+    'select 1',
+    'alter table `hiqradio_station` add index idx_hiqradio_station_name(`name`(255))'
+);
+prepare stmt from @ddl;
+execute stmt;
+deallocate prepare stmt;
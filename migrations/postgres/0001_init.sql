@@ -0,0 +1,89 @@
+-- session
+create table if not exists session (
+    "id" bigserial not null primary key,
+    "token" char(32) not null,
+    "product_id" bigint not null,
+    "user_id" bigint not null,
+    "expire" bigint not null
+);
+
+create index if not exists idx_session_token on session("token");
+
+-- 公用user和product
+create table if not exists "user" (
+    "id" bigserial not null primary key,
+    "user_name" varchar(64) null,
+    "email" varchar(64) not null,
+    "passwd" char(64) not null,
+    -- 00 正常 -- 99 已注销
+    "status" char(2) not null,
+    "update_time" bigint null,
+    unique ("email")
+);
+
+create index if not exists idx_user_name on "user"("user_name");
+
+create table if not exists product (
+    "id" bigserial not null primary key,
+    "product" varchar(64) not null,
+    "desc" varchar(256) null,
+    -- 00 正常 -- 99 已下架
+    "status" char(2) not null,
+    "update_time" bigint null,
+    unique ("product")
+);
+
+create table if not exists user_product (
+    "id" bigserial not null primary key,
+    "product_id" bigint not null,
+    "user_id" bigint not null,
+    "avatar" varchar(256) null,
+    -- 00 正常 -- 99 已注销
+    "status" char(2) not null,
+    "update_time" bigint not null
+);
+
+create index if not exists idx_user_product on user_product("product_id", "user_id");
+
+-- hiqradio
+create table if not exists hiqradio_recently (
+    "id" bigserial not null primary key,
+    "user_id" bigint not null,
+    "stationuuid" varchar(40) not null,
+    "start_time" bigint not null,
+    "end_time" bigint null
+);
+
+create index if not exists idx_hiqradio_recently_user on hiqradio_recently("user_id", "start_time");
+
+create table if not exists hiqradio_fav_group (
+    "id" bigserial not null primary key,
+    "user_id" bigint not null,
+    "create_time" bigint not null,
+    "name" varchar(255) not null,
+    "desc" varchar(1024) null,
+    "is_def" bigint not null,
+    unique ("user_id", "name")
+);
+
+create table if not exists hiqradio_favorite (
+    "id" bigserial not null primary key,
+    "user_id" bigint not null,
+    "stationuuid" varchar(40) not null,
+    "group_id" bigint not null,
+    "create_time" bigint not null,
+    unique ("user_id", "group_id", "stationuuid")
+);
+
+create index if not exists idx_hiqradio_favorite_group on hiqradio_favorite("group_id");
+
+insert into
+    product("product", "desc", "status", "update_time")
+values
+    (
+        'hiqradio',
+        'hiqradio listen the whole world',
+        '00',
+        extract(epoch from now())::bigint
+    )
+on conflict ("product") do nothing;
@@ -0,0 +1,13 @@
+-- hiqradio 删除/修改记录，同步时下发
+create table if not exists hiqradio_tombstone (
+    "id" bigserial not null primary key,
+    "user_id" bigint not null,
+    "kind" varchar(32) not null,
+    "group_name" varchar(255) not null,
+    "stationuuid" varchar(40) not null,
+    "new_name" varchar(255) not null,
+    "new_desc" varchar(1024) not null,
+    "create_time" bigint not null
+);
+
+create index if not exists idx_hiqradio_tombstone_user on hiqradio_tombstone("user_id", "create_time");
@@ -0,0 +1,2 @@
+-- 最近播放按电台过滤，按时间分页使用idx_hiqradio_recently_user
+create index if not exists idx_hiqradio_recently_station on hiqradio_recently("user_id", "stationuuid", "start_time");
@@ -0,0 +1,7 @@
+-- hiqradio 用户设置
+create table if not exists hiqradio_setting (
+    "user_id" bigint not null primary key,
+    "recently_enabled" bigint not null default 1,
+    "recently_days" bigint not null default 0,
+    "update_time" bigint not null
+);
@@ -0,0 +1,10 @@
+-- 电台全文搜索，名称、标签、国家和语言、编码的权重依次降低
+alter table hiqradio_station add column if not exists "search" tsvector
+    generated always as (
+        setweight(to_tsvector('simple', "name"), 'A') ||
+        setweight(to_tsvector('simple', "tags"), 'B') ||
+        setweight(to_tsvector('simple', "country" || ' ' || "language"), 'C') ||
+        setweight(to_tsvector('simple', "codec"), 'D')
+    ) stored;
+
+create index if not exists hiqradio_station_search on hiqradio_station using gin ("search");
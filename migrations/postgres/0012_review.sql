@@ -0,0 +1,26 @@
+-- hiqradio 电台评分和评论，每个用户对每个电台一条，评论需审核后公开
+create table if not exists hiqradio_review (
+    "id" bigserial not null primary key,
+    "user_id" bigint not null,
+    "stationuuid" varchar(40) not null,
+    "rating" bigint not null,
+    "content" varchar(2048) not null,
+    -- pending 待审核 approved 已通过 rejected 已拒绝
+    "status" varchar(16) not null,
+    "reports" bigint not null default 0,
+    "create_time" bigint not null,
+    "update_time" bigint not null,
+    unique ("user_id", "stationuuid")
+);
+
+create index if not exists idx_hiqradio_review_station on hiqradio_review("stationuuid", "status");
+create index if not exists idx_hiqradio_review_status on hiqradio_review("status");
+
+-- 评论的举报，每个用户对每条评论只计一次
+create table if not exists hiqradio_review_report (
+    "review_id" bigint not null,
+    "user_id" bigint not null,
+    "reason" varchar(1024) not null,
+    "create_time" bigint not null,
+    primary key ("review_id", "user_id")
+);
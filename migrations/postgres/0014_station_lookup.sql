@@ -0,0 +1,4 @@
+-- 导入播放列表时按播放地址和名称查找电台，地址可能超过btree的长度限制，使用hash索引
+create index if not exists idx_hiqradio_station_url on hiqradio_station using hash("url");
+create index if not exists idx_hiqradio_station_url_resolved on hiqradio_station using hash("url_resolved");
+create index if not exists idx_hiqradio_station_name on hiqradio_station using hash("name");
@@ -0,0 +1,30 @@
+-- hiqradio 按用户递增的版本号，增量同步使用
+create table if not exists hiqradio_revision (
+    `user_id` integer not null primary key,
+    `revision` integer not null
+);
+
+alter table hiqradio_recently add column `revision` integer not null default 0;
+alter table hiqradio_fav_group add column `revision` integer not null default 0;
+alter table hiqradio_favorite add column `revision` integer not null default 0;
+alter table hiqradio_tombstone add column `revision` integer not null default 0;
+
+-- 已有数据记为版本1
+update hiqradio_recently set `revision` = 1;
+update hiqradio_fav_group set `revision` = 1;
+update hiqradio_favorite set `revision` = 1;
+update hiqradio_tombstone set `revision` = 1;
+
+insert into hiqradio_revision(`user_id`, `revision`)
+select `user_id`, 1 from hiqradio_recently
+union
+select `user_id`, 1 from hiqradio_fav_group
+union
+select `user_id`, 1 from hiqradio_favorite
+union
+select `user_id`, 1 from hiqradio_tombstone;
+
+create index if not exists idx_hiqradio_recently_revision on hiqradio_recently(`user_id`, `revision`);
+create index if not exists idx_hiqradio_fav_group_revision on hiqradio_fav_group(`user_id`, `revision`);
+create index if not exists idx_hiqradio_favorite_revision on hiqradio_favorite(`user_id`, `revision`);
+create index if not exists idx_hiqradio_tombstone_revision on hiqradio_tombstone(`user_id`, `revision`);
@@ -0,0 +1,3 @@
+-- 最近播放按时间分页和按电台过滤
+create index if not exists idx_hiqradio_recently_user on hiqradio_recently(`user_id`, `start_time`);
+create index if not exists idx_hiqradio_recently_station on hiqradio_recently(`user_id`, `stationuuid`, `start_time`);
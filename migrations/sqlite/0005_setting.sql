@@ -0,0 +1,7 @@
+-- hiqradio 用户设置
+create table if not exists hiqradio_setting (
+    `user_id` integer not null primary key,
+    `recently_enabled` integer not null default 1,
+    `recently_days` integer not null default 0,
+    `update_time` integer not null
+);
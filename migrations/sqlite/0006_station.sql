@@ -0,0 +1,22 @@
+-- 电台目录，从radio-browser导出的数据导入
+create table if not exists hiqradio_station (
+    `stationuuid` varchar(40) not null primary key,
+    `name` text not null,
+    `url` text not null,
+    `url_resolved` text not null,
+    `homepage` text not null,
+    `favicon` text not null,
+    `tags` text not null,
+    `country` varchar(128) not null,
+    `countrycode` varchar(8) not null,
+    `state` varchar(128) not null,
+    `language` varchar(256) not null,
+    `codec` varchar(32) not null,
+    `bitrate` integer not null,
+    `hls` integer not null,
+    `lastcheckok` integer not null,
+    `votes` integer not null,
+    `clickcount` integer not null,
+    `deleted` integer not null default 0,
+    `update_time` integer not null
+);
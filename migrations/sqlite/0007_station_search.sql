@@ -0,0 +1,58 @@
+-- 电台全文搜索
+-- stationuuid为文本主键时rowid在vacuum后可能变化，重建表使用整数主键作为全文索引的rowid
+create table hiqradio_station_new (
+    `id` integer primary key autoincrement,
+    `stationuuid` varchar(40) not null unique,
+    `name` text not null,
+    `url` text not null,
+    `url_resolved` text not null,
+    `homepage` text not null,
+    `favicon` text not null,
+    `tags` text not null,
+    `country` varchar(128) not null,
+    `countrycode` varchar(8) not null,
+    `state` varchar(128) not null,
+    `language` varchar(256) not null,
+    `codec` varchar(32) not null,
+    `bitrate` integer not null,
+    `hls` integer not null,
+    `lastcheckok` integer not null,
+    `votes` integer not null,
+    `clickcount` integer not null,
+    `deleted` integer not null default 0,
+    `update_time` integer not null
+);
+
+insert into hiqradio_station_new(stationuuid, name, url, url_resolved, homepage, favicon, tags,
+    country, countrycode, state, language, codec, bitrate, hls, lastcheckok, votes, clickcount,
+    deleted, update_time)
+select stationuuid, name, url, url_resolved, homepage, favicon, tags,
+    country, countrycode, state, language, codec, bitrate, hls, lastcheckok, votes, clickcount,
+    deleted, update_time
+from hiqradio_station;
+
+drop table hiqradio_station;
+alter table hiqradio_station_new rename to hiqradio_station;
+
+create virtual table if not exists hiqradio_station_fts using fts5(
+    name, tags, country, language, codec,
+    content = 'hiqradio_station', content_rowid = 'id'
+);
+insert into hiqradio_station_fts(hiqradio_station_fts) values ('rebuild');
+
+create trigger if not exists hiqradio_station_fts_insert after insert on hiqradio_station begin
+    insert into hiqradio_station_fts(rowid, name, tags, country, language, codec)
+    values (new.id, new.name, new.tags, new.country, new.language, new.codec);
+end;
+
+create trigger if not exists hiqradio_station_fts_delete after delete on hiqradio_station begin
+    insert into hiqradio_station_fts(hiqradio_station_fts, rowid, name, tags, country, language, codec)
+    values ('delete', old.id, old.name, old.tags, old.country, old.language, old.codec);
+end;
+
+create trigger if not exists hiqradio_station_fts_update after update on hiqradio_station begin
+    insert into hiqradio_station_fts(hiqradio_station_fts, rowid, name, tags, country, language, codec)
+    values ('delete', old.id, old.name, old.tags, old.country, old.language, old.codec);
+    insert into hiqradio_station_fts(rowid, name, tags, country, language, codec)
+    values (new.id, new.name, new.tags, new.country, new.language, new.codec);
+end;
@@ -0,0 +1,12 @@
+-- 分组和分组中收藏的顺序，已有数据按创建顺序
+alter table hiqradio_fav_group add column `position` integer not null default 0;
+alter table hiqradio_favorite add column `position` integer not null default 0;
+
+update hiqradio_fav_group set `position` = (
+    select count(*) from hiqradio_fav_group g
+    where g.user_id = hiqradio_fav_group.user_id and g.id < hiqradio_fav_group.id
+);
+update hiqradio_favorite set `position` = (
+    select count(*) from hiqradio_favorite f
+    where f.group_id = hiqradio_favorite.group_id and f.id < hiqradio_favorite.id
+);
@@ -0,0 +1,10 @@
+-- hiqradio 公开分享的收藏分组，分组删除后分享失效
+create table if not exists hiqradio_share (
+    `id` integer not null primary key autoincrement,
+    `token` varchar(64) not null unique,
+    `user_id` integer not null,
+    `group_id` integer not null unique,
+    `create_time` integer not null
+);
+
+create index if not exists idx_hiqradio_share_user on hiqradio_share(`user_id`);
@@ -0,0 +1,11 @@
+-- hiqradio 热门电台，后台任务按日、周、月定期重建
+create table if not exists hiqradio_trending (
+    `period` varchar(16) not null,
+    `stationuuid` varchar(40) not null,
+    `listeners` integer not null,
+    `listen_time` integer not null,
+    `favorites` integer not null,
+    `score` real not null,
+    `update_time` integer not null,
+    primary key (`period`, `stationuuid`)
+);
@@ -55,18 +55,37 @@ pub fn app_router(state: AppState) -> Router {
         .route("/upload", post(user::upload))
         .route("/modify", post(user::modify))
         .route("/open_product", post(user::open_product))
+        .route("/close_product", post(user::close_product))
         .route("/user_products", post(user::user_products))
-        .route("/products", post(user::products));
+        .route("/products", post(user::products))
+        .route("/export", post(user::export))
+        .route("/import", post(user::import))
+        .route("/cancel", post(user::cancel));
 
     let router_user = Router::new().nest("/user", router_user);
 
     // hiqradio
     let router_hiqradio = Router::new()
         .route("/sync", post(hiqradio::sync))
+        .route("/sync_push", post(hiqradio::sync_push))
+        .route("/events", get(hiqradio::events))
         .route("/recently", post(hiqradio::recently))
         .route("/recently_new", post(hiqradio::recently_new))
         .route("/recently_modify", post(hiqradio::recently_modify))
         .route("/recently_clear", post(hiqradio::recently_clear))
+        .route("/presence", post(hiqradio::presence))
+        .route("/presences", post(hiqradio::presences))
+        .route("/stats", post(hiqradio::stats))
+        .route("/search", post(hiqradio::search))
+        .route("/recommend", post(hiqradio::recommend))
+        .route("/trending", get(hiqradio::trending))
+        .route("/review", post(hiqradio::review))
+        .route("/review_delete", post(hiqradio::review_delete))
+        .route("/reviews/:stationuuid", get(hiqradio::reviews))
+        .route("/my_reviews", post(hiqradio::my_reviews))
+        .route("/review_report", post(hiqradio::review_report))
+        .route("/review_queue", post(hiqradio::review_queue))
+        .route("/review_moderate", post(hiqradio::review_moderate))
         .route("/groups", post(hiqradio::groups))
         .route("/group_delete", post(hiqradio::group_delete))
         .route("/group_modify", post(hiqradio::group_modify))
@@ -74,7 +93,17 @@ pub fn app_router(state: AppState) -> Router {
         .route("/favorites", post(hiqradio::favorites))
         .route("/favorite_delete", post(hiqradio::favorite_delete))
         .route("/favorite_modify", post(hiqradio::favorite_modify))
-        .route("/favorite_new", post(hiqradio::favorite_new));
+        .route("/favorite_new", post(hiqradio::favorite_new))
+        .route("/reorder", post(hiqradio::reorder))
+        .route("/playlist_export", post(hiqradio::playlist_export))
+        .route("/playlist_import", post(hiqradio::playlist_import))
+        .route("/share", post(hiqradio::share))
+        .route("/shares", post(hiqradio::shares))
+        .route("/share_revoke", post(hiqradio::share_revoke))
+        .route("/shared/:token", get(hiqradio::shared))
+        .route("/share_copy", post(hiqradio::share_copy))
+        .route("/setting", post(hiqradio::setting))
+        .route("/setting_modify", post(hiqradio::setting_modify));
 
     let router_hiqradio = Router::new().nest("/hiqradio", router_hiqradio);
 
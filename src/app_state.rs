@@ -2,20 +2,43 @@ use async_session::MemoryStore;
 use axum::extract::FromRef;
 
 use crate::{
-    config::CONFIG, repo::{self, DynAppServRepo}, Result
+    auth_user::AuthUser,
+    config::CONFIG,
+    notify::{LibraryChanged, Notifier},
+    repo::{self, DynAppServRepo},
+    Result,
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub store: MemoryStore,
     pub repo: DynAppServRepo,
+    pub notifier: Notifier,
 }
 
 impl AppState {
     pub async fn new() -> Result<Self> {
         let store = MemoryStore::new();
         let repo = repo::new(&CONFIG.db_url).await?;
-        Ok(Self { store, repo })
+        let notifier = Notifier::new();
+        Ok(Self {
+            store,
+            repo,
+            notifier,
+        })
+    }
+
+    /// hiqradio数据修改后通知同一用户的其他会话，失败不影响请求
+    pub async fn library_changed(&self, auth_user: &AuthUser) {
+        let user_id = auth_user.user_product.user_id;
+        match self.repo.query_revision(user_id).await {
+            Ok(revision) => self.notifier.send(LibraryChanged {
+                user_id,
+                revision,
+                token: auth_user.token.clone(),
+            }),
+            Err(e) => tracing::error!("query revision error: {}", e),
+        }
     }
 }
 
@@ -0,0 +1,164 @@
+//! sqlite数据库备份和恢复
+//!
+//! 备份用`VACUUM INTO`在线生成，服务运行时也可以执行；恢复需先停止服务，
+//! 备份文件校验通过后才替换数据库文件，原文件改名保留。
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use crate::{
+    errors::Error,
+    repo::{
+        migrate,
+        sqlite::{self, SqliteRepo},
+        AppServRepo, DynAppServRepo,
+    },
+    Result,
+};
+
+/// sqlite数据库文件路径，和`repo::connect`一样支持`sqlite://`和`sqlite:`
+fn sqlite_path(db_url: &str) -> Result<PathBuf> {
+    sqlite::db_path(db_url)
+        .ok_or_else(|| Error::Internal(format!("\"{}\" is not a sqlite database", db_url)))
+}
+
+/// 备份文件名前缀，appserv.db的备份为appserv-20240101-001500-000.db
+fn backup_prefix(db_path: &Path) -> String {
+    let stem = db_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("appserv");
+    format!("{}-", stem)
+}
+
+/// 在路径后追加后缀，如appserv.db-wal
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut s = OsString::from(path);
+    s.push(suffix);
+    PathBuf::from(s)
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> Error {
+    Error::Internal(format!("{}: {}", path.display(), e))
+}
+
+/// 备份数据库到dir，只保留最新的keep个备份，keep为0时不清理，返回备份文件路径
+pub async fn backup(
+    repo: &DynAppServRepo,
+    db_url: &str,
+    dir: &str,
+    keep: usize,
+) -> Result<PathBuf> {
+    let prefix = backup_prefix(&sqlite_path(db_url)?);
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+
+    let name = format!("{}{}.db", prefix, Local::now().format("%Y%m%d-%H%M%S-%3f"));
+    let path = dir.join(name);
+    // 先写临时文件，未完成的备份不会被当成有效备份
+    let tmp = with_suffix(&path, ".tmp");
+    if let Err(e) = repo.backup(&tmp.to_string_lossy()).await {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, &path).map_err(|e| io_error(&path, e))?;
+
+    if keep > 0 {
+        prune(dir, &prefix, keep)?;
+    }
+    Ok(path)
+}
+
+/// 删除多余的旧备份
+fn prune(dir: &Path, prefix: &str, keep: usize) -> Result {
+    let mut backups: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| io_error(dir, e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with(prefix) && n.ends_with(".db"))
+                    .unwrap_or(false)
+        })
+        .collect();
+    // 文件名中的时间按字典序即为时间顺序
+    backups.sort();
+
+    let remove = backups.len().saturating_sub(keep);
+    for path in backups.into_iter().take(remove) {
+        tracing::info!("remove old backup: {}", path.display());
+        fs::remove_file(&path).map_err(|e| io_error(&path, e))?;
+    }
+    Ok(())
+}
+
+/// 校验备份文件是本程序可用的数据库，返回数据库版本
+async fn check_backup(path: &Path) -> Result<i64> {
+    let repo = SqliteRepo::new(&path.to_string_lossy()).await?;
+    let version = repo.schema_version().await;
+    repo.close().await;
+
+    let version = version?;
+    if version == 0 {
+        return Err(Error::DatabaseException(format!(
+            "{} is not an appserv database",
+            path.display()
+        )));
+    }
+    migrate::check_newer(version)?;
+    Ok(version)
+}
+
+/// 从备份文件恢复数据库，需先停止服务，返回恢复后的数据库版本。
+/// 备份先复制到数据库旁校验，原数据库文件改名为`<db>.<时间>.bak`保留
+pub async fn restore(db_url: &str, file: &str) -> Result<i64> {
+    let db = sqlite_path(db_url)?;
+    let db = db.as_path();
+    let file = Path::new(file);
+    if !file.is_file() {
+        return Err(Error::Internal(format!(
+            "backup file {} not exists",
+            file.display()
+        )));
+    }
+
+    // 校验的是副本，不改动备份文件本身
+    let tmp = with_suffix(db, ".restore");
+    fs::copy(file, &tmp).map_err(|e| io_error(&tmp, e))?;
+    let version = match check_backup(&tmp).await {
+        Ok(version) => version,
+        Err(e) => {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = fs::remove_file(with_suffix(&tmp, suffix));
+            }
+            return Err(e);
+        }
+    };
+
+    // 残留的wal必须和原数据库一起移走，否则会被应用到恢复的数据库上
+    let bak = with_suffix(
+        db,
+        &format!(".{}.bak", Local::now().format("%Y%m%d-%H%M%S")),
+    );
+    for suffix in ["", "-wal", "-shm"] {
+        let from = with_suffix(db, suffix);
+        if from.exists() {
+            let to = with_suffix(&bak, suffix);
+            fs::rename(&from, &to).map_err(|e| io_error(&from, e))?;
+        }
+    }
+    if bak.exists() {
+        tracing::info!("old database moved to {}", bak.display());
+    }
+    fs::rename(&tmp, db).map_err(|e| io_error(db, e))?;
+
+    Ok(version)
+}
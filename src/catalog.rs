@@ -0,0 +1,230 @@
+//! 电台目录，从radio-browser导出的JSON或CSV数据导入
+//!
+//! 每次导入整份数据，导入前已有、这次数据中没有的电台标记为已删除，
+//! 收藏和播放记录中的stationuuid仍可查到电台信息。
+//! 搜索使用各数据库的全文索引，用户收藏和最近播放过的电台相关度加权。
+//! 返回的电台带本站用户审核通过的平均评分。
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::{
+    errors::Error,
+    model::hiqradio::Station,
+    proto::{RecentlyFilter, StationFilter, SEARCH_BOOST_MAX},
+    repo::DynAppServRepo,
+    Result,
+};
+
+/// 每个事务写入的电台数
+const SAVE_CHUNK: usize = 500;
+
+/// radio-browser导出数据中的电台，字段可能为null
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DumpStation {
+    stationuuid: String,
+    name: Option<String>,
+    url: Option<String>,
+    url_resolved: Option<String>,
+    homepage: Option<String>,
+    favicon: Option<String>,
+    tags: Option<String>,
+    country: Option<String>,
+    countrycode: Option<String>,
+    state: Option<String>,
+    language: Option<String>,
+    codec: Option<String>,
+    bitrate: Option<i64>,
+    hls: Option<i64>,
+    lastcheckok: Option<i64>,
+    votes: Option<i64>,
+    clickcount: Option<i64>,
+}
+
+impl DumpStation {
+    fn into_station(self, update_time: i64) -> Station {
+        Station {
+            stationuuid: self.stationuuid.trim().to_ascii_lowercase(),
+            name: self.name.unwrap_or_default(),
+            url: self.url.unwrap_or_default(),
+            url_resolved: self.url_resolved.unwrap_or_default(),
+            homepage: self.homepage.unwrap_or_default(),
+            favicon: self.favicon.unwrap_or_default(),
+            tags: self.tags.unwrap_or_default(),
+            country: self.country.unwrap_or_default(),
+            countrycode: self.countrycode.unwrap_or_default(),
+            state: self.state.unwrap_or_default(),
+            language: self.language.unwrap_or_default(),
+            codec: self.codec.unwrap_or_default(),
+            bitrate: self.bitrate.unwrap_or_default(),
+            hls: self.hls.unwrap_or_default(),
+            lastcheckok: self.lastcheckok.unwrap_or_default(),
+            votes: self.votes.unwrap_or_default(),
+            clickcount: self.clickcount.unwrap_or_default(),
+            deleted: 0,
+            update_time,
+            rating: 0.0,
+            ratings: 0,
+        }
+    }
+}
+
+fn parse_json(content: &str) -> Result<Vec<DumpStation>> {
+    serde_json::from_str(content).map_err(|e| Error::Parse(format!("station json: {}", e)))
+}
+
+fn parse_csv(content: &str) -> Result<Vec<DumpStation>> {
+    csv::Reader::from_reader(content.as_bytes())
+        .deserialize()
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| Error::Parse(format!("station csv: {}", e)))
+}
+
+/// 解析导出数据，JSON为电台数组，CSV第一行为字段名；去掉没有stationuuid的和重复的电台
+pub fn parse(content: &str, update_time: i64) -> Result<Vec<Station>> {
+    let content = content.trim_start_matches('\u{feff}');
+    let dump = if content.trim_start().starts_with('[') {
+        parse_json(content)?
+    } else {
+        parse_csv(content)?
+    };
+
+    let mut seen = HashSet::new();
+    Ok(dump
+        .into_iter()
+        .map(|s| s.into_station(update_time))
+        .filter(|s| !s.stationuuid.is_empty() && seen.insert(s.stationuuid.clone()))
+        .collect())
+}
+
+/// 导入的数量
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Imported {
+    pub stations: usize,
+    /// 这次数据中没有、标记为已删除的电台
+    pub deleted: usize,
+}
+
+/// 导入电台目录，stations为一份完整的数据
+pub async fn import(repo: &DynAppServRepo, stations: &[Station]) -> Result<Imported> {
+    // 空数据多半是导出失败，不能把所有电台标记为删除
+    let Some(first) = stations.first() else {
+        return Err(Error::Parse(String::from("station dump is empty")));
+    };
+    let update_time = first.update_time;
+
+    for chunk in stations.chunks(SAVE_CHUNK) {
+        repo.save_stations(chunk).await?;
+    }
+    let deleted = repo.delete_stations_before(update_time).await?;
+
+    Ok(Imported {
+        stations: stations.len(),
+        deleted,
+    })
+}
+
+/// 从文件导入电台目录
+pub async fn import_file(repo: &DynAppServRepo, file: &Path) -> Result<Imported> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| Error::Internal(format!("{}: {}", file.display(), e)))?;
+    let stations = parse(&content, Local::now().timestamp())?;
+    import(repo, &stations).await
+}
+
+/// 查询一组stationuuid对应的电台，重复的只查一次
+pub async fn stations<'a>(
+    repo: &DynAppServRepo,
+    stationuuids: impl IntoIterator<Item = &'a String>,
+) -> Result<Vec<Station>> {
+    let mut seen = HashSet::new();
+    let stationuuids: Vec<_> = stationuuids
+        .into_iter()
+        .filter(|s| seen.insert(*s))
+        .cloned()
+        .collect();
+    if stationuuids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let stations = repo.query_stations(&stationuuids).await?;
+    with_ratings(repo, stations).await
+}
+
+/// 填充电台的平均评分和评分数
+pub async fn with_ratings(repo: &DynAppServRepo, stations: Vec<Station>) -> Result<Vec<Station>> {
+    if stations.is_empty() {
+        return Ok(stations);
+    }
+    let stationuuids: Vec<_> = stations.iter().map(|s| s.stationuuid.clone()).collect();
+    let ratings: HashMap<_, _> = repo
+        .query_ratings(&stationuuids)
+        .await?
+        .into_iter()
+        .map(|r| (r.stationuuid.clone(), r))
+        .collect();
+    Ok(stations
+        .into_iter()
+        .map(|mut s| {
+            if let Some(r) = ratings.get(&s.stationuuid) {
+                s.rating = r.rating;
+                s.ratings = r.ratings;
+            }
+            s
+        })
+        .collect())
+}
+
+/// 用户收藏和最近播放过的电台，收藏在前
+async fn boost_stations(repo: &DynAppServRepo, user_id: i64) -> Result<Vec<String>> {
+    let favorites = repo.query_favorites(user_id).await?;
+    let recently = repo
+        .query_recently_page(
+            user_id,
+            &RecentlyFilter {
+                after: None,
+                since: None,
+                until: None,
+                stationuuid: None,
+                limit: SEARCH_BOOST_MAX as i64,
+            },
+        )
+        .await?;
+
+    let mut seen = HashSet::new();
+    Ok(favorites
+        .into_iter()
+        .map(|f| f.stationuuid)
+        .chain(recently.into_iter().map(|r| r.stationuuid))
+        .filter(|s| seen.insert(s.clone()))
+        .take(SEARCH_BOOST_MAX)
+        .collect())
+}
+
+/// 搜索电台，用户收藏和最近播放过的电台排在前面，返回电台和下一页的offset
+pub async fn search(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    filter: &StationFilter,
+) -> Result<(Vec<Station>, Option<i64>)> {
+    // 多查一条判断是否还有下一页
+    let mut filter = filter.clone();
+    filter.boost = boost_stations(repo, user_id).await?;
+    filter.limit += 1;
+    let mut stations = with_ratings(repo, repo.search_stations(&filter).await?).await?;
+
+    let limit = filter.limit as usize - 1;
+    let next = if stations.len() > limit {
+        stations.truncate(limit);
+        Some(filter.offset + limit as i64)
+    } else {
+        None
+    };
+    Ok((stations, next))
+}
@@ -12,7 +12,43 @@ pub struct Config {
     pub token_expire: i64,
     pub token_refresh: i64,
     pub db_url: String,
+    /// 启动时自动执行数据库迁移
+    #[serde(default = "default_auto_migrate")]
+    pub auto_migrate: bool,
     pub listen: String,
+    /// 数据库备份目录
+    #[serde(default = "default_backup_path")]
+    pub backup_path: String,
+    /// 保留的备份数量，0表示不做每日备份，手动备份也不清理旧文件
+    #[serde(default = "default_backup_keep")]
+    pub backup_keep: usize,
+    /// 注销账号的数据保留天数，过后清理
+    #[serde(default = "default_cancel_grace_days")]
+    pub cancel_grace_days: i64,
+    /// 最近播放保留天数，默认0不限制，需要时显式配置
+    #[serde(default = "default_recently_max_days")]
+    pub recently_max_days: i64,
+    /// 每个用户保留的最近播放条数，默认0不限制，需要时显式配置
+    #[serde(default = "default_recently_max_rows")]
+    pub recently_max_rows: i64,
+    /// 导出播放列表时电台的播放地址，{stationuuid}替换为电台id
+    #[serde(default = "default_playlist_station_url")]
+    pub playlist_station_url: String,
+    /// 重建相似电台的间隔(秒)，0表示不重建
+    #[serde(default = "default_recommend_interval")]
+    pub recommend_interval: u64,
+    /// 参与推荐计算的最近播放天数
+    #[serde(default = "default_recommend_recently_days")]
+    pub recommend_recently_days: i64,
+    /// 重建热门电台的间隔(秒)，0表示不重建
+    #[serde(default = "default_trending_interval")]
+    pub trending_interval: u64,
+    /// 评论审核员的邮箱
+    #[serde(default)]
+    pub review_moderators: Vec<String>,
+    /// 播放状态的有效期(秒)，客户端应在过期前发送心跳
+    #[serde(default = "default_presence_ttl")]
+    pub presence_ttl: i64,
     pub session_interval: usize,
     pub clean_interval: usize,
     pub smtp_sender: Option<String>,
@@ -24,7 +60,19 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             db_url: String::from("sqlite://appserv.db"),
+            auto_migrate: true,
             listen: String::from("127.0.0.1:3000"),
+            backup_path: default_backup_path(),
+            backup_keep: default_backup_keep(),
+            cancel_grace_days: default_cancel_grace_days(),
+            recently_max_days: default_recently_max_days(),
+            recently_max_rows: default_recently_max_rows(),
+            playlist_station_url: default_playlist_station_url(),
+            recommend_interval: default_recommend_interval(),
+            recommend_recently_days: default_recommend_recently_days(),
+            trending_interval: default_trending_interval(),
+            review_moderators: Vec::new(),
+            presence_ttl: default_presence_ttl(),
             session_interval: 60,
             clean_interval: 900,
             smtp_sender: None,
@@ -39,9 +87,11 @@ impl Default for Config {
 }
 
 impl Config {
+    /// 配置文件优先取环境变量APPSERV_CONFIG，其次是第一个命令行参数
     pub fn load_config() -> Self {
-        let cfg_file = std::env::args()
-            .nth(1)
+        let cfg_file = std::env::var("APPSERV_CONFIG")
+            .ok()
+            .or_else(|| std::env::args().nth(1))
             .unwrap_or_else(|| String::from("config.toml"));
         let cfg = if let Ok(content) = std::fs::read_to_string(cfg_file) {
             let cfg = toml::from_str(&content);
@@ -68,6 +118,50 @@ impl Config {
     }
 }
 
+fn default_auto_migrate() -> bool {
+    true
+}
+
+fn default_backup_path() -> String {
+    String::from("./backup")
+}
+
+fn default_backup_keep() -> usize {
+    7
+}
+
+fn default_cancel_grace_days() -> i64 {
+    30
+}
+
+fn default_recently_max_days() -> i64 {
+    0
+}
+
+fn default_recently_max_rows() -> i64 {
+    0
+}
+
+fn default_playlist_station_url() -> String {
+    String::from("https://all.api.radio-browser.info/m3u/url/{stationuuid}")
+}
+
+fn default_recommend_interval() -> u64 {
+    6 * 3600
+}
+
+fn default_recommend_recently_days() -> i64 {
+    90
+}
+
+fn default_trending_interval() -> u64 {
+    3600
+}
+
+fn default_presence_ttl() -> i64 {
+    90
+}
+
 fn check_path(path_str: &str) {
     let path = Path::new(path_str);
     if !path.exists() {
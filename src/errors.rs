@@ -78,8 +78,8 @@ impl IntoResponse for Error {
                 format!("protocol parse error: \"{}\"", reject.body_text()),
             ),
             Error::Parse(message) => (E_PARSE_REQ, format!("protocol error: \"{}\"", message)),
-            Error::ParseEmail => (E_PARSE_EMAIL_REQ, format!("protocol email error")),
-            Error::SendEmail => (E_SEND_EMAIL, format!("send email error")),
+            Error::ParseEmail => (E_PARSE_EMAIL_REQ, "protocol email error".to_string()),
+            Error::SendEmail => (E_SEND_EMAIL, "send email error".to_string()),
             Error::UserExists(message) => (E_USER_EXISTS, format!("signup error: \"{}\"", message)),
             Error::DatabaseException(message) => {
                 (E_DATABASE, format!("database exception: \"{}\"", message))
@@ -87,20 +87,20 @@ impl IntoResponse for Error {
             Error::Internal(message) => {
                 (E_INTERNAL, format!("internal exception: \"{}\"", message))
             }
-            Error::Captcha => (E_BAD_CAPTCHA, format!("captcha error")),
-            Error::UserPasswdError => (E_BAD_PASSWD, format!("user password error")),
-            Error::UserNotExists => (E_USER_NOT_EXISTS, format!("user not exists")),
-            Error::UserNotLogin => (E_USER_NOT_LOGIN, format!("user not login")),
-            Error::EmailVerifyCode => (E_EMAIL_VERIFY_CODE, format!("email captcha error")),
-            Error::Frequent => (E_TOO_FREQUENT, format!("operation too frequent")),
-            Error::ProductNotExists => (E_PRODUCT_NOT_EXISTS, format!("product not exists")),
-            Error::ProductNotOpen => (E_PRODUCT_NOT_OPEN, format!("product not open")),
-            Error::TokenInvalid => (E_TOKEN_INVALID, format!("token invalid")),
+            Error::Captcha => (E_BAD_CAPTCHA, "captcha error".to_string()),
+            Error::UserPasswdError => (E_BAD_PASSWD, "user password error".to_string()),
+            Error::UserNotExists => (E_USER_NOT_EXISTS, "user not exists".to_string()),
+            Error::UserNotLogin => (E_USER_NOT_LOGIN, "user not login".to_string()),
+            Error::EmailVerifyCode => (E_EMAIL_VERIFY_CODE, "email captcha error".to_string()),
+            Error::Frequent => (E_TOO_FREQUENT, "operation too frequent".to_string()),
+            Error::ProductNotExists => (E_PRODUCT_NOT_EXISTS, "product not exists".to_string()),
+            Error::ProductNotOpen => (E_PRODUCT_NOT_OPEN, "product not open".to_string()),
+            Error::TokenInvalid => (E_TOKEN_INVALID, "token invalid".to_string()),
             Error::UserPasswordTooShort => (
                 E_PASSWORD_TOOL_SHORT,
-                format!("password illegal, length must at least 6"),
+                "password illegal, length must at least 6".to_string(),
             ),
-            Error::EmailDiff => (E_EMAIL_DIFF, format!("email not equal")),
+            Error::EmailDiff => (E_EMAIL_DIFF, "email not equal".to_string()),
         };
         let body = Json(json!({
             "error": error,
@@ -58,7 +58,7 @@ pub async fn captcha(
         .store_session(session)
         .await
         .map_err(|e| Error::Internal(format!("store session error: {}", e)))?
-        .ok_or(Error::Internal(format!("store session error")))?;
+        .ok_or(Error::Internal("store session error".to_string()))?;
 
     let resp = CaptchaRsp {
         error: E_SUCCESS,
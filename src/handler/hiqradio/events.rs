@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use axum::{
+    debug_handler,
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    app_state::AppState, auth_user::AuthUser, notify::LibraryEvent, proto::LibraryChangedEvent,
+    Result,
+};
+
+/// 数据修改通知(Server-Sent Events)
+///
+/// 连接后先推送一次当前版本号，之后同一用户的其他会话修改数据时推送`library_changed`，
+/// 通知丢失时推送`lagged`，客户端收到后调用sync。
+/// 会话退出登录、关闭产品或注销时推送`closed`后结束。
+#[debug_handler(state = AppState)]
+pub async fn events(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let user_id = auth_user.user_product.user_id;
+    let revision = state.repo.query_revision(user_id).await?;
+    tracing::info!(
+        "events subscribe, user: {}, revision: {}",
+        user_id,
+        revision
+    );
+
+    let stream = tokio_stream::once(LibraryEvent::Changed(revision))
+        .chain(state.notifier.subscribe(user_id, &auth_user.token))
+        .map(|event| match event {
+            LibraryEvent::Changed(revision) => Event::default()
+                .event("library_changed")
+                .json_data(LibraryChangedEvent { revision }),
+            LibraryEvent::Lagged => Ok(Event::default().event("lagged").data("{}")),
+            LibraryEvent::Closed => Ok(Event::default().event("closed").data("{}")),
+        });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(30))))
+}
@@ -23,7 +23,7 @@ pub async fn favorite_delete(
         )));
     }
 
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state
         .repo
         .delete_favorite(
@@ -32,6 +32,8 @@ pub async fn favorite_delete(
             &payload.group_names,
         )
         .await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
@@ -17,7 +17,7 @@ pub async fn favorite_modify(
 ) -> JsonResult<BaseRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
 
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state
         .repo
         .modify_favorite(
@@ -26,6 +26,8 @@ pub async fn favorite_modify(
             &payload.group_names,
         )
         .await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
@@ -12,11 +12,13 @@ pub async fn favorite_new(
 ) -> JsonResult<BaseRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
 
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state
         .repo
         .new_favorite(user_product.user_id, &payload.new_favorite)
         .await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
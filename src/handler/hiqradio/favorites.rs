@@ -1,20 +1,38 @@
 use crate::{
-    app_state::AppState, auth_user::AuthUser, errors::E_SUCCESS, handler::ok_with_trace,
-    proto::FavoritesRsp, JsonResult,
+    app_state::AppState,
+    auth_user::AuthUser,
+    catalog,
+    errors::E_SUCCESS,
+    handler::{ok_with_trace, optional_json},
+    proto::{FavoritesReq, FavoritesRsp},
+    JsonResult,
 };
-use axum::{debug_handler, extract::State};
+use axum::{body::Bytes, debug_handler, extract::State};
 #[debug_handler(state = AppState)]
 pub async fn favorites(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    body: Bytes,
 ) -> JsonResult<FavoritesRsp> {
+    let payload = optional_json::<FavoritesReq>(&body)?.unwrap_or_default();
+
     let user_product = auth_user.user_product;
     let favorites = state.repo.query_favorites(user_product.user_id).await?;
 
+    // 电台目录中已删除的电台
+    let stations = catalog::stations(&state.repo, favorites.iter().map(|f| &f.stationuuid)).await?;
+    let deleted = stations
+        .iter()
+        .filter(|s| s.deleted != 0)
+        .map(|s| s.stationuuid.clone())
+        .collect();
+
     let rsp = FavoritesRsp {
         error: E_SUCCESS,
         message: "success".into(),
         favorites,
+        stations: payload.with_station.then_some(stations),
+        deleted,
     };
 
     ok_with_trace(rsp)
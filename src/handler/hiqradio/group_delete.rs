@@ -17,11 +17,13 @@ pub async fn group_delete(
 ) -> JsonResult<BaseRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
 
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state
         .repo
         .delete_groups(user_product.user_id, &payload.groups)
         .await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
@@ -12,7 +12,7 @@ pub async fn group_modify(
 ) -> JsonResult<BaseRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
 
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state
         .repo
         .modify_group(
@@ -22,6 +22,8 @@ pub async fn group_modify(
             &payload.desc,
         )
         .await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
@@ -21,10 +21,7 @@ pub async fn groups(
     let mut groups = state.repo.query_groups(user_product.user_id).await?;
     if let Some(payload) = payload.groups {
         if !payload.is_empty() {
-            groups = groups
-                .into_iter()
-                .filter(|group| payload.contains(&group.name))
-                .collect();
+            groups.retain(|group| payload.contains(&group.name));
         }
     }
     let rsp = GroupsRsp {
@@ -36,3 +36,78 @@ pub use favorite_modify::favorite_modify;
 
 mod sync;
 pub use sync::sync;
+
+mod sync_push;
+pub use sync_push::sync_push;
+
+mod events;
+pub use events::events;
+
+mod setting;
+pub use setting::setting;
+
+mod setting_modify;
+pub use setting_modify::setting_modify;
+
+mod stats;
+pub use stats::stats;
+
+mod playlist_export;
+pub use playlist_export::playlist_export;
+
+mod playlist_import;
+pub use playlist_import::playlist_import;
+
+mod search;
+pub use search::search;
+
+mod reorder;
+pub use reorder::reorder;
+
+mod share;
+pub use share::share;
+
+mod shares;
+pub use shares::shares;
+
+mod share_revoke;
+pub use share_revoke::share_revoke;
+
+mod shared;
+pub use shared::shared;
+
+mod share_copy;
+pub use share_copy::share_copy;
+
+mod recommend;
+pub use recommend::recommend;
+
+mod trending;
+pub use trending::trending;
+
+mod review;
+pub use review::review;
+
+mod review_delete;
+pub use review_delete::review_delete;
+
+mod reviews;
+pub use reviews::reviews;
+
+mod my_reviews;
+pub use my_reviews::my_reviews;
+
+mod review_report;
+pub use review_report::review_report;
+
+mod review_queue;
+pub use review_queue::review_queue;
+
+mod review_moderate;
+pub use review_moderate::review_moderate;
+
+mod presence;
+pub use presence::presence;
+
+mod presences;
+pub use presences::presences;
@@ -0,0 +1,24 @@
+use axum::{debug_handler, extract::State};
+
+use crate::{
+    app_state::AppState, auth_user::AuthUser, errors::E_SUCCESS, handler::ok_with_trace,
+    proto::MyReviewsRsp, JsonResult,
+};
+
+/// 查询自己的评论和审核状态
+#[debug_handler(state = AppState)]
+pub async fn my_reviews(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> JsonResult<MyReviewsRsp> {
+    let user_product = &auth_user.user_product;
+    let reviews = state.repo.query_user_reviews(user_product.user_id).await?;
+
+    let rsp = MyReviewsRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        reviews,
+    };
+
+    ok_with_trace(rsp)
+}
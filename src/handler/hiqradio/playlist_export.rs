@@ -0,0 +1,45 @@
+use axum::{
+    debug_handler,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    playlist::{self, Format},
+    proto::PlaylistExportReq,
+    JsonRejection, Result,
+};
+
+/// 导出收藏为播放列表，以附件形式下载
+#[debug_handler(state = AppState)]
+pub async fn playlist_export(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<PlaylistExportReq>,
+) -> Result<Response> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let format = Format::parse(&payload.format)?;
+    let group = payload.group.as_deref().filter(|g| !g.is_empty());
+    let user_id = auth_user.user_product.user_id;
+    let content = playlist::export(&state.repo, user_id, format, group).await?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}.{}\"",
+        urlencoding::encode(group.unwrap_or("hiqradio")),
+        format.extension()
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        content,
+    )
+        .into_response())
+}
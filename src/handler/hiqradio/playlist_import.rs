@@ -0,0 +1,56 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::{Error, E_SUCCESS},
+    handler::ok_with_trace,
+    playlist::{self, Format},
+    proto::{PlaylistImportReq, PlaylistImportRsp},
+    JsonRejection, JsonResult,
+};
+
+/// 导入其他应用导出的播放列表到分组
+#[debug_handler(state = AppState)]
+pub async fn playlist_import(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<PlaylistImportReq>,
+) -> JsonResult<PlaylistImportRsp> {
+    tracing::info!(
+        "\nreq: import playlist to group: {}, format: {:?}, size: {}\n",
+        payload.group,
+        payload.format,
+        payload.content.len()
+    );
+
+    if payload.group.is_empty() {
+        return Err(Error::Parse(String::from("group is empty")));
+    }
+    let format = payload.format.as_deref().map(Format::parse).transpose()?;
+    let entries = playlist::parse(format, &payload.content)?;
+
+    let user_id = auth_user.user_product.user_id;
+    let imported = playlist::import(
+        &state.repo,
+        user_id,
+        &payload.group,
+        &payload.desc,
+        &entries,
+    )
+    .await?;
+    if imported.group_created || imported.favorites > 0 {
+        state.library_changed(&auth_user).await;
+    }
+
+    let rsp = PlaylistImportRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        group_created: imported.group_created,
+        favorites: imported.favorites,
+        unmatched: imported.unmatched,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,43 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+use chrono::Local;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    config::CONFIG,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    presence,
+    proto::{PresenceReq, PresenceRsp},
+    JsonRejection, JsonResult,
+};
+
+/// 正在播放的心跳，记录设备当前播放的电台
+#[debug_handler(state = AppState)]
+pub async fn presence(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<PresenceReq>,
+) -> JsonResult<PresenceRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    presence::heartbeat(
+        &state.repo,
+        user_product.user_id,
+        &auth_user.token,
+        &payload,
+        Local::now().timestamp(),
+        CONFIG.presence_ttl,
+    )
+    .await?;
+
+    let rsp = PresenceRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        ttl: CONFIG.presence_ttl,
+    };
+
+    ok_with_trace(rsp)
+}
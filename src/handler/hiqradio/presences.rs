@@ -0,0 +1,32 @@
+use axum::{debug_handler, extract::State};
+use chrono::Local;
+
+use crate::{
+    app_state::AppState, auth_user::AuthUser, errors::E_SUCCESS, handler::ok_with_trace, presence,
+    proto::PresencesRsp, JsonResult,
+};
+
+/// 查询自己正在播放的设备
+#[debug_handler(state = AppState)]
+pub async fn presences(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> JsonResult<PresencesRsp> {
+    let user_product = &auth_user.user_product;
+    let (presences, stations) = presence::presences(
+        &state.repo,
+        user_product.user_id,
+        &auth_user.token,
+        Local::now().timestamp(),
+    )
+    .await?;
+
+    let rsp = PresencesRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        presences,
+        stations,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -1,20 +1,62 @@
-use axum::{debug_handler, extract::State};
+use axum::{body::Bytes, debug_handler, extract::State};
 
 use crate::{
-    app_state::AppState, auth_user::AuthUser, errors::E_SUCCESS, handler::ok_with_trace,
-    proto::RecentlyRsp, JsonResult,
+    app_state::AppState,
+    auth_user::AuthUser,
+    catalog,
+    errors::E_SUCCESS,
+    handler::{ok_with_trace, optional_json},
+    proto::{RecentlyFilter, RecentlyReq, RecentlyRsp},
+    JsonResult,
 };
 #[debug_handler(state = AppState)]
 pub async fn recently(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    body: Bytes,
 ) -> JsonResult<RecentlyRsp> {
     let user_product = auth_user.user_product;
-    let recently = state.repo.query_recently(user_product.user_id).await?;
+
+    // 旧版本客户端不带请求体，返回全部记录
+    let Some(payload) = optional_json::<RecentlyReq>(&body)? else {
+        let recently = state.repo.query_recently(user_product.user_id).await?;
+        let rsp = RecentlyRsp {
+            error: E_SUCCESS,
+            message: "success".into(),
+            recently,
+            cursor: None,
+            stations: None,
+        };
+        return ok_with_trace(rsp);
+    };
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    // 多查一条判断是否还有下一页
+    let mut filter = RecentlyFilter::try_from(&payload)?;
+    let limit = filter.limit as usize;
+    filter.limit += 1;
+    let mut recently = state
+        .repo
+        .query_recently_page(user_product.user_id, &filter)
+        .await?;
+    let cursor = if recently.len() > limit {
+        recently.truncate(limit);
+        recently.last().map(RecentlyFilter::cursor)
+    } else {
+        None
+    };
+    let stations = if payload.with_station {
+        Some(catalog::stations(&state.repo, recently.iter().map(|r| &r.stationuuid)).await?)
+    } else {
+        None
+    };
+
     let rsp = RecentlyRsp {
         error: E_SUCCESS,
         message: "success".into(),
         recently,
+        cursor,
+        stations,
     };
 
     ok_with_trace(rsp)
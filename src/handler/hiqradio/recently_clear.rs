@@ -9,8 +9,10 @@ pub async fn recently_clear(
     State(state): State<AppState>,
     auth_user: AuthUser,
 ) -> JsonResult<BaseRsp> {
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state.repo.delete_recently(user_product.user_id).await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
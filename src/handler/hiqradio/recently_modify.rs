@@ -17,7 +17,7 @@ pub async fn recently_modify(
 ) -> JsonResult<BaseRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
 
-    let user_product = auth_user.user_product;
+    let user_product = &auth_user.user_product;
     state
         .repo
         .modify_recently(
@@ -27,6 +27,8 @@ pub async fn recently_modify(
             payload.end_time,
         )
         .await?;
+    state.library_changed(&auth_user).await;
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
@@ -12,11 +12,17 @@ pub async fn recently_new(
 ) -> JsonResult<BaseRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
 
-    let user_product = auth_user.user_product;
-    state
-        .repo
-        .new_recently(user_product.user_id, &payload.new_recently)
-        .await?;
+    // 用户关闭了最近播放时不记录
+    let user_product = &auth_user.user_product;
+    let setting = state.repo.query_setting(user_product.user_id).await?;
+    if setting.recently_enabled != 0 {
+        state
+            .repo
+            .new_recently(user_product.user_id, &payload.new_recently)
+            .await?;
+        state.library_changed(&auth_user).await;
+    }
+
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
@@ -0,0 +1,39 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+use chrono::Local;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    config::CONFIG,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{RecommendReq, RecommendRsp, RECOMMEND_PAGE_MAX, RECOMMEND_PAGE_SIZE},
+    recommend, JsonRejection, JsonResult,
+};
+#[debug_handler(state = AppState)]
+pub async fn recommend(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<RecommendReq>,
+) -> JsonResult<RecommendRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let limit = payload
+        .limit
+        .unwrap_or(RECOMMEND_PAGE_SIZE)
+        .clamp(1, RECOMMEND_PAGE_MAX);
+    let since = Local::now().timestamp() - CONFIG.recommend_recently_days * 86400;
+    let user_product = &auth_user.user_product;
+    let (recommendations, stations) =
+        recommend::recommend(&state.repo, user_product.user_id, since, limit as usize).await?;
+
+    let rsp = RecommendRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        recommendations,
+        stations,
+    };
+
+    ok_with_trace(rsp)
+}
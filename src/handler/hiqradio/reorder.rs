@@ -0,0 +1,32 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{ReorderReq, ReorderRsp},
+    repo::order,
+    JsonRejection, JsonResult,
+};
+#[debug_handler(state = AppState)]
+pub async fn reorder(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ReorderReq>,
+) -> JsonResult<ReorderRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    let order = order::reorder(&state.repo, user_product.user_id, &payload).await?;
+    state.library_changed(&auth_user).await;
+
+    let rsp = ReorderRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        order,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,41 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+use chrono::Local;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{ReviewReq, ReviewRsp},
+    review, JsonRejection, JsonResult,
+};
+
+/// 评分和评论电台，带内容的评论审核通过后公开
+#[debug_handler(state = AppState)]
+pub async fn review(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ReviewReq>,
+) -> JsonResult<ReviewRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    let review = review::review(
+        &state.repo,
+        user_product.user_id,
+        &payload.stationuuid,
+        payload.rating,
+        payload.content.as_deref().unwrap_or_default(),
+        Local::now().timestamp(),
+    )
+    .await?;
+
+    let rsp = ReviewRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        review,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,36 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{BaseRsp, ReviewDeleteReq},
+    JsonRejection, JsonResult,
+};
+
+/// 删除自己对电台的评论，没有评论时也返回成功
+#[debug_handler(state = AppState)]
+pub async fn review_delete(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ReviewDeleteReq>,
+) -> JsonResult<BaseRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    state
+        .repo
+        .delete_review(
+            user_product.user_id,
+            &payload.stationuuid.trim().to_ascii_lowercase(),
+        )
+        .await?;
+
+    let rsp = BaseRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+    };
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,30 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{BaseRsp, ReviewModerateReq},
+    review, JsonRejection, JsonResult,
+};
+
+/// 审核评论，只有审核员可以操作
+#[debug_handler(state = AppState)]
+pub async fn review_moderate(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ReviewModerateReq>,
+) -> JsonResult<BaseRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    review::check_moderator(&auth_user.user)?;
+    review::moderate(&state.repo, payload.id, &payload.status).await?;
+
+    let rsp = BaseRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+    };
+    ok_with_trace(rsp)
+}
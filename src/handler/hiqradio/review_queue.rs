@@ -0,0 +1,34 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{ReviewQueueRsp, ReviewsReq},
+    review, JsonRejection, JsonResult,
+};
+
+/// 待审核的评论，只有审核员可以查询
+#[debug_handler(state = AppState)]
+pub async fn review_queue(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ReviewsReq>,
+) -> JsonResult<ReviewQueueRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    review::check_moderator(&auth_user.user)?;
+    let (offset, limit) = review::page(&payload)?;
+    let (reviews, offset) = review::queue(&state.repo, offset, limit).await?;
+
+    let rsp = ReviewQueueRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        reviews,
+        offset,
+    };
+
+    ok_with_trace(rsp)
+}
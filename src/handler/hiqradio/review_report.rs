@@ -0,0 +1,38 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+use chrono::Local;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{BaseRsp, ReviewReportReq},
+    review, JsonRejection, JsonResult,
+};
+
+/// 举报评论，同一用户重复举报只计一次
+#[debug_handler(state = AppState)]
+pub async fn review_report(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ReviewReportReq>,
+) -> JsonResult<BaseRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    review::report(
+        &state.repo,
+        user_product.user_id,
+        payload.id,
+        payload.reason.as_deref().unwrap_or_default(),
+        Local::now().timestamp(),
+    )
+    .await?;
+
+    let rsp = BaseRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+    };
+    ok_with_trace(rsp)
+}
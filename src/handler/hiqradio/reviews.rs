@@ -0,0 +1,39 @@
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Path, Query, State},
+};
+
+use crate::{
+    app_state::AppState,
+    errors::{Error, E_SUCCESS},
+    handler::ok_with_trace,
+    proto::{ReviewsReq, ReviewsRsp},
+    review, JsonResult,
+};
+
+/// 电台的评分和审核通过的评论，不需要登录
+#[debug_handler(state = AppState)]
+pub async fn reviews(
+    State(state): State<AppState>,
+    Path(stationuuid): Path<String>,
+    payload: std::result::Result<Query<ReviewsReq>, QueryRejection>,
+) -> JsonResult<ReviewsRsp> {
+    let Query(payload) = payload.map_err(|e| Error::Parse(e.body_text()))?;
+    tracing::info!("\nreq: {} {:?}\n", &stationuuid, &payload);
+
+    let (offset, limit) = review::page(&payload)?;
+    let (rating, reviews, offset) =
+        review::reviews(&state.repo, &stationuuid, offset, limit).await?;
+
+    let rsp = ReviewsRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        stationuuid: rating.stationuuid,
+        rating: rating.rating,
+        ratings: rating.ratings,
+        reviews,
+        offset,
+    };
+
+    ok_with_trace(rsp)
+}
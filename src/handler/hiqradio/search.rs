@@ -0,0 +1,33 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    catalog,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{SearchReq, SearchRsp, StationFilter},
+    JsonRejection, JsonResult,
+};
+#[debug_handler(state = AppState)]
+pub async fn search(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<SearchReq>,
+) -> JsonResult<SearchRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let filter = StationFilter::try_from(&payload)?;
+    let user_product = &auth_user.user_product;
+    let (stations, offset) = catalog::search(&state.repo, user_product.user_id, &filter).await?;
+
+    let rsp = SearchRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        stations,
+        offset,
+    };
+
+    ok_with_trace(rsp)
+}
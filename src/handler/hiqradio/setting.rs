@@ -0,0 +1,19 @@
+use axum::{debug_handler, extract::State};
+
+use crate::{
+    app_state::AppState, auth_user::AuthUser, errors::E_SUCCESS, handler::ok_with_trace,
+    proto::SettingRsp, JsonResult,
+};
+#[debug_handler(state = AppState)]
+pub async fn setting(State(state): State<AppState>, auth_user: AuthUser) -> JsonResult<SettingRsp> {
+    let user_product = &auth_user.user_product;
+    let setting = state.repo.query_setting(user_product.user_id).await?;
+
+    let rsp = SettingRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        setting,
+    };
+
+    ok_with_trace(rsp)
+}
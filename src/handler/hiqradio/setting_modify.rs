@@ -0,0 +1,44 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::{Error, E_SUCCESS},
+    handler::ok_with_trace,
+    proto::{SettingModifyReq, SettingRsp},
+    JsonRejection, JsonResult,
+};
+#[debug_handler(state = AppState)]
+pub async fn setting_modify(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<SettingModifyReq>,
+) -> JsonResult<SettingRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    let mut setting = state.repo.query_setting(user_product.user_id).await?;
+    if let Some(enabled) = payload.recently_enabled {
+        setting.recently_enabled = if enabled != 0 { 1 } else { 0 };
+    }
+    if let Some(days) = payload.recently_days {
+        if days < 0 {
+            return Err(Error::Parse(format!("recently_days {}", days)));
+        }
+        setting.recently_days = days;
+    }
+    // 关闭最近播放时清空已有记录，其他设备同步时一并清空
+    if state.repo.modify_setting(&setting).await? {
+        state.library_changed(&auth_user).await;
+    }
+
+    let setting = state.repo.query_setting(user_product.user_id).await?;
+    let rsp = SettingRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        setting,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,32 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{ShareReq, ShareRsp},
+    share, JsonRejection, JsonResult,
+};
+
+/// 分享分组，返回分享的token
+#[debug_handler(state = AppState)]
+pub async fn share(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ShareReq>,
+) -> JsonResult<ShareRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    let share = share::share(&state.repo, user_product.user_id, &payload.group).await?;
+
+    let rsp = ShareRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        share,
+    };
+
+    ok_with_trace(rsp)
+}
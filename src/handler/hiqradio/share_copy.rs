@@ -0,0 +1,45 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{ShareCopyReq, ShareCopyRsp},
+    share, JsonRejection, JsonResult,
+};
+
+/// 复制分享的分组到自己的收藏
+#[debug_handler(state = AppState)]
+pub async fn share_copy(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ShareCopyReq>,
+) -> JsonResult<ShareCopyRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_id = auth_user.user_product.user_id;
+    let group = payload.group.as_deref().filter(|g| !g.is_empty());
+    let (group, imported) = share::copy(
+        &state.repo,
+        user_id,
+        &payload.token,
+        group,
+        payload.desc.as_deref(),
+    )
+    .await?;
+    if imported.group_created || imported.favorites > 0 {
+        state.library_changed(&auth_user).await;
+    }
+
+    let rsp = ShareCopyRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        group,
+        group_created: imported.group_created,
+        favorites: imported.favorites,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,33 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{BaseRsp, ShareReq},
+    JsonRejection, JsonResult,
+};
+
+/// 取消分享，原来的token失效，没有分享时也返回成功
+#[debug_handler(state = AppState)]
+pub async fn share_revoke(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<ShareReq>,
+) -> JsonResult<BaseRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let user_product = &auth_user.user_product;
+    state
+        .repo
+        .delete_share(user_product.user_id, &payload.group)
+        .await?;
+
+    let rsp = BaseRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+    };
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,31 @@
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use crate::{
+    app_state::AppState, errors::E_SUCCESS, handler::ok_with_trace, proto::SharedRsp, share,
+    JsonResult,
+};
+
+/// 查询分享的分组，不需要登录
+#[debug_handler(state = AppState)]
+pub async fn shared(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> JsonResult<SharedRsp> {
+    tracing::info!("\nreq: shared: {}\n", token);
+
+    let shared = share::shared(&state.repo, &token).await?;
+
+    let rsp = SharedRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        name: shared.name,
+        desc: shared.desc,
+        favorites: shared.favorites,
+        stations: shared.stations,
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,21 @@
+use axum::{debug_handler, extract::State};
+
+use crate::{
+    app_state::AppState, auth_user::AuthUser, errors::E_SUCCESS, handler::ok_with_trace,
+    proto::SharesRsp, JsonResult,
+};
+
+/// 查询自己分享的分组
+#[debug_handler(state = AppState)]
+pub async fn shares(State(state): State<AppState>, auth_user: AuthUser) -> JsonResult<SharesRsp> {
+    let user_product = &auth_user.user_product;
+    let shares = state.repo.query_shares(user_product.user_id).await?;
+
+    let rsp = SharesRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        shares,
+    };
+
+    ok_with_trace(rsp)
+}
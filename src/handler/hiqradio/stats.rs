@@ -0,0 +1,47 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+use chrono::{FixedOffset, Local, Offset};
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::{Error, E_SUCCESS},
+    handler::ok_with_trace,
+    proto::{StatsReq, StatsRsp, STATS_TOP, STATS_TOP_MAX},
+    repo::stats,
+    JsonRejection, JsonResult,
+};
+#[debug_handler(state = AppState)]
+pub async fn stats(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<StatsReq>,
+) -> JsonResult<StatsRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let offset = match payload.tz_offset {
+        Some(minutes) => FixedOffset::east_opt(minutes * 60)
+            .ok_or_else(|| Error::Parse(format!("tz_offset {}", minutes)))?,
+        None => Local::now().offset().fix(),
+    };
+    let top = payload.top.unwrap_or(STATS_TOP).clamp(1, STATS_TOP_MAX);
+
+    let user_product = &auth_user.user_product;
+    let stats = stats::listening_stats(
+        &state.repo,
+        user_product.user_id,
+        payload.since,
+        payload.until,
+        offset,
+        top,
+    )
+    .await?;
+
+    let rsp = StatsRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        stats,
+    };
+
+    ok_with_trace(rsp)
+}
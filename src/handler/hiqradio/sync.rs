@@ -1,6 +1,7 @@
 use crate::{
     app_state::AppState,
     auth_user::AuthUser,
+    catalog,
     errors::E_SUCCESS,
     handler::ok_with_trace,
     proto::{SyncReq, SyncRsp},
@@ -8,18 +9,39 @@ use crate::{
 };
 use axum::{debug_handler, extract::State, Json};
 use axum_extra::extract::WithRejection;
+use chrono::Local;
 #[debug_handler(state = AppState)]
 pub async fn sync(
     State(state): State<AppState>,
     auth_user: AuthUser,
-    WithRejection(Json(payload) , _): JsonRejection<SyncReq>,
+    WithRejection(Json(payload), _): JsonRejection<SyncReq>,
 ) -> JsonResult<SyncRsp> {
     tracing::info!("\nreq: {:?}\n", &payload);
     let user_product = auth_user.user_product;
+    let revision = state.repo.query_revision(user_product.user_id).await?;
     let (groups, recently, favorites) = state
         .repo
-        .query_sync(user_product.user_id, -1)
+        .query_sync(user_product.user_id, payload.revision, revision)
         .await?;
+    // 首次同步客户端没有数据，不需要删除记录
+    let tombstones = if payload.revision > 0 {
+        state
+            .repo
+            .query_tombstones(user_product.user_id, payload.revision, revision)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    let stations = if payload.with_station {
+        let stationuuids = recently
+            .iter()
+            .map(|r| &r.stationuuid)
+            .chain(favorites.iter().map(|f| &f.stationuuid));
+        Some(catalog::stations(&state.repo, stationuuids).await?)
+    } else {
+        None
+    };
 
     let rsp = SyncRsp {
         error: E_SUCCESS,
@@ -27,6 +49,10 @@ pub async fn sync(
         groups,
         recently,
         favorites,
+        tombstones,
+        revision,
+        server_time: Local::now().timestamp(),
+        stations,
     };
 
     ok_with_trace(rsp)
@@ -0,0 +1,59 @@
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{SyncPushReq, SyncPushRsp, SYNC_OP_APPLIED},
+    repo::merge,
+    JsonRejection, JsonResult,
+};
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+use chrono::Local;
+#[debug_handler(state = AppState)]
+pub async fn sync_push(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<SyncPushReq>,
+) -> JsonResult<SyncPushRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+    let user_product = &auth_user.user_product;
+    let (revision, results) = merge::push_sync(
+        &state.repo,
+        user_product.user_id,
+        payload.revision,
+        &payload.ops,
+    )
+    .await?;
+    if results.contains(&SYNC_OP_APPLIED) {
+        state.library_changed(&auth_user).await;
+    }
+
+    // 返回合并后客户端需要的全部修改，包括本次应用的操作
+    let (groups, recently, favorites) = state
+        .repo
+        .query_sync(user_product.user_id, payload.revision, revision)
+        .await?;
+    let tombstones = if payload.revision > 0 {
+        state
+            .repo
+            .query_tombstones(user_product.user_id, payload.revision, revision)
+            .await?
+    } else {
+        Vec::new()
+    };
+
+    let rsp = SyncPushRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        results: results.into_iter().map(String::from).collect(),
+        groups,
+        recently,
+        favorites,
+        tombstones,
+        revision,
+        server_time: Local::now().timestamp(),
+    };
+
+    ok_with_trace(rsp)
+}
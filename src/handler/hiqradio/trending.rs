@@ -0,0 +1,47 @@
+use axum::{
+    debug_handler,
+    extract::{rejection::QueryRejection, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+
+use crate::{
+    app_state::AppState,
+    errors::{Error, E_SUCCESS},
+    handler::ok_with_trace,
+    proto::{TrendingReq, TrendingRsp, TRENDING_MAX_AGE, TRENDING_PAGE_MAX, TRENDING_PAGE_SIZE},
+    trending, Result,
+};
+
+/// 本站用户的热门电台，不需要登录，响应可以被缓存
+#[debug_handler(state = AppState)]
+pub async fn trending(
+    State(state): State<AppState>,
+    payload: std::result::Result<Query<TrendingReq>, QueryRejection>,
+) -> Result<impl IntoResponse> {
+    let Query(payload) = payload.map_err(|e| Error::Parse(e.body_text()))?;
+    tracing::info!("\nreq: {:?}\n", &payload);
+
+    let period = payload.period.unwrap_or_else(|| String::from("week"));
+    let limit = payload
+        .limit
+        .unwrap_or(TRENDING_PAGE_SIZE)
+        .clamp(1, TRENDING_PAGE_MAX);
+    let (trending, stations) = trending::query(&state.repo, &period, limit).await?;
+
+    let rsp = TrendingRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        period,
+        trending,
+        stations,
+    };
+
+    Ok((
+        [(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", TRENDING_MAX_AGE),
+        )],
+        ok_with_trace(rsp)?,
+    ))
+}
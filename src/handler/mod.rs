@@ -9,10 +9,20 @@ pub mod user;
 
 pub mod hiqradio;
 
-pub const COOKIE_NAME: &'static str = "SESSION";
+pub const COOKIE_NAME: &str = "SESSION";
 
 pub fn ok_with_trace<T: core::fmt::Debug>(rsp: T) -> crate::Result<axum::Json<T>> {
     tracing::info!("\nrsp: {:?}\n", rsp);
 
     Ok(axum::Json(rsp))
 }
+
+/// 解析可选的json请求体，旧版本客户端不带请求体时返回None
+pub fn optional_json<T: serde::de::DeserializeOwned>(body: &[u8]) -> crate::Result<Option<T>> {
+    if body.iter().all(|b| b.is_ascii_whitespace()) {
+        return Ok(None);
+    }
+    serde_json::from_slice(body)
+        .map(Some)
+        .map_err(|e| crate::errors::Error::Parse(e.to_string()))
+}
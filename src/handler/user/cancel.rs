@@ -0,0 +1,81 @@
+use async_session::SessionStore;
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::{extract::WithRejection, headers, TypedHeader};
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::{Error, E_SUCCESS},
+    handler::{ok_with_trace, COOKIE_NAME},
+    proto::{BaseRsp, CancelReq},
+    util::gen_passwd,
+    JsonRejection, JsonResult,
+};
+
+/// 注销账号，需再次验证密码或邮箱验证码。
+/// 注销后账号立即不可用，分享和评论不再公开，数据在`cancel_grace_days`天后清理
+#[debug_handler(state = AppState)]
+pub async fn cancel(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    cookies: Option<TypedHeader<headers::Cookie>>,
+    WithRejection(Json(payload), _): JsonRejection<CancelReq>,
+) -> JsonResult<BaseRsp> {
+    let user = auth_user.user;
+    tracing::info!("\nreq: cancel user {}\n", user.email);
+
+    match (&payload.passwd, &payload.captcha, &payload.code) {
+        (Some(passwd), _, _) if !passwd.is_empty() => {
+            if gen_passwd(&user.email, passwd) != user.passwd {
+                return Err(Error::UserPasswdError);
+            }
+        }
+        (_, Some(captcha), Some(code)) if !captcha.is_empty() && !code.is_empty() => {
+            let TypedHeader(cookies) = cookies.ok_or(Error::Captcha)?;
+            let cookie = cookies.get(COOKIE_NAME).ok_or(Error::Captcha)?;
+
+            let session = state
+                .store
+                .load_session(cookie.to_string())
+                .await
+                .map_err(|_| Error::Captcha)?
+                .ok_or(Error::Captcha)?;
+
+            let session_captcha: String = session.get("captcha").ok_or(Error::Captcha)?;
+            let session_code: String = session.get("code").ok_or(Error::Captcha)?;
+            let email: String = session.get("email").ok_or(Error::Captcha)?;
+
+            if session_captcha.to_lowercase() != captcha.to_lowercase() {
+                return Err(Error::Captcha);
+            }
+            if session_code.to_lowercase() != code.to_lowercase() {
+                return Err(Error::EmailVerifyCode);
+            }
+            if email != user.email {
+                return Err(Error::EmailDiff);
+            }
+
+            state
+                .store
+                .destroy_session(session)
+                .await
+                .map_err(|e| Error::Internal(format!("destroy session error: {}", e)))?;
+        }
+        _ => {
+            return Err(Error::Parse(String::from(
+                "password or email code is required",
+            )))
+        }
+    }
+
+    state.repo.cancel_user(user.id.unwrap()).await?;
+    // 会话全部失效，数据在宽限期后清理时已没有订阅
+    state.notifier.close(user.id.unwrap(), None);
+
+    let rsp = BaseRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+    };
+
+    ok_with_trace(rsp)
+}
@@ -0,0 +1,37 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    model::product::PRODUCT_HIQRADIO,
+    proto::{BaseRsp, CloseProductReq},
+    JsonRejection, JsonResult,
+};
+
+/// 关闭产品，该产品的会话全部失效，再次open_product时恢复
+#[debug_handler(state = AppState)]
+pub async fn close_product(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<CloseProductReq>,
+) -> JsonResult<BaseRsp> {
+    tracing::info!("\nreq: {:?}\n", &payload);
+    let user_id = auth_user.user_product.user_id;
+    state
+        .repo
+        .close_product(user_id, &payload.product, payload.purge)
+        .await?;
+    // 只有hiqradio有数据修改通知，会话已全部失效，清理数据也不再通知修改
+    if payload.product == PRODUCT_HIQRADIO {
+        state.notifier.close(user_id, None);
+    }
+    let rsp = BaseRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+    };
+
+    ok_with_trace(rsp)
+}
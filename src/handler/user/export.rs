@@ -0,0 +1,31 @@
+use axum::{
+    debug_handler,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Local;
+
+use crate::{app_state::AppState, auth_user::AuthUser, repo::archive, Result};
+
+/// 导出账号数据，以附件形式下载，格式见`Archive`
+#[debug_handler(state = AppState)]
+pub async fn export(State(state): State<AppState>, auth_user: AuthUser) -> Result<Response> {
+    let user_id = auth_user.user_product.user_id;
+    let archive = archive::export(&state.repo, user_id).await?;
+    tracing::info!(
+        "export user: {}, products: {}, groups: {}, favorites: {}, recently: {}",
+        user_id,
+        archive.products.len(),
+        archive.hiqradio.groups.len(),
+        archive.hiqradio.favorites.len(),
+        archive.hiqradio.recently.len()
+    );
+
+    let disposition = format!(
+        "attachment; filename=\"appserv-archive-{}.json\"",
+        Local::now().format("%Y%m%d")
+    );
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(archive)).into_response())
+}
@@ -0,0 +1,45 @@
+use axum::{debug_handler, extract::State, Json};
+use axum_extra::extract::WithRejection;
+
+use crate::{
+    app_state::AppState,
+    auth_user::AuthUser,
+    errors::E_SUCCESS,
+    handler::ok_with_trace,
+    proto::{Archive, ImportRsp},
+    repo::archive,
+    JsonRejection, JsonResult,
+};
+
+/// 导入export导出的归档，与账号已有数据合并
+#[debug_handler(state = AppState)]
+pub async fn import(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    WithRejection(Json(payload), _): JsonRejection<Archive>,
+) -> JsonResult<ImportRsp> {
+    tracing::info!(
+        "\nreq: import archive of {}, version: {}, export time: {}\n",
+        payload.user.email,
+        payload.version,
+        payload.export_time
+    );
+
+    let user_id = auth_user.user_product.user_id;
+    let imported = archive::import(&state.repo, user_id, &payload).await?;
+    if imported.library_changed() {
+        state.library_changed(&auth_user).await;
+    }
+
+    let rsp = ImportRsp {
+        error: E_SUCCESS,
+        message: "success".into(),
+        products: imported.products,
+        avatars: imported.avatars,
+        groups: imported.groups,
+        favorites: imported.favorites,
+        recently: imported.recently,
+    };
+
+    ok_with_trace(rsp)
+}
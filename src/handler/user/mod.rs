@@ -25,8 +25,20 @@ pub use modify::modify;
 mod open_product;
 pub use open_product::open_product;
 
+mod close_product;
+pub use close_product::close_product;
+
 mod reset_passwd;
 pub use reset_passwd::reset_passwd;
 
 mod is_login;
 pub use is_login::is_login;
+
+mod export;
+pub use export::export;
+
+mod import;
+pub use import::import;
+
+mod cancel;
+pub use cancel::cancel;
@@ -29,7 +29,7 @@ pub async fn modify(
 
         let user = auth_user.user;
         let old_pass = gen_passwd(&user.email, &payload.password.unwrap());
-        let new_pass_tmp = gen_passwd(&user.email, &password);
+        let new_pass_tmp = gen_passwd(&user.email, password);
         if old_pass != new_pass_tmp {
             return Err(Error::UserPasswdError);
         }
@@ -13,7 +13,7 @@ pub async fn products(
     let rsp = ProductsRsp {
         error: E_SUCCESS,
         message: "success".into(),
-        products: products,
+        products,
     };
 
     ok_with_trace(rsp)
@@ -8,6 +8,9 @@ use crate::{
 #[debug_handler(state = AppState)]
 pub async fn signout(State(state): State<AppState>, auth_user: AuthUser) -> JsonResult<BaseRsp> {
     state.repo.delete_session(&auth_user.token).await?;
+    state
+        .notifier
+        .close(auth_user.user_product.user_id, Some(&auth_user.token));
     let rsp = BaseRsp {
         error: E_SUCCESS,
         message: "success".into(),
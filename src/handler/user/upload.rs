@@ -21,21 +21,21 @@ pub async fn upload(
     mut multipart: Multipart,
 ) -> JsonResult<UploadRsp> {
     let mut avatar_path = String::from("");
-    while let Some(field) = multipart.next_field().await.unwrap() {
+    if let Some(field) = multipart.next_field().await.unwrap() {
         let file_name = field
             .file_name()
             .ok_or(Error::Internal(String::from("fail to get file name")))?
             .to_string();
         let data = field.bytes().await.map_err(|e| {
-            Error::Internal(format!("fail to get file name, error: {}", e.to_string()))
+            Error::Internal(format!("fail to get file name, error: {}", e))
         })?;
 
         let file_ext: Vec<_> = file_name.split(".").collect();
 
         let mut ext = String::from("");
         if let Some(f_ext) = file_ext.last() {
-            ext.push_str(".");
-            ext.push_str(*&f_ext);
+            ext.push('.');
+            ext.push_str(f_ext);
         }
         let alphabet: [char; 16] = [
             '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f',
@@ -50,11 +50,10 @@ pub async fn upload(
             Error::Internal(format!(
                 "fail to write file: {}, error: {}",
                 path_str,
-                e.to_string()
+                e
             ))
         })?;
         avatar_path = file_name;
-        break;
     }
 
     let rsp = UploadRsp {
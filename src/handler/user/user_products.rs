@@ -14,7 +14,7 @@ pub async fn user_products(
     let rsp = ProductsRsp {
         error: E_SUCCESS,
         message: "success".into(),
-        products: products,
+        products,
     };
 
     ok_with_trace(rsp)
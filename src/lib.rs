@@ -2,12 +2,21 @@ use axum::Json;
 
 pub mod app_router;
 pub mod app_state;
+pub mod backup;
+pub mod catalog;
 pub mod config;
 pub mod errors;
 pub mod handler;
 pub mod model;
+pub mod notify;
+pub mod playlist;
+pub mod presence;
 pub mod proto;
+pub mod recommend;
 pub mod repo;
+pub mod review;
+pub mod share;
+pub mod trending;
 pub mod util;
 
 pub mod auth_user;
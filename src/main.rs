@@ -1,6 +1,6 @@
 use std::{fs, time::Duration};
 
-use appserv::{app_router::app_router, app_state::AppState, config::CONFIG};
+use appserv::{app_router::app_router, app_state::AppState, config::CONFIG, repo};
 use chrono::{Datelike, Days, Local, TimeZone};
 use tokio::{net::TcpListener, signal};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -26,6 +26,147 @@ async fn main() {
         )
         .init();
 
+    // appserv [config.toml] [serve|migrate|backup|restore <backup.db>|import-stations <dump>|recommend|trending]
+    let command = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| String::from("serve"));
+    match command.as_str() {
+        "serve" => serve().await,
+        "migrate" => migrate().await,
+        "backup" => backup().await,
+        "restore" => restore().await,
+        "import-stations" => import_stations().await,
+        "recommend" => recommend().await,
+        "trending" => trending().await,
+        _ => {
+            tracing::error!(
+                "unknown command \"{}\", usage: appserv [config.toml] [serve|migrate|backup|restore <backup.db>|import-stations <dump>|recommend|trending]",
+                command
+            );
+            std::process::exit(-1);
+        }
+    }
+}
+
+async fn migrate() {
+    let repo = repo::connect(&CONFIG.db_url).await;
+    if let Err(e) = &repo {
+        tracing::error!("connect database error: {}", e);
+        std::process::exit(-1);
+    }
+    let repo = repo.unwrap();
+    if let Err(e) = repo.migrate().await {
+        tracing::error!("migrate error: {}", e);
+        std::process::exit(-1);
+    }
+    match repo.schema_version().await {
+        Ok(version) => tracing::info!("migrate done, schema version: {}", version),
+        Err(e) => tracing::error!("query schema version error: {}", e),
+    }
+}
+
+async fn backup() {
+    let repo = repo::connect(&CONFIG.db_url).await;
+    if let Err(e) = &repo {
+        tracing::error!("connect database error: {}", e);
+        std::process::exit(-1);
+    }
+    let repo = repo.unwrap();
+    match appserv::backup::backup(
+        &repo,
+        &CONFIG.db_url,
+        &CONFIG.backup_path,
+        CONFIG.backup_keep,
+    )
+    .await
+    {
+        Ok(path) => tracing::info!("backup done: {}", path.display()),
+        Err(e) => {
+            tracing::error!("backup error: {}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+/// 恢复前需先停止服务
+async fn restore() {
+    let file = std::env::args().nth(3);
+    if file.is_none() {
+        tracing::error!("usage: appserv config.toml restore <backup.db>");
+        std::process::exit(-1);
+    }
+    match appserv::backup::restore(&CONFIG.db_url, &file.unwrap()).await {
+        Ok(version) => tracing::info!("restore done, schema version: {}", version),
+        Err(e) => {
+            tracing::error!("restore error: {}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+/// 导入radio-browser导出的电台数据，json或csv
+async fn import_stations() {
+    let file = std::env::args().nth(3);
+    if file.is_none() {
+        tracing::error!("usage: appserv config.toml import-stations <stations.json|stations.csv>");
+        std::process::exit(-1);
+    }
+    let repo = repo::connect(&CONFIG.db_url).await;
+    if let Err(e) = &repo {
+        tracing::error!("connect database error: {}", e);
+        std::process::exit(-1);
+    }
+    let repo = repo.unwrap();
+    match appserv::catalog::import_file(&repo, std::path::Path::new(&file.unwrap())).await {
+        Ok(imported) => tracing::info!(
+            "import stations done, stations: {}, deleted: {}",
+            imported.stations,
+            imported.deleted
+        ),
+        Err(e) => {
+            tracing::error!("import stations error: {}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+/// 立即重建相似电台
+async fn recommend() {
+    let repo = repo::connect(&CONFIG.db_url).await;
+    if let Err(e) = &repo {
+        tracing::error!("connect database error: {}", e);
+        std::process::exit(-1);
+    }
+    let repo = repo.unwrap();
+    let now = Local::now().timestamp();
+    let since = now - CONFIG.recommend_recently_days * 24 * 3600;
+    match appserv::recommend::build(&repo, since, now).await {
+        Ok(count) => tracing::info!("build recommend done, similar: {}", count),
+        Err(e) => {
+            tracing::error!("build recommend error: {}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+/// 立即重建热门电台
+async fn trending() {
+    let repo = repo::connect(&CONFIG.db_url).await;
+    if let Err(e) = &repo {
+        tracing::error!("connect database error: {}", e);
+        std::process::exit(-1);
+    }
+    let repo = repo.unwrap();
+    match appserv::trending::build(&repo, Local::now().timestamp()).await {
+        Ok(counts) => tracing::info!("build trending done, stations: {:?}", counts),
+        Err(e) => {
+            tracing::error!("build trending error: {}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+async fn serve() {
     let state = AppState::new().await;
     if state.is_err() {
         tracing::error!("create app state error: {}", state.err().unwrap());
@@ -77,16 +218,22 @@ async fn main() {
         loop {
             let now = Local::now().timestamp();
             if now >= start_time {
+                // 清理过了宽限期的注销账号，清空的头像文件接着被清理
+                tracing::info!("purge cancelled users..");
+                let before = now - CONFIG.cancel_grace_days * 24 * 3600;
+                match repo.purge_cancelled_users(before).await {
+                    Ok(count) => tracing::info!("purge {} cancelled users", count),
+                    Err(e) => tracing::error!("purge cancelled users error: {}", e),
+                }
+
                 // 清理文件
                 tracing::info!("clean avatar file..");
                 if let Ok(read_dir) = fs::read_dir(&CONFIG.avatar_path) {
-                    for e in read_dir {
-                        if let Ok(entry) = e {
-                            if entry.path().is_file() {
-                                if let Some(file_name) = entry.file_name().to_str() {
-                                    if let Err(e) = repo.clean_avatar_path(file_name).await {
-                                        tracing::error!("clean avatar error: {}", e);
-                                    }
+                    for entry in read_dir.flatten() {
+                        if entry.path().is_file() {
+                            if let Some(file_name) = entry.file_name().to_str() {
+                                if let Err(e) = repo.clean_avatar_path(file_name).await {
+                                    tracing::error!("clean avatar error: {}", e);
                                 }
                             }
                         }
@@ -99,6 +246,39 @@ async fn main() {
                     tracing::error!("clean avatar error: {}", e);
                 }
 
+                // 清理过期的播放状态
+                tracing::info!("clean presence..");
+                match repo.clean_presence(now).await {
+                    Ok(count) => tracing::info!("clean {} presence", count),
+                    Err(e) => tracing::error!("clean presence error: {}", e),
+                }
+
+                // 按保留策略清理最近播放
+                tracing::info!("clean recently..");
+                match repo
+                    .clean_recently(now, CONFIG.recently_max_days, CONFIG.recently_max_rows)
+                    .await
+                {
+                    Ok(count) => tracing::info!("clean {} recently", count),
+                    Err(e) => tracing::error!("clean recently error: {}", e),
+                }
+
+                // 备份数据库，只支持sqlite
+                if CONFIG.backup_keep > 0 && CONFIG.db_url.starts_with("sqlite://") {
+                    tracing::info!("backup database..");
+                    match appserv::backup::backup(
+                        &repo,
+                        &CONFIG.db_url,
+                        &CONFIG.backup_path,
+                        CONFIG.backup_keep,
+                    )
+                    .await
+                    {
+                        Ok(path) => tracing::info!("backup database to {}", path.display()),
+                        Err(e) => tracing::error!("backup database error: {}", e),
+                    }
+                }
+
                 start_time = start_time_fn();
             }
 
@@ -106,6 +286,40 @@ async fn main() {
         }
     });
 
+    if CONFIG.recommend_interval > 0 {
+        let repo = state.repo.clone();
+        tokio::spawn(async move {
+            tracing::info!("recommend task");
+            loop {
+                // 重建相似电台
+                let now = Local::now().timestamp();
+                let since = now - CONFIG.recommend_recently_days * 24 * 3600;
+                match appserv::recommend::build(&repo, since, now).await {
+                    Ok(count) => tracing::info!("build {} similar stations", count),
+                    Err(e) => tracing::error!("build recommend error: {}", e),
+                }
+
+                tokio::time::sleep(Duration::from_secs(CONFIG.recommend_interval)).await;
+            }
+        });
+    }
+
+    if CONFIG.trending_interval > 0 {
+        let repo = state.repo.clone();
+        tokio::spawn(async move {
+            tracing::info!("trending task");
+            loop {
+                // 重建热门电台
+                match appserv::trending::build(&repo, Local::now().timestamp()).await {
+                    Ok(counts) => tracing::info!("build trending stations: {:?}", counts),
+                    Err(e) => tracing::error!("build trending error: {}", e),
+                }
+
+                tokio::time::sleep(Duration::from_secs(CONFIG.trending_interval)).await;
+            }
+        });
+    }
+
     let app = app_router(state);
 
     tracing::debug!("debug..");
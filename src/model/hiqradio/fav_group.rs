@@ -8,4 +8,8 @@ pub struct FavGroup {
     pub name: String,
     pub desc: String,
     pub is_def: i64,
+    /// 分组的顺序，从0开始
+    #[serde(default)]
+    #[sqlx(default)]
+    pub position: i64,
 }
@@ -7,4 +7,8 @@ pub struct Favorite {
     pub stationuuid: String,
     pub group_id: i64,
     pub create_time: i64,
+    /// 在分组中的顺序，从0开始
+    #[serde(default)]
+    #[sqlx(default)]
+    pub position: i64,
 }
@@ -8,4 +8,28 @@ mod favorite;
 pub use favorite::Favorite;
 
 mod station_group;
-pub use station_group::StationGroup;
\ No newline at end of file
+pub use station_group::StationGroup;
+
+mod tombstone;
+pub use tombstone::*;
+
+mod setting;
+pub use setting::Setting;
+
+mod station;
+pub use station::Station;
+
+mod share;
+pub use share::Share;
+
+mod similar;
+pub use similar::{Similar, UserStation};
+
+mod trending;
+pub use trending::{StationListen, TrendingStation};
+
+mod review;
+pub use review::*;
+
+mod presence;
+pub use presence::Presence;
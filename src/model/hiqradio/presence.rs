@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+/// 设备正在播放的电台，每个用户的每个设备一条
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Presence {
+    #[serde(skip_serializing)]
+    pub user_id: i64,
+    pub device_id: String,
+    pub device_name: String,
+    /// 最近一次心跳的会话
+    #[serde(skip_serializing)]
+    pub token: String,
+    pub stationuuid: String,
+    /// 开始播放这个电台的时间
+    pub start_time: i64,
+    /// 最近一次心跳的时间
+    pub update_time: i64,
+    /// 过期时间，过期后视为不在线
+    pub expire: i64,
+    /// 是否是查询者自己的会话，查询时填充
+    #[sqlx(skip)]
+    pub current: bool,
+}
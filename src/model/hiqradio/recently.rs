@@ -8,3 +8,12 @@ pub struct Recently {
     pub start_time: i64,
     pub end_time: Option<i64>,
 }
+
+impl Recently {
+    /// 播放时长(秒)，未结束的记录为0
+    pub fn duration(&self) -> i64 {
+        self.end_time
+            .map(|end_time| (end_time - self.start_time).max(0))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+/// 待审核
+pub const REVIEW_STATUS_PENDING: &str = "pending";
+/// 审核通过，公开显示并计入平均评分
+pub const REVIEW_STATUS_APPROVED: &str = "approved";
+/// 审核拒绝
+pub const REVIEW_STATUS_REJECTED: &str = "rejected";
+
+/// 用户对电台的评分和评论，每个用户对每个电台一条
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Review {
+    pub id: Option<i64>,
+    #[serde(skip_serializing)]
+    pub user_id: i64,
+    /// 评论者的用户名，查询时填充；没有修改过注册时默认的用户名时为空
+    #[sqlx(default)]
+    pub user_name: String,
+    pub stationuuid: String,
+    /// 1到5星
+    pub rating: i64,
+    /// 为空时只评分，不需要审核
+    pub content: String,
+    pub status: String,
+    /// 上次审核后被举报的次数
+    pub reports: i64,
+    pub create_time: i64,
+    pub update_time: i64,
+}
+
+/// 公开的评论，不包括审核状态和举报数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublicReview {
+    pub id: Option<i64>,
+    pub user_name: String,
+    pub stationuuid: String,
+    pub rating: i64,
+    pub content: String,
+    pub create_time: i64,
+    pub update_time: i64,
+}
+
+impl From<Review> for PublicReview {
+    fn from(review: Review) -> Self {
+        Self {
+            id: review.id,
+            user_name: review.user_name,
+            stationuuid: review.stationuuid,
+            rating: review.rating,
+            content: review.content,
+            create_time: review.create_time,
+            update_time: review.update_time,
+        }
+    }
+}
+
+/// 电台审核通过的评分汇总
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Rating {
+    pub stationuuid: String,
+    /// 平均评分
+    pub rating: f64,
+    /// 评分数
+    pub ratings: i64,
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// 用户设置，没有记录时使用默认值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Setting {
+    pub user_id: i64,
+    /// 是否记录最近播放，0不记录
+    pub recently_enabled: i64,
+    /// 最近播放保留天数，0表示按服务端配置，比服务端配置长时不生效
+    pub recently_days: i64,
+    pub update_time: i64,
+}
+
+impl Setting {
+    pub fn new(user_id: i64) -> Self {
+        Self {
+            user_id,
+            recently_enabled: 1,
+            recently_days: 0,
+            update_time: 0,
+        }
+    }
+}
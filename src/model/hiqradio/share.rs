@@ -0,0 +1,24 @@
+use nanoid::nanoid;
+use serde::Serialize;
+
+/// 公开分享的收藏分组，持有token即可只读访问
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Share {
+    pub token: String,
+    #[serde(skip_serializing)]
+    pub user_id: i64,
+    pub group_name: String,
+    pub create_time: i64,
+}
+
+impl Share {
+    /// 不可猜测的分享token
+    pub fn new_token() -> String {
+        let alphabet: [char; 36] = [
+            '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f', 'g',
+            'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x',
+            'y', 'z',
+        ];
+        nanoid!(32, &alphabet)
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// 相似电台，score为两个电台用户向量的余弦相似度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Similar {
+    pub stationuuid: String,
+    pub similaruuid: String,
+    pub score: f64,
+    pub update_time: i64,
+}
+
+/// 用户收藏或播放过的电台，favorite为1时是收藏
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserStation {
+    pub user_id: i64,
+    pub stationuuid: String,
+    pub favorite: i64,
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// 电台目录，从radio-browser导出的数据导入
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Station {
+    pub stationuuid: String,
+    pub name: String,
+    pub url: String,
+    pub url_resolved: String,
+    pub homepage: String,
+    pub favicon: String,
+    /// 逗号分隔
+    pub tags: String,
+    pub country: String,
+    pub countrycode: String,
+    pub state: String,
+    /// 逗号分隔
+    pub language: String,
+    pub codec: String,
+    pub bitrate: i64,
+    pub hls: i64,
+    /// radio-browser最近一次检查是否可以播放
+    pub lastcheckok: i64,
+    pub votes: i64,
+    pub clickcount: i64,
+    /// 最近一次导入的数据中已没有此电台
+    pub deleted: i64,
+    /// 最近一次导入的时间(秒)
+    pub update_time: i64,
+    /// 审核通过的评分的平均值，没有评分时为0
+    #[serde(default)]
+    #[sqlx(default)]
+    pub rating: f64,
+    /// 审核通过的评分数
+    #[serde(default)]
+    #[sqlx(default)]
+    pub ratings: i64,
+}
@@ -5,4 +5,8 @@ pub struct StationGroup {
     pub group_name: String,
     pub stationuuid: String,
     pub create_time: i64,
+    /// 在分组中的顺序，从0开始
+    #[serde(default)]
+    #[sqlx(default)]
+    pub position: i64,
 }
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+/// 删除分组，group_name为分组名，分组下的收藏一并删除
+pub const TOMBSTONE_GROUP_DELETE: &str = "group_delete";
+/// 修改分组，group_name为原分组名，new_name/new_desc为修改后的值
+pub const TOMBSTONE_GROUP_MODIFY: &str = "group_modify";
+/// 删除收藏，group_name + stationuuid
+pub const TOMBSTONE_FAVORITE_DELETE: &str = "favorite_delete";
+/// 清空最近播放，create_time之前的记录都已删除
+pub const TOMBSTONE_RECENTLY_CLEAR: &str = "recently_clear";
+
+/// 删除或修改记录，同步时下发给其他设备
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Tombstone {
+    pub id: Option<i64>,
+    pub user_id: i64,
+    pub kind: String,
+    pub group_name: String,
+    pub stationuuid: String,
+    pub new_name: String,
+    pub new_desc: String,
+    /// 操作时间(秒)，离线操作为客户端时间，用于冲突处理
+    pub create_time: i64,
+    pub revision: i64,
+}
+
+impl Tombstone {
+    fn new(
+        user_id: i64,
+        revision: i64,
+        time: i64,
+        kind: &str,
+        group_name: &str,
+        stationuuid: &str,
+    ) -> Self {
+        Self {
+            id: None,
+            user_id,
+            kind: kind.to_string(),
+            group_name: group_name.to_string(),
+            stationuuid: stationuuid.to_string(),
+            new_name: String::new(),
+            new_desc: String::new(),
+            create_time: time,
+            revision,
+        }
+    }
+
+    pub fn group_delete(user_id: i64, revision: i64, time: i64, group_name: &str) -> Self {
+        Self::new(user_id, revision, time, TOMBSTONE_GROUP_DELETE, group_name, "")
+    }
+
+    pub fn group_modify(
+        user_id: i64,
+        revision: i64,
+        time: i64,
+        old_name: &str,
+        name: &str,
+        desc: &str,
+    ) -> Self {
+        Self {
+            new_name: name.to_string(),
+            new_desc: desc.to_string(),
+            ..Self::new(user_id, revision, time, TOMBSTONE_GROUP_MODIFY, old_name, "")
+        }
+    }
+
+    pub fn favorite_delete(
+        user_id: i64,
+        revision: i64,
+        time: i64,
+        group_name: &str,
+        stationuuid: &str,
+    ) -> Self {
+        Self::new(
+            user_id,
+            revision,
+            time,
+            TOMBSTONE_FAVORITE_DELETE,
+            group_name,
+            stationuuid,
+        )
+    }
+
+    pub fn recently_clear(user_id: i64, revision: i64, time: i64) -> Self {
+        Self::new(user_id, revision, time, TOMBSTONE_RECENTLY_CLEAR, "", "")
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// 一个周期内的热门电台
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct TrendingStation {
+    #[serde(skip_serializing)]
+    pub period: String,
+    pub stationuuid: String,
+    /// 播放过的用户数
+    pub listeners: i64,
+    /// 播放时长(秒)
+    pub listen_time: i64,
+    /// 新增收藏的用户数
+    pub favorites: i64,
+    pub score: f64,
+    pub update_time: i64,
+}
+
+/// 用户播放一个电台的总时长(秒)，未结束的记录不计时长
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StationListen {
+    pub stationuuid: String,
+    pub user_id: i64,
+    pub listen_time: i64,
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 
-pub const PRODUCT_STATUS_NORMAL: &'static str = "00";
-pub const PRODUCT_STATUS_CANCEL: &'static str = "99";
+pub const PRODUCT_STATUS_NORMAL: &str = "00";
+pub const PRODUCT_STATUS_CANCEL: &str = "99";
+
+/// hiqradio的产品名
+pub const PRODUCT_HIQRADIO: &str = "hiqradio";
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Product {
@@ -8,10 +8,10 @@
 
 use serde::{Deserialize, Serialize};
 
-pub const USER_STATUS_NORMAL: &'static str = "00";
-pub const USER_STATUS_CANCEL: &'static str = "99";
+pub const USER_STATUS_NORMAL: &str = "00";
+pub const USER_STATUS_CANCEL: &str = "99";
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Option<i64>,
     pub user_name: String,
@@ -10,11 +10,11 @@
 
 use serde::{Deserialize, Serialize};
 
-pub const USER_PRODUCT_STATUS_NORMAL: &'static str = "00";
-pub const USER_PRODUCT_STATUS_CANCEL: &'static str = "99";
+pub const USER_PRODUCT_STATUS_NORMAL: &str = "00";
+pub const USER_PRODUCT_STATUS_CANCEL: &str = "99";
 
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct UserProduct {
     pub id: Option<i64>,
     pub user_id: i64,
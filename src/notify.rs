@@ -0,0 +1,188 @@
+//! hiqradio数据修改通知
+//!
+//! 进程内广播，只能通知连接到同一实例的会话，多实例部署时客户端仍需定期sync。
+//! 每个用户一个广播队列，一个用户的大量修改不会使其他用户的订阅落后；最后一个订阅结束时删除队列。
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+
+/// 每个用户的广播队列长度，订阅者落后超过该长度时收到Lagged
+const CAPACITY: usize = 256;
+
+/// 用户数据已修改
+#[derive(Debug, Clone)]
+pub struct LibraryChanged {
+    pub user_id: i64,
+    pub revision: i64,
+    /// 发起修改的会话，不通知自己
+    pub token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryEvent {
+    /// 其他会话修改了数据，携带修改后的版本号
+    Changed(i64),
+    /// 丢失了部分通知，客户端应直接sync
+    Lagged,
+    /// 会话已失效(退出登录、关闭产品或注销)，这是最后一条通知
+    Closed,
+}
+
+/// 广播队列中的消息
+#[derive(Debug, Clone)]
+enum Notice {
+    Changed {
+        revision: i64,
+        token: String,
+    },
+    /// token为None时关闭用户的全部会话
+    Closed {
+        token: Option<String>,
+    },
+}
+
+type Channels = Arc<Mutex<HashMap<i64, broadcast::Sender<Notice>>>>;
+
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    channels: Channels,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self {
+            channels: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 发给用户的订阅，没有订阅时忽略
+    fn notify(&self, user_id: i64, notice: Notice) {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = channels.get(&user_id) {
+            let _ = sender.send(notice);
+        }
+    }
+
+    pub fn send(&self, changed: LibraryChanged) {
+        self.notify(
+            changed.user_id,
+            Notice::Changed {
+                revision: changed.revision,
+                token: changed.token,
+            },
+        );
+    }
+
+    /// 会话失效，结束其订阅；token为None时结束用户的全部订阅
+    pub fn close(&self, user_id: i64, token: Option<&str>) {
+        self.notify(
+            user_id,
+            Notice::Closed {
+                token: token.map(|t| t.to_string()),
+            },
+        );
+    }
+
+    /// 订阅同一用户其他会话的修改，会话失效时收到Closed后结束
+    pub fn subscribe(&self, user_id: i64, token: &str) -> Subscription {
+        let receiver = self
+            .channels
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(user_id)
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .subscribe();
+
+        let token = token.to_string();
+        let inner = BroadcastStream::new(receiver).filter_map(move |notice| match notice {
+            Ok(Notice::Changed { revision, token: t }) if t != token => {
+                Some(LibraryEvent::Changed(revision))
+            }
+            Ok(Notice::Closed { token: t }) if t.as_ref().is_none_or(|t| *t == token) => {
+                Some(LibraryEvent::Closed)
+            }
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(_)) => Some(LibraryEvent::Lagged),
+        });
+
+        Subscription {
+            user_id,
+            channels: self.channels.clone(),
+            inner: Some(Box::pin(inner)),
+        }
+    }
+
+    /// 有订阅的用户数
+    pub fn users(&self) -> usize {
+        self.channels
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 一个会话的订阅，结束或drop时没有其他订阅则删除用户的广播队列
+pub struct Subscription {
+    user_id: i64,
+    channels: Channels,
+    inner: Option<Pin<Box<dyn Stream<Item = LibraryEvent> + Send>>>,
+}
+
+impl Subscription {
+    fn close(&mut self) {
+        // 先释放自己的receiver再检查订阅数
+        if self.inner.take().is_none() {
+            return;
+        }
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if channels
+            .get(&self.user_id)
+            .is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            channels.remove(&self.user_id);
+        }
+    }
+}
+
+impl Stream for Subscription {
+    type Item = LibraryEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(LibraryEvent::Closed)) => {
+                self.close();
+                Poll::Ready(Some(LibraryEvent::Closed))
+            }
+            Poll::Ready(None) => {
+                self.close();
+                Poll::Ready(None)
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,710 @@
+//! 收藏导入导出为M3U、PLS、OPML播放列表
+//!
+//! 收藏只保存了stationuuid，导出的地址按配置`playlist_station_url`生成，标题取电台目录中的名称，
+//! 并在格式允许的地方带上stationuuid，导入时优先使用。
+//! 导入时按电台目录匹配条目：明确给出的stationuuid直接使用，地址中的stationuuid要目录中有，
+//! 再按播放地址和名称查找，找不到的条目原样返回给客户端。
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use regex::Regex;
+
+use crate::{
+    config::CONFIG,
+    errors::Error,
+    model::hiqradio::{Station, StationGroup},
+    proto::{SyncOp, SYNC_OP_APPLIED},
+    repo::{merge, DynAppServRepo},
+    Result,
+};
+
+static UUID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}")
+        .unwrap()
+});
+
+/// M3U的EXTINF属性，如tvg-id="..."
+static M3U_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap());
+
+fn find_uuid(s: &str) -> Option<String> {
+    UUID.find(s).map(|m| m.as_str().to_ascii_lowercase())
+}
+
+/// 播放列表格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    M3u,
+    Pls,
+    Opml,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "m3u" | "m3u8" => Ok(Self::M3u),
+            "pls" => Ok(Self::Pls),
+            "opml" => Ok(Self::Opml),
+            _ => Err(Error::Parse(format!("playlist format {}", name))),
+        }
+    }
+
+    /// 按内容判断格式，不带头部的纯地址列表按M3U处理
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        if content.starts_with('<') {
+            Self::Opml
+        } else if content
+            .get(..10)
+            .map(|s| s.eq_ignore_ascii_case("[playlist]"))
+            .unwrap_or(false)
+        {
+            Self::Pls
+        } else {
+            Self::M3u
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::M3u => "audio/x-mpegurl",
+            Self::Pls => "audio/x-scpls",
+            Self::Opml => "text/x-opml",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M3u => "m3u",
+            Self::Pls => "pls",
+            Self::Opml => "opml",
+        }
+    }
+}
+
+/// 播放列表中的一个条目
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub title: String,
+    pub url: String,
+    /// 条目中明确给出的stationuuid
+    pub stationuuid: Option<String>,
+}
+
+impl Entry {
+    /// 条目中明确给出的stationuuid，电台目录中没有也使用
+    pub fn given_station(&self) -> Option<String> {
+        self.stationuuid.as_deref().and_then(find_uuid)
+    }
+
+    /// 地址中的stationuuid，电台目录中有才使用
+    pub fn url_station(&self) -> Option<String> {
+        find_uuid(&self.url)
+    }
+}
+
+/// 电台的播放地址，template中的{stationuuid}替换为电台id
+fn station_url(template: &str, stationuuid: &str) -> String {
+    template.replace("{stationuuid}", stationuuid)
+}
+
+/// M3U和PLS一行一个字段，去掉名称中的换行
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// 生成播放列表，favorites按分组依次输出，names为电台目录中的名称，没有时用stationuuid
+pub fn write(
+    format: Format,
+    favorites: &[StationGroup],
+    names: &HashMap<String, String>,
+    url_template: &str,
+) -> String {
+    let title = |f: &StationGroup| {
+        names
+            .get(&f.stationuuid)
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(&f.stationuuid)
+            .clone()
+    };
+    let url = |f: &StationGroup| station_url(url_template, &f.stationuuid);
+
+    let mut out = String::new();
+    match format {
+        Format::M3u => {
+            out.push_str("#EXTM3U\n");
+            for f in favorites {
+                out.push_str(&format!(
+                    "#EXTINF:-1 tvg-id=\"{}\",{}\n#EXTGRP:{}\n{}\n",
+                    f.stationuuid,
+                    one_line(&title(f)),
+                    one_line(&f.group_name),
+                    url(f)
+                ));
+            }
+        }
+        Format::Pls => {
+            out.push_str("[playlist]\n");
+            for (i, f) in favorites.iter().enumerate() {
+                out.push_str(&format!(
+                    "File{}={}\nTitle{}={}\nLength{}=-1\n",
+                    i + 1,
+                    url(f),
+                    i + 1,
+                    one_line(&title(f)),
+                    i + 1
+                ));
+            }
+            out.push_str(&format!("NumberOfEntries={}\nVersion=2\n", favorites.len()));
+        }
+        Format::Opml => {
+            out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+            out.push_str(&format!(
+                "  <head>\n    <title>hiqradio</title>\n    <dateCreated>{}</dateCreated>\n  </head>\n  <body>\n",
+                Local::now().to_rfc2822()
+            ));
+            let mut group: Option<&str> = None;
+            for f in favorites {
+                if group != Some(f.group_name.as_str()) {
+                    if group.is_some() {
+                        out.push_str("    </outline>\n");
+                    }
+                    out.push_str(&format!(
+                        "    <outline text=\"{}\">\n",
+                        escape(&f.group_name)
+                    ));
+                    group = Some(&f.group_name);
+                }
+                out.push_str(&format!(
+                    "      <outline type=\"audio\" text=\"{}\" URL=\"{}\" stationuuid=\"{}\"/>\n",
+                    escape(&title(f)),
+                    escape(&url(f)),
+                    escape(&f.stationuuid)
+                ));
+            }
+            if group.is_some() {
+                out.push_str("    </outline>\n");
+            }
+            out.push_str("  </body>\n</opml>\n");
+        }
+    }
+    out
+}
+
+/// 在引号外的第一个逗号处分开属性和标题
+fn split_extinf(info: &str) -> (&str, &str) {
+    let mut quoted = false;
+    for (i, c) in info.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => return (&info[..i], &info[i + 1..]),
+            _ => {}
+        }
+    }
+    (info, "")
+}
+
+fn parse_m3u(content: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut entry = Entry::default();
+    for line in content.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:-1 tvg-id="..." tvg-name="a, b",title，属性值中可能有逗号
+            let (attrs, title) = split_extinf(info);
+            entry.title = title.trim().to_string();
+            entry.stationuuid = M3U_ATTR
+                .captures_iter(attrs)
+                .find(|c| c[1].eq_ignore_ascii_case("tvg-id") || &c[1] == "stationuuid")
+                .map(|c| c[2].to_string());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entry.url = line.to_string();
+            entries.push(std::mem::take(&mut entry));
+        }
+    }
+    entries
+}
+
+fn parse_pls(content: &str) -> Vec<Entry> {
+    let mut entries: BTreeMap<usize, Entry> = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let (field, index) = if let Some(index) = key.strip_prefix("file") {
+            ("file", index)
+        } else if let Some(index) = key.strip_prefix("title") {
+            ("title", index)
+        } else {
+            continue;
+        };
+        let Ok(index) = index.parse() else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        if field == "file" {
+            entry.url = value.trim().to_string();
+        } else {
+            entry.title = value.trim().to_string();
+        }
+    }
+    entries
+        .into_values()
+        .filter(|e| !e.url.is_empty())
+        .collect()
+}
+
+fn opml_entry(e: &BytesStart) -> Result<Option<Entry>> {
+    let mut entry = Entry::default();
+    for attr in e.attributes() {
+        let attr = attr.map_err(|e| Error::Parse(format!("opml: {}", e)))?;
+        let value = attr
+            .unescape_value()
+            .map_err(|e| Error::Parse(format!("opml: {}", e)))?
+            .to_string();
+        match attr.key.as_ref() {
+            b"text" => entry.title = value,
+            b"title" if entry.title.is_empty() => entry.title = value,
+            b"URL" | b"url" | b"xmlUrl" => entry.url = value,
+            b"stationuuid" => entry.stationuuid = Some(value),
+            _ => {}
+        }
+    }
+    if entry.url.is_empty() && entry.stationuuid.is_none() {
+        // 分组等不带地址的节点
+        return Ok(None);
+    }
+    Ok(Some(entry))
+}
+
+fn parse_opml(content: &str) -> Result<Vec<Entry>> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+    let mut entries = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.name().as_ref() == b"outline" => {
+                if let Some(entry) = opml_entry(&e)? {
+                    entries.push(entry);
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(Error::Parse(format!("opml: {}", e))),
+        }
+    }
+    Ok(entries)
+}
+
+/// 解析播放列表，format为None时按内容判断
+pub fn parse(format: Option<Format>, content: &str) -> Result<Vec<Entry>> {
+    match format.unwrap_or_else(|| Format::detect(content)) {
+        Format::M3u => Ok(parse_m3u(content)),
+        Format::Pls => Ok(parse_pls(content)),
+        Format::Opml => parse_opml(content),
+    }
+}
+
+/// 导出收藏，group为None时导出全部分组
+pub async fn export(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    format: Format,
+    group: Option<&str>,
+) -> Result<String> {
+    if let Some(group) = group {
+        if !repo
+            .query_groups(user_id)
+            .await?
+            .iter()
+            .any(|g| g.name == group)
+        {
+            return Err(Error::Custom(format!("group {} not exists", group)));
+        }
+    }
+
+    // 按分组和收藏的顺序导出
+    let favorites: Vec<_> = repo
+        .query_favorites(user_id)
+        .await?
+        .into_iter()
+        .filter(|f| group.map(|g| f.group_name == g).unwrap_or(true))
+        .collect();
+    let stationuuids: Vec<_> = favorites
+        .iter()
+        .map(|f| f.stationuuid.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let names = repo
+        .query_stations(&stationuuids)
+        .await?
+        .into_iter()
+        .map(|s| (s.stationuuid, s.name))
+        .collect();
+    Ok(write(
+        format,
+        &favorites,
+        &names,
+        &CONFIG.playlist_station_url,
+    ))
+}
+
+/// 地址或名称对应的电台，同一地址或名称有多个电台时取未删除、投票多的
+fn station_keys(
+    mut stations: Vec<Station>,
+    keys: impl Fn(&Station) -> Vec<&str>,
+) -> HashMap<String, String> {
+    stations.sort_by(|a, b| {
+        (a.deleted, Reverse(a.votes), &a.stationuuid).cmp(&(
+            b.deleted,
+            Reverse(b.votes),
+            &b.stationuuid,
+        ))
+    });
+    let mut map = HashMap::new();
+    for s in stations.iter() {
+        for key in keys(s).into_iter().filter(|k| !k.is_empty()) {
+            map.entry(key.to_string())
+                .or_insert_with(|| s.stationuuid.clone());
+        }
+    }
+    map
+}
+
+/// 还没有找到电台的条目
+fn pending<'a>(
+    entries: &'a [Entry],
+    stations: &'a [Option<String>],
+) -> impl Iterator<Item = &'a Entry> {
+    entries
+        .iter()
+        .zip(stations)
+        .filter(|(_, s)| s.is_none())
+        .map(|(e, _)| e)
+}
+
+/// 为还没有找到电台的条目查找
+fn fill(
+    entries: &[Entry],
+    stations: &mut [Option<String>],
+    find: impl Fn(&Entry) -> Option<String>,
+) {
+    for (entry, station) in entries.iter().zip(stations.iter_mut()) {
+        if station.is_none() {
+            *station = find(entry);
+        }
+    }
+}
+
+/// 按电台目录找出条目对应的电台，与entries一一对应，找不到的为None
+pub async fn stations(repo: &DynAppServRepo, entries: &[Entry]) -> Result<Vec<Option<String>>> {
+    let mut stations: Vec<_> = entries.iter().map(Entry::given_station).collect();
+
+    // 地址中的stationuuid
+    let uuids: Vec<_> = pending(entries, &stations)
+        .filter_map(Entry::url_station)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if !uuids.is_empty() {
+        let known: HashSet<_> = repo
+            .query_stations(&uuids)
+            .await?
+            .into_iter()
+            .map(|s| s.stationuuid)
+            .collect();
+        fill(entries, &mut stations, |e| {
+            e.url_station().filter(|s| known.contains(s))
+        });
+    }
+
+    // 播放地址
+    let urls: Vec<_> = pending(entries, &stations)
+        .map(|e| e.url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if !urls.is_empty() {
+        let by_url = station_keys(repo.query_stations_by_url(&urls).await?, |s| {
+            vec![&s.url, &s.url_resolved]
+        });
+        fill(entries, &mut stations, |e| {
+            by_url.get(e.url.trim()).cloned()
+        });
+    }
+
+    // 名称
+    let names: Vec<_> = pending(entries, &stations)
+        .map(|e| e.title.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if !names.is_empty() {
+        let by_name = station_keys(repo.query_stations_by_name(&names).await?, |s| {
+            vec![&s.name]
+        });
+        fill(entries, &mut stations, |e| {
+            by_name.get(e.title.trim()).cloned()
+        });
+    }
+
+    Ok(stations)
+}
+
+/// 导入的结果
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Imported {
+    /// 新建了分组
+    pub group_created: bool,
+    /// 新增的收藏
+    pub favorites: usize,
+    /// 找不到电台的条目，优先取标题
+    pub unmatched: Vec<String>,
+}
+
+/// 导入播放列表到group，分组不存在时新建
+pub async fn import(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    group: &str,
+    desc: &str,
+    entries: &[Entry],
+) -> Result<Imported> {
+    let mut imported = Imported::default();
+    let time = Local::now().timestamp();
+
+    let mut ops = Vec::new();
+    if !repo
+        .query_groups(user_id)
+        .await?
+        .iter()
+        .any(|g| g.name == group)
+    {
+        ops.push(SyncOp::GroupNew {
+            time,
+            name: group.to_string(),
+            desc: desc.to_string(),
+            is_def: 0,
+        });
+    }
+
+    let mut seen = HashSet::new();
+    for (entry, station) in entries.iter().zip(stations(repo, entries).await?) {
+        match station {
+            Some(stationuuid) => {
+                if seen.insert(stationuuid.clone()) {
+                    ops.push(SyncOp::FavoriteNew {
+                        time,
+                        group_name: group.to_string(),
+                        stationuuid,
+                    });
+                }
+            }
+            None => imported.unmatched.push(if entry.title.is_empty() {
+                entry.url.clone()
+            } else {
+                entry.title.clone()
+            }),
+        }
+    }
+
+    if !ops.is_empty() {
+        let revision = repo.query_revision(user_id).await?;
+        let (_, results) = merge::push_sync(repo, user_id, revision, &ops).await?;
+        for (op, result) in ops.iter().zip(results) {
+            if result != SYNC_OP_APPLIED {
+                continue;
+            }
+            match op {
+                SyncOp::GroupNew { .. } => imported.group_created = true,
+                SyncOp::FavoriteNew { .. } => imported.favorites += 1,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID_A: &str = "960c0d8f-0601-11e8-ae97-52543be04c81";
+    const UUID_B: &str = "96202f73-0601-11e8-ae97-52543be04c81";
+    const URL: &str = "http://radio.example.com/{stationuuid}";
+
+    fn entry(title: &str, url: &str, stationuuid: Option<&str>) -> Entry {
+        Entry {
+            title: title.to_string(),
+            url: url.to_string(),
+            stationuuid: stationuuid.map(|s| s.to_string()),
+        }
+    }
+
+    fn favorite(group_name: &str, stationuuid: &str) -> StationGroup {
+        StationGroup {
+            group_name: group_name.to_string(),
+            stationuuid: stationuuid.to_string(),
+            create_time: 0,
+            position: 0,
+        }
+    }
+
+    #[test]
+    fn detect_format() {
+        assert_eq!(Format::detect("\u{feff}#EXTM3U\n"), Format::M3u);
+        assert_eq!(Format::detect("http://a\n"), Format::M3u);
+        assert_eq!(Format::detect("  [Playlist]\nFile1=x"), Format::Pls);
+        assert_eq!(
+            Format::detect("<?xml version=\"1.0\"?><opml/>"),
+            Format::Opml
+        );
+        assert_eq!(Format::parse("M3U8").unwrap(), Format::M3u);
+        assert!(matches!(Format::parse("xspf"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn m3u_quoted_attrs() {
+        let m3u = format!(
+            "#EXTM3U\n#EXTINF:-1 tvg-name=\"Jazz, Blues\" tvg-id=\"{}\" group-title=\"a\",Jazz, Blues FM\nhttp://a\n\
+             #EXTINF:-1 xtvg-id=\"{}\",Other\nhttp://b\n",
+            UUID_A, UUID_B
+        );
+        assert_eq!(
+            parse(None, &m3u).unwrap(),
+            vec![
+                entry("Jazz, Blues FM", "http://a", Some(UUID_A)),
+                entry("Other", "http://b", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn m3u_crlf_without_header() {
+        let m3u = "http://a\r\n\r\n#EXTINF:-1,B\r\n  http://b  \r\n# comment\r\nhttp://c";
+        assert_eq!(
+            parse(None, m3u).unwrap(),
+            vec![
+                entry("", "http://a", None),
+                entry("B", "http://b", None),
+                entry("", "http://c", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn pls_index_gaps() {
+        let pls = "[playlist]\r\nFile3=http://c\r\nTitle3=C\r\ntitle1=A\r\nfile1=http://a\r\n\
+                   Title2=no file\r\nFile10=http://j\r\nFilex=bad\r\nNumberOfEntries=4\r\n";
+        assert_eq!(
+            parse(Some(Format::Pls), pls).unwrap(),
+            vec![
+                entry("A", "http://a", None),
+                entry("C", "http://c", None),
+                entry("", "http://j", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn opml_nested() {
+        let opml = format!(
+            r#"<?xml version="1.0"?>
+            <opml version="2.0"><body>
+              <outline text="music">
+                <outline text="rock &amp; roll">
+                  <outline type="audio" text="A &quot;1&quot;" URL="http://a?x=1&amp;y=2" stationuuid="{}"/>
+                </outline>
+                <outline title="B" url="http://b"/>
+              </outline>
+              <outline text="C" xmlUrl="http://c"></outline>
+            </body></opml>"#,
+            UUID_A
+        );
+        assert_eq!(
+            parse(None, &opml).unwrap(),
+            vec![
+                entry("A \"1\"", "http://a?x=1&y=2", Some(UUID_A)),
+                entry("B", "http://b", None),
+                entry("C", "http://c", None),
+            ]
+        );
+        for opml in [
+            "<opml><body><outline text=\"a\" URL=\"x\"",
+            "<opml><outline a=\"1\" a=\"2\"/></opml>",
+        ] {
+            assert!(matches!(
+                parse(Some(Format::Opml), opml),
+                Err(Error::Parse(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn entry_stations() {
+        let e = entry("", &format!("http://a/{}", UUID_A.to_uppercase()), None);
+        assert_eq!(e.given_station(), None);
+        assert_eq!(e.url_station(), Some(String::from(UUID_A)));
+        // tvg-id不是stationuuid时不使用
+        let e = entry("", "http://a", Some("radio.example"));
+        assert_eq!(e.given_station(), None);
+        let e = entry("", "http://a", Some(UUID_B));
+        assert_eq!(e.given_station(), Some(String::from(UUID_B)));
+    }
+
+    #[test]
+    fn write_names_and_newlines() {
+        let favorites = [
+            favorite("a\r\nb", UUID_A),
+            favorite("rock & \"roll\"", UUID_B),
+        ];
+        let names = HashMap::from([
+            (String::from(UUID_A), String::from("Radio\nA")),
+            (String::from(UUID_B), String::from(" ")),
+        ]);
+
+        let m3u = write(Format::M3u, &favorites, &names, URL);
+        assert_eq!(
+            m3u,
+            format!(
+                "#EXTM3U\n#EXTINF:-1 tvg-id=\"{a}\",Radio A\n#EXTGRP:a  b\nhttp://radio.example.com/{a}\n\
+                 #EXTINF:-1 tvg-id=\"{b}\",{b}\n#EXTGRP:rock & \"roll\"\nhttp://radio.example.com/{b}\n",
+                a = UUID_A,
+                b = UUID_B
+            )
+        );
+        let entries = parse(None, &m3u).unwrap();
+        assert_eq!(entries[0].title, "Radio A");
+        assert_eq!(entries[1].given_station(), Some(String::from(UUID_B)));
+
+        let pls = write(Format::Pls, &favorites, &names, URL);
+        assert!(pls.contains("Title1=Radio A\n"));
+        assert!(pls.contains("NumberOfEntries=2\n"));
+        assert_eq!(parse(None, &pls).unwrap().len(), 2);
+
+        let opml = write(Format::Opml, &favorites, &names, URL);
+        assert!(opml.contains("<outline text=\"rock &amp; &quot;roll&quot;\">"));
+        let entries = parse(None, &opml).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.given_station().unwrap())
+                .collect::<Vec<_>>(),
+            vec![UUID_A, UUID_B]
+        );
+    }
+}
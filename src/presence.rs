@@ -0,0 +1,81 @@
+//! 跨设备的正在播放状态
+//!
+//! 客户端播放时定期发送心跳，记录每个设备正在播放的电台和发送心跳的会话，超过有效期没有心跳视为停止播放。
+//! 状态保存在数据库中，多实例部署时也能查到其他设备，客户端据此在当前设备继续播放。
+
+use crate::{
+    catalog,
+    errors::Error,
+    model::hiqradio::{Presence, Station},
+    proto::{PresenceReq, PRESENCE_DEVICE_MAX},
+    repo::DynAppServRepo,
+    Result,
+};
+
+/// 设备的心跳，stationuuid为空时删除设备的播放状态，返回保存后的状态
+pub async fn heartbeat(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    token: &str,
+    req: &PresenceReq,
+    now: i64,
+    ttl: i64,
+) -> Result<Option<Presence>> {
+    let device_id = req.device_id.trim();
+    if device_id.is_empty() || device_id.chars().count() > PRESENCE_DEVICE_MAX {
+        return Err(Error::Parse(format!("device_id {}", device_id)));
+    }
+    let device_name = req.device_name.as_deref().unwrap_or_default().trim();
+    if device_name.chars().count() > PRESENCE_DEVICE_MAX {
+        return Err(Error::Parse(format!("device_name {}", device_name)));
+    }
+
+    let stationuuid = req
+        .stationuuid
+        .as_deref()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if stationuuid.is_empty() {
+        repo.delete_presence(user_id, device_id).await?;
+        return Ok(None);
+    }
+
+    let presence = repo
+        .save_presence(&Presence {
+            user_id,
+            device_id: device_id.to_string(),
+            device_name: device_name.to_string(),
+            token: token.to_string(),
+            stationuuid,
+            start_time: now,
+            update_time: now,
+            expire: now + ttl,
+            current: true,
+        })
+        .await?;
+    Ok(Some(Presence {
+        current: true,
+        ..presence
+    }))
+}
+
+/// 用户正在播放的设备和电台目录中有的电台信息，token对应的设备标记为current
+pub async fn presences(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    token: &str,
+    now: i64,
+) -> Result<(Vec<Presence>, Vec<Station>)> {
+    let presences: Vec<_> = repo
+        .query_presences(user_id, now)
+        .await?
+        .into_iter()
+        .map(|p| Presence {
+            current: p.token == token,
+            ..p
+        })
+        .collect();
+    let stations = catalog::stations(repo, presences.iter().map(|p| &p.stationuuid)).await?;
+    Ok((presences, stations))
+}
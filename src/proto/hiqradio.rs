@@ -1,6 +1,30 @@
 use serde::{Deserialize, Serialize};
 
-use crate::model::hiqradio::{FavGroup, Recently, StationGroup};
+use crate::model::hiqradio::{
+    FavGroup, Presence, PublicReview, Recently, Review, Setting, Share, Station, StationGroup,
+    Tombstone, TrendingStation,
+};
+
+/// 最近播放每页默认条数
+pub const RECENTLY_PAGE_SIZE: i64 = 100;
+/// 最近播放每页最大条数
+pub const RECENTLY_PAGE_MAX: i64 = 1000;
+
+/// 最近播放分页查询，不带请求体时返回全部记录
+///
+/// 时间单位与start_time一致，since/until按start_time过滤，分别为闭区间和开区间；
+/// cursor为上一页返回的cursor，没有cursor时从最新的记录开始
+#[derive(Debug, Default, Deserialize)]
+pub struct RecentlyReq {
+    pub cursor: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub stationuuid: Option<String>,
+    pub limit: Option<i64>,
+    /// 是否带上电台信息
+    #[serde(default)]
+    pub with_station: bool,
+}
 
 /// 最近播放
 #[derive(Debug, Serialize)]
@@ -8,6 +32,71 @@ pub struct RecentlyRsp {
     pub error: usize,
     pub message: String,
     pub recently: Vec<Recently>,
+    /// 下一页的cursor，没有更多记录时不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// 请求with_station时返回记录中的电台，电台目录中没有的不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stations: Option<Vec<Station>>,
+}
+
+/// 最近播放查询条件，按start_time、id倒序
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentlyFilter {
+    /// 只查询排在(start_time, id)之后的记录
+    pub after: Option<(i64, i64)>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub stationuuid: Option<String>,
+    pub limit: i64,
+}
+
+impl RecentlyFilter {
+    /// cursor格式为"start_time-id"
+    pub fn cursor(recently: &Recently) -> String {
+        format!("{}-{}", recently.start_time, recently.id.unwrap_or_default())
+    }
+
+    pub fn parse_cursor(cursor: &str) -> Option<(i64, i64)> {
+        let (start_time, id) = cursor.rsplit_once('-')?;
+        Some((start_time.parse().ok()?, id.parse().ok()?))
+    }
+
+    /// 记录是否满足查询条件，不考虑limit
+    pub fn matches(&self, recently: &Recently) -> bool {
+        let key = (recently.start_time, recently.id.unwrap_or_default());
+        self.after.is_none_or(|after| key < after)
+            && self.since.is_none_or(|since| recently.start_time >= since)
+            && self.until.is_none_or(|until| recently.start_time < until)
+            && self
+                .stationuuid
+                .as_ref()
+                .is_none_or(|s| &recently.stationuuid == s)
+    }
+}
+
+impl TryFrom<&RecentlyReq> for RecentlyFilter {
+    type Error = crate::errors::Error;
+
+    fn try_from(req: &RecentlyReq) -> Result<Self, Self::Error> {
+        let after = match &req.cursor {
+            Some(cursor) => Some(
+                Self::parse_cursor(cursor)
+                    .ok_or_else(|| crate::errors::Error::Parse(format!("cursor {}", cursor)))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            after,
+            since: req.since,
+            until: req.until,
+            stationuuid: req.stationuuid.clone().filter(|s| !s.is_empty()),
+            limit: req
+                .limit
+                .unwrap_or(RECENTLY_PAGE_SIZE)
+                .clamp(1, RECENTLY_PAGE_MAX),
+        })
+    }
 }
 
 /// 新增记录
@@ -65,11 +154,94 @@ pub struct GroupModifyReq {
     pub desc: String,
 }
 
+/// 调整顺序，group为空时调整分组的顺序，否则调整分组中收藏的顺序；
+/// order为完整的顺序(分组名或stationuuid)，或者用move把一项移动到to位置(从0开始)
+#[derive(Debug, Deserialize)]
+pub struct ReorderReq {
+    pub group: Option<String>,
+    pub order: Option<Vec<String>>,
+    #[serde(rename = "move")]
+    pub moving: Option<String>,
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReorderRsp {
+    pub error: usize,
+    pub message: String,
+    /// 调整后的顺序
+    pub order: Vec<String>,
+}
+
+/// 分享分组或取消分享
+#[derive(Debug, Deserialize)]
+pub struct ShareReq {
+    pub group: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareRsp {
+    pub error: usize,
+    pub message: String,
+    pub share: Share,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharesRsp {
+    pub error: usize,
+    pub message: String,
+    pub shares: Vec<Share>,
+}
+
+/// 分享的分组，不需要登录
+#[derive(Debug, Serialize)]
+pub struct SharedRsp {
+    pub error: usize,
+    pub message: String,
+    pub name: String,
+    pub desc: String,
+    /// 分组中收藏的stationuuid，按收藏的顺序
+    pub favorites: Vec<String>,
+    /// 电台目录中有的电台信息
+    pub stations: Vec<Station>,
+}
+
+/// 复制分享的分组到自己的收藏，group为空时使用分享的分组名，分组已存在时合并
+#[derive(Debug, Deserialize)]
+pub struct ShareCopyReq {
+    pub token: String,
+    pub group: Option<String>,
+    pub desc: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareCopyRsp {
+    pub error: usize,
+    pub message: String,
+    /// 复制到的分组
+    pub group: String,
+    pub group_created: bool,
+    /// 新增的收藏数
+    pub favorites: usize,
+}
+
+/// 不带请求体时不返回电台信息
+#[derive(Debug, Default, Deserialize)]
+pub struct FavoritesReq {
+    #[serde(default)]
+    pub with_station: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FavoritesRsp {
     pub error: usize,
     pub message: String,
     pub favorites: Vec<StationGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stations: Option<Vec<Station>>,
+    /// 电台目录中已删除的收藏电台
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -89,9 +261,14 @@ pub struct FavoriteModifyReq {
     pub group_names: Vec<String>,
 }
 
+/// revision为上次同步返回的版本号，首次同步为0
 #[derive(Debug, Deserialize)]
 pub struct SyncReq {
-    pub start_time: i64,
+    #[serde(default)]
+    pub revision: i64,
+    /// 是否带上recently/favorites中的电台信息
+    #[serde(default)]
+    pub with_station: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -101,4 +278,498 @@ pub struct SyncRsp {
     pub groups: Vec<FavGroup>,
     pub recently: Vec<Recently>,
     pub favorites: Vec<StationGroup>,
+    /// 删除/修改记录，客户端先按顺序应用，再合并上面的数据
+    pub tombstones: Vec<Tombstone>,
+    /// 本次同步到的版本号，下次同步时带上
+    pub revision: i64,
+    /// 服务器时间(秒)
+    pub server_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stations: Option<Vec<Station>>,
+}
+
+/// 离线操作，time为客户端操作时间(秒)
+///
+/// 冲突处理：与客户端上次同步之后服务端的修改比较time，后写者胜；
+/// time相同时已有版本号的服务端修改胜。最近播放只追加和结束，不存在冲突。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SyncOp {
+    GroupNew {
+        time: i64,
+        name: String,
+        desc: String,
+        is_def: i64,
+    },
+    GroupModify {
+        time: i64,
+        old_name: String,
+        name: String,
+        desc: String,
+    },
+    GroupDelete {
+        time: i64,
+        name: String,
+    },
+    FavoriteNew {
+        time: i64,
+        group_name: String,
+        stationuuid: String,
+    },
+    /// 移动收藏，收藏只保留在group_names中
+    FavoriteModify {
+        time: i64,
+        stationuuid: String,
+        group_names: Vec<String>,
+    },
+    FavoriteDelete {
+        time: i64,
+        group_name: String,
+        stationuuid: String,
+    },
+    RecentlyNew {
+        stationuuid: String,
+        start_time: i64,
+        end_time: Option<i64>,
+    },
+    RecentlyModify {
+        stationuuid: String,
+        start_time: i64,
+        end_time: i64,
+    },
+}
+
+/// 操作已应用
+pub const SYNC_OP_APPLIED: &str = "applied";
+/// 服务端有更新的修改，操作被丢弃
+pub const SYNC_OP_CONFLICT: &str = "conflict";
+/// 操作对象不存在或已存在，无需应用
+pub const SYNC_OP_IGNORED: &str = "ignored";
+
+/// revision为上次同步返回的版本号，ops按顺序整批应用
+#[derive(Debug, Deserialize)]
+pub struct SyncPushReq {
+    #[serde(default)]
+    pub revision: i64,
+    pub ops: Vec<SyncOp>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncPushRsp {
+    pub error: usize,
+    pub message: String,
+    /// 与ops一一对应的处理结果
+    pub results: Vec<String>,
+    pub groups: Vec<FavGroup>,
+    pub recently: Vec<Recently>,
+    pub favorites: Vec<StationGroup>,
+    pub tombstones: Vec<Tombstone>,
+    pub revision: i64,
+    /// 服务器时间(秒)
+    pub server_time: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingRsp {
+    pub error: usize,
+    pub message: String,
+    pub setting: Setting,
+}
+
+/// 只修改带上的字段，关闭最近播放时清空已有记录
+#[derive(Debug, Deserialize)]
+pub struct SettingModifyReq {
+    pub recently_enabled: Option<i64>,
+    pub recently_days: Option<i64>,
+}
+
+/// 统计排行默认条数
+pub const STATS_TOP: usize = 10;
+/// 统计排行最大条数
+pub const STATS_TOP_MAX: usize = 100;
+
+/// 播放统计，since/until按start_time过滤，分别为闭区间和开区间；
+/// tz_offset为客户端时区偏移(分钟，东八区为480)，按天和按小时统计使用，默认服务器时区
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsReq {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub tz_offset: Option<i32>,
+    pub top: Option<usize>,
+}
+
+/// 单个电台的统计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct StationStats {
+    pub stationuuid: String,
+    pub plays: i64,
+    /// 收听时长(秒)
+    pub time: i64,
+}
+
+/// 按天统计，day格式为2024-01-01
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DayStats {
+    pub day: String,
+    pub plays: i64,
+    pub time: i64,
+}
+
+/// 按小时统计，hour为0-23
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HourStats {
+    pub hour: u32,
+    pub plays: i64,
+    pub time: i64,
+}
+
+/// 收听统计，时长单位为秒
+#[derive(Debug, Default, Serialize)]
+pub struct ListenStats {
+    pub plays: i64,
+    pub time: i64,
+    /// 收听过的电台数
+    pub stations: i64,
+    pub top_by_time: Vec<StationStats>,
+    pub top_by_plays: Vec<StationStats>,
+    /// 有播放记录的天，按日期升序
+    pub days: Vec<DayStats>,
+    /// 固定24项
+    pub hours: Vec<HourStats>,
+    /// 时长最长的播放记录
+    pub longest: Vec<Recently>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsRsp {
+    pub error: usize,
+    pub message: String,
+    pub stats: ListenStats,
+}
+
+/// 导出收藏为播放列表，format为m3u、pls或opml，group为空时导出全部分组
+#[derive(Debug, Deserialize)]
+pub struct PlaylistExportReq {
+    pub format: String,
+    pub group: Option<String>,
+}
+
+/// 导入播放列表到group，分组不存在时新建；format为空时按内容判断
+#[derive(Debug, Deserialize)]
+pub struct PlaylistImportReq {
+    pub format: Option<String>,
+    pub group: String,
+    #[serde(default)]
+    pub desc: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaylistImportRsp {
+    pub error: usize,
+    pub message: String,
+    pub group_created: bool,
+    /// 新增的收藏数
+    pub favorites: usize,
+    /// 找不到电台的条目
+    pub unmatched: Vec<String>,
+}
+
+/// 电台搜索每页默认条数
+pub const SEARCH_PAGE_SIZE: i64 = 20;
+/// 电台搜索每页最大条数
+pub const SEARCH_PAGE_MAX: i64 = 200;
+/// 电台搜索最多翻到的位置
+pub const SEARCH_OFFSET_MAX: i64 = 2000;
+/// 搜索词最多使用的关键词数
+pub const SEARCH_TERMS_MAX: usize = 8;
+/// 收藏和最近播放过的电台，相关度乘以此系数
+pub const SEARCH_BOOST: f64 = 2.0;
+/// 参与加权的电台最大数量
+pub const SEARCH_BOOST_MAX: usize = 500;
+
+/// 搜索电台目录，query匹配名称、标签、国家、语言和编码，每个关键词按前缀匹配且都要匹配；
+/// query为空时只按条件过滤；tag、language匹配逗号分隔中的一项，countrycode、codec不区分大小写
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchReq {
+    pub query: Option<String>,
+    pub tag: Option<String>,
+    pub countrycode: Option<String>,
+    pub language: Option<String>,
+    pub codec: Option<String>,
+    pub bitrate_min: Option<i64>,
+    /// 只返回radio-browser最近一次检查可以播放的电台
+    #[serde(default)]
+    pub only_ok: bool,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchRsp {
+    pub error: usize,
+    pub message: String,
+    pub stations: Vec<Station>,
+    /// 下一页的offset，没有更多电台时不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// 电台搜索条件，按相关度、votes倒序，已删除的电台不返回
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StationFilter {
+    /// 小写的关键词，为空时不按文本匹配
+    pub terms: Vec<String>,
+    /// 以下均为小写，countrycode为大写
+    pub tag: Option<String>,
+    pub countrycode: Option<String>,
+    pub language: Option<String>,
+    pub codec: Option<String>,
+    pub bitrate_min: i64,
+    pub only_ok: bool,
+    /// 相关度乘以SEARCH_BOOST的电台
+    pub boost: Vec<String>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl StationFilter {
+    /// 把搜索词拆成关键词，字母和数字以外的字符都作为分隔
+    pub fn terms(query: &str) -> Vec<String> {
+        query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_lowercase())
+            .take(SEARCH_TERMS_MAX)
+            .collect()
+    }
+
+    /// 电台是否满足文本以外的条件
+    pub fn matches(&self, station: &Station) -> bool {
+        let contains = |list: &str, item: &str| {
+            list.split(',').any(|s| s.to_lowercase() == item)
+        };
+        station.deleted == 0
+            && station.bitrate >= self.bitrate_min
+            && (!self.only_ok || station.lastcheckok == 1)
+            && self.tag.as_ref().is_none_or(|t| contains(&station.tags, t))
+            && self
+                .language
+                .as_ref()
+                .is_none_or(|l| contains(&station.language, l))
+            && self
+                .countrycode
+                .as_ref()
+                .is_none_or(|c| station.countrycode.eq_ignore_ascii_case(c))
+            && self
+                .codec
+                .as_ref()
+                .is_none_or(|c| station.codec.eq_ignore_ascii_case(c))
+    }
+}
+
+impl TryFrom<&SearchReq> for StationFilter {
+    type Error = crate::errors::Error;
+
+    fn try_from(req: &SearchReq) -> Result<Self, Self::Error> {
+        let offset = req.offset.unwrap_or_default();
+        if !(0..=SEARCH_OFFSET_MAX).contains(&offset) {
+            return Err(crate::errors::Error::Parse(format!("offset {}", offset)));
+        }
+        // 空字符串按没有条件处理
+        let option = |s: &Option<String>| {
+            s.as_ref()
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+        };
+        Ok(Self {
+            terms: req.query.as_deref().map(Self::terms).unwrap_or_default(),
+            tag: option(&req.tag),
+            countrycode: option(&req.countrycode).map(|s| s.to_uppercase()),
+            language: option(&req.language),
+            codec: option(&req.codec),
+            bitrate_min: req.bitrate_min.unwrap_or_default(),
+            only_ok: req.only_ok,
+            boost: Vec::new(),
+            offset,
+            limit: req
+                .limit
+                .unwrap_or(SEARCH_PAGE_SIZE)
+                .clamp(1, SEARCH_PAGE_MAX),
+        })
+    }
+}
+
+/// 推荐每次默认返回的电台数
+pub const RECOMMEND_PAGE_SIZE: i64 = 20;
+/// 推荐每次最多返回的电台数
+pub const RECOMMEND_PAGE_MAX: i64 = 100;
+
+/// 根据收藏和最近播放推荐电台，不推荐已收藏的电台
+#[derive(Debug, Default, Deserialize)]
+pub struct RecommendReq {
+    pub limit: Option<i64>,
+}
+
+/// 推荐的电台，score越大越相关
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recommendation {
+    pub stationuuid: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecommendRsp {
+    pub error: usize,
+    pub message: String,
+    pub recommendations: Vec<Recommendation>,
+    /// 电台目录中有的电台信息
+    pub stations: Vec<Station>,
+}
+
+/// 热门电台每次默认返回的电台数
+pub const TRENDING_PAGE_SIZE: i64 = 50;
+/// 热门电台每次最多返回的电台数
+pub const TRENDING_PAGE_MAX: i64 = 200;
+/// 热门电台响应的缓存时间(秒)
+pub const TRENDING_MAX_AGE: u64 = 300;
+
+/// 本站用户的热门电台，不需要登录；period为day、week或month，默认week
+#[derive(Debug, Default, Deserialize)]
+pub struct TrendingReq {
+    pub period: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendingRsp {
+    pub error: usize,
+    pub message: String,
+    pub period: String,
+    /// 按热度排序
+    pub trending: Vec<TrendingStation>,
+    /// 电台目录中有的电台信息
+    pub stations: Vec<Station>,
+}
+
+/// 评论内容的最大字符数
+pub const REVIEW_CONTENT_MAX: usize = 500;
+/// 举报理由的最大字符数
+pub const REVIEW_REASON_MAX: usize = 200;
+/// 评论每页默认条数
+pub const REVIEWS_PAGE_SIZE: i64 = 20;
+/// 评论每页最大条数
+pub const REVIEWS_PAGE_MAX: i64 = 100;
+
+/// 评分和评论电台，已评论过的覆盖；content为空时只评分
+#[derive(Debug, Deserialize)]
+pub struct ReviewReq {
+    pub stationuuid: String,
+    /// 1到5星
+    pub rating: i64,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewRsp {
+    pub error: usize,
+    pub message: String,
+    pub review: Review,
+}
+
+/// 删除自己对电台的评论
+#[derive(Debug, Deserialize)]
+pub struct ReviewDeleteReq {
+    pub stationuuid: String,
+}
+
+/// 分页查询，reviews和review_queue使用
+#[derive(Debug, Default, Deserialize)]
+pub struct ReviewsReq {
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// 电台的评分和审核通过的评论，不需要登录
+#[derive(Debug, Serialize)]
+pub struct ReviewsRsp {
+    pub error: usize,
+    pub message: String,
+    pub stationuuid: String,
+    /// 平均评分，没有评分时为0
+    pub rating: f64,
+    pub ratings: i64,
+    /// 按修改时间倒序
+    pub reviews: Vec<PublicReview>,
+    /// 下一页的offset，没有更多评论时不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// 自己的评论，包括待审核和被拒绝的
+#[derive(Debug, Serialize)]
+pub struct MyReviewsRsp {
+    pub error: usize,
+    pub message: String,
+    pub reviews: Vec<Review>,
+}
+
+/// 举报评论
+#[derive(Debug, Deserialize)]
+pub struct ReviewReportReq {
+    pub id: i64,
+    pub reason: Option<String>,
+}
+
+/// 待审核的评论，举报多的在前，只有审核员可以查询
+#[derive(Debug, Serialize)]
+pub struct ReviewQueueRsp {
+    pub error: usize,
+    pub message: String,
+    pub reviews: Vec<Review>,
+    /// 下一页的offset，没有更多评论时不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// 审核评论，status为approved或rejected
+#[derive(Debug, Deserialize)]
+pub struct ReviewModerateReq {
+    pub id: i64,
+    pub status: String,
+}
+
+/// 设备id和设备名的最大字符数
+pub const PRESENCE_DEVICE_MAX: usize = 64;
+
+/// 正在播放的心跳，stationuuid为空时表示停止播放
+#[derive(Debug, Deserialize)]
+pub struct PresenceReq {
+    pub device_id: String,
+    pub device_name: Option<String>,
+    pub stationuuid: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PresenceRsp {
+    pub error: usize,
+    pub message: String,
+    /// 播放状态的有效期(秒)，应在过期前再次发送心跳
+    pub ttl: i64,
+}
+
+/// 正在播放的设备，当前会话的设备current为true
+#[derive(Debug, Serialize)]
+pub struct PresencesRsp {
+    pub error: usize,
+    pub message: String,
+    pub presences: Vec<Presence>,
+    /// 电台目录中有的电台信息
+    pub stations: Vec<Station>,
+}
+
+/// events推送的library_changed事件
+#[derive(Debug, Serialize)]
+pub struct LibraryChangedEvent {
+    pub revision: i64,
 }
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::model::product::Product;
+use crate::model::{hiqradio::StationGroup, product::Product};
 
 use super::BaseRsp;
 
@@ -74,6 +74,22 @@ pub struct OpenProductReq {
     pub product: String,
 }
 
+/// 关闭产品，purge为true时同时清理头像和产品数据
+#[derive(Debug, Deserialize)]
+pub struct CloseProductReq {
+    pub product: String,
+    #[serde(default)]
+    pub purge: bool,
+}
+
+/// 注销账号，需验证密码，或验证码和邮箱验证码
+#[derive(Debug, Deserialize)]
+pub struct CancelReq {
+    pub passwd: Option<String>,
+    pub captcha: Option<String>,
+    pub code: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ResetPasswdReq {
     pub email: String,
@@ -81,3 +97,109 @@ pub struct ResetPasswdReq {
     pub captcha: String,
     pub code: String,
 }
+
+/// 账号归档格式标识
+pub const ARCHIVE_FORMAT: &str = "appserv-archive";
+/// 账号归档格式版本，导入时不接受更新的版本
+pub const ARCHIVE_VERSION: i64 = 1;
+
+/// 账号数据归档，export下载的JSON文件，import原样上传
+///
+/// ```json
+/// {
+///   "format": "appserv-archive",
+///   "version": 1,
+///   "export_time": 1714000000,
+///   "user": { "user_name": "foo", "email": "foo@bar.com", "update_time": 1714000000 },
+///   "products": [
+///     { "product": "hiqradio", "update_time": 1714000000,
+///       "avatar": { "name": "1a2b.png", "data": "<base64>" } }
+///   ],
+///   "hiqradio": {
+///     "groups": [ { "name": "默认", "desc": "", "is_def": 1, "create_time": 1714000000 } ],
+///     "favorites": [ { "group_name": "默认", "stationuuid": "...", "create_time": 1714000000 } ],
+///     "recently": [ { "stationuuid": "...", "start_time": 1714000000000, "end_time": null } ]
+///   }
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: String,
+    pub version: i64,
+    /// 导出时间(秒)
+    pub export_time: i64,
+    pub user: ArchiveUser,
+    #[serde(default)]
+    pub products: Vec<ArchiveProduct>,
+    #[serde(default)]
+    pub hiqradio: ArchiveHiqradio,
+}
+
+/// 用户信息，不含密码
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveUser {
+    pub user_name: String,
+    pub email: String,
+    pub update_time: i64,
+}
+
+/// 已开通的产品及头像
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveProduct {
+    pub product: String,
+    pub update_time: i64,
+    pub avatar: Option<ArchiveAvatar>,
+}
+
+/// 头像文件，data为base64编码的文件内容
+#[derive(Serialize, Deserialize)]
+pub struct ArchiveAvatar {
+    pub name: String,
+    pub data: String,
+}
+
+impl std::fmt::Debug for ArchiveAvatar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveAvatar")
+            .field("name", &self.name)
+            .field("data", &format_args!("{} bytes", self.data.len()))
+            .finish()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ArchiveHiqradio {
+    #[serde(default)]
+    pub groups: Vec<ArchiveGroup>,
+    #[serde(default)]
+    pub favorites: Vec<StationGroup>,
+    #[serde(default)]
+    pub recently: Vec<ArchiveRecently>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveGroup {
+    pub name: String,
+    pub desc: String,
+    pub is_def: i64,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveRecently {
+    pub stationuuid: String,
+    pub start_time: i64,
+    pub end_time: Option<i64>,
+}
+
+/// 导入结果，各项为实际新增的数量，已存在的不重复导入
+#[derive(Debug, Serialize)]
+pub struct ImportRsp {
+    pub error: usize,
+    pub message: String,
+    pub products: usize,
+    pub avatars: usize,
+    pub groups: usize,
+    pub favorites: usize,
+    pub recently: usize,
+}
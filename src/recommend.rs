@@ -0,0 +1,303 @@
+//! 基于协同过滤的电台推荐
+//!
+//! 后台任务定期用全部用户的收藏和最近播放重建相似电台：每个用户是电台向量的一维，
+//! 收藏的权重为1，只播放过的权重为`RECENTLY_WEIGHT`，两个电台的相似度为向量的余弦相似度。
+//! 推荐时以用户的收藏和最近播放为种子，累加种子的相似电台得分，不推荐已收藏的电台。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    catalog,
+    model::hiqradio::{Similar, Station},
+    proto::{RecentlyFilter, Recommendation},
+    repo::DynAppServRepo,
+    Result,
+};
+
+/// 只播放过的电台的权重
+const RECENTLY_WEIGHT: f64 = 0.5;
+/// 每个用户参与计算的电台数，超过时优先取收藏
+const USER_STATIONS_MAX: usize = 200;
+/// 至少有这么多用户同时收藏或播放过，两个电台才算相似
+const MIN_USERS: usize = 2;
+/// 每个电台保留的相似电台数
+const SIMILAR_MAX: usize = 50;
+/// 推荐时使用的最近播放数
+const SEEDS_RECENTLY_MAX: i64 = 200;
+
+/// 分页读取用户电台的条数
+const USER_STATIONS_PAGE: i64 = 10000;
+/// 同时统计的电台对数，超过时丢弃同时出现的用户数较少的电台对
+const PAIRS_MAX: usize = 2_000_000;
+
+/// 电台两两同时出现的统计，电台用下标表示
+#[derive(Debug, Default)]
+struct CoOccurrence {
+    index: HashMap<String, usize>,
+    stations: Vec<String>,
+    /// 电台向量的模的平方
+    norms: Vec<f64>,
+    /// 电台对(小下标, 大下标)的点积和同时出现的用户数
+    pairs: HashMap<(usize, usize), (f64, usize)>,
+}
+
+impl CoOccurrence {
+    fn station(&mut self, stationuuid: String) -> usize {
+        *self
+            .index
+            .entry(stationuuid)
+            .or_insert_with_key(|stationuuid| {
+                self.stations.push(stationuuid.clone());
+                self.norms.push(0.0);
+                self.stations.len() - 1
+            })
+    }
+
+    /// 加入一个用户的电台和权重，超过`USER_STATIONS_MAX`时优先取权重高的
+    fn add_user(&mut self, weights: HashMap<usize, f64>, pairs_max: usize) {
+        let mut weights: Vec<_> = weights.into_iter().collect();
+        weights.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        weights.truncate(USER_STATIONS_MAX);
+
+        for (i, (a, wa)) in weights.iter().enumerate() {
+            self.norms[*a] += wa * wa;
+            for (b, wb) in &weights[i + 1..] {
+                let pair = self.pairs.entry((*a.min(b), *a.max(b))).or_default();
+                pair.0 += wa * wb;
+                pair.1 += 1;
+            }
+        }
+        if self.pairs.len() > pairs_max {
+            self.prune(pairs_max / 2);
+        }
+    }
+
+    /// 只保留同时出现的用户数最多的不超过keep个电台对，用户数相同的一起丢弃
+    fn prune(&mut self, keep: usize) {
+        let mut counts: Vec<_> = self.pairs.values().map(|p| p.1).collect();
+        if counts.len() <= keep {
+            return;
+        }
+        let (_, min, _) = counts.select_nth_unstable_by(keep, |a, b| b.cmp(a));
+        let min = *min;
+        self.pairs.retain(|_, p| p.1 > min);
+    }
+
+    /// 每个电台按相似度倒序取前`SIMILAR_MAX`个相似电台
+    fn similar(self, now: i64) -> Vec<Similar> {
+        let mut similar: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
+        for ((a, b), (dot, count)) in self.pairs {
+            if count < MIN_USERS {
+                continue;
+            }
+            let score = dot / (self.norms[a] * self.norms[b]).sqrt();
+            similar.entry(a).or_default().push((b, score));
+            similar.entry(b).or_default().push((a, score));
+        }
+
+        let stations = self.stations;
+        let mut rows = Vec::new();
+        for (station, mut list) in similar {
+            list.sort_by(|a, b| b.1.total_cmp(&a.1).then(stations[a.0].cmp(&stations[b.0])));
+            list.truncate(SIMILAR_MAX);
+            rows.extend(list.into_iter().map(|(other, score)| Similar {
+                stationuuid: stations[station].clone(),
+                similaruuid: stations[other].clone(),
+                score,
+                update_time: now,
+            }));
+        }
+        rows
+    }
+}
+
+/// 重建相似电台，since之后的最近播放参与计算，返回相似电台的条数
+///
+/// 按用户分页读取，同时只保留一个用户的电台；电台对超过`PAIRS_MAX`时结果是近似的
+pub async fn build(repo: &DynAppServRepo, since: i64, now: i64) -> Result<usize> {
+    let mut co = CoOccurrence::default();
+    let mut user: Option<(i64, HashMap<usize, f64>)> = None;
+    let mut after = (i64::MIN, String::new());
+    loop {
+        let page = repo
+            .query_user_stations(since, after.0, &after.1, USER_STATIONS_PAGE)
+            .await?;
+        let last = page.len() < USER_STATIONS_PAGE as usize;
+        if let Some(s) = page.last() {
+            after = (s.user_id, s.stationuuid.clone());
+        }
+        for s in page {
+            if user.as_ref().is_some_and(|(id, _)| *id != s.user_id) {
+                if let Some((_, weights)) = user.take() {
+                    co.add_user(weights, PAIRS_MAX);
+                }
+            }
+            let weight = if s.favorite == 1 {
+                1.0
+            } else {
+                RECENTLY_WEIGHT
+            };
+            let station = co.station(s.stationuuid);
+            user.get_or_insert_with(|| (s.user_id, HashMap::new()))
+                .1
+                .insert(station, weight);
+        }
+        if last {
+            break;
+        }
+    }
+    if let Some((_, weights)) = user {
+        co.add_user(weights, PAIRS_MAX);
+    }
+
+    let rows = co.similar(now);
+    repo.save_similar(&rows).await?;
+    Ok(rows.len())
+}
+
+/// 给用户推荐电台，按得分倒序，返回推荐和电台目录中有的电台信息；
+/// 已收藏和电台目录中已删除的电台不推荐
+pub async fn recommend(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    since: i64,
+    limit: usize,
+) -> Result<(Vec<Recommendation>, Vec<Station>)> {
+    let favorites: HashSet<_> = repo
+        .query_favorites(user_id)
+        .await?
+        .into_iter()
+        .map(|f| f.stationuuid)
+        .collect();
+
+    let mut seeds: HashMap<String, f64> = favorites.iter().map(|s| (s.clone(), 1.0)).collect();
+    let filter = RecentlyFilter {
+        after: None,
+        since: Some(since),
+        until: None,
+        stationuuid: None,
+        limit: SEEDS_RECENTLY_MAX,
+    };
+    for r in repo.query_recently_page(user_id, &filter).await? {
+        seeds.entry(r.stationuuid).or_insert(RECENTLY_WEIGHT);
+    }
+    if seeds.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let seed_uuids: Vec<_> = seeds.keys().cloned().collect();
+    let mut scores: HashMap<String, f64> = HashMap::new();
+    for s in repo.query_similar(&seed_uuids).await? {
+        if favorites.contains(&s.similaruuid) {
+            continue;
+        }
+        *scores.entry(s.similaruuid).or_default() += seeds[&s.stationuuid] * s.score;
+    }
+
+    let candidates: Vec<_> = scores.keys().cloned().collect();
+    let (deleted, stations): (Vec<_>, Vec<_>) = repo
+        .query_stations(&candidates)
+        .await?
+        .into_iter()
+        .partition(|s| s.deleted != 0);
+    for s in deleted {
+        scores.remove(&s.stationuuid);
+    }
+
+    let mut recommendations: Vec<_> = scores
+        .into_iter()
+        .map(|(stationuuid, score)| Recommendation { stationuuid, score })
+        .collect();
+    recommendations.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.stationuuid.cmp(&b.stationuuid))
+    });
+    recommendations.truncate(limit);
+
+    let stations = stations
+        .into_iter()
+        .filter(|s| {
+            recommendations
+                .iter()
+                .any(|r| r.stationuuid == s.stationuuid)
+        })
+        .collect();
+    let stations = catalog::with_ratings(repo, stations).await?;
+    Ok((recommendations, stations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(co: &mut CoOccurrence, stations: &[(&str, f64)], pairs_max: usize) {
+        let weights = stations
+            .iter()
+            .map(|(s, w)| (co.station(s.to_string()), *w))
+            .collect();
+        co.add_user(weights, pairs_max);
+    }
+
+    fn scores(rows: &[Similar], stationuuid: &str) -> Vec<(String, f64)> {
+        rows.iter()
+            .filter(|s| s.stationuuid == stationuuid)
+            .map(|s| (s.similaruuid.clone(), s.score))
+            .collect()
+    }
+
+    #[test]
+    fn cosine() {
+        let mut co = CoOccurrence::default();
+        add(&mut co, &[("a", 1.0), ("b", 1.0)], PAIRS_MAX);
+        add(
+            &mut co,
+            &[("a", 1.0), ("b", RECENTLY_WEIGHT), ("c", 1.0)],
+            PAIRS_MAX,
+        );
+        let rows = co.similar(100);
+        // a=(1, 1)，b=(1, 0.5)；c只有一个用户，不算相似
+        let score = 1.5 / (2.0f64 * 1.25).sqrt();
+        assert_eq!(scores(&rows, "a"), vec![(String::from("b"), score)]);
+        assert_eq!(scores(&rows, "b"), vec![(String::from("a"), score)]);
+        assert!(scores(&rows, "c").is_empty());
+        assert!(rows.iter().all(|s| s.update_time == 100));
+    }
+
+    #[test]
+    fn user_stations_max() {
+        let mut co = CoOccurrence::default();
+        let stations: Vec<_> = (0..USER_STATIONS_MAX + 10)
+            .map(|i| format!("s{:04}", i))
+            .collect();
+        let weights: Vec<_> = stations
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.as_str(), if i < 10 { RECENTLY_WEIGHT } else { 1.0 }))
+            .collect();
+        add(&mut co, &weights, usize::MAX);
+        // 只播放过的权重低，被去掉
+        let n = USER_STATIONS_MAX;
+        assert_eq!(co.pairs.len(), n * (n - 1) / 2);
+        assert!(co.norms[..10].iter().all(|n| *n == 0.0));
+    }
+
+    #[test]
+    fn prune_pairs() {
+        let mut co = CoOccurrence::default();
+        add(&mut co, &[("a", 1.0), ("b", 1.0)], 2);
+        add(&mut co, &[("a", 1.0), ("b", 1.0)], 2);
+        add(&mut co, &[("c", 1.0), ("d", 1.0)], 2);
+        assert_eq!(co.pairs.len(), 2);
+        // 超过2对时只保留同时出现的用户数最多的1对
+        add(&mut co, &[("e", 1.0), ("f", 1.0)], 2);
+        assert_eq!(co.pairs.keys().collect::<Vec<_>>(), vec![&(0, 1)]);
+        // 用户数相同时一起丢弃
+        add(&mut co, &[("c", 1.0), ("d", 1.0)], 2);
+        add(&mut co, &[("e", 1.0), ("f", 1.0)], 2);
+        assert_eq!(co.pairs.keys().collect::<Vec<_>>(), vec![&(0, 1)]);
+
+        let rows = co.similar(0);
+        assert_eq!(scores(&rows, "a"), vec![(String::from("b"), 1.0)]);
+    }
+}
@@ -0,0 +1,245 @@
+//! 账号数据导出和导入
+//!
+//! 导出的格式见`Archive`。导入到当前账号时与已有数据合并：已开通的产品和已设置的头像保持不变，
+//! hiqradio数据转换成离线操作交给`merge::push_sync`，同名分组、相同收藏和播放记录不会重复写入。
+
+use std::fs;
+
+use base64::prelude::*;
+use chrono::Local;
+use nanoid::nanoid;
+
+use crate::{
+    config::CONFIG,
+    errors::Error,
+    proto::{
+        Archive, ArchiveAvatar, ArchiveGroup, ArchiveHiqradio, ArchiveProduct, ArchiveRecently,
+        ArchiveUser, SyncOp, ARCHIVE_FORMAT, ARCHIVE_VERSION, SYNC_OP_APPLIED,
+    },
+    Result,
+};
+
+use super::{merge, DynAppServRepo};
+
+/// 导入的数量
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Imported {
+    /// 新开通的产品
+    pub products: usize,
+    pub avatars: usize,
+    pub groups: usize,
+    pub favorites: usize,
+    pub recently: usize,
+}
+
+impl Imported {
+    /// hiqradio数据是否有修改
+    pub fn library_changed(&self) -> bool {
+        self.groups + self.favorites + self.recently > 0
+    }
+}
+
+fn read_avatar(name: &str) -> Result<Option<ArchiveAvatar>> {
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let path = format!("{}/{}", &CONFIG.avatar_path, name);
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::warn!("avatar file {} not found, skip", path);
+            return Ok(None);
+        }
+        Err(e) => return Err(Error::Internal(format!("read file error: {}", e))),
+    };
+    Ok(Some(ArchiveAvatar {
+        name: name.to_string(),
+        data: BASE64_STANDARD.encode(data),
+    }))
+}
+
+/// 头像按上传的规则重新命名，只保留扩展名
+fn write_avatar(avatar: &ArchiveAvatar) -> Result<String> {
+    let data = BASE64_STANDARD
+        .decode(&avatar.data)
+        .map_err(|e| Error::Parse(format!("avatar {}: {}", avatar.name, e)))?;
+
+    let ext = avatar
+        .name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(|ext| format!(".{}", ext))
+        .unwrap_or_default();
+    let alphabet: [char; 16] = [
+        '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f',
+    ];
+    let file_name = format!("{}{}", nanoid!(32, &alphabet), ext);
+
+    let path = format!("{}/{}", &CONFIG.avatar_path, file_name);
+    fs::write(&path, data)
+        .map_err(|e| Error::Internal(format!("fail to write file: {}, error: {}", path, e)))?;
+    Ok(file_name)
+}
+
+/// 导出用户的全部数据
+pub async fn export(repo: &DynAppServRepo, user_id: i64) -> Result<Archive> {
+    let user = repo.query_user(user_id).await?;
+
+    let mut products = Vec::new();
+    for product in repo.query_user_products(user_id).await? {
+        let user_product = repo
+            .query_user_product(user_id, product.id.unwrap())
+            .await?;
+        products.push(ArchiveProduct {
+            product: product.product,
+            update_time: user_product.update_time,
+            avatar: read_avatar(&user_product.avatar)?,
+        });
+    }
+
+    let groups = repo
+        .query_groups(user_id)
+        .await?
+        .into_iter()
+        .map(|g| ArchiveGroup {
+            name: g.name,
+            desc: g.desc,
+            is_def: g.is_def,
+            create_time: g.create_time,
+        })
+        .collect();
+    let favorites = repo.query_favorites(user_id).await?;
+    let recently = repo
+        .query_recently(user_id)
+        .await?
+        .into_iter()
+        .map(|r| ArchiveRecently {
+            stationuuid: r.stationuuid,
+            start_time: r.start_time,
+            end_time: r.end_time,
+        })
+        .collect();
+
+    Ok(Archive {
+        format: String::from(ARCHIVE_FORMAT),
+        version: ARCHIVE_VERSION,
+        export_time: Local::now().timestamp(),
+        user: ArchiveUser {
+            user_name: user.user_name,
+            email: user.email,
+            update_time: user.update_time,
+        },
+        products,
+        hiqradio: ArchiveHiqradio {
+            groups,
+            favorites,
+            recently,
+        },
+    })
+}
+
+/// 开通归档中的产品，产品未设置头像时使用归档中的头像
+async fn import_products(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    archive: &Archive,
+    imported: &mut Imported,
+) -> Result {
+    let opened = repo.query_user_products(user_id).await?;
+    for p in archive.products.iter() {
+        if !opened.iter().any(|o| o.product == p.product) {
+            match repo.open_product(user_id, &p.product).await {
+                Ok(()) => imported.products += 1,
+                Err(Error::ProductNotExists) => {
+                    tracing::warn!("import product {} not exists, skip", p.product);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let Some(avatar) = &p.avatar else {
+            continue;
+        };
+        let product = repo
+            .query_user_products(user_id)
+            .await?
+            .into_iter()
+            .find(|o| o.product == p.product)
+            .ok_or(Error::ProductNotOpen)?;
+        let product_id = product.id.unwrap();
+        let user_product = repo.query_user_product(user_id, product_id).await?;
+        if user_product.avatar.is_empty() {
+            let file_name = write_avatar(avatar)?;
+            repo.update_user_info(user_id, product_id, None, None, Some(file_name))
+                .await?;
+            imported.avatars += 1;
+        }
+    }
+    Ok(())
+}
+
+/// hiqradio数据转换成离线操作，已有默认分组时归档中的默认分组作为普通分组导入
+async fn library_ops(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    hiqradio: &ArchiveHiqradio,
+) -> Result<Vec<SyncOp>> {
+    let has_def = repo
+        .query_groups(user_id)
+        .await?
+        .iter()
+        .any(|g| g.is_def == 1);
+
+    let groups = hiqradio.groups.iter().map(|g| SyncOp::GroupNew {
+        time: g.create_time,
+        name: g.name.clone(),
+        desc: g.desc.clone(),
+        is_def: if has_def { 0 } else { g.is_def },
+    });
+    let favorites = hiqradio.favorites.iter().map(|f| SyncOp::FavoriteNew {
+        time: f.create_time,
+        group_name: f.group_name.clone(),
+        stationuuid: f.stationuuid.clone(),
+    });
+    let recently = hiqradio.recently.iter().map(|r| SyncOp::RecentlyNew {
+        stationuuid: r.stationuuid.clone(),
+        start_time: r.start_time,
+        end_time: r.end_time,
+    });
+
+    Ok(groups.chain(favorites).chain(recently).collect())
+}
+
+/// 导入归档到user_id的账号，与已有数据合并，返回实际导入的数量
+pub async fn import(repo: &DynAppServRepo, user_id: i64, archive: &Archive) -> Result<Imported> {
+    if archive.format != ARCHIVE_FORMAT || archive.version > ARCHIVE_VERSION {
+        return Err(Error::Parse(format!(
+            "archive format {} version {}",
+            archive.format, archive.version
+        )));
+    }
+
+    let mut imported = Imported::default();
+    import_products(repo, user_id, archive, &mut imported).await?;
+
+    let ops = library_ops(repo, user_id, &archive.hiqradio).await?;
+    if !ops.is_empty() {
+        let revision = repo.query_revision(user_id).await?;
+        let (_, results) = merge::push_sync(repo, user_id, revision, &ops).await?;
+        for (op, result) in ops.iter().zip(results) {
+            if result != SYNC_OP_APPLIED {
+                continue;
+            }
+            match op {
+                SyncOp::GroupNew { .. } => imported.groups += 1,
+                SyncOp::FavoriteNew { .. } => imported.favorites += 1,
+                SyncOp::RecentlyNew { .. } => imported.recently += 1,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(imported)
+}
@@ -0,0 +1,1892 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::Local;
+
+use crate::{
+    config::CONFIG,
+    errors::Error,
+    model::{
+        hiqradio::{
+            FavGroup, Favorite, Presence, Rating, Recently, Review, Setting, Share, Similar,
+            Station, StationGroup, StationListen, Tombstone, TrendingStation, UserStation,
+            REVIEW_STATUS_APPROVED, REVIEW_STATUS_PENDING, TOMBSTONE_FAVORITE_DELETE,
+            TOMBSTONE_GROUP_DELETE, TOMBSTONE_RECENTLY_CLEAR,
+        },
+        product::{Product, PRODUCT_HIQRADIO, PRODUCT_STATUS_NORMAL},
+        session::Session,
+        user::{User, USER_STATUS_CANCEL, USER_STATUS_NORMAL},
+        user_product::{UserProduct, USER_PRODUCT_STATUS_CANCEL, USER_PRODUCT_STATUS_NORMAL},
+    },
+    proto::{
+        GroupNew, RecentlyFilter, RecentlyNew, ResetPasswdReq, SignInReq, SignUpReq, StationFilter,
+        StationStats, SyncOp, SEARCH_BOOST,
+    },
+    util::gen_passwd,
+    Result,
+};
+
+use super::{
+    migrate::SCHEMA_VERSION, retention::RecentlyRetention, stats::HourPlays, AppServRepo, Change,
+};
+
+/// 内存中的表，字段和sqlite的表一一对应，模型中没有的列放在元组里
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    seq: HashMap<&'static str, i64>,
+    session: Vec<Session>,
+    user: Vec<User>,
+    product: Vec<(Product, String)>,
+    user_product: Vec<UserProduct>,
+    hiqradio_revision: HashMap<i64, i64>,
+    hiqradio_recently: Vec<(Recently, i64)>,
+    hiqradio_fav_group: Vec<(FavGroup, i64)>,
+    hiqradio_favorite: Vec<(Favorite, i64)>,
+    hiqradio_tombstone: Vec<Tombstone>,
+    hiqradio_setting: HashMap<i64, Setting>,
+    hiqradio_share: Vec<(Share, i64)>,
+    hiqradio_similar: Vec<Similar>,
+    hiqradio_trending: Vec<TrendingStation>,
+    hiqradio_review: Vec<Review>,
+    hiqradio_review_report: Vec<(i64, i64)>,
+    hiqradio_presence: Vec<Presence>,
+    hiqradio_station: HashMap<String, Station>,
+}
+
+impl Tables {
+    /// 每张表独立自增，和autoincrement一致
+    fn next_id(&mut self, table: &'static str) -> i64 {
+        let seq = self.seq.entry(table).or_default();
+        *seq += 1;
+        *seq
+    }
+
+    /// 分享关联的分组仍存在时返回分享，分组名取当前的名字
+    fn shared_group(&self, share: &Share, group_id: i64) -> Option<(Share, i64)> {
+        self.hiqradio_fav_group
+            .iter()
+            .find(|(g, _)| g.id == Some(group_id))
+            .map(|(g, _)| {
+                (
+                    Share {
+                        group_name: g.name.clone(),
+                        ..share.clone()
+                    },
+                    g.position,
+                )
+            })
+    }
+
+    /// 用户未注销且开通了hiqradio，公开的分享和评论只展示这些用户的
+    fn hiqradio_user(&self, user_id: i64) -> bool {
+        self.user
+            .iter()
+            .any(|u| u.id == Some(user_id) && u.status == USER_STATUS_NORMAL)
+            && self
+                .user_products(user_id)
+                .iter()
+                .any(|p| p.product == PRODUCT_HIQRADIO)
+    }
+
+    /// 填充评论者的用户名，注册时默认的用户名是邮箱的前缀，不公开
+    fn with_user_name(&self, review: &Review) -> Review {
+        let user_name = self
+            .user
+            .iter()
+            .find(|u| u.id == Some(review.user_id))
+            .filter(|u| u.email.split('@').next() != Some(u.user_name.as_str()))
+            .map(|u| u.user_name.clone())
+            .unwrap_or_default();
+        Review {
+            user_name,
+            ..review.clone()
+        }
+    }
+
+    fn user_products(&self, user_id: i64) -> Vec<Product> {
+        self.user_product
+            .iter()
+            .filter(|up| up.user_id == user_id && up.status == USER_PRODUCT_STATUS_NORMAL)
+            .filter_map(|up| {
+                self.product
+                    .iter()
+                    .find(|(p, status)| {
+                        p.id == Some(up.product_id) && status == PRODUCT_STATUS_NORMAL
+                    })
+                    .map(|(p, _)| p.clone())
+            })
+            .collect()
+    }
+
+    fn open_product(&mut self, user_id: i64, product: &str) -> Result<Product> {
+        let product = self
+            .product
+            .iter()
+            .find(|(p, status)| p.product == product && status == PRODUCT_STATUS_NORMAL)
+            .map(|(p, _)| p.clone())
+            .ok_or(Error::ProductNotExists)?;
+
+        // 关闭过的产品恢复原记录，不新增重复记录
+        if let Some(up) = self.user_product.iter_mut().find(|up| {
+            up.user_id == user_id
+                && up.product_id == product.id.unwrap()
+                && up.status == USER_PRODUCT_STATUS_CANCEL
+        }) {
+            up.status = String::from(USER_PRODUCT_STATUS_NORMAL);
+            up.update_time = Local::now().timestamp();
+            return Ok(product);
+        }
+
+        let id = self.next_id("user_product");
+        self.user_product.push(UserProduct {
+            id: Some(id),
+            user_id,
+            product_id: product.id.unwrap(),
+            avatar: String::new(),
+            status: String::from(USER_PRODUCT_STATUS_NORMAL),
+            update_time: Local::now().timestamp(),
+        });
+
+        Ok(product)
+    }
+
+    /// 删除用户的hiqradio数据，删除记录和版本号一并删除，关闭产品或注销后不再同步
+    fn purge_hiqradio(&mut self, user_id: i64) {
+        self.hiqradio_recently.retain(|(r, _)| r.user_id != user_id);
+        self.hiqradio_favorite.retain(|(f, _)| f.user_id != user_id);
+        self.hiqradio_fav_group.retain(|(g, _)| g.user_id != user_id);
+        self.hiqradio_tombstone.retain(|t| t.user_id != user_id);
+        self.hiqradio_revision.remove(&user_id);
+        self.hiqradio_setting.remove(&user_id);
+        self.hiqradio_share.retain(|(s, _)| s.user_id != user_id);
+        let reviews: HashSet<_> = self
+            .hiqradio_review
+            .iter()
+            .filter(|r| r.user_id == user_id)
+            .filter_map(|r| r.id)
+            .collect();
+        self.hiqradio_review_report
+            .retain(|(id, uid)| *uid != user_id && !reviews.contains(id));
+        self.hiqradio_review.retain(|r| r.user_id != user_id);
+        self.hiqradio_presence.retain(|p| p.user_id != user_id);
+    }
+
+    /// 删除注销用户的会话和hiqradio数据，清空头像，匿名化用户信息
+    fn purge_user(&mut self, user_id: i64) {
+        self.purge_hiqradio(user_id);
+        self.session.retain(|s| s.user_id != user_id);
+        self.user_product
+            .iter_mut()
+            .filter(|up| up.user_id == user_id)
+            .for_each(|up| up.avatar.clear());
+        if let Some(user) = self.user.iter_mut().find(|u| u.id == Some(user_id)) {
+            user.user_name.clear();
+            user.email = format!("cancelled-{}", user_id);
+            user.passwd.clear();
+        }
+    }
+
+    fn clean_recently(&mut self, now: i64, max_days: i64, max_rows: i64) -> usize {
+        let mut times: HashMap<i64, Vec<i64>> = HashMap::new();
+        for (r, _) in self.hiqradio_recently.iter() {
+            times.entry(r.user_id).or_default().push(r.start_time);
+        }
+        let cutoffs: Vec<_> = times
+            .into_iter()
+            .filter_map(|(user_id, mut times)| {
+                times.sort_unstable_by_key(|t| Reverse(*t));
+                let setting = self.hiqradio_setting.get(&user_id);
+                let retention = RecentlyRetention {
+                    user_id,
+                    oldest: *times.last()?,
+                    newest: times[0],
+                    // 每个用户只保留最新的max_rows条
+                    kept_oldest: usize::try_from(max_rows)
+                        .ok()
+                        .and_then(|n| times.get(n.checked_sub(1)?))
+                        .copied(),
+                    recently_enabled: setting.map(|s| s.recently_enabled),
+                    recently_days: setting.map(|s| s.recently_days),
+                };
+                Some((user_id, retention.cutoff(now, max_days)?))
+            })
+            .collect();
+
+        cutoffs
+            .into_iter()
+            .map(|(user_id, cutoff)| {
+                let change = self.next_change(user_id);
+                self.clear_recently(change, Some(cutoff))
+            })
+            .sum()
+    }
+
+    /// 删除用户开始时间早于before的最近播放并写入清空记录，before为None时全部删除、清空时间取change.time；
+    /// 已有更晚的清空记录时沿用其时间，返回删除的条数
+    fn clear_recently(&mut self, change: Change, before: Option<i64>) -> usize {
+        let size = self.hiqradio_recently.len();
+        self.hiqradio_recently.retain(|(r, _)| {
+            r.user_id != change.user_id || before.is_some_and(|before| r.start_time >= before)
+        });
+
+        let cleared = self
+            .hiqradio_tombstone
+            .iter()
+            .filter(|t| t.user_id == change.user_id && t.kind == TOMBSTONE_RECENTLY_CLEAR)
+            .map(|t| t.create_time)
+            .max();
+        let time = before.unwrap_or(change.time).max(cleared.unwrap_or(i64::MIN));
+        self.delete_tombstone(change.user_id, TOMBSTONE_RECENTLY_CLEAR, "", "");
+        self.new_tombstone(Tombstone::recently_clear(
+            change.user_id,
+            change.revision,
+            time,
+        ));
+
+        size - self.hiqradio_recently.len()
+    }
+
+    /// 递增用户版本号，一次修改使用同一版本号
+    fn next_change(&mut self, user_id: i64) -> Change {
+        let revision = self.hiqradio_revision.entry(user_id).or_default();
+        *revision += 1;
+        Change {
+            user_id,
+            revision: *revision,
+            time: Local::now().timestamp(),
+        }
+    }
+
+    fn new_tombstone(&mut self, tombstone: Tombstone) {
+        let id = self.next_id("hiqradio_tombstone");
+        self.hiqradio_tombstone.push(Tombstone {
+            id: Some(id),
+            ..tombstone
+        });
+    }
+
+    /// 记录重新创建后，之前的删除记录作废
+    fn delete_tombstone(&mut self, user_id: i64, kind: &str, group_name: &str, stationuuid: &str) {
+        self.hiqradio_tombstone.retain(|t| {
+            !(t.user_id == user_id
+                && t.kind == kind
+                && t.group_name == group_name
+                && t.stationuuid == stationuuid)
+        });
+    }
+
+    fn group(&self, user_id: i64, name: &str) -> Option<&FavGroup> {
+        self.hiqradio_fav_group
+            .iter()
+            .map(|(g, _)| g)
+            .find(|g| g.user_id == user_id && g.name == name)
+    }
+
+    fn groups(&self, user_id: i64, revisions: impl RangeBounds<i64>) -> Vec<FavGroup> {
+        let mut groups: Vec<_> = self
+            .hiqradio_fav_group
+            .iter()
+            .filter(|(g, revision)| g.user_id == user_id && revisions.contains(revision))
+            .map(|(g, _)| g.clone())
+            .collect();
+        groups.sort_by_key(|g| (g.position, g.id));
+        groups
+    }
+
+    fn station_groups(&self, user_id: i64, revisions: impl RangeBounds<i64>) -> Vec<StationGroup> {
+        let mut favorites: Vec<_> = self
+            .hiqradio_favorite
+            .iter()
+            .filter(|(f, revision)| f.user_id == user_id && revisions.contains(revision))
+            .filter_map(|(f, _)| {
+                self.hiqradio_fav_group
+                    .iter()
+                    .find(|(g, _)| g.id == Some(f.group_id) && g.user_id == f.user_id)
+                    .map(|(g, _)| ((g.position, g.id, f.position, f.id), g, f))
+            })
+            .collect();
+        favorites.sort_by_key(|(key, _, _)| *key);
+        favorites
+            .into_iter()
+            .map(|(_, g, f)| StationGroup {
+                group_name: g.name.clone(),
+                stationuuid: f.stationuuid.clone(),
+                create_time: f.create_time,
+                position: f.position,
+            })
+            .collect()
+    }
+
+    /// 新建分组排在最后
+    fn next_group_position(&self, user_id: i64) -> i64 {
+        self.hiqradio_fav_group
+            .iter()
+            .filter(|(g, _)| g.user_id == user_id)
+            .map(|(g, _)| g.position + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// 新收藏排在分组的最后
+    fn next_favorite_position(&self, group_id: i64) -> i64 {
+        self.hiqradio_favorite
+            .iter()
+            .filter(|(f, _)| f.group_id == group_id)
+            .map(|(f, _)| f.position + 1)
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn recently(&self, user_id: i64, revisions: impl RangeBounds<i64>) -> Vec<Recently> {
+        let mut recently: Vec<_> = self
+            .hiqradio_recently
+            .iter()
+            .filter(|(r, revision)| r.user_id == user_id && revisions.contains(revision))
+            .map(|(r, _)| r.clone())
+            .collect();
+        recently.sort_by_key(|r| Reverse(r.start_time));
+        recently
+    }
+    fn new_recently(&mut self, change: Change, recently: &[RecentlyNew]) {
+        for e in recently.iter() {
+            let id = self.next_id("hiqradio_recently");
+            self.hiqradio_recently.push((
+                Recently {
+                    id: Some(id),
+                    user_id: change.user_id,
+                    stationuuid: e.stationuuid.clone(),
+                    start_time: e.start_time,
+                    end_time: e.end_time,
+                },
+                change.revision,
+            ));
+        }
+    }
+
+    fn modify_recently(&mut self, change: Change, stationuuid: &str, start_time: i64, end_time: i64) {
+        self.hiqradio_recently
+            .iter_mut()
+            .filter(|(r, _)| {
+                r.user_id == change.user_id
+                    && r.stationuuid == stationuuid
+                    && r.start_time == start_time
+            })
+            .for_each(|(r, revision)| {
+                r.end_time = Some(end_time);
+                *revision = change.revision;
+            });
+    }
+
+    fn delete_groups(&mut self, change: Change, groups: &[String]) -> Result {
+        let user_id = change.user_id;
+        let mut group_ids = Vec::new();
+        for e in groups.iter() {
+            let group = self
+                .group(user_id, e)
+                .ok_or_else(|| Error::DatabaseException(format!("group \"{}\" not found", e)))?;
+            group_ids.push(group.id);
+        }
+
+        self.hiqradio_favorite
+            .retain(|(f, _)| !group_ids.contains(&Some(f.group_id)));
+        self.hiqradio_fav_group
+            .retain(|(g, _)| !(g.user_id == user_id && groups.contains(&g.name)));
+        for e in groups.iter() {
+            self.new_tombstone(Tombstone::group_delete(
+                user_id,
+                change.revision,
+                change.time,
+                e,
+            ));
+        }
+        Ok(())
+    }
+
+    fn new_groups(&mut self, change: Change, groups: &[GroupNew]) {
+        let user_id = change.user_id;
+        for e in groups.iter() {
+            if self.group(user_id, &e.name).is_some() {
+                continue;
+            }
+
+            if e.is_def > 0 {
+                if let Some((fg, _)) = self
+                    .hiqradio_fav_group
+                    .iter()
+                    .find(|(g, _)| g.user_id == user_id && g.is_def == 1)
+                {
+                    if fg.create_time > e.create_time {
+                        continue;
+                    }
+
+                    let tombstone =
+                        Tombstone::group_delete(user_id, change.revision, change.time, &fg.name);
+                    self.hiqradio_fav_group
+                        .retain(|(g, _)| !(g.user_id == user_id && g.is_def == 1));
+                    self.new_tombstone(tombstone);
+                }
+            }
+
+            let id = self.next_id("hiqradio_fav_group");
+            let position = self.next_group_position(user_id);
+            self.hiqradio_fav_group.push((
+                FavGroup {
+                    id: Some(id),
+                    user_id,
+                    create_time: e.create_time,
+                    name: e.name.clone(),
+                    desc: e.desc.clone(),
+                    is_def: e.is_def,
+                    position,
+                },
+                change.revision,
+            ));
+            self.delete_tombstone(user_id, TOMBSTONE_GROUP_DELETE, &e.name, "");
+        }
+    }
+
+    fn modify_group(&mut self, change: Change, old_name: &str, name: &str, desc: &str) {
+        let user_id = change.user_id;
+        let mut modified = 0;
+        self.hiqradio_fav_group
+            .iter_mut()
+            .filter(|(g, _)| g.user_id == user_id && g.name == old_name)
+            .for_each(|(g, revision)| {
+                g.name = name.to_string();
+                g.desc = desc.to_string();
+                *revision = change.revision;
+                modified += 1;
+            });
+
+        if modified > 0 {
+            self.new_tombstone(Tombstone::group_modify(
+                user_id,
+                change.revision,
+                change.time,
+                old_name,
+                name,
+                desc,
+            ));
+            self.delete_tombstone(user_id, TOMBSTONE_GROUP_DELETE, name, "");
+        }
+    }
+
+    fn new_favorite(&mut self, change: Change, stations: &[StationGroup]) -> Result {
+        let user_id = change.user_id;
+
+        // 分组不存在时整批失败，先检查再写入
+        let mut group_ids = Vec::new();
+        for elem in stations.iter() {
+            let group = self.group(user_id, &elem.group_name).ok_or_else(|| {
+                Error::DatabaseException(format!("group \"{}\" not found", &elem.group_name))
+            })?;
+            group_ids.push(group.id.unwrap());
+        }
+
+        for (elem, group_id) in stations.iter().zip(group_ids) {
+            if self.hiqradio_favorite.iter().any(|(f, _)| {
+                f.user_id == user_id && f.group_id == group_id && f.stationuuid == elem.stationuuid
+            }) {
+                continue;
+            }
+
+            let id = self.next_id("hiqradio_favorite");
+            let position = self.next_favorite_position(group_id);
+            self.hiqradio_favorite.push((
+                Favorite {
+                    id: Some(id),
+                    user_id,
+                    stationuuid: elem.stationuuid.clone(),
+                    group_id,
+                    create_time: elem.create_time,
+                    position,
+                },
+                change.revision,
+            ));
+            self.delete_tombstone(
+                user_id,
+                TOMBSTONE_FAVORITE_DELETE,
+                &elem.group_name,
+                &elem.stationuuid,
+            );
+        }
+        Ok(())
+    }
+
+    /// 删除分组中的一个收藏
+    fn delete_group_favorite(&mut self, change: Change, group_name: &str, stationuuid: &str) {
+        let user_id = change.user_id;
+        let Some(group_id) = self.group(user_id, group_name).and_then(|g| g.id) else {
+            return;
+        };
+
+        let len = self.hiqradio_favorite.len();
+        self.hiqradio_favorite.retain(|(f, _)| {
+            !(f.user_id == user_id && f.group_id == group_id && f.stationuuid == stationuuid)
+        });
+        if self.hiqradio_favorite.len() < len {
+            self.new_tombstone(Tombstone::favorite_delete(
+                user_id,
+                change.revision,
+                change.time,
+                group_name,
+                stationuuid,
+            ));
+        }
+    }
+
+    fn modify_favorite(&mut self, change: Change, stationuuid: &str, groups: &[String]) -> Result {
+        let user_id = change.user_id;
+        if !self
+            .hiqradio_favorite
+            .iter()
+            .any(|(f, _)| f.user_id == user_id && f.stationuuid == stationuuid)
+        {
+            return Err(Error::DatabaseException("station not found".to_string()));
+        }
+
+        for e in self.station_groups(user_id, ..) {
+            if e.stationuuid == stationuuid && !groups.contains(&e.group_name) {
+                self.new_tombstone(Tombstone::favorite_delete(
+                    user_id,
+                    change.revision,
+                    change.time,
+                    &e.group_name,
+                    stationuuid,
+                ));
+            }
+        }
+        let old_positions: HashMap<_, _> = self
+            .hiqradio_favorite
+            .iter()
+            .filter(|(f, _)| f.user_id == user_id && f.stationuuid == stationuuid)
+            .map(|(f, _)| (f.group_id, f.position))
+            .collect();
+        self.hiqradio_favorite
+            .retain(|(f, _)| !(f.user_id == user_id && f.stationuuid == stationuuid));
+
+        let new_groups: Vec<_> = self
+            .hiqradio_fav_group
+            .iter()
+            .filter(|(g, _)| g.user_id == user_id && groups.contains(&g.name))
+            .map(|(g, _)| (g.id.unwrap(), g.name.clone()))
+            .collect();
+
+        for (group_id, group_name) in new_groups {
+            let id = self.next_id("hiqradio_favorite");
+            // 仍在原分组中的收藏保持原来的位置
+            let position = old_positions
+                .get(&group_id)
+                .copied()
+                .unwrap_or_else(|| self.next_favorite_position(group_id));
+            self.hiqradio_favorite.push((
+                Favorite {
+                    id: Some(id),
+                    user_id,
+                    stationuuid: stationuuid.to_string(),
+                    group_id,
+                    create_time: change.time,
+                    position,
+                },
+                change.revision,
+            ));
+            self.delete_tombstone(user_id, TOMBSTONE_FAVORITE_DELETE, &group_name, stationuuid);
+        }
+
+        Ok(())
+    }
+
+    /// 应用一个离线操作，time为客户端操作时间
+    fn apply_op(&mut self, change: Change, op: &SyncOp) -> Result {
+        match op {
+            SyncOp::GroupNew {
+                time,
+                name,
+                desc,
+                is_def,
+            } => {
+                let group = GroupNew {
+                    create_time: *time,
+                    name: name.clone(),
+                    desc: desc.clone(),
+                    is_def: *is_def,
+                };
+                self.new_groups(Change { time: *time, ..change }, &[group]);
+            }
+            SyncOp::GroupModify {
+                time,
+                old_name,
+                name,
+                desc,
+            } => self.modify_group(Change { time: *time, ..change }, old_name, name, desc),
+            SyncOp::GroupDelete { time, name } => {
+                self.delete_groups(Change { time: *time, ..change }, std::slice::from_ref(name))?
+            }
+            SyncOp::FavoriteNew {
+                time,
+                group_name,
+                stationuuid,
+            } => {
+                let station = StationGroup {
+                    group_name: group_name.clone(),
+                    stationuuid: stationuuid.clone(),
+                    create_time: *time,
+                    position: 0,
+                };
+                self.new_favorite(change, &[station])?;
+            }
+            SyncOp::FavoriteModify {
+                time,
+                stationuuid,
+                group_names,
+            } => self.modify_favorite(Change { time: *time, ..change }, stationuuid, group_names)?,
+            SyncOp::FavoriteDelete {
+                time,
+                group_name,
+                stationuuid,
+            } => self.delete_group_favorite(
+                Change { time: *time, ..change },
+                group_name,
+                stationuuid,
+            ),
+            SyncOp::RecentlyNew {
+                stationuuid,
+                start_time,
+                end_time,
+            } => {
+                let recently = RecentlyNew {
+                    stationuuid: stationuuid.clone(),
+                    start_time: *start_time,
+                    end_time: *end_time,
+                };
+                self.new_recently(change, &[recently]);
+            }
+            SyncOp::RecentlyModify {
+                stationuuid,
+                start_time,
+                end_time,
+            } => self.modify_recently(change, stationuuid, *start_time, *end_time),
+        }
+        Ok(())
+    }
+}
+
+/// 关键词的相关度，名称、标签、国家和语言、编码的权重依次降低，有关键词不匹配时返回None
+fn station_rank(station: &Station, terms: &[String]) -> Option<f64> {
+    let fields = [
+        (&station.name, 10.0),
+        (&station.tags, 5.0),
+        (&station.country, 2.0),
+        (&station.language, 2.0),
+        (&station.codec, 1.0),
+    ];
+    if terms.is_empty() {
+        return Some(1.0);
+    }
+    let mut rank = 0.0;
+    for term in terms {
+        let hits: f64 = fields
+            .iter()
+            .filter(|(field, _)| {
+                field
+                    .split(|c: char| !c.is_alphanumeric())
+                    .any(|word| word.to_lowercase().starts_with(term.as_str()))
+            })
+            .map(|(_, weight)| weight)
+            .sum();
+        if hits == 0.0 {
+            return None;
+        }
+        rank += hits;
+    }
+    Some(rank)
+}
+
+/// 纯内存实现，用于测试和演示，进程退出后数据丢失
+#[derive(Debug, Clone)]
+pub struct MemoryRepo {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl MemoryRepo {
+    pub fn new() -> Self {
+        let mut tables = Tables::default();
+        let id = tables.next_id("product");
+        tables.product.push((
+            Product {
+                id: Some(id),
+                product: String::from("hiqradio"),
+                desc: String::from("hiqradio listen the whole world"),
+                update_time: Local::now().timestamp(),
+            },
+            String::from(PRODUCT_STATUS_NORMAL),
+        ));
+
+        Self {
+            tables: Arc::new(Mutex::new(tables)),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Tables>> {
+        self.tables
+            .lock()
+            .map_err(|e| Error::DatabaseException(format!("memory repo poisoned: {}", e)))
+    }
+
+    /// 递增版本号后修改，失败时恢复修改前的数据，和数据库事务一致
+    fn change<T>(&self, user_id: i64, f: impl FnOnce(&mut Tables, Change) -> Result<T>) -> Result<T> {
+        let mut tables = self.lock()?;
+        let backup = tables.clone();
+        let change = tables.next_change(user_id);
+        f(&mut tables, change).inspect_err(|_| *tables = backup)
+    }
+
+    fn user_from_signup(&self, signup: &SignUpReq, passwd: String) -> User {
+        let user_name = signup
+            .email
+            .split('@')
+            .collect::<Vec<_>>()
+            .first()
+            .unwrap()
+            .to_string();
+        User {
+            id: None,
+            user_name,
+            email: signup.email.clone(),
+            passwd,
+            status: String::from(USER_STATUS_NORMAL),
+            update_time: Local::now().timestamp_millis(),
+        }
+    }
+}
+
+impl Default for MemoryRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl AppServRepo for MemoryRepo {
+    async fn schema_version(&self) -> Result<i64> {
+        Ok(SCHEMA_VERSION)
+    }
+    async fn migrate(&self) -> Result {
+        Ok(())
+    }
+
+    async fn backup(&self, _path: &str) -> Result {
+        Err(Error::Internal(String::from("memory repo backup not supported")))
+    }
+
+    async fn clean_avatar_path(&self, path: &str) -> Result {
+        let used = self.lock()?.user_product.iter().any(|up| up.avatar == path);
+        if !used {
+            let path = format!("{}/{}", &CONFIG.avatar_path, path);
+            tracing::info!("remove unused avatar: {}", &path);
+            fs::remove_file(path)
+                .map_err(|e| Error::Internal(format!("remove file error: {}", e)))?;
+        }
+        Ok(())
+    }
+    async fn clean_session(&self) -> Result {
+        let now = Local::now().timestamp_millis();
+        self.lock()?.session.retain(|s| s.expire > now);
+        Ok(())
+    }
+
+    async fn purge_cancelled_users(&self, before: i64) -> Result<usize> {
+        let mut tables = self.lock()?;
+        let users: Vec<_> = tables
+            .user
+            .iter()
+            .filter(|u| {
+                u.status == USER_STATUS_CANCEL && !u.passwd.is_empty() && u.update_time < before
+            })
+            .map(|u| u.id.unwrap())
+            .collect();
+        for user_id in users.iter() {
+            tables.purge_user(*user_id);
+        }
+        Ok(users.len())
+    }
+
+    async fn create_user(&self, signup: &SignUpReq) -> Result<User> {
+        let mut tables = self.lock()?;
+        if let Some(user) = tables.user.iter().find(|u| u.email == signup.email) {
+            return Err(Error::UserExists(format!(
+                "email \"{}\" exists",
+                &user.email
+            )));
+        }
+
+        let product_id = tables
+            .product
+            .iter()
+            .find(|(p, _)| p.product == signup.product)
+            .and_then(|(p, _)| p.id)
+            .ok_or(Error::ProductNotExists)?;
+
+        let passwd = gen_passwd(&signup.email, &signup.passwd);
+        let mut user = self.user_from_signup(signup, passwd);
+        user.id = Some(tables.next_id("user"));
+        tables.user.push(user.clone());
+
+        let id = tables.next_id("user_product");
+        tables.user_product.push(UserProduct {
+            id: Some(id),
+            user_id: user.id.unwrap(),
+            product_id,
+            avatar: String::new(),
+            status: String::from(USER_PRODUCT_STATUS_NORMAL),
+            update_time: Local::now().timestamp(),
+        });
+
+        Ok(user)
+    }
+
+    async fn signin_user(&self, signin: &SignInReq) -> Result<(User, Product, Session)> {
+        let mut tables = self.lock()?;
+        let user = tables
+            .user
+            .iter()
+            .find(|u| u.status == USER_STATUS_NORMAL && u.email == signin.email)
+            .cloned()
+            .ok_or(Error::UserNotExists)?;
+
+        let passwd = gen_passwd(&user.email, &signin.passwd);
+        if passwd != user.passwd {
+            return Err(Error::UserPasswdError);
+        }
+
+        let open_product = tables
+            .user_products(user.id.unwrap())
+            .into_iter()
+            .find(|product| product.product == signin.product);
+
+        let product = match open_product {
+            Some(product) => product,
+            None if signin.product_open_flag => {
+                tables.open_product(user.id.unwrap(), &signin.product)?
+            }
+            None => return Err(Error::ProductNotOpen),
+        };
+
+        let mut token = Session::token(product.id.unwrap(), user.id.unwrap());
+        token.id = Some(tables.next_id("session"));
+        tables.session.push(token.clone());
+
+        Ok((user, product, token))
+    }
+
+    async fn reset_user_passwd(&self, reset: &ResetPasswdReq) -> Result {
+        let mut tables = self.lock()?;
+        let user = tables
+            .user
+            .iter_mut()
+            .find(|u| u.status == USER_STATUS_NORMAL && u.email == reset.email)
+            .ok_or(Error::UserNotExists)?;
+
+        user.passwd = gen_passwd(&user.email, &reset.passwd);
+        user.update_time = Local::now().timestamp();
+
+        Ok(())
+    }
+    async fn cancel_user(&self, user_id: i64) -> Result {
+        let mut tables = self.lock()?;
+        let user = tables
+            .user
+            .iter_mut()
+            .find(|u| u.id == Some(user_id) && u.status == USER_STATUS_NORMAL)
+            .ok_or(Error::UserNotExists)?;
+        user.status = String::from(USER_STATUS_CANCEL);
+        user.update_time = Local::now().timestamp();
+        tables.session.retain(|s| s.user_id != user_id);
+        Ok(())
+    }
+
+    async fn open_product(&self, user_id: i64, product: &str) -> Result {
+        let mut tables = self.lock()?;
+        if !tables
+            .user_products(user_id)
+            .iter()
+            .any(|p| p.product == product)
+        {
+            tables.open_product(user_id, product)?;
+        }
+        Ok(())
+    }
+
+    async fn close_product(&self, user_id: i64, product: &str, purge: bool) -> Result {
+        let mut tables = self.lock()?;
+        let product_id = tables
+            .user_products(user_id)
+            .into_iter()
+            .find(|p| p.product == product)
+            .and_then(|p| p.id)
+            .ok_or(Error::ProductNotOpen)?;
+
+        if let Some(up) = tables.user_product.iter_mut().find(|up| {
+            up.user_id == user_id
+                && up.product_id == product_id
+                && up.status == USER_PRODUCT_STATUS_NORMAL
+        }) {
+            up.status = String::from(USER_PRODUCT_STATUS_CANCEL);
+            up.update_time = Local::now().timestamp();
+            if purge {
+                up.avatar.clear();
+            }
+        }
+        tables
+            .session
+            .retain(|s| s.user_id != user_id || s.product_id != product_id);
+        // 目前只有hiqradio有产品数据
+        if purge && product == PRODUCT_HIQRADIO {
+            tables.purge_hiqradio(user_id);
+        }
+        Ok(())
+    }
+    async fn get_session(&self, token: &str) -> Result<Session> {
+        let mut tables = self.lock()?;
+        let now = Local::now().timestamp_millis();
+
+        let pos = tables
+            .session
+            .iter()
+            .position(|s| s.token == token)
+            .ok_or(Error::TokenInvalid)?;
+
+        if tables.session[pos].expire < now {
+            tables.session.remove(pos);
+            return Err(Error::TokenInvalid);
+        }
+
+        let session = &mut tables.session[pos];
+        if session.expire - now < CONFIG.token_refresh * 1000 {
+            session.expire = now + CONFIG.token_expire * 1000;
+        }
+
+        Ok(session.clone())
+    }
+
+    async fn update_user_info(
+        &self,
+        user_id: i64,
+        product_id: i64,
+        user_name: Option<String>,
+        new_passwd: Option<String>,
+        avatar: Option<String>,
+    ) -> Result {
+        let mut tables = self.lock()?;
+
+        if let Some(user) = tables.user.iter_mut().find(|u| u.id == Some(user_id)) {
+            if let Some(new_user_name) = user_name {
+                user.user_name = new_user_name;
+            }
+            if let Some(new_passwd) = new_passwd {
+                user.passwd = new_passwd;
+            }
+        }
+        if let Some(new_avatar) = avatar {
+            tables
+                .user_product
+                .iter_mut()
+                .filter(|up| up.user_id == user_id && up.product_id == product_id)
+                .for_each(|up| up.avatar = new_avatar.clone());
+        }
+
+        Ok(())
+    }
+
+    async fn query_user_products(&self, user_id: i64) -> Result<Vec<Product>> {
+        Ok(self.lock()?.user_products(user_id))
+    }
+
+    async fn query_products(&self) -> Result<Vec<Product>> {
+        let products = self
+            .lock()?
+            .product
+            .iter()
+            .filter(|(_, status)| status == PRODUCT_STATUS_NORMAL)
+            .map(|(p, _)| p.clone())
+            .collect();
+
+        Ok(products)
+    }
+
+    async fn query_product(&self, product_id: i64) -> Result<Product> {
+        self.lock()?
+            .product
+            .iter()
+            .find(|(p, status)| p.id == Some(product_id) && status == PRODUCT_STATUS_NORMAL)
+            .map(|(p, _)| p.clone())
+            .ok_or(Error::ProductNotExists)
+    }
+    async fn query_user(&self, user_id: i64) -> Result<User> {
+        self.lock()?
+            .user
+            .iter()
+            .find(|u| u.id == Some(user_id) && u.status == USER_STATUS_NORMAL)
+            .cloned()
+            .ok_or(Error::UserNotExists)
+    }
+    async fn query_user_product(&self, user_id: i64, product_id: i64) -> Result<UserProduct> {
+        self.lock()?
+            .user_product
+            .iter()
+            .find(|up| {
+                up.user_id == user_id
+                    && up.product_id == product_id
+                    && up.status == USER_PRODUCT_STATUS_NORMAL
+            })
+            .cloned()
+            .ok_or(Error::ProductNotOpen)
+    }
+    async fn delete_session(&self, token: &str) -> Result {
+        let mut tables = self.lock()?;
+        tables.session.retain(|s| s.token != token);
+        tables.hiqradio_presence.retain(|p| p.token != token);
+        Ok(())
+    }
+
+    async fn query_recently(&self, user_id: i64) -> Result<Vec<Recently>> {
+        Ok(self.lock()?.recently(user_id, ..))
+    }
+
+    async fn query_recently_page(
+        &self,
+        user_id: i64,
+        filter: &RecentlyFilter,
+    ) -> Result<Vec<Recently>> {
+        let mut recently: Vec<_> = self
+            .lock()?
+            .recently(user_id, ..)
+            .into_iter()
+            .filter(|r| filter.matches(r))
+            .collect();
+        recently.sort_by_key(|r| Reverse((r.start_time, r.id)));
+        recently.truncate(filter.limit.max(0) as usize);
+        Ok(recently)
+    }
+
+    async fn query_recently_stations(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<StationStats>> {
+        let mut stations: BTreeMap<String, StationStats> = BTreeMap::new();
+        for r in self.lock()?.recently(user_id, ..) {
+            if !(since..until).contains(&r.start_time) {
+                continue;
+            }
+            let station = stations
+                .entry(r.stationuuid.clone())
+                .or_insert_with(|| StationStats {
+                    stationuuid: r.stationuuid.clone(),
+                    plays: 0,
+                    time: 0,
+                });
+            station.plays += 1;
+            station.time += r.duration();
+        }
+        Ok(stations.into_values().collect())
+    }
+
+    async fn query_recently_hours(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+        offset: i64,
+    ) -> Result<Vec<HourPlays>> {
+        let mut hours: BTreeMap<i64, HourPlays> = BTreeMap::new();
+        for r in self.lock()?.recently(user_id, ..) {
+            if !(since..until).contains(&r.start_time) {
+                continue;
+            }
+            let hour = (r.start_time + offset).div_euclid(3600);
+            let plays = hours.entry(hour).or_insert(HourPlays {
+                hour,
+                plays: 0,
+                time: 0,
+            });
+            plays.plays += 1;
+            plays.time += r.duration();
+        }
+        Ok(hours.into_values().collect())
+    }
+
+    async fn query_recently_longest(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+        limit: i64,
+    ) -> Result<Vec<Recently>> {
+        let mut recently: Vec<_> = self
+            .lock()?
+            .recently(user_id, ..)
+            .into_iter()
+            .filter(|r| (since..until).contains(&r.start_time) && r.duration() > 0)
+            .collect();
+        recently.sort_by_key(|r| Reverse((r.duration(), r.start_time, r.id)));
+        recently.truncate(limit.max(0) as usize);
+        Ok(recently)
+    }
+
+    async fn delete_recently(&self, user_id: i64) -> Result {
+        self.change(user_id, |tables, change| {
+            tables.clear_recently(change, None);
+            Ok(())
+        })
+    }
+
+    async fn new_recently(&self, user_id: i64, recently: &[RecentlyNew]) -> Result {
+        self.change(user_id, |tables, change| {
+            tables.new_recently(change, recently);
+            Ok(())
+        })
+    }
+
+    async fn modify_recently(
+        &self,
+        user_id: i64,
+        stationuuid: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result {
+        self.change(user_id, |tables, change| {
+            tables.modify_recently(change, stationuuid, start_time, end_time);
+            Ok(())
+        })
+    }
+
+    async fn clean_recently(&self, now: i64, max_days: i64, max_rows: i64) -> Result<usize> {
+        Ok(self.lock()?.clean_recently(now, max_days, max_rows))
+    }
+
+    async fn query_setting(&self, user_id: i64) -> Result<Setting> {
+        Ok(self
+            .lock()?
+            .hiqradio_setting
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_else(|| Setting::new(user_id)))
+    }
+
+    async fn modify_setting(&self, setting: &Setting) -> Result<bool> {
+        let mut setting = setting.clone();
+        setting.update_time = Local::now().timestamp();
+        let mut tables = self.lock()?;
+        let clear = setting.recently_enabled == 0
+            && tables
+                .hiqradio_setting
+                .get(&setting.user_id)
+                .is_none_or(|s| s.recently_enabled != 0);
+        if clear {
+            let change = tables.next_change(setting.user_id);
+            tables.clear_recently(change, None);
+        }
+        tables.hiqradio_setting.insert(setting.user_id, setting);
+        Ok(clear)
+    }
+
+    async fn query_groups(&self, user_id: i64) -> Result<Vec<FavGroup>> {
+        Ok(self.lock()?.groups(user_id, ..))
+    }
+
+    async fn delete_groups(&self, user_id: i64, groups: &[String]) -> Result {
+        self.change(user_id, |tables, change| tables.delete_groups(change, groups))
+    }
+
+    async fn new_groups(&self, user_id: i64, groups: &[GroupNew]) -> Result {
+        self.change(user_id, |tables, change| {
+            tables.new_groups(change, groups);
+            Ok(())
+        })
+    }
+    async fn modify_group(&self, user_id: i64, old_name: &str, name: &str, desc: &str) -> Result {
+        self.change(user_id, |tables, change| {
+            tables.modify_group(change, old_name, name, desc);
+            Ok(())
+        })
+    }
+
+    async fn query_favorites(&self, user_id: i64) -> Result<Vec<StationGroup>> {
+        Ok(self.lock()?.station_groups(user_id, ..))
+    }
+    async fn new_favorite(&self, user_id: i64, stations: &[StationGroup]) -> Result {
+        self.change(user_id, |tables, change| tables.new_favorite(change, stations))
+    }
+
+    async fn delete_favorite(
+        &self,
+        user_id: i64,
+        favorites: &Option<Vec<String>>,
+        group_names: &Option<Vec<String>>,
+    ) -> Result {
+        self.change(user_id, |tables, change| {
+            if let Some(favorites) = favorites {
+                for e in tables.station_groups(user_id, ..) {
+                    if favorites.contains(&e.stationuuid) {
+                        tables.new_tombstone(Tombstone::favorite_delete(
+                            user_id,
+                            change.revision,
+                            change.time,
+                            &e.group_name,
+                            &e.stationuuid,
+                        ));
+                    }
+                }
+                tables
+                    .hiqradio_favorite
+                    .retain(|(f, _)| !(f.user_id == user_id && favorites.contains(&f.stationuuid)));
+            }
+            if let Some(group_names) = group_names {
+                for e in tables.station_groups(user_id, ..) {
+                    if group_names.contains(&e.group_name) {
+                        tables.new_tombstone(Tombstone::favorite_delete(
+                            user_id,
+                            change.revision,
+                            change.time,
+                            &e.group_name,
+                            &e.stationuuid,
+                        ));
+                    }
+                }
+                let group_ids: Vec<_> = tables
+                    .hiqradio_fav_group
+                    .iter()
+                    .filter(|(g, _)| group_names.contains(&g.name))
+                    .map(|(g, _)| g.id)
+                    .collect();
+                tables.hiqradio_favorite.retain(|(f, _)| {
+                    !(f.user_id == user_id && group_ids.contains(&Some(f.group_id)))
+                });
+            }
+
+            Ok(())
+        })
+    }
+
+    async fn modify_favorite(&self, user_id: i64, stationuuid: &str, groups: &[String]) -> Result {
+        self.change(user_id, |tables, change| {
+            tables.modify_favorite(change, stationuuid, groups)
+        })
+    }
+
+    async fn reorder_groups(&self, user_id: i64, names: &[String]) -> Result {
+        self.change(user_id, |tables, change| {
+            for (position, name) in names.iter().enumerate() {
+                tables
+                    .hiqradio_fav_group
+                    .iter_mut()
+                    .filter(|(g, _)| {
+                        g.user_id == user_id && &g.name == name && g.position != position as i64
+                    })
+                    .for_each(|(g, revision)| {
+                        g.position = position as i64;
+                        *revision = change.revision;
+                    });
+            }
+            Ok(())
+        })
+    }
+
+    async fn reorder_favorites(&self, user_id: i64, group_name: &str, stationuuids: &[String]) -> Result {
+        self.change(user_id, |tables, change| {
+            let group_id = tables
+                .group(user_id, group_name)
+                .and_then(|g| g.id)
+                .ok_or_else(|| {
+                    Error::DatabaseException(format!("group \"{}\" not found", group_name))
+                })?;
+            for (position, stationuuid) in stationuuids.iter().enumerate() {
+                tables
+                    .hiqradio_favorite
+                    .iter_mut()
+                    .filter(|(f, _)| {
+                        f.group_id == group_id
+                            && &f.stationuuid == stationuuid
+                            && f.position != position as i64
+                    })
+                    .for_each(|(f, revision)| {
+                        f.position = position as i64;
+                        *revision = change.revision;
+                    });
+            }
+            Ok(())
+        })
+    }
+
+    async fn query_revision(&self, user_id: i64) -> Result<i64> {
+        Ok(self
+            .lock()?
+            .hiqradio_revision
+            .get(&user_id)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn query_sync(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<(Vec<FavGroup>, Vec<Recently>, Vec<StationGroup>)> {
+        let tables = self.lock()?;
+        let revisions = (Bound::Excluded(start_revision), Bound::Included(end_revision));
+
+        let fav_groups = tables.groups(user_id, revisions);
+        let recently = tables.recently(user_id, revisions);
+        let stations = tables.station_groups(user_id, revisions);
+
+        Ok((fav_groups, recently, stations))
+    }
+
+    async fn query_tombstones(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<Vec<Tombstone>> {
+        let tombstones = self
+            .lock()?
+            .hiqradio_tombstone
+            .iter()
+            .filter(|t| {
+                t.user_id == user_id && t.revision > start_revision && t.revision <= end_revision
+            })
+            .cloned()
+            .collect();
+
+        Ok(tombstones)
+    }
+
+    async fn apply_sync(&self, user_id: i64, revision: i64, ops: &[SyncOp]) -> Result<Option<i64>> {
+        let mut tables = self.lock()?;
+        if tables.hiqradio_revision.get(&user_id).copied().unwrap_or_default() != revision {
+            return Ok(None);
+        }
+
+        let backup = tables.clone();
+        let change = tables.next_change(user_id);
+        for op in ops.iter() {
+            if let Err(e) = tables.apply_op(change, op) {
+                *tables = backup;
+                return Err(e);
+            }
+        }
+        Ok(Some(change.revision))
+    }
+
+    async fn share_group(&self, user_id: i64, group_name: &str, token: &str) -> Result<Share> {
+        let mut tables = self.lock()?;
+        let group_id = tables
+            .group(user_id, group_name)
+            .and_then(|g| g.id)
+            .ok_or_else(|| Error::DatabaseException(format!("group \"{}\" not found", group_name)))?;
+        if let Some((share, _)) = tables.hiqradio_share.iter().find(|(_, id)| *id == group_id) {
+            return Ok(share.clone());
+        }
+
+        let share = Share {
+            token: token.to_string(),
+            user_id,
+            group_name: group_name.to_string(),
+            create_time: Local::now().timestamp(),
+        };
+        tables.hiqradio_share.push((share.clone(), group_id));
+        Ok(share)
+    }
+
+    async fn query_shares(&self, user_id: i64) -> Result<Vec<Share>> {
+        let tables = self.lock()?;
+        let mut shares = tables
+            .hiqradio_share
+            .iter()
+            .filter(|(s, _)| s.user_id == user_id)
+            .filter_map(|(s, group_id)| tables.shared_group(s, *group_id).map(|(s, p)| (p, *group_id, s)))
+            .collect::<Vec<_>>();
+        shares.sort_by_key(|(position, group_id, _)| (*position, *group_id));
+
+        Ok(shares.into_iter().map(|(_, _, s)| s).collect())
+    }
+
+    async fn query_share(&self, token: &str) -> Result<Option<Share>> {
+        let tables = self.lock()?;
+        Ok(tables
+            .hiqradio_share
+            .iter()
+            .find(|(s, _)| s.token == token && tables.hiqradio_user(s.user_id))
+            .and_then(|(s, group_id)| tables.shared_group(s, *group_id))
+            .map(|(s, _)| s))
+    }
+
+    async fn delete_share(&self, user_id: i64, group_name: &str) -> Result<bool> {
+        let mut tables = self.lock()?;
+        let Some(group_id) = tables.group(user_id, group_name).and_then(|g| g.id) else {
+            return Ok(false);
+        };
+        let len = tables.hiqradio_share.len();
+        tables.hiqradio_share.retain(|(_, id)| *id != group_id);
+
+        Ok(tables.hiqradio_share.len() < len)
+    }
+
+    async fn query_user_stations(
+        &self,
+        since: i64,
+        after_user_id: i64,
+        after_stationuuid: &str,
+        limit: i64,
+    ) -> Result<Vec<UserStation>> {
+        let tables = self.lock()?;
+        let favorites = tables
+            .hiqradio_favorite
+            .iter()
+            .map(|(f, _)| (f.user_id, f.stationuuid.clone(), 1));
+        let recently = tables
+            .hiqradio_recently
+            .iter()
+            .filter(|(r, _)| r.start_time >= since)
+            .map(|(r, _)| (r.user_id, r.stationuuid.clone(), 0));
+        let mut stations: BTreeMap<_, i64> = BTreeMap::new();
+        for (user_id, stationuuid, favorite) in favorites.chain(recently) {
+            if (user_id, stationuuid.as_str()) <= (after_user_id, after_stationuuid) {
+                continue;
+            }
+            let f = stations.entry((user_id, stationuuid)).or_default();
+            *f = (*f).max(favorite);
+        }
+
+        Ok(stations
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|((user_id, stationuuid), favorite)| UserStation {
+                user_id,
+                stationuuid,
+                favorite,
+            })
+            .collect())
+    }
+
+    async fn save_similar(&self, similar: &[Similar]) -> Result {
+        self.lock()?.hiqradio_similar = similar.to_vec();
+        Ok(())
+    }
+
+    async fn query_similar(&self, stationuuids: &[String]) -> Result<Vec<Similar>> {
+        let mut similar: Vec<_> = self
+            .lock()?
+            .hiqradio_similar
+            .iter()
+            .filter(|s| stationuuids.contains(&s.stationuuid))
+            .cloned()
+            .collect();
+        similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Ok(similar)
+    }
+
+    async fn query_station_listens(&self, since: i64, until: i64) -> Result<Vec<StationListen>> {
+        let tables = self.lock()?;
+        let mut listens: BTreeMap<(String, i64), i64> = BTreeMap::new();
+        for (r, _) in &tables.hiqradio_recently {
+            if r.start_time < since || r.start_time > until {
+                continue;
+            }
+            let listen_time = r
+                .end_time
+                .map(|end_time| (end_time - r.start_time).max(0))
+                .unwrap_or_default();
+            *listens
+                .entry((r.stationuuid.clone(), r.user_id))
+                .or_default() += listen_time;
+        }
+
+        Ok(listens
+            .into_iter()
+            .map(|((stationuuid, user_id), listen_time)| StationListen {
+                stationuuid,
+                user_id,
+                listen_time,
+            })
+            .collect())
+    }
+
+    async fn query_new_favorites(&self, since: i64, until: i64) -> Result<Vec<UserStation>> {
+        let tables = self.lock()?;
+        let favorites: BTreeSet<_> = tables
+            .hiqradio_favorite
+            .iter()
+            .filter(|(f, _)| f.create_time >= since && f.create_time <= until)
+            .map(|(f, _)| (f.user_id, f.stationuuid.clone()))
+            .collect();
+
+        Ok(favorites
+            .into_iter()
+            .map(|(user_id, stationuuid)| UserStation {
+                user_id,
+                stationuuid,
+                favorite: 1,
+            })
+            .collect())
+    }
+
+    async fn save_trending(&self, period: &str, stations: &[TrendingStation]) -> Result {
+        let mut tables = self.lock()?;
+        tables.hiqradio_trending.retain(|s| s.period != period);
+        tables
+            .hiqradio_trending
+            .extend(stations.iter().map(|s| TrendingStation {
+                period: period.to_string(),
+                ..s.clone()
+            }));
+        Ok(())
+    }
+
+    async fn query_trending(&self, period: &str, limit: i64) -> Result<Vec<TrendingStation>> {
+        let mut stations: Vec<_> = self
+            .lock()?
+            .hiqradio_trending
+            .iter()
+            .filter(|s| s.period == period)
+            .cloned()
+            .collect();
+        stations.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.stationuuid.cmp(&b.stationuuid))
+        });
+        stations.truncate(limit.max(0) as usize);
+
+        Ok(stations)
+    }
+
+    async fn save_review(&self, review: &Review) -> Result<Review> {
+        let mut tables = self.lock()?;
+        let id = match tables
+            .hiqradio_review
+            .iter_mut()
+            .find(|r| r.user_id == review.user_id && r.stationuuid == review.stationuuid)
+        {
+            Some(r) => {
+                r.rating = review.rating;
+                r.content = review.content.clone();
+                r.status = review.status.clone();
+                r.reports = 0;
+                r.update_time = review.update_time;
+                r.id.unwrap()
+            }
+            None => {
+                let id = tables.next_id("hiqradio_review");
+                tables.hiqradio_review.push(Review {
+                    id: Some(id),
+                    user_name: String::new(),
+                    reports: 0,
+                    ..review.clone()
+                });
+                id
+            }
+        };
+        tables.hiqradio_review_report.retain(|(rid, _)| *rid != id);
+
+        let review = tables
+            .hiqradio_review
+            .iter()
+            .find(|r| r.id == Some(id))
+            .unwrap();
+        Ok(tables.with_user_name(review))
+    }
+
+    async fn delete_review(&self, user_id: i64, stationuuid: &str) -> Result<bool> {
+        let mut tables = self.lock()?;
+        let Some(pos) = tables
+            .hiqradio_review
+            .iter()
+            .position(|r| r.user_id == user_id && r.stationuuid == stationuuid)
+        else {
+            return Ok(false);
+        };
+        let review = tables.hiqradio_review.remove(pos);
+        tables
+            .hiqradio_review_report
+            .retain(|(id, _)| Some(*id) != review.id);
+        Ok(true)
+    }
+
+    async fn query_review(&self, id: i64) -> Result<Option<Review>> {
+        let tables = self.lock()?;
+        Ok(tables
+            .hiqradio_review
+            .iter()
+            .find(|r| r.id == Some(id))
+            .map(|r| tables.with_user_name(r)))
+    }
+
+    async fn query_user_reviews(&self, user_id: i64) -> Result<Vec<Review>> {
+        let tables = self.lock()?;
+        let mut reviews: Vec<_> = tables
+            .hiqradio_review
+            .iter()
+            .filter(|r| r.user_id == user_id)
+            .map(|r| tables.with_user_name(r))
+            .collect();
+        reviews.sort_by_key(|r| (Reverse(r.update_time), Reverse(r.id)));
+        Ok(reviews)
+    }
+
+    async fn query_station_reviews(
+        &self,
+        stationuuid: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Review>> {
+        let tables = self.lock()?;
+        let mut reviews: Vec<_> = tables
+            .hiqradio_review
+            .iter()
+            .filter(|r| {
+                r.stationuuid == stationuuid
+                    && r.status == REVIEW_STATUS_APPROVED
+                    && tables.hiqradio_user(r.user_id)
+            })
+            .collect();
+        reviews.sort_by_key(|r| (Reverse(r.update_time), Reverse(r.id)));
+        Ok(reviews
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|r| tables.with_user_name(r))
+            .collect())
+    }
+
+    async fn query_pending_reviews(&self, offset: i64, limit: i64) -> Result<Vec<Review>> {
+        let tables = self.lock()?;
+        let mut reviews: Vec<_> = tables
+            .hiqradio_review
+            .iter()
+            .filter(|r| r.status == REVIEW_STATUS_PENDING)
+            .collect();
+        reviews.sort_by_key(|r| (Reverse(r.reports), r.update_time, r.id));
+        Ok(reviews
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|r| tables.with_user_name(r))
+            .collect())
+    }
+
+    async fn moderate_review(&self, id: i64, status: &str) -> Result<bool> {
+        let mut tables = self.lock()?;
+        let Some(review) = tables.hiqradio_review.iter_mut().find(|r| r.id == Some(id)) else {
+            return Ok(false);
+        };
+        review.status = status.to_string();
+        review.reports = 0;
+        Ok(true)
+    }
+
+    async fn report_review(
+        &self,
+        id: i64,
+        user_id: i64,
+        _reason: &str,
+        _time: i64,
+        pending_reports: i64,
+    ) -> Result<i64> {
+        let mut tables = self.lock()?;
+        let reported = !tables.hiqradio_review_report.contains(&(id, user_id));
+        let Some(review) = tables.hiqradio_review.iter_mut().find(|r| r.id == Some(id)) else {
+            return Err(Error::DatabaseException(format!("review {} not found", id)));
+        };
+        if reported {
+            review.reports += 1;
+            if review.status == REVIEW_STATUS_APPROVED && review.reports >= pending_reports {
+                review.status = String::from(REVIEW_STATUS_PENDING);
+            }
+        }
+        let reports = review.reports;
+        if reported {
+            tables.hiqradio_review_report.push((id, user_id));
+        }
+        Ok(reports)
+    }
+
+    async fn query_ratings(&self, stationuuids: &[String]) -> Result<Vec<Rating>> {
+        let tables = self.lock()?;
+        let mut ratings: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+        for r in tables.hiqradio_review.iter().filter(|r| {
+            r.status == REVIEW_STATUS_APPROVED
+                && stationuuids.contains(&r.stationuuid)
+                && tables.hiqradio_user(r.user_id)
+        }) {
+            let rating = ratings.entry(&r.stationuuid).or_default();
+            rating.0 += r.rating;
+            rating.1 += 1;
+        }
+        Ok(ratings
+            .into_iter()
+            .map(|(stationuuid, (sum, count))| Rating {
+                stationuuid: stationuuid.to_string(),
+                rating: sum as f64 / count as f64,
+                ratings: count,
+            })
+            .collect())
+    }
+
+    async fn save_presence(&self, presence: &Presence) -> Result<Presence> {
+        let mut tables = self.lock()?;
+        let saved = match tables
+            .hiqradio_presence
+            .iter_mut()
+            .find(|p| p.user_id == presence.user_id && p.device_id == presence.device_id)
+        {
+            Some(p) => {
+                let start_time = if p.stationuuid == presence.stationuuid {
+                    p.start_time
+                } else {
+                    presence.start_time
+                };
+                *p = Presence {
+                    start_time,
+                    current: false,
+                    ..presence.clone()
+                };
+                p.clone()
+            }
+            None => {
+                let p = Presence {
+                    current: false,
+                    ..presence.clone()
+                };
+                tables.hiqradio_presence.push(p.clone());
+                p
+            }
+        };
+        Ok(saved)
+    }
+
+    async fn delete_presence(&self, user_id: i64, device_id: &str) -> Result<bool> {
+        let mut tables = self.lock()?;
+        let size = tables.hiqradio_presence.len();
+        tables
+            .hiqradio_presence
+            .retain(|p| p.user_id != user_id || p.device_id != device_id);
+        Ok(tables.hiqradio_presence.len() < size)
+    }
+
+    async fn query_presences(&self, user_id: i64, now: i64) -> Result<Vec<Presence>> {
+        let mut presences: Vec<_> = self
+            .lock()?
+            .hiqradio_presence
+            .iter()
+            .filter(|p| p.user_id == user_id && p.expire > now)
+            .cloned()
+            .collect();
+        presences.sort_by(|a, b| {
+            b.update_time
+                .cmp(&a.update_time)
+                .then_with(|| a.device_id.cmp(&b.device_id))
+        });
+        Ok(presences)
+    }
+
+    async fn clean_presence(&self, now: i64) -> Result<usize> {
+        let mut tables = self.lock()?;
+        let size = tables.hiqradio_presence.len();
+        tables.hiqradio_presence.retain(|p| p.expire > now);
+        Ok(size - tables.hiqradio_presence.len())
+    }
+
+    async fn save_stations(&self, stations: &[Station]) -> Result {
+        let mut tables = self.lock()?;
+        for s in stations {
+            let mut station = s.clone();
+            station.deleted = 0;
+            tables
+                .hiqradio_station
+                .insert(station.stationuuid.clone(), station);
+        }
+        Ok(())
+    }
+
+    async fn delete_stations_before(&self, before: i64) -> Result<usize> {
+        let mut tables = self.lock()?;
+        let mut count = 0;
+        for station in tables.hiqradio_station.values_mut() {
+            if station.deleted == 0 && station.update_time < before {
+                station.deleted = 1;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn query_stations(&self, stationuuids: &[String]) -> Result<Vec<Station>> {
+        let tables = self.lock()?;
+        Ok(stationuuids
+            .iter()
+            .filter_map(|s| tables.hiqradio_station.get(s).cloned())
+            .collect())
+    }
+
+    async fn query_stations_by_url(&self, urls: &[String]) -> Result<Vec<Station>> {
+        let urls: HashSet<_> = urls.iter().collect();
+        Ok(self
+            .lock()?
+            .hiqradio_station
+            .values()
+            .filter(|s| urls.contains(&s.url) || urls.contains(&s.url_resolved))
+            .cloned()
+            .collect())
+    }
+
+    async fn query_stations_by_name(&self, names: &[String]) -> Result<Vec<Station>> {
+        let names: HashSet<_> = names.iter().collect();
+        Ok(self
+            .lock()?
+            .hiqradio_station
+            .values()
+            .filter(|s| names.contains(&s.name))
+            .cloned()
+            .collect())
+    }
+
+    async fn search_stations(&self, filter: &StationFilter) -> Result<Vec<Station>> {
+        let tables = self.lock()?;
+        let mut stations: Vec<_> = tables
+            .hiqradio_station
+            .values()
+            .filter(|s| filter.matches(s))
+            .filter_map(|s| {
+                let rank = station_rank(s, &filter.terms)?;
+                if filter.boost.contains(&s.stationuuid) {
+                    Some((rank * SEARCH_BOOST, s))
+                } else {
+                    Some((rank, s))
+                }
+            })
+            .collect();
+        stations.sort_by(|(a_rank, a), (b_rank, b)| {
+            b_rank
+                .total_cmp(a_rank)
+                .then_with(|| b.votes.cmp(&a.votes))
+                .then_with(|| a.stationuuid.cmp(&b.stationuuid))
+        });
+        Ok(stations
+            .into_iter()
+            .skip(filter.offset.max(0) as usize)
+            .take(filter.limit.max(0) as usize)
+            .map(|(_, s)| s.clone())
+            .collect())
+    }
+}
@@ -0,0 +1,440 @@
+//! hiqradio离线操作合并
+//!
+//! 先读出客户端上次同步之后服务端的修改，按`SyncOp`的冲突规则筛选出要应用的操作，
+//! 再用`apply_sync`在版本号未变化时整批写入；期间有其他修改则重新合并。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    errors::Error,
+    model::hiqradio::{
+        FavGroup, TOMBSTONE_FAVORITE_DELETE, TOMBSTONE_GROUP_DELETE, TOMBSTONE_GROUP_MODIFY,
+        TOMBSTONE_RECENTLY_CLEAR,
+    },
+    proto::{SyncOp, SYNC_OP_APPLIED, SYNC_OP_CONFLICT, SYNC_OP_IGNORED},
+    Result,
+};
+
+use super::DynAppServRepo;
+
+/// 版本号一直变化时的最大重试次数
+const MAX_RETRY: usize = 3;
+
+/// 冲突检测的对象
+#[derive(Debug, Hash, PartialEq, Eq)]
+enum Key {
+    /// 分组的任何修改
+    Group(String),
+    /// 分组被删除或改名
+    GroupRemoved(String),
+    Favorite(String, String),
+    /// 电台在任意分组中的收藏
+    Station(String),
+    RecentlyClear,
+}
+
+/// 服务端修改的对象及最后修改时间
+#[derive(Debug, Default)]
+struct Changes(HashMap<Key, i64>);
+
+impl Changes {
+    fn touch(&mut self, key: Key, time: i64) {
+        let t = self.0.entry(key).or_insert(time);
+        *t = (*t).max(time);
+    }
+
+    /// 服务端修改不早于操作时间则冲突
+    fn conflict(&self, keys: &[Key], time: i64) -> bool {
+        keys.iter()
+            .any(|key| self.0.get(key).map(|t| *t >= time).unwrap_or(false))
+    }
+}
+
+/// 合并过程中模拟的服务端数据
+#[derive(Debug, Default)]
+struct State {
+    groups: Vec<FavGroup>,
+    favorites: HashSet<(String, String)>,
+    recently: HashSet<(String, i64)>,
+    /// 用户关闭了最近播放时不写入
+    recently_enabled: bool,
+}
+
+impl State {
+    fn has_group(&self, name: &str) -> bool {
+        self.groups.iter().any(|g| g.name == name)
+    }
+
+    fn remove_group(&mut self, name: &str) {
+        self.groups.retain(|g| g.name != name);
+        self.favorites.retain(|(g, _)| g != name);
+    }
+
+    /// 应用操作，返回是否有修改
+    fn apply(&mut self, op: &SyncOp) -> bool {
+        match op {
+            SyncOp::GroupNew {
+                time,
+                name,
+                desc,
+                is_def,
+            } => {
+                if self.has_group(name) {
+                    return false;
+                }
+                if *is_def > 0 {
+                    if let Some(def) = self.groups.iter().find(|g| g.is_def == 1) {
+                        if def.create_time > *time {
+                            return false;
+                        }
+                        let def = def.name.clone();
+                        self.remove_group(&def);
+                    }
+                }
+                self.groups.push(FavGroup {
+                    id: None,
+                    user_id: 0,
+                    create_time: *time,
+                    name: name.clone(),
+                    desc: desc.clone(),
+                    is_def: *is_def,
+                    position: 0,
+                });
+                true
+            }
+            SyncOp::GroupModify { old_name, name, .. } => {
+                if !self.has_group(old_name) || (name != old_name && self.has_group(name)) {
+                    return false;
+                }
+                self.groups
+                    .iter_mut()
+                    .filter(|g| &g.name == old_name)
+                    .for_each(|g| g.name = name.clone());
+                self.favorites = self
+                    .favorites
+                    .drain()
+                    .map(|(g, s)| {
+                        if &g == old_name {
+                            (name.clone(), s)
+                        } else {
+                            (g, s)
+                        }
+                    })
+                    .collect();
+                true
+            }
+            SyncOp::GroupDelete { name, .. } => {
+                if !self.has_group(name) {
+                    return false;
+                }
+                self.remove_group(name);
+                true
+            }
+            SyncOp::FavoriteNew {
+                group_name,
+                stationuuid,
+                ..
+            } => {
+                self.has_group(group_name)
+                    && self
+                        .favorites
+                        .insert((group_name.clone(), stationuuid.clone()))
+            }
+            SyncOp::FavoriteModify {
+                stationuuid,
+                group_names,
+                ..
+            } => {
+                if !self.favorites.iter().any(|(_, s)| s == stationuuid) {
+                    return false;
+                }
+                self.favorites.retain(|(_, s)| s != stationuuid);
+                let groups: Vec<_> = group_names
+                    .iter()
+                    .filter(|g| self.has_group(g))
+                    .map(|g| (g.clone(), stationuuid.clone()))
+                    .collect();
+                self.favorites.extend(groups);
+                true
+            }
+            SyncOp::FavoriteDelete {
+                group_name,
+                stationuuid,
+                ..
+            } => self
+                .favorites
+                .remove(&(group_name.clone(), stationuuid.clone())),
+            SyncOp::RecentlyNew {
+                stationuuid,
+                start_time,
+                ..
+            } => self.recently_enabled && self.recently.insert((stationuuid.clone(), *start_time)),
+            SyncOp::RecentlyModify {
+                stationuuid,
+                start_time,
+                ..
+            } => self.recently.contains(&(stationuuid.clone(), *start_time)),
+        }
+    }
+}
+
+/// 操作涉及的对象和操作时间
+fn keys(op: &SyncOp) -> (Vec<Key>, i64) {
+    match op {
+        SyncOp::GroupNew { time, name, .. } | SyncOp::GroupDelete { time, name } => {
+            (vec![Key::Group(name.clone())], *time)
+        }
+        SyncOp::GroupModify {
+            time,
+            old_name,
+            name,
+            ..
+        } => (
+            vec![Key::Group(old_name.clone()), Key::Group(name.clone())],
+            *time,
+        ),
+        SyncOp::FavoriteNew {
+            time,
+            group_name,
+            stationuuid,
+        } => (
+            vec![
+                Key::GroupRemoved(group_name.clone()),
+                Key::Favorite(group_name.clone(), stationuuid.clone()),
+            ],
+            *time,
+        ),
+        SyncOp::FavoriteModify {
+            time,
+            stationuuid,
+            group_names,
+        } => {
+            let mut keys: Vec<_> = group_names
+                .iter()
+                .map(|g| Key::GroupRemoved(g.clone()))
+                .collect();
+            keys.push(Key::Station(stationuuid.clone()));
+            (keys, *time)
+        }
+        SyncOp::FavoriteDelete {
+            time,
+            group_name,
+            stationuuid,
+        } => (
+            vec![Key::Favorite(group_name.clone(), stationuuid.clone())],
+            *time,
+        ),
+        // 清空之前开始的播放记录不再写入
+        SyncOp::RecentlyNew { start_time, .. } | SyncOp::RecentlyModify { start_time, .. } => {
+            (vec![Key::RecentlyClear], *start_time)
+        }
+    }
+}
+
+/// 读取版本号在(revision, current]之间的服务端修改
+async fn changes(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    revision: i64,
+    current: i64,
+) -> Result<Changes> {
+    let mut changes = Changes::default();
+
+    let (groups, _, favorites) = repo.query_sync(user_id, revision, current).await?;
+    for g in groups {
+        changes.touch(Key::Group(g.name), g.create_time);
+    }
+    for f in favorites {
+        changes.touch(Key::Station(f.stationuuid.clone()), f.create_time);
+        changes.touch(Key::Favorite(f.group_name, f.stationuuid), f.create_time);
+    }
+
+    for t in repo.query_tombstones(user_id, revision, current).await? {
+        match t.kind.as_str() {
+            TOMBSTONE_GROUP_DELETE => {
+                changes.touch(Key::GroupRemoved(t.group_name.clone()), t.create_time);
+                changes.touch(Key::Group(t.group_name), t.create_time);
+            }
+            TOMBSTONE_GROUP_MODIFY => {
+                changes.touch(Key::GroupRemoved(t.group_name.clone()), t.create_time);
+                changes.touch(Key::Group(t.group_name), t.create_time);
+                changes.touch(Key::Group(t.new_name), t.create_time);
+            }
+            TOMBSTONE_FAVORITE_DELETE => {
+                changes.touch(Key::Station(t.stationuuid.clone()), t.create_time);
+                changes.touch(Key::Favorite(t.group_name, t.stationuuid), t.create_time);
+            }
+            TOMBSTONE_RECENTLY_CLEAR => changes.touch(Key::RecentlyClear, t.create_time),
+            _ => {}
+        }
+    }
+
+    Ok(changes)
+}
+
+async fn state(repo: &DynAppServRepo, user_id: i64, ops: &[SyncOp]) -> Result<State> {
+    let groups = repo.query_groups(user_id).await?;
+    let favorites = repo
+        .query_favorites(user_id)
+        .await?
+        .into_iter()
+        .map(|f| (f.group_name, f.stationuuid))
+        .collect();
+
+    let has_recently = ops.iter().any(|op| {
+        matches!(
+            op,
+            SyncOp::RecentlyNew { .. } | SyncOp::RecentlyModify { .. }
+        )
+    });
+    let (recently, recently_enabled) = if has_recently {
+        let recently = repo
+            .query_recently(user_id)
+            .await?
+            .into_iter()
+            .map(|r| (r.stationuuid, r.start_time))
+            .collect();
+        let setting = repo.query_setting(user_id).await?;
+        (recently, setting.recently_enabled != 0)
+    } else {
+        (HashSet::new(), true)
+    };
+
+    Ok(State {
+        groups,
+        favorites,
+        recently,
+        recently_enabled,
+    })
+}
+
+/// 合并客户端的离线操作，revision为客户端上次同步的版本号，
+/// 返回合并后的版本号和每个操作的处理结果
+pub async fn push_sync(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    revision: i64,
+    ops: &[SyncOp],
+) -> Result<(i64, Vec<&'static str>)> {
+    for _ in 0..MAX_RETRY {
+        let current = repo.query_revision(user_id).await?;
+        let changes = changes(repo, user_id, revision, current).await?;
+        let mut state = state(repo, user_id, ops).await?;
+
+        let mut accepted = Vec::new();
+        let mut results = Vec::new();
+        for op in ops.iter() {
+            let (keys, time) = keys(op);
+            if changes.conflict(&keys, time) {
+                results.push(SYNC_OP_CONFLICT);
+            } else if state.apply(op) {
+                accepted.push(op.clone());
+                results.push(SYNC_OP_APPLIED);
+            } else {
+                results.push(SYNC_OP_IGNORED);
+            }
+        }
+
+        if accepted.is_empty() {
+            return Ok((current, results));
+        }
+        if let Some(revision) = repo.apply_sync(user_id, current, &accepted).await? {
+            return Ok((revision, results));
+        }
+    }
+
+    Err(Error::Frequent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_changes(keys: Vec<(Key, i64)>) -> Changes {
+        let mut changes = Changes::default();
+        for (key, time) in keys {
+            changes.touch(key, time);
+        }
+        changes
+    }
+
+    fn group_removed(group_name: &str, time: i64) -> Changes {
+        server_changes(vec![(Key::GroupRemoved(group_name.to_string()), time)])
+    }
+
+    fn conflict(changes: &Changes, op: &SyncOp) -> bool {
+        let (keys, time) = keys(op);
+        changes.conflict(&keys, time)
+    }
+
+    fn favorite_new(time: i64, group_name: &str, stationuuid: &str) -> SyncOp {
+        SyncOp::FavoriteNew {
+            time,
+            group_name: group_name.to_string(),
+            stationuuid: stationuuid.to_string(),
+        }
+    }
+
+    #[test]
+    fn touch_keeps_latest() {
+        let changes = server_changes(vec![
+            (Key::Station(String::from("s")), 20),
+            (Key::Station(String::from("s")), 10),
+        ]);
+        assert_eq!(changes.0.get(&Key::Station(String::from("s"))), Some(&20));
+    }
+
+    #[test]
+    fn conflict_when_not_earlier() {
+        let changes = server_changes(vec![(Key::Group(String::from("a")), 100)]);
+        let group_delete = |time| SyncOp::GroupDelete {
+            time,
+            name: String::from("a"),
+        };
+        assert!(conflict(&changes, &group_delete(99)));
+        // 时间相同按服务端为准
+        assert!(conflict(&changes, &group_delete(100)));
+        assert!(!conflict(&changes, &group_delete(101)));
+        // 没有修改的对象不冲突
+        assert!(!Changes::default().conflict(&[Key::Group(String::from("a"))], 0));
+    }
+
+    #[test]
+    fn conflict_any_key() {
+        let changes = server_changes(vec![(Key::Group(String::from("new")), 100)]);
+        let modify = SyncOp::GroupModify {
+            time: 50,
+            old_name: String::from("old"),
+            name: String::from("new"),
+            desc: String::new(),
+        };
+        assert!(conflict(&changes, &modify));
+
+        // 收藏到被删除或改名的分组
+        let changes = group_removed("g", 100);
+        assert!(conflict(&changes, &favorite_new(50, "g", "s")));
+        assert!(!conflict(&changes, &favorite_new(150, "g", "s")));
+        assert!(!conflict(&changes, &favorite_new(50, "other", "s")));
+        let favorite_modify = SyncOp::FavoriteModify {
+            time: 50,
+            stationuuid: String::from("s"),
+            group_names: vec![String::from("other"), String::from("g")],
+        };
+        assert!(conflict(&changes, &favorite_modify));
+    }
+
+    #[test]
+    fn recently_before_clear() {
+        let changes = server_changes(vec![(Key::RecentlyClear, 100)]);
+        let recently_new = |start_time| SyncOp::RecentlyNew {
+            stationuuid: String::from("s"),
+            start_time,
+            end_time: None,
+        };
+        assert!(conflict(&changes, &recently_new(99)));
+        assert!(!conflict(&changes, &recently_new(101)));
+        // 收藏的修改不影响最近播放
+        let changes = group_removed("g", 100);
+        assert!(!conflict(&changes, &recently_new(0)));
+    }
+}
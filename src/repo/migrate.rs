@@ -0,0 +1,274 @@
+//! 数据库版本迁移
+//!
+//! 每个后端在`migrations/<backend>/`下维护按版本号排序的迁移脚本，
+//! 已执行的版本记录在`schema_version`表中。
+//! mysql的ddl会隐式提交，迁移中途失败时已执行的部分不会回滚，
+//! 所以mysql的迁移脚本需要可以重复执行，ddl执行前先检查列或索引是否已存在。
+
+use crate::{errors::Error, Result};
+
+/// 当前程序对应的数据库版本
+pub const SCHEMA_VERSION: i64 = 14;
+
+/// 版本表，三种后端通用
+pub const SCHEMA_VERSION_TABLE: &str = r#"create table if not exists schema_version (
+    version bigint not null primary key,
+    description varchar(256) not null,
+    applied_time bigint not null
+)"#;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+pub static SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "init",
+        sql: include_str!("../../migrations/sqlite/0001_init.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "tombstone",
+        sql: include_str!("../../migrations/sqlite/0002_tombstone.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "revision",
+        sql: include_str!("../../migrations/sqlite/0003_revision.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "recently index",
+        sql: include_str!("../../migrations/sqlite/0004_recently_index.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "setting",
+        sql: include_str!("../../migrations/sqlite/0005_setting.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "station",
+        sql: include_str!("../../migrations/sqlite/0006_station.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "station search",
+        sql: include_str!("../../migrations/sqlite/0007_station_search.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "position",
+        sql: include_str!("../../migrations/sqlite/0008_position.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "share",
+        sql: include_str!("../../migrations/sqlite/0009_share.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "similar",
+        sql: include_str!("../../migrations/sqlite/0010_similar.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "trending",
+        sql: include_str!("../../migrations/sqlite/0011_trending.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "review",
+        sql: include_str!("../../migrations/sqlite/0012_review.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "presence",
+        sql: include_str!("../../migrations/sqlite/0013_presence.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "station lookup",
+        sql: include_str!("../../migrations/sqlite/0014_station_lookup.sql"),
+    },
+];
+
+pub static MYSQL_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "init",
+        sql: include_str!("../../migrations/mysql/0001_init.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "tombstone",
+        sql: include_str!("../../migrations/mysql/0002_tombstone.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "revision",
+        sql: include_str!("../../migrations/mysql/0003_revision.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "recently index",
+        sql: include_str!("../../migrations/mysql/0004_recently_index.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "setting",
+        sql: include_str!("../../migrations/mysql/0005_setting.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "station",
+        sql: include_str!("../../migrations/mysql/0006_station.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "station search",
+        sql: include_str!("../../migrations/mysql/0007_station_search.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "position",
+        sql: include_str!("../../migrations/mysql/0008_position.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "share",
+        sql: include_str!("../../migrations/mysql/0009_share.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "similar",
+        sql: include_str!("../../migrations/mysql/0010_similar.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "trending",
+        sql: include_str!("../../migrations/mysql/0011_trending.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "review",
+        sql: include_str!("../../migrations/mysql/0012_review.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "presence",
+        sql: include_str!("../../migrations/mysql/0013_presence.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "station lookup",
+        sql: include_str!("../../migrations/mysql/0014_station_lookup.sql"),
+    },
+];
+
+pub static POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "init",
+        sql: include_str!("../../migrations/postgres/0001_init.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "tombstone",
+        sql: include_str!("../../migrations/postgres/0002_tombstone.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "revision",
+        sql: include_str!("../../migrations/postgres/0003_revision.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "recently index",
+        sql: include_str!("../../migrations/postgres/0004_recently_index.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "setting",
+        sql: include_str!("../../migrations/postgres/0005_setting.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "station",
+        sql: include_str!("../../migrations/postgres/0006_station.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "station search",
+        sql: include_str!("../../migrations/postgres/0007_station_search.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "position",
+        sql: include_str!("../../migrations/postgres/0008_position.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "share",
+        sql: include_str!("../../migrations/postgres/0009_share.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "similar",
+        sql: include_str!("../../migrations/postgres/0010_similar.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "trending",
+        sql: include_str!("../../migrations/postgres/0011_trending.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "review",
+        sql: include_str!("../../migrations/postgres/0012_review.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "presence",
+        sql: include_str!("../../migrations/postgres/0013_presence.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "station lookup",
+        sql: include_str!("../../migrations/postgres/0014_station_lookup.sql"),
+    },
+];
+
+/// 数据库版本比程序新时拒绝运行
+pub fn check_newer(version: i64) -> Result {
+    if version > SCHEMA_VERSION {
+        return Err(Error::DatabaseException(format!(
+            "database schema version {} is newer than supported version {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// 不自动迁移时，数据库版本必须和程序一致
+pub fn check_version(version: i64) -> Result {
+    check_newer(version)?;
+    if version < SCHEMA_VERSION {
+        return Err(Error::DatabaseException(format!(
+            "database schema version {} is older than {}, please run migrate first",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// 待执行的迁移脚本
+pub fn pending(
+    migrations: &'static [Migration],
+    version: i64,
+) -> impl Iterator<Item = &'static Migration> {
+    migrations.iter().filter(move |m| m.version > version)
+}
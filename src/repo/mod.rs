@@ -1,30 +1,57 @@
+pub mod archive;
+pub mod memory;
+pub mod merge;
+pub mod migrate;
 pub mod mysql;
+pub mod order;
+pub mod postgres;
+pub mod retention;
 pub mod sqlite;
+pub mod stats;
 
 use std::sync::Arc;
 
 use async_trait::async_trait;
 
 use crate::{
+    config::CONFIG,
     errors,
     model::{
-        hiqradio::{FavGroup, Recently, StationGroup},
+        hiqradio::{
+            FavGroup, Presence, Rating, Recently, Review, Setting, Share, Similar, Station,
+            StationGroup, StationListen, Tombstone, TrendingStation, UserStation,
+        },
         product::Product,
         session::Session,
         user::User,
         user_product::UserProduct,
     },
-    proto::{GroupNew, RecentlyNew, ResetPasswdReq, SignInReq, SignUpReq},
+    proto::{
+        GroupNew, RecentlyFilter, RecentlyNew, ResetPasswdReq, SignInReq, SignUpReq, StationFilter,
+        StationStats, SyncOp,
+    },
     Result,
 };
 
-// use self::mysql::MySQLRepo;
+use self::memory::MemoryRepo;
+use self::mysql::MySQLRepo;
+use self::postgres::PgRepo;
 use self::sqlite::SqliteRepo;
+use self::stats::HourPlays;
 
 #[async_trait]
 pub trait AppServRepo {
+    // schema
+    async fn schema_version(&self) -> Result<i64>;
+    async fn migrate(&self) -> Result;
+    /// 在线备份到path，path必须不存在，目前只支持sqlite
+    async fn backup(&self, path: &str) -> Result;
+
     async fn clean_avatar_path(&self, path: &str) -> Result;
     async fn clean_session(&self) -> Result;
+    /// 清理注销时间(秒)早于before的用户：删除hiqradio数据，清空头像，匿名化用户信息，
+    /// 返回清理的用户数
+    async fn purge_cancelled_users(&self, before: i64) -> Result<usize>;
 
     // service
     async fn create_user(&self, signup: &SignUpReq) -> Result<User>;
@@ -40,7 +67,13 @@ pub trait AppServRepo {
         avatar: Option<String>,
     ) -> Result;
 
+    /// 注销用户并删除全部会话，数据保留到宽限期后由purge_cancelled_users清理；
+    /// 注销后分享和评论立即不再公开，公开的查询都只返回未注销用户的数据
+    async fn cancel_user(&self, user_id: i64) -> Result;
+
     async fn open_product(&self, user_id: i64, product: &str) -> Result;
+    /// 关闭产品并删除该产品的会话，purge时在同一事务内清理头像和产品数据，不保留删除记录
+    async fn close_product(&self, user_id: i64, product: &str, purge: bool) -> Result;
 
     // dao
     async fn query_user_products(&self, user_id: i64) -> Result<Vec<Product>>;
@@ -52,17 +85,33 @@ pub trait AppServRepo {
 
     // hiqradio dao
     async fn query_recently(&self, user_id: i64) -> Result<Vec<Recently>>;
+    /// 按条件查询最近播放，最多返回filter.limit条
+    async fn query_recently_page(&self, user_id: i64, filter: &RecentlyFilter) -> Result<Vec<Recently>>;
+    /// 按电台汇总start_time在[since, until)内的播放次数和时长
+    async fn query_recently_stations(&self, user_id: i64, since: i64, until: i64) -> Result<Vec<StationStats>>;
+    /// 按本地时间的小时汇总，offset为时区偏移(秒)，按小时升序
+    async fn query_recently_hours(&self, user_id: i64, since: i64, until: i64, offset: i64) -> Result<Vec<HourPlays>>;
+    /// 时长最长的limit条，时长相同时较新的在前
+    async fn query_recently_longest(&self, user_id: i64, since: i64, until: i64, limit: i64) -> Result<Vec<Recently>>;
     async fn delete_recently(&self, user_id: i64) -> Result;
-    async fn new_recently(&self, user_id: i64, recently: &Vec<RecentlyNew>) -> Result;
+    async fn new_recently(&self, user_id: i64, recently: &[RecentlyNew]) -> Result;
     async fn modify_recently(&self, user_id: i64, stationuuid: &str, start_time: i64, end_time: i64) -> Result;
+    /// 按保留策略清理最近播放，now为当前时间(秒)，max_days/max_rows为0表示不限制；
+    /// 用户设置的保留天数更短时按用户设置，关闭记录的用户全部清理；
+    /// 每个清理的用户在一个事务内删除并写入清空记录，返回删除的条数
+    async fn clean_recently(&self, now: i64, max_days: i64, max_rows: i64) -> Result<usize>;
+
+    async fn query_setting(&self, user_id: i64) -> Result<Setting>;
+    /// 关闭最近播放时在同一事务内清空已有记录并写入清空记录，返回是否清空
+    async fn modify_setting(&self, setting: &Setting) -> Result<bool>;
 
     async fn query_groups(&self, user_id: i64) -> Result<Vec<FavGroup>>;
-    async fn delete_groups(&self, user_id: i64, groups: &Vec<String>) -> Result;
-    async fn new_groups(&self, user_id: i64, groups: &Vec<GroupNew>) -> Result;
+    async fn delete_groups(&self, user_id: i64, groups: &[String]) -> Result;
+    async fn new_groups(&self, user_id: i64, groups: &[GroupNew]) -> Result;
     async fn modify_group(&self, user_id: i64, old_name: &str, name: &str, desc: &str) -> Result;
 
     async fn query_favorites(&self, user_id: i64) -> Result<Vec<StationGroup>>;
-    async fn new_favorite(&self, user_id: i64, stations: &Vec<StationGroup>) -> Result;
+    async fn new_favorite(&self, user_id: i64, stations: &[StationGroup]) -> Result;
     async fn delete_favorite(
         &self,
         user_id: i64,
@@ -73,24 +122,157 @@ pub trait AppServRepo {
         &self,
         user_id: i64,
         stationuuid: &str,
-        groups: &Vec<String>,
+        groups: &[String],
     ) -> Result;
+    /// 按names的顺序设置分组的位置，不存在的分组忽略
+    async fn reorder_groups(&self, user_id: i64, names: &[String]) -> Result;
+    /// 按stationuuids的顺序设置分组中收藏的位置，不存在的收藏忽略
+    async fn reorder_favorites(&self, user_id: i64, group_name: &str, stationuuids: &[String]) -> Result;
+
+    // hiqradio sync, 返回版本号在(start_revision, end_revision]之间的修改
+    async fn query_revision(&self, user_id: i64) -> Result<i64>;
     async fn query_sync(
         &self,
         user_id: i64,
-        start_time: i64,
+        start_revision: i64,
+        end_revision: i64,
     ) -> Result<(Vec<FavGroup>, Vec<Recently>, Vec<StationGroup>)>;
+    async fn query_tombstones(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<Vec<Tombstone>>;
+    /// 版本号仍为revision时在一个事务内按顺序应用ops，返回新版本号；
+    /// 版本号已变化时不做修改，返回None
+    async fn apply_sync(&self, user_id: i64, revision: i64, ops: &[SyncOp]) -> Result<Option<i64>>;
+
+    // 分享
+    /// 分享分组，已分享的返回原来的分享
+    async fn share_group(&self, user_id: i64, group_name: &str, token: &str) -> Result<Share>;
+    async fn query_shares(&self, user_id: i64) -> Result<Vec<Share>>;
+    /// 按token查询分享，分组已删除、分享者已注销或已关闭hiqradio时返回None
+    async fn query_share(&self, token: &str) -> Result<Option<Share>>;
+    /// 取消分享，返回是否有分享
+    async fn delete_share(&self, user_id: i64, group_name: &str) -> Result<bool>;
+
+    // 推荐
+    /// 用户收藏的电台和start_time不早于since的最近播放中的电台，按user_id和stationuuid排序分页，
+    /// 返回(after_user_id, after_stationuuid)之后的limit条；同时收藏和播放过的favorite为1
+    async fn query_user_stations(
+        &self,
+        since: i64,
+        after_user_id: i64,
+        after_stationuuid: &str,
+        limit: i64,
+    ) -> Result<Vec<UserStation>>;
+    /// 用similar替换全部相似电台
+    async fn save_similar(&self, similar: &[Similar]) -> Result;
+    /// 查询电台的相似电台，按score倒序
+    async fn query_similar(&self, stationuuids: &[String]) -> Result<Vec<Similar>>;
+
+    // 热门
+    /// start_time在[since, until]内的播放，按电台和用户汇总时长
+    async fn query_station_listens(&self, since: i64, until: i64) -> Result<Vec<StationListen>>;
+    /// create_time在[since, until]内新增的收藏，同一用户收藏在多个分组的只返回一次
+    async fn query_new_favorites(&self, since: i64, until: i64) -> Result<Vec<UserStation>>;
+    /// 用stations替换period的热门电台
+    async fn save_trending(&self, period: &str, stations: &[TrendingStation]) -> Result;
+    /// 查询period的热门电台，按score倒序
+    async fn query_trending(&self, period: &str, limit: i64) -> Result<Vec<TrendingStation>>;
+
+    // 评分和评论
+    /// 保存用户对电台的评论，已有的覆盖并清空举报，保留create_time
+    async fn save_review(&self, review: &Review) -> Result<Review>;
+    /// 删除用户对电台的评论和举报，返回是否有评论
+    async fn delete_review(&self, user_id: i64, stationuuid: &str) -> Result<bool>;
+    async fn query_review(&self, id: i64) -> Result<Option<Review>>;
+    /// 查询用户的评论，按update_time倒序
+    async fn query_user_reviews(&self, user_id: i64) -> Result<Vec<Review>>;
+    /// 查询电台已通过的评论，按update_time倒序；评论者已注销或已关闭hiqradio的不返回
+    async fn query_station_reviews(
+        &self,
+        stationuuid: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Review>>;
+    /// 查询待审核的评论，举报多的在前
+    async fn query_pending_reviews(&self, offset: i64, limit: i64) -> Result<Vec<Review>>;
+    /// 设置评论的审核状态并清空举报数，返回是否有评论
+    async fn moderate_review(&self, id: i64, status: &str) -> Result<bool>;
+    /// 举报评论，同一用户只计一次；已通过的评论举报数达到pending_reports时重新待审核。
+    /// 返回评论的举报数
+    async fn report_review(
+        &self,
+        id: i64,
+        user_id: i64,
+        reason: &str,
+        time: i64,
+        pending_reports: i64,
+    ) -> Result<i64>;
+    /// 电台已通过评论的平均评分，不统计已注销或已关闭hiqradio的用户的评论，没有评论的电台不返回
+    async fn query_ratings(&self, stationuuids: &[String]) -> Result<Vec<Rating>>;
+
+    // 正在播放
+    /// 保存设备的播放状态，电台没变时保留start_time
+    async fn save_presence(&self, presence: &Presence) -> Result<Presence>;
+    /// 删除设备的播放状态，返回是否有记录
+    async fn delete_presence(&self, user_id: i64, device_id: &str) -> Result<bool>;
+    /// 查询用户未过期的播放状态，最近心跳的在前
+    async fn query_presences(&self, user_id: i64, now: i64) -> Result<Vec<Presence>>;
+    /// 删除已过期的播放状态，返回删除的数量
+    async fn clean_presence(&self, now: i64) -> Result<usize>;
+
+    // 电台目录
+    /// 写入电台，已存在的覆盖并取消删除标记
+    async fn save_stations(&self, stations: &[Station]) -> Result;
+    /// update_time早于before的电台标记为已删除，返回标记的数量
+    async fn delete_stations_before(&self, before: i64) -> Result<usize>;
+    /// 查询电台，不存在的电台不返回
+    async fn query_stations(&self, stationuuids: &[String]) -> Result<Vec<Station>>;
+    /// 按播放地址查询电台，url或url_resolved相同即可，包括已删除的电台
+    async fn query_stations_by_url(&self, urls: &[String]) -> Result<Vec<Station>>;
+    /// 按名称查询电台，名称完全相同，包括已删除的电台
+    async fn query_stations_by_name(&self, names: &[String]) -> Result<Vec<Station>>;
+    /// 搜索电台，按相关度排序
+    async fn search_stations(&self, filter: &StationFilter) -> Result<Vec<Station>>;
+}
+
+/// 一次修改的用户、版本号和操作时间(秒)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Change {
+    pub user_id: i64,
+    pub revision: i64,
+    pub time: i64,
 }
 
 pub type DynAppServRepo = Arc<dyn AppServRepo + Send + Sync>;
 
+/// 连接数据库并检查版本，`auto_migrate`时自动执行迁移
 pub async fn new(url: &str) -> Result<DynAppServRepo> {
-    // if url.starts_with("mysql") {
-    //     let repo = MySQLRepo::new(url).await?;
-    //     return Ok(Arc::new(repo));
-    // }
-    if url.starts_with("sqlite") {
-        let url = url.strip_prefix("sqlite://").unwrap();
+    let repo = connect(url).await?;
+    if CONFIG.auto_migrate {
+        repo.migrate().await?;
+    } else {
+        migrate::check_version(repo.schema_version().await?)?;
+    }
+    Ok(repo)
+}
+
+/// 仅连接数据库，不检查版本
+pub async fn connect(url: &str) -> Result<DynAppServRepo> {
+    if url.starts_with("memory://") {
+        return Ok(Arc::new(MemoryRepo::new()));
+    }
+    if url.starts_with("mysql://") {
+        let repo = MySQLRepo::new(url).await?;
+        return Ok(Arc::new(repo));
+    }
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        let repo = PgRepo::new(url).await?;
+        return Ok(Arc::new(repo));
+    }
+    if url.starts_with("sqlite:") {
         let repo = SqliteRepo::new(url).await?;
         return Ok(Arc::new(repo));
     }
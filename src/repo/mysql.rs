@@ -1,165 +1,3032 @@
-// use async_trait::async_trait;
-// use data_encoding::HEXLOWER;
-// use ring::digest::{Context, SHA256};
-// use sqlx::{mysql::MySqlPoolOptions, MySql, Pool};
-
-// use crate::{
-//     errors::Error,
-//     model::user::{User, USER_STATUS_NORMAL, USER_STATUS_WAIT_ACTIVATE},
-//     proto::{SignInReq, SignUpReq},
-//     Result,
-// };
-
-// use super::AppServRepo;
-
-// #[derive(Debug, Clone)]
-// pub struct MySQLRepo {
-//     pool: Pool<MySql>,
-// }
-
-// impl MySQLRepo {
-//     pub async fn new(url: &str) -> Result<Self> {
-//         let pool = MySqlPoolOptions::new()
-//             .max_connections(10)
-//             .connect(url)
-//             .await
-//             .map_err(|e| Error::DatabaseException(format!("connecting to mysql error: {}", e)))?;
-//         Ok(Self { pool })
-//     }
-
-//     fn user_from_signup(&self, signup: SignUpReq, passwd: String) -> User {
-//         User {
-//             id: None,
-//             user_name: signup.user_name,
-//             email: signup.email,
-//             passwd,
-//             status: String::from(USER_STATUS_WAIT_ACTIVATE),
-//             avatar: None,
-//             active_time: None,
-//             update_time: None,
-//         }
-//     }
-// }
-
-// #[async_trait]
-// impl AppServRepo for MySQLRepo {
-//     async fn create_user(&self, signup: SignUpReq) -> Result<User> {
-//         let user = sqlx::query_as::<_, User>(
-//             r#"select id, user_name, email, passwd, status, avatar, active_time, update_time
-//             from user where user_name = ?"#,
-//         )
-//         .bind(&signup.user_name)
-//         .fetch_optional(&self.pool)
-//         .await
-//         .map_err(|e| {
-//             tracing::error!("query user error: {}", e);
-//             Error::DatabaseException
-//         })?;
-//         if user.is_some() {
-//             return Err(Error::UserExists(format!(
-//                 "user \"{}\" exists",
-//                 &signup.user_name
-//             )));
-//         }
-
-//         let mut txn = self.pool.begin().await.map_err(|e| {
-//             tracing::error!("start transaction error: {}", e);
-//             Error::DatabaseException
-//         })?;
-//         let passwd = {
-//             let mut context = Context::new(&SHA256);
-//             let mut data = String::new();
-//             data.push_str(&signup.user_name);
-//             data.push_str(&signup.passwd);
-//             context.update(data.as_bytes());
-//             let digest = context.finish();
-//             HEXLOWER.encode(digest.as_ref())
-//         };
-//         let user = self.user_from_signup(signup, passwd);
-
-//         let last_insert_id = sqlx::query(
-//             "insert into user(`user_name`, `email`, `passwd`, `status`, `update_time`) values (?, ?, ?, ?, now())",
-//         )
-//         .bind(user.user_name)
-//         .bind(user.email)
-//         .bind(user.passwd)
-//         .bind(user.status)
-//         .execute(&mut *txn).await.map_err(|e| {
-//             tracing::error!("insert user error: {}", e);
-//             Error::DatabaseException
-//         })?.last_insert_id();
-
-//         let user = sqlx::query_as::<_, User>(
-//             r#"select id, user_name, email, passwd, status, avatar, active_time, update_time
-//             from user where id = ?"#,
-//         )
-//         .bind(last_insert_id)
-//         .fetch_one(&mut *txn)
-//         .await
-//         .map_err(|e| {
-//             tracing::error!(?e);
-//             Error::DatabaseException
-//         })?;
-
-//         txn.commit().await.map_err(|e| {
-//             tracing::error!("insert user commit error: {}", e);
-//             Error::DatabaseException
-//         })?;
-
-//         Ok(user)
-//     }
-
-//     async fn activate_user(&self, user_id: i64) -> Result {
-//         let mut txn = self.pool.begin().await.map_err(|e| {
-//             tracing::error!("start transaction error: {}", e);
-//             Error::DatabaseException
-//         })?;
-
-//         sqlx::query(
-//             "update user set status = ?, active_time = now(), update_time = now() where id = ?",
-//         )
-//         .bind(USER_STATUS_NORMAL)
-//         .bind(user_id)
-//         .execute(&mut *txn)
-//         .await
-//         .map_err(|e| {
-//             tracing::error!("update user error: {}", e);
-//             Error::DatabaseException
-//         })?;
-
-//         txn.commit().await.map_err(|e| {
-//             tracing::error!("update user commit error: {}", e);
-//             Error::DatabaseException
-//         })?;
-
-//         Ok(())
-//     }
-//     async fn signin_user(&self, signin: SignInReq) -> Result<User> {
-//         let user = sqlx::query_as::<_, User>(
-//             r#"select id, user_name, email, passwd, status, avatar, active_time, update_time
-//             from user where user_name = ?"#,
-//         )
-//         .bind(&signin.user_name)
-//         .fetch_one(&self.pool)
-//         .await
-//         .map_err(|e| {
-//             tracing::error!(?e);
-//             Error::UserNotExists
-//         })?;
-
-//         let passwd = {
-//             let mut context = Context::new(&SHA256);
-//             let mut data = String::new();
-//             data.push_str(&signin.user_name);
-//             data.push_str(&signin.passwd);
-//             context.update(data.as_bytes());
-//             let digest = context.finish();
-//             HEXLOWER.encode(digest.as_ref())
-//         };
-//         if passwd != user.passwd {
-//             return Err(Error::UserPasswdError);
-//         }
-
-//         Ok(user)
-//     }
-// }
+use std::fs;
+
+use async_trait::async_trait;
+use chrono::Local;
+use sqlx::Executor;
+use sqlx::{mysql::MySqlPoolOptions, Connection, MySql, Pool, Transaction};
+
+use crate::{
+    config::CONFIG,
+    errors::Error,
+    model::{
+        hiqradio::{
+            FavGroup, Favorite, Presence, Rating, Recently, Review, Setting, Share, Similar,
+            Station, StationGroup, StationListen, Tombstone, TrendingStation, UserStation,
+            TOMBSTONE_FAVORITE_DELETE, TOMBSTONE_GROUP_DELETE, TOMBSTONE_RECENTLY_CLEAR,
+        },
+        product::{Product, PRODUCT_HIQRADIO},
+        session::Session,
+        user::{User, USER_STATUS_NORMAL},
+        user_product::UserProduct,
+    },
+    proto::{
+        GroupNew, RecentlyFilter, RecentlyNew, ResetPasswdReq, SignInReq, SignUpReq, StationFilter,
+        StationStats, SyncOp, SEARCH_BOOST,
+    },
+    util::gen_passwd,
+    Result,
+};
+
+use super::{
+    migrate::{self, MYSQL_MIGRATIONS, SCHEMA_VERSION_TABLE},
+    retention::RecentlyRetention,
+    stats::HourPlays,
+    AppServRepo, Change,
+};
+
+/// 每个有最近播放的用户的时间范围、按条数保留的最早一条和设置
+const RETENTION_SELECT: &str = r#"select t.user_id, min(t.start_time) as oldest, max(t.start_time) as newest,
+            max(case when t.rn = ? then t.start_time end) as kept_oldest,
+            s.recently_enabled, s.recently_days
+            from (
+                select user_id, start_time, row_number() over (
+                    partition by user_id order by start_time desc, id desc
+                ) as rn
+                from hiqradio_recently
+            ) t
+            left join hiqradio_setting s on s.user_id = t.user_id
+            group by t.user_id, s.recently_enabled, s.recently_days"#;
+
+/// 评论和评论者的用户名，用户名是注册时默认的邮箱前缀时为空
+const REVIEW_SELECT: &str = r#"select a.id, a.user_id,
+            case when b.user_name = substring_index(b.email, '@', 1) then '' else coalesce(b.user_name, '') end as user_name,
+            a.stationuuid, a.rating, a.content, a.`status`, a.reports, a.create_time, a.update_time
+            from hiqradio_review a left join user b on a.user_id = b.id"#;
+
+#[derive(Debug, Clone)]
+pub struct MySQLRepo {
+    pool: Pool<MySql>,
+}
+
+impl MySQLRepo {
+    pub async fn new(url: &str) -> Result<Self> {
+        let pool = MySqlPoolOptions::new()
+            .max_connections(10)
+            .connect(url)
+            .await
+            .map_err(|e| Error::DatabaseException(format!("connecting to mysql error: {}", e)))?;
+        Ok(Self { pool })
+    }
+
+    fn user_from_signup(&self, signup: &SignUpReq, passwd: String) -> User {
+        let user_name = signup
+            .email
+            .split("@")
+            .collect::<Vec<_>>().first()
+            .unwrap()
+            .to_string();
+        User {
+            id: None,
+            user_name,
+            email: signup.email.clone(),
+            passwd,
+            status: String::from(USER_STATUS_NORMAL),
+            update_time: Local::now().timestamp_millis(),
+        }
+    }
+
+    fn build_in_param<T>(&self, param: &[T]) -> String {
+        let len = param.len();
+        match len {
+            0 => String::from(""),
+            1 => String::from("?"),
+            _ => format!("?{}", ", ?".repeat(param.len() - 1)),
+        }
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, MySql>> {
+        let txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(txn)
+    }
+    async fn rollback(&self, txn: Transaction<'static, MySql>) -> Result {
+        txn.rollback()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+    async fn commit(&self, txn: Transaction<'static, MySql>) -> Result {
+        txn.commit()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 开通产品，关闭过的产品恢复原记录，不新增重复记录
+    async fn open_user_product_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        user_id: i64,
+        product_id: i64,
+    ) -> Result {
+        let restored = sqlx::query(
+            "update user_product set status = '00', update_time = unix_timestamp() where user_id = ? and product_id = ? and status = '99'",
+        )
+        .bind(user_id)
+        .bind(product_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        if restored == 0 {
+            sqlx::query(
+                "insert into user_product(`user_id`, `product_id`, `status`, `update_time`) values (?, ?, '00', unix_timestamp())",
+            )
+            .bind(user_id)
+            .bind(product_id)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 关闭产品并删除该产品的会话，purge时清空头像
+    async fn close_user_product_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        user_id: i64,
+        product_id: i64,
+        purge: bool,
+    ) -> Result {
+        sqlx::query(
+            "update user_product set status = '99', update_time = ? where user_id = ? and product_id = ? and status = '00'",
+        )
+        .bind(Local::now().timestamp())
+        .bind(user_id)
+        .bind(product_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        if purge {
+            sqlx::query("update user_product set avatar = '' where user_id = ? and product_id = ?")
+                .bind(user_id)
+                .bind(product_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+
+        sqlx::query("delete from session where user_id = ? and product_id = ?")
+            .bind(user_id)
+            .bind(product_id)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除用户的hiqradio数据，删除记录和版本号一并删除，关闭产品或注销后不再同步
+    async fn purge_hiqradio_txn(&self, txn: &mut Transaction<'static, MySql>, user_id: i64) -> Result {
+        for sql in [
+            "delete from hiqradio_recently where user_id = ?",
+            "delete from hiqradio_favorite where user_id = ?",
+            "delete from hiqradio_fav_group where user_id = ?",
+            "delete from hiqradio_tombstone where user_id = ?",
+            "delete from hiqradio_revision where user_id = ?",
+            "delete from hiqradio_setting where user_id = ?",
+            "delete from hiqradio_share where user_id = ?",
+            "delete from hiqradio_review_report where user_id = ?",
+            "delete from hiqradio_review_report where review_id in (select id from hiqradio_review where user_id = ?)",
+            "delete from hiqradio_review where user_id = ?",
+            "delete from hiqradio_presence where user_id = ?",
+        ] {
+            sqlx::query(sql)
+                .bind(user_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 删除注销用户的会话和hiqradio数据，清空头像，匿名化用户信息
+    async fn purge_user_txn(&self, txn: &mut Transaction<'static, MySql>, user_id: i64) -> Result {
+        self.purge_hiqradio_txn(txn, user_id).await?;
+        for sql in [
+            "delete from session where user_id = ?",
+            "update user_product set avatar = '' where user_id = ?",
+            r#"update user set user_name = '', email = concat('cancelled-', id), passwd = '' where id = ?"#,
+        ] {
+            sqlx::query(sql)
+                .bind(user_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 开始事务并递增用户版本号，同一事务内的修改使用同一版本号
+    async fn begin_revision(&self, user_id: i64) -> Result<(Transaction<'static, MySql>, Change)> {
+        let mut txn = self.begin().await?;
+
+        if let Err(e) = sqlx::query(
+            r#"insert into hiqradio_revision(user_id, revision) values (?, 1)
+            on duplicate key update revision = revision + 1"#,
+        )
+        .bind(user_id)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        match sqlx::query_scalar::<_, i64>(
+            r#"select revision from hiqradio_revision where user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(revision) => Ok((
+                txn,
+                Change {
+                    user_id,
+                    revision,
+                    time: Local::now().timestamp(),
+                },
+            )),
+            Err(e) => {
+                self.rollback(txn).await?;
+                Err(Error::DatabaseException(e.to_string()))
+            }
+        }
+    }
+
+    async fn new_tombstone(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        tombstone: &Tombstone,
+    ) -> Result {
+        sqlx::query(
+            r#"insert into hiqradio_tombstone(user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision) 
+            values (?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(tombstone.user_id)
+        .bind(&tombstone.kind)
+        .bind(&tombstone.group_name)
+        .bind(&tombstone.stationuuid)
+        .bind(&tombstone.new_name)
+        .bind(&tombstone.new_desc)
+        .bind(tombstone.create_time)
+        .bind(tombstone.revision)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 记录重新创建后，之前的删除记录作废
+    async fn delete_tombstone(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        user_id: i64,
+        kind: &str,
+        group_name: &str,
+        stationuuid: &str,
+    ) -> Result {
+        sqlx::query(
+            r#"delete from hiqradio_tombstone 
+            where user_id = ? and kind = ? and group_name = ? and stationuuid = ?"#,
+        )
+        .bind(user_id)
+        .bind(kind)
+        .bind(group_name)
+        .bind(stationuuid)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除用户开始时间早于before的最近播放并写入清空记录，before为None时全部删除、清空时间取change.time；
+    /// 已有更晚的清空记录时沿用其时间，返回删除的条数
+    async fn clear_recently(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: &Change,
+        before: Option<i64>,
+    ) -> Result<u64> {
+        let count = sqlx::query(
+            "delete from hiqradio_recently where user_id = ? and start_time < ?",
+        )
+        .bind(change.user_id)
+        .bind(before.unwrap_or(i64::MAX))
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        let cleared = sqlx::query_scalar::<_, Option<i64>>(
+            "select max(create_time) from hiqradio_tombstone where user_id = ? and kind = ?",
+        )
+        .bind(change.user_id)
+        .bind(TOMBSTONE_RECENTLY_CLEAR)
+        .fetch_one(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        let time = before.unwrap_or(change.time).max(cleared.unwrap_or(i64::MIN));
+
+        self.delete_tombstone(txn, change.user_id, TOMBSTONE_RECENTLY_CLEAR, "", "")
+            .await?;
+        self.new_tombstone(
+            txn,
+            &Tombstone::recently_clear(change.user_id, change.revision, time),
+        )
+        .await?;
+        Ok(count)
+    }
+
+    /// 保存设置，change不为None时同时清空最近播放
+    async fn modify_setting_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        setting: &Setting,
+        change: Option<&Change>,
+    ) -> Result {
+        sqlx::query(
+            r#"insert into hiqradio_setting(user_id, recently_enabled, recently_days, update_time)
+            values (?, ?, ?, ?)
+            on duplicate key update
+            recently_enabled = values(recently_enabled),
+            recently_days = values(recently_days),
+            update_time = values(update_time)"#,
+        )
+        .bind(setting.user_id)
+        .bind(setting.recently_enabled)
+        .bind(setting.recently_days)
+        .bind(Local::now().timestamp())
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        if let Some(change) = change {
+            self.clear_recently(txn, change, None).await?;
+        }
+        Ok(())
+    }
+
+    /// 新建分组排在最后
+    async fn next_group_position(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        user_id: i64,
+    ) -> Result<i64> {
+        sqlx::query_scalar::<_, i64>(
+            r#"select cast(coalesce(max(`position`) + 1, 0) as signed) from hiqradio_fav_group where user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))
+    }
+
+    /// 新收藏排在分组的最后
+    async fn next_favorite_position(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        group_id: i64,
+    ) -> Result<i64> {
+        sqlx::query_scalar::<_, i64>(
+            r#"select cast(coalesce(max(`position`) + 1, 0) as signed) from hiqradio_favorite where group_id = ?"#,
+        )
+        .bind(group_id)
+        .fetch_one(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))
+    }
+
+    /// 即将删除的收藏，用于生成删除记录
+    async fn query_deleted_favorites(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        user_id: i64,
+        column: &str,
+        params: &[String],
+    ) -> Result<Vec<StationGroup>> {
+        let query_str = format!(
+            r#"select a.name as group_name,  b.stationuuid, b.create_time, b.`position`
+            from hiqradio_fav_group a, hiqradio_favorite b
+            where a.id = b.group_id and a.user_id = b.user_id and b.user_id = ? and {} in ({})"#,
+            column,
+            self.build_in_param(params)
+        );
+        let mut query = sqlx::query_as::<_, StationGroup>(&query_str);
+        query = query.bind(user_id);
+        for param in params {
+            query = query.bind(param);
+        }
+        let deleted = query
+            .fetch_all(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(deleted)
+    }
+    async fn new_recently_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        recently: &[RecentlyNew],
+    ) -> Result {
+        for e in recently.iter() {
+            sqlx::query(
+                r#"insert into hiqradio_recently(user_id, stationuuid, start_time, end_time, revision) 
+                values (?, ?, ?, ?, ?)"#,
+            )
+            .bind(change.user_id)
+            .bind(&e.stationuuid)
+            .bind(e.start_time)
+            .bind(e.end_time)
+            .bind(change.revision)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn modify_recently_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        stationuuid: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result {
+        sqlx::query(
+            r#"update hiqradio_recently set end_time = ?, revision = ? where stationuuid = ? and start_time = ? and user_id = ?"#,
+        )
+        .bind(end_time)
+        .bind(change.revision)
+        .bind(stationuuid)
+        .bind(start_time)
+        .bind(change.user_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_groups_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        groups: &[String],
+    ) -> Result {
+        for e in groups.iter() {
+            let group = sqlx::query_as::<_, FavGroup>(
+                r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def 
+                from hiqradio_fav_group
+                where user_id = ? and name = ?"#,
+            )
+            .bind(change.user_id)
+            .bind(e)
+            .fetch_one(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            sqlx::query(r#"delete from hiqradio_favorite where group_id = ?"#)
+                .bind(group.id.unwrap())
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            sqlx::query(r#"delete from hiqradio_fav_group where name = ? and user_id = ?"#)
+                .bind(e)
+                .bind(change.user_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            self.new_tombstone(
+                txn,
+                &Tombstone::group_delete(change.user_id, change.revision, change.time, e),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn new_groups_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        groups: &[GroupNew],
+    ) -> Result {
+        for e in groups.iter() {
+            if sqlx::query_as::<_, FavGroup>(
+                r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def 
+                from hiqradio_fav_group
+                where user_id = ? and name = ?"#,
+            )
+            .bind(change.user_id)
+            .bind(&e.name)
+            .fetch_one(&mut **txn)
+            .await
+            .is_ok()
+            {
+                continue;
+            }
+
+            if e.is_def > 0 {
+                if let Ok(fg) = sqlx::query_as::<_, FavGroup>(
+                    r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def 
+                    from hiqradio_fav_group
+                    where user_id = ? and is_def = 1"#,
+                )
+                .bind(change.user_id)
+                .fetch_one(&mut **txn)
+                .await
+                {
+                    if fg.create_time > e.create_time {
+                        continue;
+                    }
+
+                    sqlx::query(
+                        r#"delete from hiqradio_fav_group where user_id = ? and is_def = 1"#,
+                    )
+                    .bind(change.user_id)
+                    .execute(&mut **txn)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+                    self.new_tombstone(
+                        txn,
+                        &Tombstone::group_delete(
+                            change.user_id,
+                            change.revision,
+                            change.time,
+                            &fg.name,
+                        ),
+                    )
+                    .await?;
+                }
+            }
+
+            let position = self.next_group_position(txn, change.user_id).await?;
+            sqlx::query(
+                r#"insert into hiqradio_fav_group(user_id, create_time, name, `desc`, is_def, revision, `position`) 
+                values(?, ?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(change.user_id)
+            .bind(e.create_time)
+            .bind(&e.name)
+            .bind(&e.desc)
+            .bind(e.is_def)
+            .bind(change.revision)
+            .bind(position)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            self.delete_tombstone(txn, change.user_id, TOMBSTONE_GROUP_DELETE, &e.name, "")
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn modify_group_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        old_name: &str,
+        name: &str,
+        desc: &str,
+    ) -> Result {
+        let res = sqlx::query(
+            r#"update hiqradio_fav_group set name = ?, `desc` = ?, revision = ? where name = ? and user_id = ?"#,
+        )
+        .bind(name)
+        .bind(desc)
+        .bind(change.revision)
+        .bind(old_name)
+        .bind(change.user_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        if res.rows_affected() > 0 {
+            self.new_tombstone(
+                txn,
+                &Tombstone::group_modify(
+                    change.user_id,
+                    change.revision,
+                    change.time,
+                    old_name,
+                    name,
+                    desc,
+                ),
+            )
+            .await?;
+            self.delete_tombstone(txn, change.user_id, TOMBSTONE_GROUP_DELETE, name, "")
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn new_favorite_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        stations: &[StationGroup],
+    ) -> Result {
+        for elem in stations.iter() {
+            let group = sqlx::query_as::<_, FavGroup>(
+                r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def 
+                from hiqradio_fav_group
+                where user_id = ? and name = ?"#,
+            )
+            .bind(change.user_id)
+            .bind(&elem.group_name)
+            .fetch_one(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            if sqlx::query_as::<_, Favorite>(
+                r#"select id, user_id, stationuuid, group_id, create_time
+                from hiqradio_favorite
+                where user_id = ? and group_id = ? and stationuuid = ?"#,
+            )
+            .bind(change.user_id)
+            .bind(group.id.unwrap())
+            .bind(&elem.stationuuid)
+            .fetch_one(&mut **txn)
+            .await
+            .is_ok()
+            {
+                continue;
+            }
+
+            let position = self.next_favorite_position(txn, group.id.unwrap()).await?;
+            sqlx::query(
+                r#"insert into hiqradio_favorite(user_id, stationuuid, group_id, create_time, revision, `position`) 
+                values(?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(change.user_id)
+            .bind(&elem.stationuuid)
+            .bind(group.id.unwrap())
+            .bind(elem.create_time)
+            .bind(change.revision)
+            .bind(position)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            self.delete_tombstone(
+                txn,
+                change.user_id,
+                TOMBSTONE_FAVORITE_DELETE,
+                &elem.group_name,
+                &elem.stationuuid,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// 删除分组中的一个收藏
+    async fn delete_group_favorite_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        group_name: &str,
+        stationuuid: &str,
+    ) -> Result {
+        let res = sqlx::query(
+            r#"delete from hiqradio_favorite 
+            where user_id = ? and stationuuid = ? and group_id in (
+                select id from hiqradio_fav_group where user_id = ? and name = ?
+            )"#,
+        )
+        .bind(change.user_id)
+        .bind(stationuuid)
+        .bind(change.user_id)
+        .bind(group_name)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        if res.rows_affected() > 0 {
+            self.new_tombstone(
+                txn,
+                &Tombstone::favorite_delete(
+                    change.user_id,
+                    change.revision,
+                    change.time,
+                    group_name,
+                    stationuuid,
+                ),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn modify_favorite_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        stationuuid: &str,
+        groups: &[String],
+    ) -> Result {
+        let old_groups = self
+            .query_deleted_favorites(txn, change.user_id, "b.stationuuid", &[stationuuid.to_string()])
+            .await?;
+        if old_groups.is_empty() {
+            return Err(Error::DatabaseException("station not found".to_string()));
+        }
+
+        sqlx::query(
+            r#"delete from hiqradio_favorite  
+            where user_id = ? and stationuuid = ?"#,
+        )
+        .bind(change.user_id)
+        .bind(stationuuid)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let groups = if groups.is_empty() {
+            Vec::new()
+        } else {
+            let query_str = format!(
+                r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def 
+            from hiqradio_fav_group
+            where user_id = ? and name in ({})"#,
+                self.build_in_param(groups)
+            );
+
+            let mut query = sqlx::query_as::<_, FavGroup>(&query_str);
+
+            query = query.bind(change.user_id);
+            for param in groups {
+                query = query.bind(param);
+            }
+            query
+                .fetch_all(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?
+        };
+
+        for e in old_groups
+            .iter()
+            .filter(|e| !groups.iter().any(|g| g.name == e.group_name))
+        {
+            self.new_tombstone(
+                txn,
+                &Tombstone::favorite_delete(
+                    change.user_id,
+                    change.revision,
+                    change.time,
+                    &e.group_name,
+                    stationuuid,
+                ),
+            )
+            .await?;
+        }
+
+        for e in groups {
+            // 仍在原分组中的收藏保持原来的位置
+            let position = match old_groups.iter().find(|g| g.group_name == e.name) {
+                Some(old) => old.position,
+                None => self.next_favorite_position(txn, e.id.unwrap()).await?,
+            };
+            sqlx::query(
+                r#"insert into hiqradio_favorite(user_id, stationuuid, group_id, create_time, revision, `position`) 
+                values(?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(change.user_id)
+            .bind(stationuuid)
+            .bind(e.id.unwrap())
+            .bind(change.time)
+            .bind(change.revision)
+            .bind(position)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            self.delete_tombstone(txn, change.user_id, TOMBSTONE_FAVORITE_DELETE, &e.name, stationuuid)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 应用一个离线操作，time为客户端操作时间
+    async fn apply_op_txn(
+        &self,
+        txn: &mut Transaction<'static, MySql>,
+        change: Change,
+        op: &SyncOp,
+    ) -> Result {
+        match op {
+            SyncOp::GroupNew {
+                time,
+                name,
+                desc,
+                is_def,
+            } => {
+                let group = GroupNew {
+                    create_time: *time,
+                    name: name.clone(),
+                    desc: desc.clone(),
+                    is_def: *is_def,
+                };
+                self.new_groups_txn(txn, Change { time: *time, ..change }, &[group])
+                    .await
+            }
+            SyncOp::GroupModify {
+                time,
+                old_name,
+                name,
+                desc,
+            } => {
+                self.modify_group_txn(txn, Change { time: *time, ..change }, old_name, name, desc)
+                    .await
+            }
+            SyncOp::GroupDelete { time, name } => {
+                self.delete_groups_txn(txn, Change { time: *time, ..change }, std::slice::from_ref(name))
+                    .await
+            }
+            SyncOp::FavoriteNew {
+                time,
+                group_name,
+                stationuuid,
+            } => {
+                let station = StationGroup {
+                    group_name: group_name.clone(),
+                    stationuuid: stationuuid.clone(),
+                    create_time: *time,
+                    position: 0,
+                };
+                self.new_favorite_txn(txn, change, &[station]).await
+            }
+            SyncOp::FavoriteModify {
+                time,
+                stationuuid,
+                group_names,
+            } => {
+                self.modify_favorite_txn(
+                    txn,
+                    Change { time: *time, ..change },
+                    stationuuid,
+                    group_names,
+                )
+                .await
+            }
+            SyncOp::FavoriteDelete {
+                time,
+                group_name,
+                stationuuid,
+            } => {
+                self.delete_group_favorite_txn(
+                    txn,
+                    Change { time: *time, ..change },
+                    group_name,
+                    stationuuid,
+                )
+                .await
+            }
+            SyncOp::RecentlyNew {
+                stationuuid,
+                start_time,
+                end_time,
+            } => {
+                let recently = RecentlyNew {
+                    stationuuid: stationuuid.clone(),
+                    start_time: *start_time,
+                    end_time: *end_time,
+                };
+                self.new_recently_txn(txn, change, &[recently]).await
+            }
+            SyncOp::RecentlyModify {
+                stationuuid,
+                start_time,
+                end_time,
+            } => {
+                self.modify_recently_txn(txn, change, stationuuid, *start_time, *end_time)
+                    .await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AppServRepo for MySQLRepo {
+    async fn schema_version(&self) -> Result<i64> {
+        self.pool
+            .execute(SCHEMA_VERSION_TABLE)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let version: Option<i64> = sqlx::query_scalar("select max(version) from schema_version")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(version.unwrap_or(0))
+    }
+    async fn migrate(&self) -> Result {
+        // 多实例同时启动时，用命名锁保证只有一个实例执行迁移
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        let locked: Option<i64> = sqlx::query_scalar("select get_lock('appserv_migrate', 60)")
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        if locked != Some(1) {
+            return Err(Error::DatabaseException(String::from(
+                "wait for migrate lock timeout",
+            )));
+        }
+
+        let res = async {
+            let version = self.schema_version().await?;
+            migrate::check_newer(version)?;
+
+            for m in migrate::pending(MYSQL_MIGRATIONS, version) {
+                tracing::info!("migrate mysql to version {}: {}", m.version, m.description);
+                // mysql的ddl会隐式提交，事务只能保证版本记录；迁移脚本可以重复执行，
+                // 中途失败后下次启动重新执行整个迁移
+                let mut txn = conn
+                    .begin()
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?;
+                (&mut *txn).execute(m.sql).await.map_err(|e| {
+                    Error::DatabaseException(format!("migrate version {} error: {}", m.version, e))
+                })?;
+                sqlx::query(
+                    "insert into schema_version(version, description, applied_time) values (?, ?, ?)",
+                )
+                .bind(m.version)
+                .bind(m.description)
+                .bind(Local::now().timestamp_millis())
+                .execute(&mut *txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+                txn.commit()
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?;
+            }
+            Ok(())
+        }
+        .await;
+
+        sqlx::query("select release_lock('appserv_migrate')")
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        res
+    }
+
+    async fn backup(&self, _path: &str) -> Result {
+        Err(Error::Internal(String::from(
+            "mysql online backup not supported, use mysqldump",
+        )))
+    }
+
+    async fn clean_avatar_path(&self, path: &str) -> Result {
+        if let Err(sqlx::Error::RowNotFound) = sqlx::query_as::<_, UserProduct>(
+            r#"select id, product_id, user_id, ifnull(avatar, '') as avatar, status, update_time
+            from user_product 
+            where avatar = ?"#,
+        )
+        .bind(path)
+        .fetch_one(&self.pool)
+        .await
+        {
+            let path = format!("{}/{}", &CONFIG.avatar_path, path);
+            tracing::info!("remove unused avatar: {}", &path);
+            fs::remove_file(path)
+                .map_err(|e| Error::Internal(format!("remove file error: {}", e)))?;
+        }
+        Ok(())
+    }
+    async fn clean_session(&self) -> Result {
+        let now = Local::now().timestamp_millis();
+        let session: Vec<_> = sqlx::query_as::<_, Session>(
+            r#"select id, token, user_id, product_id, expire 
+            from session 
+            where expire <= ?"#,
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .iter()
+        .map(|e| e.id.unwrap())
+        .collect();
+
+        if !session.is_empty() {
+            let mut txn = self.begin().await?;
+
+            let query_str = format!(
+                r#"delete from session  
+                where id in ({})"#,
+                self.build_in_param(&session)
+            );
+
+            let mut query = sqlx::query(&query_str);
+
+            for param in session {
+                query = query.bind(param);
+            }
+            if let Err(e) = query.execute(&mut *txn).await {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+
+            self.commit(txn).await?;
+        }
+
+        Ok(())
+    }
+    async fn purge_cancelled_users(&self, before: i64) -> Result<usize> {
+        let users: Vec<i64> = sqlx::query_scalar(
+            r#"select id from user where status = '99' and passwd != '' and update_time < ?"#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        for user_id in users.iter() {
+            let mut txn = self.begin().await?;
+            if let Err(e) = self.purge_user_txn(&mut txn, *user_id).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(users.len())
+    }
+
+    async fn create_user(&self, signup: &SignUpReq) -> Result<User> {
+        if let Some(user) = sqlx::query_as::<_, User>(
+            r#"select id, ifnull(user_name, '') as user_name, email, passwd, status, ifnull(update_time, 0) as update_time
+            from user where email = ?"#,
+        )
+        .bind(&signup.email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        {
+            return Err(Error::UserExists(format!(
+                "email \"{}\" exists",
+                &user.email
+            )));
+        }
+
+        let product = sqlx::query_as::<_, Product>(
+            r#"select id, product, ifnull(`desc`, '') as `desc`, status, ifnull(update_time, 0) as update_time
+            from product where product = ?"#,
+        )
+        .bind(&signup.product)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .ok_or(Error::ProductNotExists)?;
+
+        let mut txn = self.begin().await?;
+        let passwd = gen_passwd(&signup.email, &signup.passwd);
+        let user = self.user_from_signup(signup, passwd);
+
+        let last_insert_id = match sqlx::query(
+            "insert into user(user_name, email, passwd, status, update_time) values (?, ?, ?, ?, ?)",
+        )
+        .bind(&user.user_name)
+        .bind(&user.email)
+        .bind(user.passwd)
+        .bind(user.status)
+        .bind(user.update_time)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(res) => res.last_insert_id(),
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        let user = sqlx::query_as::<_, User>(
+            r#"select id, ifnull(user_name, '') as user_name, email, passwd, status, ifnull(update_time, 0) as update_time
+            from user where id = ?"#,
+        )
+        .bind(last_insert_id)
+        .fetch_one(&mut *txn)
+        .await;
+        if let Err(e) = &user {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+        let user = user.unwrap();
+
+        if let Err(e) = sqlx::query(
+            "insert into user_product(user_id, product_id, status, update_time) values (?, ?, '00', unix_timestamp())",
+        )
+        .bind(user.id.unwrap())
+        .bind(product.id.unwrap())
+        .execute(&mut *txn)
+        .await{
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        self.commit(txn).await?;
+
+        Ok(user)
+    }
+
+    async fn signin_user(&self, signin: &SignInReq) -> Result<(User, Product, Session)> {
+        let user = sqlx::query_as::<_, User>(
+            r#"select id, ifnull(user_name, '') as user_name, email, passwd, status, ifnull(update_time, 0) as update_time
+            from user where status = '00' and email = ?"#,
+        )
+        .bind(&signin.email)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            match e {
+                sqlx::Error::RowNotFound => Error::UserNotExists,
+
+                _ => Error::DatabaseException(e.to_string()),
+            }
+        })?;
+
+        let passwd = gen_passwd(&user.email, &signin.passwd);
+        if passwd != user.passwd {
+            return Err(Error::UserPasswdError);
+        }
+
+        let products = self.query_user_products(user.id.unwrap()).await?;
+
+        let open_products: Vec<_> = products
+            .into_iter()
+            .filter(|product| product.product == signin.product)
+            .collect();
+
+        let product = if open_products.is_empty() {
+            if signin.product_open_flag {
+                let mut txn = self.begin().await?;
+
+                let product = sqlx::query_as::<_, Product>(
+                    r#"select id, product, ifnull(`desc`, '') as `desc`, status, ifnull(update_time, 0) as update_time
+                    from product 
+                    where status = '00' and product = ?"#,
+                )
+                .bind(&signin.product)
+                .fetch_one(&mut *txn)
+                .await
+                .map_err(|e| {
+                    match e {
+                        sqlx::Error::RowNotFound => Error::ProductNotExists,
+
+                        _ => Error::DatabaseException(e.to_string()),
+                    }
+                })?;
+
+                self.open_user_product_txn(&mut txn, user.id.unwrap(), product.id.unwrap())
+                    .await?;
+
+                self.commit(txn).await?;
+
+                product
+            } else {
+                return Err(Error::ProductNotOpen);
+            }
+        } else {
+            open_products[0].clone()
+        };
+
+        let token = {
+            let mut txn = self.begin().await?;
+            let token = Session::token(product.id.unwrap(), user.id.unwrap());
+
+            if let Err(e) = sqlx::query(
+                "insert into session(token, user_id, product_id, expire) values (?, ?, ?, ?)",
+            )
+            .bind(&token.token)
+            .bind(token.user_id)
+            .bind(token.product_id)
+            .bind(token.expire)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+
+            self.commit(txn).await?;
+
+            token
+        };
+
+        Ok((user, product, token))
+    }
+
+    async fn reset_user_passwd(&self, reset: &ResetPasswdReq) -> Result {
+        let user = sqlx::query_as::<_, User>(
+            r#"select id, ifnull(user_name, '') as user_name, email, passwd, status, ifnull(update_time, 0) as update_time
+            from user where status = '00' and email = ?"#,
+        )
+        .bind(&reset.email)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            match e {
+                sqlx::Error::RowNotFound => Error::UserNotExists,
+
+                _ => Error::DatabaseException(e.to_string()),
+            }
+        })?;
+
+        let passwd = gen_passwd(&user.email, &reset.passwd);
+
+        let mut txn = self.begin().await?;
+
+        sqlx::query(
+            "update user set passwd = ?, update_time = unix_timestamp() where id = ?",
+        )
+        .bind(&passwd)
+        .bind(user.id.unwrap())
+        .execute(&mut *txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        self.commit(txn).await?;
+
+        Ok(())
+    }
+    async fn cancel_user(&self, user_id: i64) -> Result {
+        let mut txn = self.begin().await?;
+        match sqlx::query(
+            r#"update user set status = '99', update_time = ? where status = '00' and id = ?"#,
+        )
+        .bind(Local::now().timestamp())
+        .bind(user_id)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                self.rollback(txn).await?;
+                return Err(Error::UserNotExists);
+            }
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+            Ok(_) => {}
+        }
+        if let Err(e) = sqlx::query("delete from session where user_id = ?")
+            .bind(user_id)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+        self.commit(txn).await
+    }
+
+    async fn open_product(&self, user_id: i64, product: &str) -> Result {
+        let products = self.query_user_products(user_id).await?;
+
+        let open_products: Vec<_> = products
+            .into_iter()
+            .filter(|p| p.product == product)
+            .collect();
+
+        if open_products.is_empty() {
+            let mut txn = self.begin().await?;
+
+            let product = sqlx::query_as::<_, Product>(
+                r#"select id, product, ifnull(`desc`, '') as `desc`, status, ifnull(update_time, 0) as update_time
+                    from product 
+                    where status = '00' and product = ?"#,
+            )
+            .bind(product)
+            .fetch_one(&mut *txn)
+            .await
+            .map_err(|e| {
+                match e {
+                    sqlx::Error::RowNotFound => Error::ProductNotExists,
+
+                    _ => Error::DatabaseException(e.to_string()),
+                }
+            })?;
+
+            self.open_user_product_txn(&mut txn, user_id, product.id.unwrap())
+                .await?;
+
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn close_product(&self, user_id: i64, product: &str, purge: bool) -> Result {
+        let product_id = self
+            .query_user_products(user_id)
+            .await?
+            .into_iter()
+            .find(|p| p.product == product)
+            .and_then(|p| p.id)
+            .ok_or(Error::ProductNotOpen)?;
+
+        let mut txn = self.begin().await?;
+        if let Err(e) = self
+            .close_user_product_txn(&mut txn, user_id, product_id, purge)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+        // 目前只有hiqradio有产品数据
+        if purge && product == PRODUCT_HIQRADIO {
+            if let Err(e) = self.purge_hiqradio_txn(&mut txn, user_id).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+        }
+        self.commit(txn).await
+    }
+
+    async fn get_session(&self, token: &str) -> Result<Session> {
+        let mut session = sqlx::query_as::<_, Session>(
+            r#"select id, token, user_id, product_id, expire 
+            from session 
+            where token = ?"#,
+        )
+        .bind(token)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            match e {
+                sqlx::Error::RowNotFound => Error::TokenInvalid,
+
+                _ => Error::DatabaseException(e.to_string()),
+            }
+        })?;
+
+        let res = {
+            let mut res = Err(Error::TokenInvalid);
+
+            let mut txn = self.begin().await?;
+
+            let now = Local::now().timestamp_millis();
+
+            if session.expire < now {
+                if let Err(e) = sqlx::query("delete from session where token = ?")
+                    .bind(&session.token)
+                    .execute(&mut *txn)
+                    .await
+                {
+                    self.rollback(txn).await?;
+                    return Err(Error::DatabaseException(e.to_string()));
+                }
+            } else if session.expire - now < CONFIG.token_refresh * 1000 {
+                let expire = now + CONFIG.token_expire * 1000;
+                if let Err(e) = sqlx::query("update session set expire = ? where token = ?")
+                    .bind(expire)
+                    .bind(&session.token)
+                    .execute(&mut *txn)
+                    .await
+                {
+                    self.rollback(txn).await?;
+                    return Err(Error::DatabaseException(e.to_string()));
+                }
+
+                session.expire = expire;
+                res = Ok(session)
+            } else {
+                res = Ok(session)
+            }
+
+            self.commit(txn).await?;
+            res
+        };
+
+        res
+    }
+
+    async fn update_user_info(
+        &self,
+        user_id: i64,
+        product_id: i64,
+        user_name: Option<String>,
+        new_passwd: Option<String>,
+        avatar: Option<String>,
+    ) -> Result {
+        let mut txn = self.begin().await?;
+
+        if let Some(new_user_name) = user_name {
+            if let Err(e) = sqlx::query("update user set user_name = ? where id = ?")
+                .bind(&new_user_name)
+                .bind(user_id)
+                .execute(&mut *txn)
+                .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+        if let Some(new_passwd) = new_passwd {
+            if let Err(e) = sqlx::query("update user set passwd = ? where id = ?")
+                .bind(&new_passwd)
+                .bind(user_id)
+                .execute(&mut *txn)
+                .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+        if let Some(new_avatar) = avatar {
+            if let Err(e) = sqlx::query(
+                "update user_product set avatar = ? where user_id = ? and product_id = ?",
+            )
+            .bind(&new_avatar)
+            .bind(user_id)
+            .bind(product_id)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_user_products(&self, user_id: i64) -> Result<Vec<Product>> {
+        let products = sqlx::query_as::<_, Product>(
+            r#"select a.id as id, a.product as product, ifnull(a.`desc`, '') as `desc`, ifnull(a.update_time, 0) as update_time
+            from product a, user_product b 
+            where a.id = b.product_id and a.status = '00' and b.status = '00' and b.user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(products)
+    }
+
+    async fn query_products(&self) -> Result<Vec<Product>> {
+        let products = sqlx::query_as::<_, Product>(
+            r#"select a.id as id, a.product as product, ifnull(a.`desc`, '') as `desc`, ifnull(a.update_time, 0) as update_time
+            from product a
+            where a.status = '00' and 1 = ? "#,
+        )
+        .bind(1)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(products)
+    }
+
+    async fn query_product(&self, product_id: i64) -> Result<Product> {
+        let product = sqlx::query_as::<_, Product>(
+            r#"select id, product, ifnull(`desc`, '') as `desc`, status, ifnull(update_time, 0) as update_time
+            from product 
+            where status = '00' and id = ?"#,
+        )
+        .bind(product_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            match e {
+                sqlx::Error::RowNotFound => Error::ProductNotExists,
+
+                _ => Error::DatabaseException(e.to_string()),
+            }
+        })?;
+
+        Ok(product)
+    }
+    async fn query_user(&self, user_id: i64) -> Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            r#"select id, ifnull(user_name, '') as user_name, email, passwd, status, ifnull(update_time, 0) as update_time
+            from user
+            where status = '00' and id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            match e {
+                sqlx::Error::RowNotFound => Error::UserNotExists,
+
+                _ => Error::DatabaseException(e.to_string()),
+            }
+        })?;
+
+        Ok(user)
+    }
+    async fn query_user_product(&self, user_id: i64, product_id: i64) -> Result<UserProduct> {
+        let user_product = sqlx::query_as::<_, UserProduct>(
+            r#"select id, product_id, user_id, ifnull(avatar, '') as avatar, status, update_time
+            from user_product 
+            where status = '00' and product_id = ? and user_id = ?"#,
+        )
+        .bind(product_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| {
+            match e {
+                sqlx::Error::RowNotFound => Error::ProductNotOpen,
+
+                _ => Error::DatabaseException(e.to_string()),
+            }
+        })?;
+
+        Ok(user_product)
+    }
+    async fn delete_session(&self, token: &str) -> Result {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query("delete from session where token = ?")
+            .bind(token)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        if let Err(e) = sqlx::query("delete from hiqradio_presence where token = ?")
+            .bind(token)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_recently(&self, user_id: i64) -> Result<Vec<Recently>> {
+        let recently = sqlx::query_as::<_, Recently>(
+            r#"select id, user_id, stationuuid, start_time, end_time 
+            from hiqradio_recently
+            where user_id = ? order by start_time desc"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(recently)
+    }
+
+    async fn query_recently_page(
+        &self,
+        user_id: i64,
+        filter: &RecentlyFilter,
+    ) -> Result<Vec<Recently>> {
+        let station = if filter.stationuuid.is_some() {
+            "and stationuuid = ?"
+        } else {
+            ""
+        };
+        let sql = format!(
+            r#"select id, user_id, stationuuid, start_time, end_time
+            from hiqradio_recently
+            where user_id = ? and start_time >= ? and start_time < ?
+            and start_time <= ? and (start_time < ? or id < ?) {}
+            order by start_time desc, id desc limit ?"#,
+            station
+        );
+        let (after_time, after_id) = filter.after.unwrap_or((i64::MAX, i64::MAX));
+        let mut query = sqlx::query_as::<_, Recently>(&sql)
+            .bind(user_id)
+            .bind(filter.since.unwrap_or(i64::MIN))
+            .bind(filter.until.unwrap_or(i64::MAX))
+            .bind(after_time)
+            .bind(after_time)
+            .bind(after_id);
+        if let Some(stationuuid) = &filter.stationuuid {
+            query = query.bind(stationuuid);
+        }
+        let recently = query
+            .bind(filter.limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(recently)
+    }
+
+    async fn query_recently_stations(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<StationStats>> {
+        let stations = sqlx::query_as::<_, StationStats>(
+            r#"select stationuuid, count(*) as plays,
+            cast(coalesce(sum(case when end_time > start_time then end_time - start_time else 0 end), 0) as signed) as `time`
+            from hiqradio_recently
+            where user_id = ? and start_time >= ? and start_time < ?
+            group by stationuuid"#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn query_recently_hours(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+        offset: i64,
+    ) -> Result<Vec<HourPlays>> {
+        // 按本地时间向下取整到小时，1970年之前的时间也向下取整
+        let hours = sqlx::query_as::<_, HourPlays>(
+            r#"select (t.local_time - ((t.local_time % 3600) + 3600) % 3600) div 3600 as hour,
+            count(*) as plays, cast(coalesce(sum(t.duration), 0) as signed) as `time`
+            from (
+                select start_time + ? as local_time,
+                case when end_time > start_time then end_time - start_time else 0 end as duration
+                from hiqradio_recently
+                where user_id = ? and start_time >= ? and start_time < ?
+            ) t
+            group by hour order by hour"#,
+        )
+        .bind(offset)
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(hours)
+    }
+
+    async fn query_recently_longest(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+        limit: i64,
+    ) -> Result<Vec<Recently>> {
+        let recently = sqlx::query_as::<_, Recently>(
+            r#"select id, user_id, stationuuid, start_time, end_time
+            from hiqradio_recently
+            where user_id = ? and start_time >= ? and start_time < ? and end_time > start_time
+            order by end_time - start_time desc, start_time desc, id desc limit ?"#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(recently)
+    }
+
+    async fn delete_recently(&self, user_id: i64) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self.clear_recently(&mut txn, &change, None).await {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn new_recently(&self, user_id: i64, recently: &[RecentlyNew]) -> Result {
+        for chunk in recently.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.new_recently_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn modify_recently(
+        &self,
+        user_id: i64,
+        stationuuid: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self
+            .modify_recently_txn(&mut txn, change, stationuuid, start_time, end_time)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn clean_recently(&self, now: i64, max_days: i64, max_rows: i64) -> Result<usize> {
+        let users = sqlx::query_as::<_, RecentlyRetention>(RETENTION_SELECT)
+            .bind(max_rows)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let mut count = 0;
+        for user in users {
+            let Some(cutoff) = user.cutoff(now, max_days) else {
+                continue;
+            };
+            let (mut txn, change) = self.begin_revision(user.user_id).await?;
+            match self.clear_recently(&mut txn, &change, Some(cutoff)).await {
+                Ok(n) => count += n,
+                Err(e) => {
+                    self.rollback(txn).await?;
+                    return Err(e);
+                }
+            }
+            self.commit(txn).await?;
+        }
+
+        Ok(count as usize)
+    }
+
+    async fn query_setting(&self, user_id: i64) -> Result<Setting> {
+        let setting = sqlx::query_as::<_, Setting>(
+            r#"select user_id, recently_enabled, recently_days, update_time
+            from hiqradio_setting where user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(setting.unwrap_or_else(|| Setting::new(user_id)))
+    }
+
+    async fn modify_setting(&self, setting: &Setting) -> Result<bool> {
+        let was_enabled = self.query_setting(setting.user_id).await?.recently_enabled != 0;
+        let clear = was_enabled && setting.recently_enabled == 0;
+        let (mut txn, change) = if clear {
+            let (txn, change) = self.begin_revision(setting.user_id).await?;
+            (txn, Some(change))
+        } else {
+            (self.begin().await?, None)
+        };
+        if let Err(e) = self
+            .modify_setting_txn(&mut txn, setting, change.as_ref())
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(clear)
+    }
+
+    async fn query_groups(&self, user_id: i64) -> Result<Vec<FavGroup>> {
+        let groups = sqlx::query_as::<_, FavGroup>(
+            r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def, `position`
+            from hiqradio_fav_group
+            where user_id = ? order by `position`, id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(groups)
+    }
+
+    async fn delete_groups(&self, user_id: i64, groups: &[String]) -> Result {
+        for chunk in groups.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.delete_groups_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn new_groups(&self, user_id: i64, groups: &[GroupNew]) -> Result {
+        for chunk in groups.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.new_groups_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+    async fn modify_group(&self, user_id: i64, old_name: &str, name: &str, desc: &str) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self
+            .modify_group_txn(&mut txn, change, old_name, name, desc)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_favorites(&self, user_id: i64) -> Result<Vec<StationGroup>> {
+        let groups = sqlx::query_as::<_, StationGroup>(
+            r#"select a.name as group_name,  b.stationuuid, b.create_time, b.`position`
+            from hiqradio_fav_group a, hiqradio_favorite b
+            where a.id = b.group_id and a.user_id = b.user_id and a.user_id = ?
+            order by a.`position`, a.id, b.`position`, b.id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(groups)
+    }
+    async fn new_favorite(&self, user_id: i64, stations: &[StationGroup]) -> Result {
+        for chunk in stations.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.new_favorite_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_favorite(
+        &self,
+        user_id: i64,
+        favorites: &Option<Vec<String>>,
+        group_names: &Option<Vec<String>>,
+    ) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+
+        if let Some(favorites) = favorites.as_ref().filter(|e| !e.is_empty()) {
+            let deleted = match self
+                .query_deleted_favorites(&mut txn, user_id, "b.stationuuid", favorites)
+                .await
+            {
+                Ok(deleted) => deleted,
+                Err(e) => {
+                    self.rollback(txn).await?;
+                    return Err(e);
+                }
+            };
+            for e in deleted.iter() {
+                if let Err(e) = self
+                    .new_tombstone(
+                        &mut txn,
+                        &Tombstone::favorite_delete(
+                            user_id,
+                            change.revision,
+                            change.time,
+                            &e.group_name,
+                            &e.stationuuid,
+                        ),
+                    )
+                    .await
+                {
+                    self.rollback(txn).await?;
+                    return Err(e);
+                }
+            }
+
+            let query_str = format!(
+                r#"delete from hiqradio_favorite  
+                where user_id = ? and stationuuid in ({})"#,
+                self.build_in_param(favorites)
+            );
+
+            let mut query = sqlx::query(&query_str);
+            query = query.bind(user_id);
+
+            for param in favorites {
+                query = query.bind(param);
+            }
+            if let Err(e) = query.execute(&mut *txn).await {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+        if let Some(group_names) = group_names.as_ref().filter(|e| !e.is_empty()) {
+            let deleted = match self
+                .query_deleted_favorites(&mut txn, user_id, "a.name", group_names)
+                .await
+            {
+                Ok(deleted) => deleted,
+                Err(e) => {
+                    self.rollback(txn).await?;
+                    return Err(e);
+                }
+            };
+            for e in deleted.iter() {
+                if let Err(e) = self
+                    .new_tombstone(
+                        &mut txn,
+                        &Tombstone::favorite_delete(
+                            user_id,
+                            change.revision,
+                            change.time,
+                            &e.group_name,
+                            &e.stationuuid,
+                        ),
+                    )
+                    .await
+                {
+                    self.rollback(txn).await?;
+                    return Err(e);
+                }
+            }
+
+            let query_str = format!(
+                r#"delete from hiqradio_favorite  
+                where user_id = ? and group_id in (
+                    select id from hiqradio_fav_group where name in ({})
+                )"#,
+                self.build_in_param(group_names)
+            );
+
+            let mut query = sqlx::query(&query_str);
+            query = query.bind(user_id);
+
+            for param in group_names {
+                query = query.bind(param);
+            }
+
+            if let Err(e) = query.execute(&mut *txn).await {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn modify_favorite(
+        &self,
+        user_id: i64,
+        stationuuid: &str,
+        groups: &[String],
+    ) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self
+            .modify_favorite_txn(&mut txn, change, stationuuid, groups)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn reorder_groups(&self, user_id: i64, names: &[String]) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        for (position, name) in names.iter().enumerate() {
+            if let Err(e) = sqlx::query(
+                r#"update hiqradio_fav_group set `position` = ?, revision = ?
+                where user_id = ? and name = ? and `position` != ?"#,
+            )
+            .bind(position as i64)
+            .bind(change.revision)
+            .bind(user_id)
+            .bind(name)
+            .bind(position as i64)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn reorder_favorites(&self, user_id: i64, group_name: &str, stationuuids: &[String]) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        let group_id = match sqlx::query_scalar::<_, i64>(
+            r#"select id from hiqradio_fav_group where user_id = ? and name = ?"#,
+        )
+        .bind(user_id)
+        .bind(group_name)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(group_id) => group_id,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        for (position, stationuuid) in stationuuids.iter().enumerate() {
+            if let Err(e) = sqlx::query(
+                r#"update hiqradio_favorite set `position` = ?, revision = ?
+                where group_id = ? and stationuuid = ? and `position` != ?"#,
+            )
+            .bind(position as i64)
+            .bind(change.revision)
+            .bind(group_id)
+            .bind(stationuuid)
+            .bind(position as i64)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_revision(&self, user_id: i64) -> Result<i64> {
+        let revision = sqlx::query_scalar::<_, i64>(
+            r#"select revision from hiqradio_revision where user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(revision.unwrap_or_default())
+    }
+
+    async fn query_sync(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<(Vec<FavGroup>, Vec<Recently>, Vec<StationGroup>)> {
+        let fav_groups = sqlx::query_as::<_, FavGroup>(
+            r#"select id, user_id, create_time, name, ifnull(`desc`, '') as `desc`, is_def, `position`
+            from hiqradio_fav_group
+            where user_id = ? and revision > ? and revision <= ? order by `position`, id"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let recently = sqlx::query_as::<_, Recently>(
+            r#"select id, user_id, stationuuid, start_time, end_time 
+            from hiqradio_recently
+            where user_id = ? and revision > ? and revision <= ?  order by start_time desc"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let stations = sqlx::query_as::<_, StationGroup>(
+            r#"select a.name as group_name,  b.stationuuid, b.create_time, b.`position`
+            from hiqradio_fav_group a, hiqradio_favorite b
+            where a.id = b.group_id and a.user_id = b.user_id and a.user_id = ? and b.revision > ? and b.revision <= ?
+            order by a.`position`, a.id, b.`position`, b.id"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok((fav_groups, recently, stations))
+    }
+
+    async fn query_tombstones(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<Vec<Tombstone>> {
+        let tombstones = sqlx::query_as::<_, Tombstone>(
+            r#"select id, user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision 
+            from hiqradio_tombstone
+            where user_id = ? and revision > ? and revision <= ? order by id"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(tombstones)
+    }
+
+    async fn apply_sync(&self, user_id: i64, revision: i64, ops: &[SyncOp]) -> Result<Option<i64>> {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if change.revision != revision + 1 {
+            self.rollback(txn).await?;
+            return Ok(None);
+        }
+
+        for op in ops.iter() {
+            if let Err(e) = self.apply_op_txn(&mut txn, change, op).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(Some(change.revision))
+    }
+
+    async fn share_group(&self, user_id: i64, group_name: &str, token: &str) -> Result<Share> {
+        let mut txn = self.begin().await?;
+        let group_id = match sqlx::query_scalar::<_, i64>(
+            r#"select id from hiqradio_fav_group where user_id = ? and name = ?"#,
+        )
+        .bind(user_id)
+        .bind(group_name)
+        .fetch_optional(&mut *txn)
+        .await
+        {
+            Ok(Some(group_id)) => group_id,
+            Ok(None) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(format!("group \"{}\" not found", group_name)));
+            }
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        if let Err(e) = sqlx::query(
+            r#"insert ignore into hiqradio_share(token, user_id, group_id, create_time) values (?, ?, ?, ?)"#,
+        )
+        .bind(token)
+        .bind(user_id)
+        .bind(group_id)
+        .bind(Local::now().timestamp())
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let share = match sqlx::query_as::<_, Share>(
+            r#"select a.token, a.user_id, b.name as group_name, a.create_time
+            from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and a.group_id = ?"#,
+        )
+        .bind(group_id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(share) => share,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(share)
+    }
+
+    async fn query_shares(&self, user_id: i64) -> Result<Vec<Share>> {
+        let shares = sqlx::query_as::<_, Share>(
+            r#"select a.token, a.user_id, b.name as group_name, a.create_time
+            from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and a.user_id = ?
+            order by b.position, b.id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(shares)
+    }
+
+    async fn query_share(&self, token: &str) -> Result<Option<Share>> {
+        let share = sqlx::query_as::<_, Share>(
+            r#"select a.token, a.user_id, b.name as group_name, a.create_time
+            from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and a.token = ?
+            and exists (select 1 from user c, user_product d, product e
+                where c.id = a.user_id and c.status = '00' and d.user_id = c.id
+                and d.product_id = e.id and d.status = '00' and e.product = ?)"#,
+        )
+        .bind(token)
+        .bind(PRODUCT_HIQRADIO)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(share)
+    }
+
+    async fn delete_share(&self, user_id: i64, group_name: &str) -> Result<bool> {
+        let res = sqlx::query(
+            r#"delete a from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and b.user_id = ? and b.name = ?"#,
+        )
+        .bind(user_id)
+        .bind(group_name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn query_user_stations(
+        &self,
+        since: i64,
+        after_user_id: i64,
+        after_stationuuid: &str,
+        limit: i64,
+    ) -> Result<Vec<UserStation>> {
+        let stations = sqlx::query_as::<_, UserStation>(
+            r#"select user_id, stationuuid, max(favorite) as favorite from (
+                select user_id, stationuuid, cast(1 as signed) as favorite from hiqradio_favorite
+                where user_id > ? or (user_id = ? and stationuuid > ?)
+                union all
+                select user_id, stationuuid, cast(0 as signed) as favorite from hiqradio_recently
+                where start_time >= ? and (user_id > ? or (user_id = ? and stationuuid > ?))
+            ) s
+            group by user_id, stationuuid
+            order by user_id, stationuuid
+            limit ?"#,
+        )
+        .bind(after_user_id)
+        .bind(after_user_id)
+        .bind(after_stationuuid)
+        .bind(since)
+        .bind(after_user_id)
+        .bind(after_user_id)
+        .bind(after_stationuuid)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn save_similar(&self, similar: &[Similar]) -> Result {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(r#"delete from hiqradio_similar"#)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        for chunk in similar.chunks(200) {
+            let sql = format!(
+                r#"insert into hiqradio_similar(stationuuid, similaruuid, score, update_time)
+                values {}"#,
+                vec!["(?, ?, ?, ?)"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query(&sql);
+            for s in chunk {
+                query = query
+                    .bind(&s.stationuuid)
+                    .bind(&s.similaruuid)
+                    .bind(s.score)
+                    .bind(s.update_time);
+            }
+            if let Err(e) = query.execute(&mut *txn).await {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_similar(&self, stationuuids: &[String]) -> Result<Vec<Similar>> {
+        let mut similar = Vec::new();
+        for chunk in stationuuids.chunks(100) {
+            let sql = format!(
+                r#"select stationuuid, similaruuid, score, update_time
+                from hiqradio_similar where stationuuid in ({})"#,
+                vec!["?"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query_as::<_, Similar>(&sql);
+            for stationuuid in chunk {
+                query = query.bind(stationuuid);
+            }
+            similar.extend(
+                query
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?,
+            );
+        }
+        similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Ok(similar)
+    }
+
+    async fn query_station_listens(&self, since: i64, until: i64) -> Result<Vec<StationListen>> {
+        let listens = sqlx::query_as::<_, StationListen>(
+            r#"select stationuuid, user_id,
+            cast(sum(case when end_time > start_time then end_time - start_time else 0 end) as signed) as listen_time
+            from hiqradio_recently where start_time >= ? and start_time <= ?
+            group by stationuuid, user_id"#,
+        )
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(listens)
+    }
+
+    async fn query_new_favorites(&self, since: i64, until: i64) -> Result<Vec<UserStation>> {
+        let favorites = sqlx::query_as::<_, UserStation>(
+            r#"select distinct user_id, stationuuid, cast(1 as signed) as favorite
+            from hiqradio_favorite where create_time >= ? and create_time <= ?"#,
+        )
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(favorites)
+    }
+
+    async fn save_trending(&self, period: &str, stations: &[TrendingStation]) -> Result {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(r#"delete from hiqradio_trending where period = ?"#)
+            .bind(period)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        for s in stations {
+            if let Err(e) = sqlx::query(
+                r#"insert into hiqradio_trending(
+                period, stationuuid, listeners, listen_time, favorites, score, update_time)
+                values (?, ?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(period)
+            .bind(&s.stationuuid)
+            .bind(s.listeners)
+            .bind(s.listen_time)
+            .bind(s.favorites)
+            .bind(s.score)
+            .bind(s.update_time)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_trending(&self, period: &str, limit: i64) -> Result<Vec<TrendingStation>> {
+        let stations = sqlx::query_as::<_, TrendingStation>(
+            r#"select period, stationuuid, listeners, listen_time, favorites, score, update_time
+            from hiqradio_trending where period = ?
+            order by score desc, stationuuid limit ?"#,
+        )
+        .bind(period)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn save_review(&self, review: &Review) -> Result<Review> {
+        let mut txn = self.begin().await?;
+        let id = match sqlx::query(
+            r#"insert into hiqradio_review(
+            user_id, stationuuid, rating, content, `status`, reports, create_time, update_time)
+            values (?, ?, ?, ?, ?, 0, ?, ?)
+            on duplicate key update
+            id = last_insert_id(id),
+            rating = values(rating),
+            content = values(content),
+            `status` = values(`status`),
+            reports = 0,
+            update_time = values(update_time)"#,
+        )
+        .bind(review.user_id)
+        .bind(&review.stationuuid)
+        .bind(review.rating)
+        .bind(&review.content)
+        .bind(&review.status)
+        .bind(review.create_time)
+        .bind(review.update_time)
+        .execute(&mut *txn)
+        .await
+        .map(|res| res.last_insert_id() as i64)
+        {
+            Ok(id) => id,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        // 修改后的评论重新接受举报
+        if let Err(e) = sqlx::query(r#"delete from hiqradio_review_report where review_id = ?"#)
+            .bind(id)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let review = match sqlx::query_as::<_, Review>(&format!("{} where a.id = ?", REVIEW_SELECT))
+            .bind(id)
+            .fetch_one(&mut *txn)
+            .await
+        {
+            Ok(review) => review,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(review)
+    }
+
+    async fn delete_review(&self, user_id: i64, stationuuid: &str) -> Result<bool> {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(
+            r#"delete from hiqradio_review_report where review_id in (
+            select id from hiqradio_review where user_id = ? and stationuuid = ?)"#,
+        )
+        .bind(user_id)
+        .bind(stationuuid)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let deleted = match sqlx::query(
+            r#"delete from hiqradio_review where user_id = ? and stationuuid = ?"#,
+        )
+        .bind(user_id)
+        .bind(stationuuid)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(res) => res.rows_affected() > 0,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(deleted)
+    }
+
+    async fn query_review(&self, id: i64) -> Result<Option<Review>> {
+        let review = sqlx::query_as::<_, Review>(&format!("{} where a.id = ?", REVIEW_SELECT))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(review)
+    }
+
+    async fn query_user_reviews(&self, user_id: i64) -> Result<Vec<Review>> {
+        let reviews = sqlx::query_as::<_, Review>(&format!(
+            "{} where a.user_id = ? order by a.update_time desc, a.id desc",
+            REVIEW_SELECT
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(reviews)
+    }
+
+    async fn query_station_reviews(
+        &self,
+        stationuuid: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Review>> {
+        let reviews = sqlx::query_as::<_, Review>(&format!(
+            "{} where a.stationuuid = ? and a.`status` = 'approved'
+            and exists (select 1 from user c, user_product d, product e
+                where c.id = a.user_id and c.status = '00' and d.user_id = c.id
+                and d.product_id = e.id and d.status = '00' and e.product = ?)
+            order by a.update_time desc, a.id desc limit ? offset ?",
+            REVIEW_SELECT
+        ))
+        .bind(stationuuid)
+        .bind(PRODUCT_HIQRADIO)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(reviews)
+    }
+
+    async fn query_pending_reviews(&self, offset: i64, limit: i64) -> Result<Vec<Review>> {
+        let reviews = sqlx::query_as::<_, Review>(&format!(
+            "{} where a.`status` = 'pending'
+            order by a.reports desc, a.update_time, a.id limit ? offset ?",
+            REVIEW_SELECT
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(reviews)
+    }
+
+    async fn moderate_review(&self, id: i64, status: &str) -> Result<bool> {
+        let res = sqlx::query(r#"update hiqradio_review set `status` = ?, reports = 0 where id = ?"#)
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn report_review(
+        &self,
+        id: i64,
+        user_id: i64,
+        reason: &str,
+        time: i64,
+        pending_reports: i64,
+    ) -> Result<i64> {
+        let mut txn = self.begin().await?;
+        let reported = match sqlx::query(
+            r#"insert ignore into hiqradio_review_report(review_id, user_id, reason, create_time)
+            values (?, ?, ?, ?)"#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(reason)
+        .bind(time)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(res) => res.rows_affected() > 0,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        if reported {
+            if let Err(e) = sqlx::query(
+                r#"update hiqradio_review set
+                `status` = case when `status` = 'approved' and reports + 1 >= ? then 'pending' else `status` end,
+                reports = reports + 1
+                where id = ?"#,
+            )
+            .bind(pending_reports)
+            .bind(id)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        let reports = match sqlx::query_scalar::<_, i64>(
+            r#"select reports from hiqradio_review where id = ?"#,
+        )
+        .bind(id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(reports) => reports,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(reports)
+    }
+
+    async fn query_ratings(&self, stationuuids: &[String]) -> Result<Vec<Rating>> {
+        let mut ratings = Vec::new();
+        for chunk in stationuuids.chunks(100) {
+            let sql = format!(
+                r#"select a.stationuuid, cast(avg(a.rating) as double) as rating, count(*) as ratings
+                from hiqradio_review a where a.`status` = 'approved' and a.stationuuid in ({})
+                and exists (select 1 from user c, user_product d, product e
+                    where c.id = a.user_id and c.status = '00' and d.user_id = c.id
+                    and d.product_id = e.id and d.status = '00' and e.product = ?)
+                group by a.stationuuid"#,
+                vec!["?"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query_as::<_, Rating>(&sql);
+            for stationuuid in chunk {
+                query = query.bind(stationuuid);
+            }
+            ratings.extend(
+                query
+                    .bind(PRODUCT_HIQRADIO)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?,
+            );
+        }
+
+        Ok(ratings)
+    }
+
+    async fn save_presence(&self, presence: &Presence) -> Result<Presence> {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(
+            r#"insert into hiqradio_presence(
+            user_id, device_id, device_name, token, stationuuid, start_time, update_time, expire)
+            values (?, ?, ?, ?, ?, ?, ?, ?)
+            on duplicate key update
+            start_time = if(stationuuid = values(stationuuid), start_time, values(start_time)),
+            device_name = values(device_name),
+            token = values(token),
+            stationuuid = values(stationuuid),
+            update_time = values(update_time),
+            expire = values(expire)"#,
+        )
+        .bind(presence.user_id)
+        .bind(&presence.device_id)
+        .bind(&presence.device_name)
+        .bind(&presence.token)
+        .bind(&presence.stationuuid)
+        .bind(presence.start_time)
+        .bind(presence.update_time)
+        .bind(presence.expire)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let presence = match sqlx::query_as::<_, Presence>(
+            r#"select user_id, device_id, device_name, token, stationuuid, start_time, update_time, expire
+            from hiqradio_presence where user_id = ? and device_id = ?"#,
+        )
+        .bind(presence.user_id)
+        .bind(&presence.device_id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(presence) => presence,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(presence)
+    }
+
+    async fn delete_presence(&self, user_id: i64, device_id: &str) -> Result<bool> {
+        let res = sqlx::query(r#"delete from hiqradio_presence where user_id = ? and device_id = ?"#)
+            .bind(user_id)
+            .bind(device_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn query_presences(&self, user_id: i64, now: i64) -> Result<Vec<Presence>> {
+        let presences = sqlx::query_as::<_, Presence>(
+            r#"select user_id, device_id, device_name, token, stationuuid, start_time, update_time, expire
+            from hiqradio_presence where user_id = ? and expire > ?
+            order by update_time desc, device_id"#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(presences)
+    }
+
+    async fn clean_presence(&self, now: i64) -> Result<usize> {
+        let res = sqlx::query(r#"delete from hiqradio_presence where expire <= ?"#)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() as usize)
+    }
+
+    async fn save_stations(&self, stations: &[Station]) -> Result {
+        let mut txn = self.begin().await?;
+        for s in stations {
+            if let Err(e) = sqlx::query(
+                r#"insert into hiqradio_station(
+                stationuuid, name, url, url_resolved, homepage, favicon, tags,
+                country, countrycode, `state`, `language`, codec, bitrate, hls,
+                lastcheckok, votes, clickcount, update_time)
+                values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                on duplicate key update
+                name = values(name),
+                url = values(url),
+                url_resolved = values(url_resolved),
+                homepage = values(homepage),
+                favicon = values(favicon),
+                tags = values(tags),
+                country = values(country),
+                countrycode = values(countrycode),
+                `state` = values(`state`),
+                `language` = values(`language`),
+                codec = values(codec),
+                bitrate = values(bitrate),
+                hls = values(hls),
+                lastcheckok = values(lastcheckok),
+                votes = values(votes),
+                clickcount = values(clickcount),
+                update_time = values(update_time),
+                deleted = 0"#,
+            )
+            .bind(&s.stationuuid)
+            .bind(&s.name)
+            .bind(&s.url)
+            .bind(&s.url_resolved)
+            .bind(&s.homepage)
+            .bind(&s.favicon)
+            .bind(&s.tags)
+            .bind(&s.country)
+            .bind(&s.countrycode)
+            .bind(&s.state)
+            .bind(&s.language)
+            .bind(&s.codec)
+            .bind(s.bitrate)
+            .bind(s.hls)
+            .bind(s.lastcheckok)
+            .bind(s.votes)
+            .bind(s.clickcount)
+            .bind(s.update_time)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn delete_stations_before(&self, before: i64) -> Result<usize> {
+        let count = sqlx::query(
+            r#"update hiqradio_station set deleted = 1 where deleted = 0 and update_time < ?"#,
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        Ok(count as usize)
+    }
+
+    async fn query_stations(&self, stationuuids: &[String]) -> Result<Vec<Station>> {
+        let mut stations = Vec::new();
+        for chunk in stationuuids.chunks(100) {
+            let sql = format!(
+                r#"select stationuuid, name, url, url_resolved, homepage, favicon,
+                tags, country, countrycode, `state`, `language`, codec,
+                bitrate, hls, lastcheckok, votes, clickcount, deleted,
+                update_time
+                from hiqradio_station where stationuuid in ({})"#,
+                vec!["?"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query_as::<_, Station>(&sql);
+            for stationuuid in chunk {
+                query = query.bind(stationuuid);
+            }
+            stations.extend(
+                query
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?,
+            );
+        }
+
+        Ok(stations)
+    }
+
+    async fn query_stations_by_url(&self, urls: &[String]) -> Result<Vec<Station>> {
+        let mut stations = Vec::new();
+        for chunk in urls.chunks(100) {
+            let sql = format!(
+                r#"select stationuuid, name, url, url_resolved, homepage, favicon,
+                tags, country, countrycode, `state`, `language`, codec,
+                bitrate, hls, lastcheckok, votes, clickcount, deleted,
+                update_time
+                from hiqradio_station where url in ({params}) or url_resolved in ({params})"#,
+                params = vec!["?"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query_as::<_, Station>(&sql);
+            // url和url_resolved各绑定一次
+            for url in chunk.iter().chain(chunk) {
+                query = query.bind(url);
+            }
+            stations.extend(
+                query
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?,
+            );
+        }
+
+        Ok(stations)
+    }
+
+    async fn query_stations_by_name(&self, names: &[String]) -> Result<Vec<Station>> {
+        let mut stations = Vec::new();
+        for chunk in names.chunks(100) {
+            let sql = format!(
+                r#"select stationuuid, name, url, url_resolved, homepage, favicon,
+                tags, country, countrycode, `state`, `language`, codec,
+                bitrate, hls, lastcheckok, votes, clickcount, deleted,
+                update_time
+                from hiqradio_station where name in ({params})"#,
+                params = vec!["?"; chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query_as::<_, Station>(&sql);
+            for name in chunk {
+                query = query.bind(name);
+            }
+            stations.extend(
+                query
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?,
+            );
+        }
+
+        Ok(stations)
+    }
+
+    async fn search_stations(&self, filter: &StationFilter) -> Result<Vec<Station>> {
+        const MATCH: &str =
+            "match(s.name, s.tags, s.country, s.`language`, s.codec) against (? in boolean mode)";
+        let mut rank = "1.0";
+        let mut conds = vec!["s.deleted = 0", "s.bitrate >= ?"];
+        if !filter.terms.is_empty() {
+            rank = MATCH;
+            conds.push(MATCH);
+        }
+        if filter.only_ok {
+            conds.push("s.lastcheckok = 1");
+        }
+        if filter.tag.is_some() {
+            conds.push("locate(?, concat(',', lower(s.tags), ',')) > 0");
+        }
+        if filter.language.is_some() {
+            conds.push("locate(?, concat(',', lower(s.`language`), ',')) > 0");
+        }
+        if filter.countrycode.is_some() {
+            conds.push("upper(s.countrycode) = ?");
+        }
+        if filter.codec.is_some() {
+            conds.push("lower(s.codec) = ?");
+        }
+        let boost = if filter.boost.is_empty() {
+            String::from("1.0")
+        } else {
+            format!(
+                "(case when s.stationuuid in ({}) then {:.1} else 1.0 end)",
+                vec!["?"; filter.boost.len()].join(", "),
+                SEARCH_BOOST
+            )
+        };
+        let sql = format!(
+            r#"select s.stationuuid, s.name, s.url, s.url_resolved, s.homepage, s.favicon,
+            s.tags, s.country, s.countrycode, s.`state`, s.`language`, s.codec,
+            s.bitrate, s.hls, s.lastcheckok, s.votes, s.clickcount, s.deleted,
+            s.update_time, {} * {} as score
+            from hiqradio_station s where {}
+            order by score desc, s.votes desc, s.stationuuid limit ? offset ?"#,
+            rank,
+            boost,
+            conds.join(" and ")
+        );
+
+        // 关键词只含字母和数字，都要匹配且按前缀匹配
+        let terms = filter
+            .terms
+            .iter()
+            .map(|t| format!("+{}*", t))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut query = sqlx::query_as::<_, Station>(&sql);
+        if !filter.terms.is_empty() {
+            query = query.bind(&terms);
+        }
+        for stationuuid in filter.boost.iter() {
+            query = query.bind(stationuuid);
+        }
+        query = query.bind(filter.bitrate_min);
+        if !filter.terms.is_empty() {
+            query = query.bind(&terms);
+        }
+        for item in [&filter.tag, &filter.language].into_iter().flatten() {
+            query = query.bind(format!(",{},", item));
+        }
+        for item in [&filter.countrycode, &filter.codec].into_iter().flatten() {
+            query = query.bind(item);
+        }
+        let stations = query
+            .bind(filter.limit)
+            .bind(filter.offset)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+}
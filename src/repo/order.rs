@@ -0,0 +1,141 @@
+//! 分组和分组中收藏的排序
+//!
+//! 客户端提交完整的顺序或移动一项，这里换算成完整的顺序后写入；
+//! 顺序中没有的项(如其他设备刚新增的)保持原来的相对顺序排在后面，已不存在的项忽略。
+
+use std::collections::HashSet;
+
+use crate::{errors::Error, proto::ReorderReq, Result};
+
+use super::DynAppServRepo;
+
+/// 按order调整current的顺序
+pub fn full_order(current: &[String], order: &[String]) -> Vec<String> {
+    let known: HashSet<_> = current.iter().collect();
+    let mut seen = HashSet::new();
+    order
+        .iter()
+        .filter(|s| known.contains(s))
+        .chain(current.iter())
+        .filter(|s| seen.insert(*s))
+        .cloned()
+        .collect()
+}
+
+/// 把item移动到to位置，to超出范围时移到最后
+pub fn move_to(current: &[String], item: &str, to: i64) -> Result<Vec<String>> {
+    let Some(from) = current.iter().position(|s| s == item) else {
+        return Err(Error::Custom(format!("{} not exists", item)));
+    };
+    let mut order = current.to_vec();
+    let item = order.remove(from);
+    order.insert((to.max(0) as usize).min(order.len()), item);
+    Ok(order)
+}
+
+fn new_order(current: &[String], req: &ReorderReq) -> Result<Vec<String>> {
+    match (&req.order, &req.moving) {
+        (Some(order), None) => Ok(full_order(current, order)),
+        (None, Some(item)) => move_to(current, item, req.to.unwrap_or_default()),
+        _ => Err(Error::Parse(String::from(
+            "either order or move is required",
+        ))),
+    }
+}
+
+/// 调整分组或分组中收藏的顺序，返回调整后的顺序
+pub async fn reorder(repo: &DynAppServRepo, user_id: i64, req: &ReorderReq) -> Result<Vec<String>> {
+    let groups = repo.query_groups(user_id).await?;
+    let Some(group) = &req.group else {
+        let current: Vec<_> = groups.into_iter().map(|g| g.name).collect();
+        let order = new_order(&current, req)?;
+        repo.reorder_groups(user_id, &order).await?;
+        return Ok(order);
+    };
+
+    if !groups.iter().any(|g| &g.name == group) {
+        return Err(Error::Custom(format!("group {} not exists", group)));
+    }
+    let current: Vec<_> = repo
+        .query_favorites(user_id)
+        .await?
+        .into_iter()
+        .filter(|f| &f.group_name == group)
+        .map(|f| f.stationuuid)
+        .collect();
+    let order = new_order(&current, req)?;
+    repo.reorder_favorites(user_id, group, &order).await?;
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn req(order: Option<&[&str]>, moving: Option<&str>, to: Option<i64>) -> ReorderReq {
+        ReorderReq {
+            group: None,
+            order: order.map(list),
+            moving: moving.map(|s| s.to_string()),
+            to,
+        }
+    }
+
+    #[test]
+    fn full_order_keeps_missing_and_ignores_unknown() {
+        let current = list(&["a", "b", "c", "d"]);
+        assert_eq!(
+            full_order(&current, &list(&["c", "a", "b", "d"])),
+            list(&["c", "a", "b", "d"])
+        );
+        // 顺序中没有的保持原来的相对顺序排在后面
+        assert_eq!(
+            full_order(&current, &list(&["d", "b"])),
+            list(&["d", "b", "a", "c"])
+        );
+        // 已不存在的和重复的忽略
+        assert_eq!(
+            full_order(&current, &list(&["x", "c", "c", "a"])),
+            list(&["c", "a", "b", "d"])
+        );
+        assert_eq!(full_order(&current, &[]), current);
+        assert!(full_order(&[], &list(&["a"])).is_empty());
+    }
+
+    #[test]
+    fn move_item() {
+        let current = list(&["a", "b", "c"]);
+        assert_eq!(move_to(&current, "c", 0).unwrap(), list(&["c", "a", "b"]));
+        assert_eq!(move_to(&current, "a", 1).unwrap(), list(&["b", "a", "c"]));
+        assert_eq!(move_to(&current, "b", 1).unwrap(), current);
+        // 超出范围时移到最前或最后
+        assert_eq!(move_to(&current, "a", 100).unwrap(), list(&["b", "c", "a"]));
+        assert_eq!(move_to(&current, "c", -1).unwrap(), list(&["c", "a", "b"]));
+        assert!(matches!(move_to(&current, "x", 0), Err(Error::Custom(_))));
+    }
+
+    #[test]
+    fn order_or_move() {
+        let current = list(&["a", "b", "c"]);
+        assert_eq!(
+            new_order(&current, &req(Some(&["b"]), None, None)).unwrap(),
+            list(&["b", "a", "c"])
+        );
+        assert_eq!(
+            new_order(&current, &req(None, Some("a"), Some(2))).unwrap(),
+            list(&["b", "c", "a"])
+        );
+        // 没有to时移到最前
+        assert_eq!(
+            new_order(&current, &req(None, Some("c"), None)).unwrap(),
+            list(&["c", "a", "b"])
+        );
+        for req in [req(None, None, None), req(Some(&["a"]), Some("a"), Some(0))] {
+            assert!(matches!(new_order(&current, &req), Err(Error::Parse(_))));
+        }
+    }
+}
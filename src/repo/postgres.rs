@@ -0,0 +1,2696 @@
+use std::fs;
+
+use async_trait::async_trait;
+use chrono::Local;
+use sqlx::Executor;
+use sqlx::{postgres::PgPoolOptions, Connection, Pool, Postgres, Transaction};
+
+use crate::{
+    config::CONFIG,
+    errors::Error,
+    model::{
+        hiqradio::{
+            FavGroup, Presence, Rating, Recently, Review, Setting, Share, Similar, Station,
+            StationGroup, StationListen, Tombstone, TrendingStation, UserStation,
+            TOMBSTONE_FAVORITE_DELETE, TOMBSTONE_GROUP_DELETE, TOMBSTONE_RECENTLY_CLEAR,
+        },
+        product::{Product, PRODUCT_HIQRADIO},
+        session::Session,
+        user::{User, USER_STATUS_NORMAL},
+        user_product::UserProduct,
+    },
+    proto::{
+        GroupNew, RecentlyFilter, RecentlyNew, ResetPasswdReq, SignInReq, SignUpReq, StationFilter,
+        StationStats, SyncOp, SEARCH_BOOST,
+    },
+    util::gen_passwd,
+    Result,
+};
+
+use super::{
+    migrate::{self, POSTGRES_MIGRATIONS, SCHEMA_VERSION_TABLE},
+    retention::RecentlyRetention,
+    stats::HourPlays,
+    AppServRepo, Change,
+};
+
+/// 每个有最近播放的用户的时间范围、按条数保留的最早一条和设置
+const RETENTION_SELECT: &str = r#"select t.user_id, min(t.start_time) as oldest, max(t.start_time) as newest,
+            max(case when t.rn = $1 then t.start_time end) as kept_oldest,
+            s.recently_enabled, s.recently_days
+            from (
+                select user_id, start_time, row_number() over (
+                    partition by user_id order by start_time desc, id desc
+                ) as rn
+                from hiqradio_recently
+            ) t
+            left join hiqradio_setting s on s.user_id = t.user_id
+            group by t.user_id, s.recently_enabled, s.recently_days"#;
+
+/// 评论和评论者的用户名，用户名是注册时默认的邮箱前缀时为空
+const REVIEW_SELECT: &str = r#"select a.id, a.user_id,
+            case when b.user_name = split_part(b.email, '@', 1) then '' else coalesce(b.user_name, '') end as user_name,
+            a.stationuuid, a.rating, a.content, a.status, a.reports, a.create_time, a.update_time
+            from hiqradio_review a left join "user" b on a.user_id = b.id"#;
+
+#[derive(Debug, Clone)]
+pub struct PgRepo {
+    pool: Pool<Postgres>,
+}
+
+impl PgRepo {
+    pub async fn new(url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(10)
+            .connect(url)
+            .await
+            .map_err(|e| {
+                Error::DatabaseException(format!("connecting to postgres error: {}", e))
+            })?;
+
+        Ok(Self { pool })
+    }
+
+    fn user_from_signup(&self, signup: &SignUpReq, passwd: String) -> User {
+        let user_name = signup
+            .email
+            .split('@')
+            .collect::<Vec<_>>()
+            .first()
+            .unwrap()
+            .to_string();
+        User {
+            id: None,
+            user_name,
+            email: signup.email.clone(),
+            passwd,
+            status: String::from(USER_STATUS_NORMAL),
+            update_time: Local::now().timestamp_millis(),
+        }
+    }
+
+    /// 开通产品，返回开通的产品id
+    async fn insert_user_product(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        user_id: i64,
+        product: &str,
+    ) -> Result<i64> {
+        // 关闭过的产品恢复原记录，不新增重复记录
+        let restored: Option<i64> = sqlx::query_scalar(
+            r#"update user_product set status = '00', update_time = extract(epoch from now())::bigint
+            where user_id = $1 and status = '99'
+                and product_id = (select id from product where status = '00' and product = $2)
+            returning product_id"#,
+        )
+        .bind(user_id)
+        .bind(product)
+        .fetch_all(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .into_iter()
+        .next();
+        if let Some(product_id) = restored {
+            return Ok(product_id);
+        }
+
+        let product_id: Option<i64> = sqlx::query_scalar(
+            r#"insert into user_product(user_id, product_id, status, update_time)
+            select $1, id, '00', extract(epoch from now())::bigint
+            from product
+            where status = '00' and product = $2
+            returning product_id"#,
+        )
+        .bind(user_id)
+        .bind(product)
+        .fetch_optional(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        product_id.ok_or(Error::ProductNotExists)
+    }
+
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>> {
+        let txn = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(txn)
+    }
+    async fn rollback(&self, txn: Transaction<'static, Postgres>) -> Result {
+        txn.rollback()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+    async fn commit(&self, txn: Transaction<'static, Postgres>) -> Result {
+        txn.commit()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 关闭产品并删除该产品的会话，purge时清空头像
+    async fn close_user_product_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        user_id: i64,
+        product_id: i64,
+        purge: bool,
+    ) -> Result {
+        sqlx::query(
+            "update user_product set status = '99', update_time = $1 where user_id = $2 and product_id = $3 and status = '00'",
+        )
+        .bind(Local::now().timestamp())
+        .bind(user_id)
+        .bind(product_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        if purge {
+            sqlx::query("update user_product set avatar = '' where user_id = $1 and product_id = $2")
+                .bind(user_id)
+                .bind(product_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+
+        sqlx::query("delete from session where user_id = $1 and product_id = $2")
+            .bind(user_id)
+            .bind(product_id)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除用户的hiqradio数据，删除记录和版本号一并删除，关闭产品或注销后不再同步
+    async fn purge_hiqradio_txn(&self, txn: &mut Transaction<'static, Postgres>, user_id: i64) -> Result {
+        for sql in [
+            "delete from hiqradio_recently where user_id = $1",
+            "delete from hiqradio_favorite where user_id = $1",
+            "delete from hiqradio_fav_group where user_id = $1",
+            "delete from hiqradio_tombstone where user_id = $1",
+            "delete from hiqradio_revision where user_id = $1",
+            "delete from hiqradio_setting where user_id = $1",
+            "delete from hiqradio_share where user_id = $1",
+            "delete from hiqradio_review_report where user_id = $1",
+            "delete from hiqradio_review_report where review_id in (select id from hiqradio_review where user_id = $1)",
+            "delete from hiqradio_review where user_id = $1",
+            "delete from hiqradio_presence where user_id = $1",
+        ] {
+            sqlx::query(sql)
+                .bind(user_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 删除注销用户的会话和hiqradio数据，清空头像，匿名化用户信息
+    async fn purge_user_txn(&self, txn: &mut Transaction<'static, Postgres>, user_id: i64) -> Result {
+        self.purge_hiqradio_txn(txn, user_id).await?;
+        for sql in [
+            "delete from session where user_id = $1",
+            "update user_product set avatar = '' where user_id = $1",
+            r#"update "user" set user_name = '', email = 'cancelled-' || id, passwd = '' where id = $1"#,
+        ] {
+            sqlx::query(sql)
+                .bind(user_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 开始事务并递增用户版本号，同一事务内的修改使用同一版本号
+    async fn begin_revision(&self, user_id: i64) -> Result<(Transaction<'static, Postgres>, Change)> {
+        let mut txn = self.begin().await?;
+
+        match sqlx::query_scalar::<_, i64>(
+            r#"insert into hiqradio_revision(user_id, revision) values ($1, 1)
+            on conflict (user_id) do update set revision = hiqradio_revision.revision + 1
+            returning revision"#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(revision) => Ok((
+                txn,
+                Change {
+                    user_id,
+                    revision,
+                    time: Local::now().timestamp(),
+                },
+            )),
+            Err(e) => {
+                self.rollback(txn).await?;
+                Err(Error::DatabaseException(e.to_string()))
+            }
+        }
+    }
+
+    async fn new_tombstone(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        tombstone: &Tombstone,
+    ) -> Result {
+        sqlx::query(
+            r#"insert into hiqradio_tombstone(user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision)
+            values ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+        )
+        .bind(tombstone.user_id)
+        .bind(&tombstone.kind)
+        .bind(&tombstone.group_name)
+        .bind(&tombstone.stationuuid)
+        .bind(&tombstone.new_name)
+        .bind(&tombstone.new_desc)
+        .bind(tombstone.create_time)
+        .bind(tombstone.revision)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 记录重新创建后，之前的删除记录作废
+    async fn delete_tombstone(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        user_id: i64,
+        kind: &str,
+        group_name: &str,
+        stationuuid: &str,
+    ) -> Result {
+        sqlx::query(
+            r#"delete from hiqradio_tombstone
+            where user_id = $1 and kind = $2 and group_name = $3 and stationuuid = $4"#,
+        )
+        .bind(user_id)
+        .bind(kind)
+        .bind(group_name)
+        .bind(stationuuid)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除用户开始时间早于before的最近播放并写入清空记录，before为None时全部删除、清空时间取change.time；
+    /// 已有更晚的清空记录时沿用其时间，返回删除的条数
+    async fn clear_recently(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: &Change,
+        before: Option<i64>,
+    ) -> Result<u64> {
+        let count = sqlx::query(
+            "delete from hiqradio_recently where user_id = $1 and start_time < $2",
+        )
+        .bind(change.user_id)
+        .bind(before.unwrap_or(i64::MAX))
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        let cleared = sqlx::query_scalar::<_, Option<i64>>(
+            "select max(create_time) from hiqradio_tombstone where user_id = $1 and kind = $2",
+        )
+        .bind(change.user_id)
+        .bind(TOMBSTONE_RECENTLY_CLEAR)
+        .fetch_one(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        let time = before.unwrap_or(change.time).max(cleared.unwrap_or(i64::MIN));
+
+        self.delete_tombstone(txn, change.user_id, TOMBSTONE_RECENTLY_CLEAR, "", "")
+            .await?;
+        self.new_tombstone(
+            txn,
+            &Tombstone::recently_clear(change.user_id, change.revision, time),
+        )
+        .await?;
+        Ok(count)
+    }
+
+    /// 保存设置，change不为None时同时清空最近播放
+    async fn modify_setting_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        setting: &Setting,
+        change: Option<&Change>,
+    ) -> Result {
+        sqlx::query(
+            r#"insert into hiqradio_setting(user_id, recently_enabled, recently_days, update_time)
+            values ($1, $2, $3, $4)
+            on conflict(user_id) do update set
+            recently_enabled = excluded.recently_enabled,
+            recently_days = excluded.recently_days,
+            update_time = excluded.update_time"#,
+        )
+        .bind(setting.user_id)
+        .bind(setting.recently_enabled)
+        .bind(setting.recently_days)
+        .bind(Local::now().timestamp())
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        if let Some(change) = change {
+            self.clear_recently(txn, change, None).await?;
+        }
+        Ok(())
+    }
+
+    async fn new_recently_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        recently: &[RecentlyNew],
+    ) -> Result {
+        for e in recently.iter() {
+            sqlx::query(
+                r#"insert into hiqradio_recently(user_id, stationuuid, start_time, end_time, revision)
+                values ($1, $2, $3, $4, $5)"#,
+            )
+            .bind(change.user_id)
+            .bind(&e.stationuuid)
+            .bind(e.start_time)
+            .bind(e.end_time)
+            .bind(change.revision)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn modify_recently_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        stationuuid: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result {
+        sqlx::query(
+            r#"update hiqradio_recently set end_time = $1, revision = $2
+            where stationuuid = $3 and start_time = $4 and user_id = $5"#,
+        )
+        .bind(end_time)
+        .bind(change.revision)
+        .bind(stationuuid)
+        .bind(start_time)
+        .bind(change.user_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_groups_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        groups: &[String],
+    ) -> Result {
+        for e in groups.iter() {
+            let group_id = sqlx::query_scalar::<_, i64>(
+                r#"delete from hiqradio_fav_group where user_id = $1 and name = $2 returning id"#,
+            )
+            .bind(change.user_id)
+            .bind(e)
+            .fetch_optional(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?
+            .ok_or_else(|| Error::DatabaseException(format!("group \"{}\" not found", e)))?;
+
+            sqlx::query(r#"delete from hiqradio_favorite where group_id = $1"#)
+                .bind(group_id)
+                .execute(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            self.new_tombstone(
+                txn,
+                &Tombstone::group_delete(change.user_id, change.revision, change.time, e),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn new_groups_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        groups: &[GroupNew],
+    ) -> Result {
+        for e in groups.iter() {
+            let def_group = if e.is_def > 0 {
+                sqlx::query_as::<_, FavGroup>(
+                    r#"select id, user_id, create_time, name, coalesce("desc", '') as "desc", is_def
+                    from hiqradio_fav_group
+                    where user_id = $1 and is_def = 1"#,
+                )
+                .bind(change.user_id)
+                .fetch_optional(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?
+            } else {
+                None
+            };
+
+            if let Some(fg) = &def_group {
+                if fg.create_time > e.create_time {
+                    continue;
+                }
+            }
+
+            let Some(group_id) = sqlx::query_scalar::<_, i64>(
+                r#"insert into hiqradio_fav_group(user_id, create_time, name, "desc", is_def, revision, position)
+                values($1, $2, $3, $4, $5, $6,
+                (select coalesce(max(position) + 1, 0) from hiqradio_fav_group where user_id = $1))
+                on conflict (user_id, name) do nothing
+                returning id"#,
+            )
+            .bind(change.user_id)
+            .bind(e.create_time)
+            .bind(&e.name)
+            .bind(&e.desc)
+            .bind(e.is_def)
+            .bind(change.revision)
+            .fetch_optional(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?
+            else {
+                continue;
+            };
+
+            self.delete_tombstone(txn, change.user_id, TOMBSTONE_GROUP_DELETE, &e.name, "")
+                .await?;
+
+            if def_group.is_some() {
+                let deleted = sqlx::query_scalar::<_, String>(
+                    r#"delete from hiqradio_fav_group where user_id = $1 and is_def = 1 and id <> $2
+                    returning name"#,
+                )
+                .bind(change.user_id)
+                .bind(group_id)
+                .fetch_all(&mut **txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+                for name in deleted.iter() {
+                    self.new_tombstone(
+                        txn,
+                        &Tombstone::group_delete(change.user_id, change.revision, change.time, name),
+                    )
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn modify_group_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        old_name: &str,
+        name: &str,
+        desc: &str,
+    ) -> Result {
+        let modified = sqlx::query(
+            r#"update hiqradio_fav_group set name = $1, "desc" = $2, revision = $3 where name = $4 and user_id = $5"#,
+        )
+        .bind(name)
+        .bind(desc)
+        .bind(change.revision)
+        .bind(old_name)
+        .bind(change.user_id)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        if modified > 0 {
+            self.new_tombstone(
+                txn,
+                &Tombstone::group_modify(
+                    change.user_id,
+                    change.revision,
+                    change.time,
+                    old_name,
+                    name,
+                    desc,
+                ),
+            )
+            .await?;
+            self.delete_tombstone(txn, change.user_id, TOMBSTONE_GROUP_DELETE, name, "")
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn new_favorite_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        stations: &[StationGroup],
+    ) -> Result {
+        for elem in stations.iter() {
+            let group_id = sqlx::query_scalar::<_, i64>(
+                r#"select id from hiqradio_fav_group where user_id = $1 and name = $2"#,
+            )
+            .bind(change.user_id)
+            .bind(&elem.group_name)
+            .fetch_optional(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?
+            .ok_or_else(|| {
+                Error::DatabaseException(format!("group \"{}\" not found", &elem.group_name))
+            })?;
+
+            sqlx::query(
+                r#"insert into hiqradio_favorite(user_id, stationuuid, group_id, create_time, revision, position)
+                values($1, $2, $3, $4, $5,
+                (select coalesce(max(position) + 1, 0) from hiqradio_favorite where group_id = $3))
+                on conflict (user_id, group_id, stationuuid) do nothing"#,
+            )
+            .bind(change.user_id)
+            .bind(&elem.stationuuid)
+            .bind(group_id)
+            .bind(elem.create_time)
+            .bind(change.revision)
+            .execute(&mut **txn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            self.delete_tombstone(
+                txn,
+                change.user_id,
+                TOMBSTONE_FAVORITE_DELETE,
+                &elem.group_name,
+                &elem.stationuuid,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// 删除分组中的一个收藏
+    async fn delete_group_favorite_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        group_name: &str,
+        stationuuid: &str,
+    ) -> Result {
+        let deleted = sqlx::query(
+            r#"delete from hiqradio_favorite
+            where user_id = $1 and stationuuid = $2 and group_id in (
+                select id from hiqradio_fav_group where user_id = $1 and name = $3
+            )"#,
+        )
+        .bind(change.user_id)
+        .bind(stationuuid)
+        .bind(group_name)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        if deleted > 0 {
+            self.new_tombstone(
+                txn,
+                &Tombstone::favorite_delete(
+                    change.user_id,
+                    change.revision,
+                    change.time,
+                    group_name,
+                    stationuuid,
+                ),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn modify_favorite_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        stationuuid: &str,
+        groups: &[String],
+    ) -> Result {
+        // 不在新分组中的收藏记为删除
+        sqlx::query(
+            r#"insert into hiqradio_tombstone(user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision)
+            select b.user_id, $4, a.name, b.stationuuid, '', '', $5, $6
+            from hiqradio_fav_group a, hiqradio_favorite b
+            where a.id = b.group_id and a.user_id = b.user_id and b.user_id = $1 and b.stationuuid = $2
+            and not (a.name = any($3))"#,
+        )
+        .bind(change.user_id)
+        .bind(stationuuid)
+        .bind(groups)
+        .bind(TOMBSTONE_FAVORITE_DELETE)
+        .bind(change.time)
+        .bind(change.revision)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let deleted = sqlx::query_as::<_, (i64, i64)>(
+            r#"delete from hiqradio_favorite
+            where user_id = $1 and stationuuid = $2
+            returning group_id, position"#,
+        )
+        .bind(change.user_id)
+        .bind(stationuuid)
+        .fetch_all(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        if deleted.is_empty() {
+            return Err(Error::DatabaseException("station not found".to_string()));
+        }
+        let (group_ids, positions): (Vec<_>, Vec<_>) = deleted.into_iter().unzip();
+
+        // 仍在原分组中的收藏保持原来的位置，新分组中排在最后
+        sqlx::query(
+            r#"insert into hiqradio_favorite(user_id, stationuuid, group_id, create_time, revision, position)
+            select $1, $2, g.id, $4, $5, coalesce(o.position,
+                (select coalesce(max(f.position) + 1, 0) from hiqradio_favorite f where f.group_id = g.id))
+            from hiqradio_fav_group g
+            left join unnest($6::bigint[], $7::bigint[]) as o(group_id, position) on o.group_id = g.id
+            where g.user_id = $1 and g.name = any($3)
+            on conflict (user_id, group_id, stationuuid) do nothing"#,
+        )
+        .bind(change.user_id)
+        .bind(stationuuid)
+        .bind(groups)
+        .bind(change.time)
+        .bind(change.revision)
+        .bind(&group_ids)
+        .bind(&positions)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        sqlx::query(
+            r#"delete from hiqradio_tombstone
+            where user_id = $1 and kind = $2 and stationuuid = $3 and group_name = any($4)"#,
+        )
+        .bind(change.user_id)
+        .bind(TOMBSTONE_FAVORITE_DELETE)
+        .bind(stationuuid)
+        .bind(groups)
+        .execute(&mut **txn)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 应用一个离线操作，time为客户端操作时间
+    async fn apply_op_txn(
+        &self,
+        txn: &mut Transaction<'static, Postgres>,
+        change: Change,
+        op: &SyncOp,
+    ) -> Result {
+        match op {
+            SyncOp::GroupNew {
+                time,
+                name,
+                desc,
+                is_def,
+            } => {
+                let group = GroupNew {
+                    create_time: *time,
+                    name: name.clone(),
+                    desc: desc.clone(),
+                    is_def: *is_def,
+                };
+                self.new_groups_txn(txn, Change { time: *time, ..change }, &[group])
+                    .await
+            }
+            SyncOp::GroupModify {
+                time,
+                old_name,
+                name,
+                desc,
+            } => {
+                self.modify_group_txn(txn, Change { time: *time, ..change }, old_name, name, desc)
+                    .await
+            }
+            SyncOp::GroupDelete { time, name } => {
+                self.delete_groups_txn(txn, Change { time: *time, ..change }, std::slice::from_ref(name))
+                    .await
+            }
+            SyncOp::FavoriteNew {
+                time,
+                group_name,
+                stationuuid,
+            } => {
+                let station = StationGroup {
+                    group_name: group_name.clone(),
+                    stationuuid: stationuuid.clone(),
+                    create_time: *time,
+                    position: 0,
+                };
+                self.new_favorite_txn(txn, change, &[station]).await
+            }
+            SyncOp::FavoriteModify {
+                time,
+                stationuuid,
+                group_names,
+            } => {
+                self.modify_favorite_txn(
+                    txn,
+                    Change { time: *time, ..change },
+                    stationuuid,
+                    group_names,
+                )
+                .await
+            }
+            SyncOp::FavoriteDelete {
+                time,
+                group_name,
+                stationuuid,
+            } => {
+                self.delete_group_favorite_txn(
+                    txn,
+                    Change { time: *time, ..change },
+                    group_name,
+                    stationuuid,
+                )
+                .await
+            }
+            SyncOp::RecentlyNew {
+                stationuuid,
+                start_time,
+                end_time,
+            } => {
+                let recently = RecentlyNew {
+                    stationuuid: stationuuid.clone(),
+                    start_time: *start_time,
+                    end_time: *end_time,
+                };
+                self.new_recently_txn(txn, change, &[recently]).await
+            }
+            SyncOp::RecentlyModify {
+                stationuuid,
+                start_time,
+                end_time,
+            } => {
+                self.modify_recently_txn(txn, change, stationuuid, *start_time, *end_time)
+                    .await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AppServRepo for PgRepo {
+    async fn schema_version(&self) -> Result<i64> {
+        self.pool
+            .execute(SCHEMA_VERSION_TABLE)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let version: Option<i64> = sqlx::query_scalar("select max(version) from schema_version")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(version.unwrap_or(0))
+    }
+    async fn migrate(&self) -> Result {
+        // 多实例同时启动时，用advisory lock保证只有一个实例执行迁移
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        sqlx::query("select pg_advisory_lock(hashtext('appserv_migrate'))")
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let res = async {
+            (&mut *conn)
+                .execute(SCHEMA_VERSION_TABLE)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+            let version: Option<i64> =
+                sqlx::query_scalar("select max(version) from schema_version")
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?;
+            let version = version.unwrap_or(0);
+            migrate::check_newer(version)?;
+
+            for m in migrate::pending(POSTGRES_MIGRATIONS, version) {
+                tracing::info!("migrate postgres to version {}: {}", m.version, m.description);
+                let mut txn = conn
+                    .begin()
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?;
+                (&mut *txn).execute(m.sql).await.map_err(|e| {
+                    Error::DatabaseException(format!("migrate version {} error: {}", m.version, e))
+                })?;
+                sqlx::query(
+                    "insert into schema_version(version, description, applied_time) values ($1, $2, $3)",
+                )
+                .bind(m.version)
+                .bind(m.description)
+                .bind(Local::now().timestamp_millis())
+                .execute(&mut *txn)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+                txn.commit()
+                    .await
+                    .map_err(|e| Error::DatabaseException(e.to_string()))?;
+            }
+            Ok(())
+        }
+        .await;
+
+        sqlx::query("select pg_advisory_unlock(hashtext('appserv_migrate'))")
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        res
+    }
+
+    async fn backup(&self, _path: &str) -> Result {
+        Err(Error::Internal(String::from(
+            "postgres online backup not supported, use pg_dump",
+        )))
+    }
+
+    async fn clean_avatar_path(&self, path: &str) -> Result {
+        if let Err(sqlx::Error::RowNotFound) = sqlx::query_as::<_, UserProduct>(
+            r#"select id, product_id, user_id, coalesce(avatar, '') as avatar, status, update_time
+            from user_product
+            where avatar = $1"#,
+        )
+        .bind(path)
+        .fetch_one(&self.pool)
+        .await
+        {
+            let path = format!("{}/{}", &CONFIG.avatar_path, path);
+            tracing::info!("remove unused avatar: {}", &path);
+            fs::remove_file(path)
+                .map_err(|e| Error::Internal(format!("remove file error: {}", e)))?;
+        }
+        Ok(())
+    }
+    async fn clean_session(&self) -> Result {
+        let now = Local::now().timestamp_millis();
+        sqlx::query("delete from session where expire <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(())
+    }
+    async fn purge_cancelled_users(&self, before: i64) -> Result<usize> {
+        let users: Vec<i64> = sqlx::query_scalar(
+            r#"select id from "user" where status = '99' and passwd != '' and update_time < $1"#,
+        )
+        .bind(before)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        for user_id in users.iter() {
+            let mut txn = self.begin().await?;
+            if let Err(e) = self.purge_user_txn(&mut txn, *user_id).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(users.len())
+    }
+
+    async fn create_user(&self, signup: &SignUpReq) -> Result<User> {
+        let product = sqlx::query_as::<_, Product>(
+            r#"select id, product, coalesce("desc", '') as "desc", status, coalesce(update_time, 0) as update_time
+            from product where product = $1"#,
+        )
+        .bind(&signup.product)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .ok_or(Error::ProductNotExists)?;
+
+        let mut txn = self.begin().await?;
+        let passwd = gen_passwd(&signup.email, &signup.passwd);
+        let user = self.user_from_signup(signup, passwd);
+
+        let user = match sqlx::query_as::<_, User>(
+            r#"insert into "user"(user_name, email, passwd, status, update_time)
+            values ($1, $2, $3, $4, $5)
+            on conflict (email) do nothing
+            returning id, coalesce(user_name, '') as user_name, email, passwd, status, coalesce(update_time, 0) as update_time"#,
+        )
+        .bind(&user.user_name)
+        .bind(&user.email)
+        .bind(user.passwd)
+        .bind(user.status)
+        .bind(user.update_time)
+        .fetch_optional(&mut *txn)
+        .await
+        {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                self.rollback(txn).await?;
+                return Err(Error::UserExists(format!(
+                    "email \"{}\" exists",
+                    &signup.email
+                )));
+            }
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        if let Err(e) = sqlx::query(
+            "insert into user_product(user_id, product_id, status, update_time) values ($1, $2, '00', extract(epoch from now())::bigint)",
+        )
+        .bind(user.id.unwrap())
+        .bind(product.id.unwrap())
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        self.commit(txn).await?;
+
+        Ok(user)
+    }
+
+    async fn signin_user(&self, signin: &SignInReq) -> Result<(User, Product, Session)> {
+        let user = sqlx::query_as::<_, User>(
+            r#"select id, coalesce(user_name, '') as user_name, email, passwd, status, coalesce(update_time, 0) as update_time
+            from "user" where status = '00' and email = $1"#,
+        )
+        .bind(&signin.email)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::UserNotExists,
+
+            _ => Error::DatabaseException(e.to_string()),
+        })?;
+
+        let passwd = gen_passwd(&user.email, &signin.passwd);
+        if passwd != user.passwd {
+            return Err(Error::UserPasswdError);
+        }
+
+        let products = self.query_user_products(user.id.unwrap()).await?;
+
+        let open_products: Vec<_> = products
+            .into_iter()
+            .filter(|product| product.product == signin.product)
+            .collect();
+
+        let product = if open_products.is_empty() {
+            if signin.product_open_flag {
+                let mut txn = self.begin().await?;
+                let product_id = self
+                    .insert_user_product(&mut txn, user.id.unwrap(), &signin.product)
+                    .await?;
+                self.commit(txn).await?;
+
+                self.query_product(product_id).await?
+            } else {
+                return Err(Error::ProductNotOpen);
+            }
+        } else {
+            open_products[0].clone()
+        };
+
+        let mut token = Session::token(product.id.unwrap(), user.id.unwrap());
+        let id: i64 = sqlx::query_scalar(
+            "insert into session(token, user_id, product_id, expire) values ($1, $2, $3, $4) returning id",
+        )
+        .bind(&token.token)
+        .bind(token.user_id)
+        .bind(token.product_id)
+        .bind(token.expire)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        token.id = Some(id);
+
+        Ok((user, product, token))
+    }
+
+    async fn reset_user_passwd(&self, reset: &ResetPasswdReq) -> Result {
+        let passwd = gen_passwd(&reset.email, &reset.passwd);
+
+        sqlx::query_scalar::<_, i64>(
+            r#"update "user" set passwd = $1, update_time = extract(epoch from now())::bigint
+            where status = '00' and email = $2
+            returning id"#,
+        )
+        .bind(&passwd)
+        .bind(&reset.email)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .ok_or(Error::UserNotExists)?;
+
+        Ok(())
+    }
+    async fn cancel_user(&self, user_id: i64) -> Result {
+        let mut txn = self.begin().await?;
+        match sqlx::query(
+            r#"update "user" set status = '99', update_time = $1 where status = '00' and id = $2"#,
+        )
+        .bind(Local::now().timestamp())
+        .bind(user_id)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                self.rollback(txn).await?;
+                return Err(Error::UserNotExists);
+            }
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+            Ok(_) => {}
+        }
+        if let Err(e) = sqlx::query("delete from session where user_id = $1")
+            .bind(user_id)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+        self.commit(txn).await
+    }
+
+    async fn open_product(&self, user_id: i64, product: &str) -> Result {
+        let products = self.query_user_products(user_id).await?;
+
+        let open_products: Vec<_> = products
+            .into_iter()
+            .filter(|p| p.product == product)
+            .collect();
+
+        if open_products.is_empty() {
+            let mut txn = self.begin().await?;
+            self.insert_user_product(&mut txn, user_id, product).await?;
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn close_product(&self, user_id: i64, product: &str, purge: bool) -> Result {
+        let product_id = self
+            .query_user_products(user_id)
+            .await?
+            .into_iter()
+            .find(|p| p.product == product)
+            .and_then(|p| p.id)
+            .ok_or(Error::ProductNotOpen)?;
+
+        let mut txn = self.begin().await?;
+        if let Err(e) = self
+            .close_user_product_txn(&mut txn, user_id, product_id, purge)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+        // 目前只有hiqradio有产品数据
+        if purge && product == PRODUCT_HIQRADIO {
+            if let Err(e) = self.purge_hiqradio_txn(&mut txn, user_id).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+        }
+        self.commit(txn).await
+    }
+
+    async fn get_session(&self, token: &str) -> Result<Session> {
+        let mut session = sqlx::query_as::<_, Session>(
+            r#"select id, token, user_id, product_id, expire
+            from session
+            where token = $1"#,
+        )
+        .bind(token)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::TokenInvalid,
+
+            _ => Error::DatabaseException(e.to_string()),
+        })?;
+
+        let now = Local::now().timestamp_millis();
+
+        if session.expire < now {
+            sqlx::query("delete from session where token = $1")
+                .bind(&session.token)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            return Err(Error::TokenInvalid);
+        }
+
+        if session.expire - now < CONFIG.token_refresh * 1000 {
+            let expire = now + CONFIG.token_expire * 1000;
+            sqlx::query("update session set expire = $1 where token = $2")
+                .bind(expire)
+                .bind(&session.token)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+            session.expire = expire;
+        }
+
+        Ok(session)
+    }
+
+    async fn update_user_info(
+        &self,
+        user_id: i64,
+        product_id: i64,
+        user_name: Option<String>,
+        new_passwd: Option<String>,
+        avatar: Option<String>,
+    ) -> Result {
+        let mut txn = self.begin().await?;
+
+        if let Some(new_user_name) = user_name {
+            if let Err(e) = sqlx::query(r#"update "user" set user_name = $1 where id = $2"#)
+                .bind(&new_user_name)
+                .bind(user_id)
+                .execute(&mut *txn)
+                .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+        if let Some(new_passwd) = new_passwd {
+            if let Err(e) = sqlx::query(r#"update "user" set passwd = $1 where id = $2"#)
+                .bind(&new_passwd)
+                .bind(user_id)
+                .execute(&mut *txn)
+                .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+        if let Some(new_avatar) = avatar {
+            if let Err(e) = sqlx::query(
+                "update user_product set avatar = $1 where user_id = $2 and product_id = $3",
+            )
+            .bind(&new_avatar)
+            .bind(user_id)
+            .bind(product_id)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_user_products(&self, user_id: i64) -> Result<Vec<Product>> {
+        let products = sqlx::query_as::<_, Product>(
+            r#"select a.id as id, a.product as product, coalesce(a."desc", '') as "desc", coalesce(a.update_time, 0) as update_time
+            from product a, user_product b
+            where a.id = b.product_id and a.status = '00' and b.status = '00' and b.user_id = $1
+            order by b.id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(products)
+    }
+
+    async fn query_products(&self) -> Result<Vec<Product>> {
+        let products = sqlx::query_as::<_, Product>(
+            r#"select a.id as id, a.product as product, coalesce(a."desc", '') as "desc", coalesce(a.update_time, 0) as update_time
+            from product a
+            where a.status = '00'
+            order by a.id"#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(products)
+    }
+
+    async fn query_product(&self, product_id: i64) -> Result<Product> {
+        let product = sqlx::query_as::<_, Product>(
+            r#"select id, product, coalesce("desc", '') as "desc", status, coalesce(update_time, 0) as update_time
+            from product
+            where status = '00' and id = $1"#,
+        )
+        .bind(product_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::ProductNotExists,
+
+            _ => Error::DatabaseException(e.to_string()),
+        })?;
+
+        Ok(product)
+    }
+    async fn query_user(&self, user_id: i64) -> Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            r#"select id, coalesce(user_name, '') as user_name, email, passwd, status, coalesce(update_time, 0) as update_time
+            from "user"
+            where status = '00' and id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::UserNotExists,
+
+            _ => Error::DatabaseException(e.to_string()),
+        })?;
+
+        Ok(user)
+    }
+    async fn query_user_product(&self, user_id: i64, product_id: i64) -> Result<UserProduct> {
+        let user_product = sqlx::query_as::<_, UserProduct>(
+            r#"select id, product_id, user_id, coalesce(avatar, '') as avatar, status, update_time
+            from user_product
+            where status = '00' and product_id = $1 and user_id = $2"#,
+        )
+        .bind(product_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::ProductNotOpen,
+
+            _ => Error::DatabaseException(e.to_string()),
+        })?;
+
+        Ok(user_product)
+    }
+    async fn delete_session(&self, token: &str) -> Result {
+        for sql in [
+            "delete from session where token = $1",
+            "delete from hiqradio_presence where token = $1",
+        ] {
+            sqlx::query(sql)
+                .bind(token)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| Error::DatabaseException(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn query_recently(&self, user_id: i64) -> Result<Vec<Recently>> {
+        let recently = sqlx::query_as::<_, Recently>(
+            r#"select id, user_id, stationuuid, start_time, end_time
+            from hiqradio_recently
+            where user_id = $1 order by start_time desc"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(recently)
+    }
+
+    async fn query_recently_page(
+        &self,
+        user_id: i64,
+        filter: &RecentlyFilter,
+    ) -> Result<Vec<Recently>> {
+        let station = if filter.stationuuid.is_some() {
+            "and stationuuid = $7"
+        } else {
+            ""
+        };
+        let sql = format!(
+            r#"select id, user_id, stationuuid, start_time, end_time
+            from hiqradio_recently
+            where user_id = $1 and start_time >= $2 and start_time < $3
+            and start_time <= $4 and (start_time < $5 or id < $6) {}
+            order by start_time desc, id desc limit ${}"#,
+            station,
+            if filter.stationuuid.is_some() { 8 } else { 7 }
+        );
+        let (after_time, after_id) = filter.after.unwrap_or((i64::MAX, i64::MAX));
+        let mut query = sqlx::query_as::<_, Recently>(&sql)
+            .bind(user_id)
+            .bind(filter.since.unwrap_or(i64::MIN))
+            .bind(filter.until.unwrap_or(i64::MAX))
+            .bind(after_time)
+            .bind(after_time)
+            .bind(after_id);
+        if let Some(stationuuid) = &filter.stationuuid {
+            query = query.bind(stationuuid);
+        }
+        let recently = query
+            .bind(filter.limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(recently)
+    }
+
+    async fn query_recently_stations(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<StationStats>> {
+        let stations = sqlx::query_as::<_, StationStats>(
+            r#"select stationuuid, count(*) as plays,
+            cast(coalesce(sum(case when end_time > start_time then end_time - start_time else 0 end), 0) as bigint) as "time"
+            from hiqradio_recently
+            where user_id = $1 and start_time >= $2 and start_time < $3
+            group by stationuuid"#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn query_recently_hours(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+        offset: i64,
+    ) -> Result<Vec<HourPlays>> {
+        // 按本地时间向下取整到小时，1970年之前的时间也向下取整
+        let hours = sqlx::query_as::<_, HourPlays>(
+            r#"select (t.local_time - ((t.local_time % 3600) + 3600) % 3600) / 3600 as hour,
+            count(*) as plays, cast(coalesce(sum(t.duration), 0) as bigint) as "time"
+            from (
+                select start_time + $1 as local_time,
+                case when end_time > start_time then end_time - start_time else 0 end as duration
+                from hiqradio_recently
+                where user_id = $2 and start_time >= $3 and start_time < $4
+            ) t
+            group by hour order by hour"#,
+        )
+        .bind(offset)
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(hours)
+    }
+
+    async fn query_recently_longest(
+        &self,
+        user_id: i64,
+        since: i64,
+        until: i64,
+        limit: i64,
+    ) -> Result<Vec<Recently>> {
+        let recently = sqlx::query_as::<_, Recently>(
+            r#"select id, user_id, stationuuid, start_time, end_time
+            from hiqradio_recently
+            where user_id = $1 and start_time >= $2 and start_time < $3 and end_time > start_time
+            order by end_time - start_time desc, start_time desc, id desc limit $4"#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(until)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(recently)
+    }
+
+    async fn delete_recently(&self, user_id: i64) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self.clear_recently(&mut txn, &change, None).await {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn new_recently(&self, user_id: i64, recently: &[RecentlyNew]) -> Result {
+        for chunk in recently.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.new_recently_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn modify_recently(
+        &self,
+        user_id: i64,
+        stationuuid: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self
+            .modify_recently_txn(&mut txn, change, stationuuid, start_time, end_time)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn clean_recently(&self, now: i64, max_days: i64, max_rows: i64) -> Result<usize> {
+        let users = sqlx::query_as::<_, RecentlyRetention>(RETENTION_SELECT)
+            .bind(max_rows)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let mut count = 0;
+        for user in users {
+            let Some(cutoff) = user.cutoff(now, max_days) else {
+                continue;
+            };
+            let (mut txn, change) = self.begin_revision(user.user_id).await?;
+            match self.clear_recently(&mut txn, &change, Some(cutoff)).await {
+                Ok(n) => count += n,
+                Err(e) => {
+                    self.rollback(txn).await?;
+                    return Err(e);
+                }
+            }
+            self.commit(txn).await?;
+        }
+
+        Ok(count as usize)
+    }
+
+    async fn query_setting(&self, user_id: i64) -> Result<Setting> {
+        let setting = sqlx::query_as::<_, Setting>(
+            r#"select user_id, recently_enabled, recently_days, update_time
+            from hiqradio_setting where user_id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(setting.unwrap_or_else(|| Setting::new(user_id)))
+    }
+
+    async fn modify_setting(&self, setting: &Setting) -> Result<bool> {
+        let was_enabled = self.query_setting(setting.user_id).await?.recently_enabled != 0;
+        let clear = was_enabled && setting.recently_enabled == 0;
+        let (mut txn, change) = if clear {
+            let (txn, change) = self.begin_revision(setting.user_id).await?;
+            (txn, Some(change))
+        } else {
+            (self.begin().await?, None)
+        };
+        if let Err(e) = self
+            .modify_setting_txn(&mut txn, setting, change.as_ref())
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(clear)
+    }
+
+    async fn query_groups(&self, user_id: i64) -> Result<Vec<FavGroup>> {
+        let groups = sqlx::query_as::<_, FavGroup>(
+            r#"select id, user_id, create_time, name, coalesce("desc", '') as "desc", is_def, position
+            from hiqradio_fav_group
+            where user_id = $1
+            order by position, id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(groups)
+    }
+
+    async fn delete_groups(&self, user_id: i64, groups: &[String]) -> Result {
+        for chunk in groups.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.delete_groups_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn new_groups(&self, user_id: i64, groups: &[GroupNew]) -> Result {
+        for chunk in groups.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.new_groups_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+    async fn modify_group(&self, user_id: i64, old_name: &str, name: &str, desc: &str) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self
+            .modify_group_txn(&mut txn, change, old_name, name, desc)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_favorites(&self, user_id: i64) -> Result<Vec<StationGroup>> {
+        let groups = sqlx::query_as::<_, StationGroup>(
+            r#"select a.name as group_name,  b.stationuuid, b.create_time, b.position
+            from hiqradio_fav_group a, hiqradio_favorite b
+            where a.id = b.group_id and a.user_id = b.user_id and a.user_id = $1
+            order by a.position, a.id, b.position, b.id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(groups)
+    }
+    async fn new_favorite(&self, user_id: i64, stations: &[StationGroup]) -> Result {
+        for chunk in stations.chunks(50) {
+            let (mut txn, change) = self.begin_revision(user_id).await?;
+            if let Err(e) = self.new_favorite_txn(&mut txn, change, chunk).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+            self.commit(txn).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_favorite(
+        &self,
+        user_id: i64,
+        favorites: &Option<Vec<String>>,
+        group_names: &Option<Vec<String>>,
+    ) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+
+        if let Some(favorites) = favorites {
+            if let Err(e) = sqlx::query(
+                r#"insert into hiqradio_tombstone(user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision)
+                select b.user_id, $3, a.name, b.stationuuid, '', '', $4, $5
+                from hiqradio_fav_group a, hiqradio_favorite b
+                where a.id = b.group_id and a.user_id = b.user_id and b.user_id = $1 and b.stationuuid = any($2)"#,
+            )
+            .bind(user_id)
+            .bind(favorites)
+            .bind(TOMBSTONE_FAVORITE_DELETE)
+            .bind(change.time)
+            .bind(change.revision)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+
+            if let Err(e) = sqlx::query(
+                r#"delete from hiqradio_favorite
+                where user_id = $1 and stationuuid = any($2)"#,
+            )
+            .bind(user_id)
+            .bind(favorites)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+        if let Some(group_names) = group_names {
+            if let Err(e) = sqlx::query(
+                r#"insert into hiqradio_tombstone(user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision)
+                select b.user_id, $3, a.name, b.stationuuid, '', '', $4, $5
+                from hiqradio_fav_group a, hiqradio_favorite b
+                where a.id = b.group_id and a.user_id = b.user_id and b.user_id = $1 and a.name = any($2)"#,
+            )
+            .bind(user_id)
+            .bind(group_names)
+            .bind(TOMBSTONE_FAVORITE_DELETE)
+            .bind(change.time)
+            .bind(change.revision)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+
+            if let Err(e) = sqlx::query(
+                r#"delete from hiqradio_favorite
+                where user_id = $1 and group_id in (
+                    select id from hiqradio_fav_group where user_id = $1 and name = any($2)
+                )"#,
+            )
+            .bind(user_id)
+            .bind(group_names)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn modify_favorite(
+        &self,
+        user_id: i64,
+        stationuuid: &str,
+        groups: &[String],
+    ) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = self
+            .modify_favorite_txn(&mut txn, change, stationuuid, groups)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(e);
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn reorder_groups(&self, user_id: i64, names: &[String]) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if let Err(e) = sqlx::query(
+            r#"update hiqradio_fav_group g set position = o.position - 1, revision = $3
+            from unnest($2::text[]) with ordinality as o(name, position)
+            where g.user_id = $1 and g.name = o.name and g.position <> o.position - 1"#,
+        )
+        .bind(user_id)
+        .bind(names)
+        .bind(change.revision)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn reorder_favorites(&self, user_id: i64, group_name: &str, stationuuids: &[String]) -> Result {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        let group_id = match sqlx::query_scalar::<_, i64>(
+            r#"select id from hiqradio_fav_group where user_id = $1 and name = $2"#,
+        )
+        .bind(user_id)
+        .bind(group_name)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(group_id) => group_id,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        if let Err(e) = sqlx::query(
+            r#"update hiqradio_favorite f set position = o.position - 1, revision = $3
+            from unnest($2::text[]) with ordinality as o(stationuuid, position)
+            where f.group_id = $1 and f.stationuuid = o.stationuuid and f.position <> o.position - 1"#,
+        )
+        .bind(group_id)
+        .bind(stationuuids)
+        .bind(change.revision)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_revision(&self, user_id: i64) -> Result<i64> {
+        let revision = sqlx::query_scalar::<_, i64>(
+            r#"select revision from hiqradio_revision where user_id = $1"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(revision.unwrap_or_default())
+    }
+
+    async fn query_sync(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<(Vec<FavGroup>, Vec<Recently>, Vec<StationGroup>)> {
+        let fav_groups = sqlx::query_as::<_, FavGroup>(
+            r#"select id, user_id, create_time, name, coalesce("desc", '') as "desc", is_def, position
+            from hiqradio_fav_group
+            where user_id = $1 and revision > $2 and revision <= $3
+            order by position, id"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let recently = sqlx::query_as::<_, Recently>(
+            r#"select id, user_id, stationuuid, start_time, end_time
+            from hiqradio_recently
+            where user_id = $1 and revision > $2 and revision <= $3  order by start_time desc"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        let stations = sqlx::query_as::<_, StationGroup>(
+            r#"select a.name as group_name,  b.stationuuid, b.create_time, b.position
+            from hiqradio_fav_group a, hiqradio_favorite b
+            where a.id = b.group_id and a.user_id = b.user_id and a.user_id = $1 and b.revision > $2 and b.revision <= $3
+            order by a.position, a.id, b.position, b.id"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok((fav_groups, recently, stations))
+    }
+
+    async fn query_tombstones(
+        &self,
+        user_id: i64,
+        start_revision: i64,
+        end_revision: i64,
+    ) -> Result<Vec<Tombstone>> {
+        let tombstones = sqlx::query_as::<_, Tombstone>(
+            r#"select id, user_id, kind, group_name, stationuuid, new_name, new_desc, create_time, revision
+            from hiqradio_tombstone
+            where user_id = $1 and revision > $2 and revision <= $3 order by id"#,
+        )
+        .bind(user_id)
+        .bind(start_revision)
+        .bind(end_revision)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(tombstones)
+    }
+
+    async fn apply_sync(&self, user_id: i64, revision: i64, ops: &[SyncOp]) -> Result<Option<i64>> {
+        let (mut txn, change) = self.begin_revision(user_id).await?;
+        if change.revision != revision + 1 {
+            self.rollback(txn).await?;
+            return Ok(None);
+        }
+
+        for op in ops.iter() {
+            if let Err(e) = self.apply_op_txn(&mut txn, change, op).await {
+                self.rollback(txn).await?;
+                return Err(e);
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(Some(change.revision))
+    }
+
+    async fn share_group(&self, user_id: i64, group_name: &str, token: &str) -> Result<Share> {
+        let mut txn = self.begin().await?;
+        let group_id = match sqlx::query_scalar::<_, i64>(
+            r#"select id from hiqradio_fav_group where user_id = $1 and name = $2"#,
+        )
+        .bind(user_id)
+        .bind(group_name)
+        .fetch_optional(&mut *txn)
+        .await
+        {
+            Ok(Some(group_id)) => group_id,
+            Ok(None) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(format!("group \"{}\" not found", group_name)));
+            }
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        if let Err(e) = sqlx::query(
+            r#"insert into hiqradio_share(token, user_id, group_id, create_time) values ($1, $2, $3, $4)
+            on conflict(group_id) do nothing"#,
+        )
+        .bind(token)
+        .bind(user_id)
+        .bind(group_id)
+        .bind(Local::now().timestamp())
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let share = match sqlx::query_as::<_, Share>(
+            r#"select a.token, a.user_id, b.name as group_name, a.create_time
+            from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and a.group_id = $1"#,
+        )
+        .bind(group_id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(share) => share,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(share)
+    }
+
+    async fn query_shares(&self, user_id: i64) -> Result<Vec<Share>> {
+        let shares = sqlx::query_as::<_, Share>(
+            r#"select a.token, a.user_id, b.name as group_name, a.create_time
+            from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and a.user_id = $1
+            order by b.position, b.id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(shares)
+    }
+
+    async fn query_share(&self, token: &str) -> Result<Option<Share>> {
+        let share = sqlx::query_as::<_, Share>(
+            r#"select a.token, a.user_id, b.name as group_name, a.create_time
+            from hiqradio_share a, hiqradio_fav_group b
+            where a.group_id = b.id and a.token = $1
+            and exists (select 1 from "user" c, user_product d, product e
+                where c.id = a.user_id and c.status = '00' and d.user_id = c.id
+                and d.product_id = e.id and d.status = '00' and e.product = $2)"#,
+        )
+        .bind(token)
+        .bind(PRODUCT_HIQRADIO)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(share)
+    }
+
+    async fn delete_share(&self, user_id: i64, group_name: &str) -> Result<bool> {
+        let res = sqlx::query(
+            r#"delete from hiqradio_share
+            where group_id in (select id from hiqradio_fav_group where user_id = $1 and name = $2)"#,
+        )
+        .bind(user_id)
+        .bind(group_name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn query_user_stations(
+        &self,
+        since: i64,
+        after_user_id: i64,
+        after_stationuuid: &str,
+        limit: i64,
+    ) -> Result<Vec<UserStation>> {
+        let stations = sqlx::query_as::<_, UserStation>(
+            r#"select user_id, stationuuid, max(favorite) as favorite from (
+                select user_id, stationuuid, 1::bigint as favorite from hiqradio_favorite
+                where user_id > $2 or (user_id = $2 and stationuuid > $3)
+                union all
+                select user_id, stationuuid, 0::bigint as favorite from hiqradio_recently
+                where start_time >= $1 and (user_id > $2 or (user_id = $2 and stationuuid > $3))
+            ) s
+            group by user_id, stationuuid
+            order by user_id, stationuuid
+            limit $4"#,
+        )
+        .bind(since)
+        .bind(after_user_id)
+        .bind(after_stationuuid)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn save_similar(&self, similar: &[Similar]) -> Result {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(r#"delete from hiqradio_similar"#)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        if let Err(e) = sqlx::query(
+            r#"insert into hiqradio_similar(stationuuid, similaruuid, score, update_time)
+            select * from unnest($1::varchar[], $2::varchar[], $3::float8[], $4::bigint[])"#,
+        )
+        .bind(similar.iter().map(|s| s.stationuuid.clone()).collect::<Vec<_>>())
+        .bind(similar.iter().map(|s| s.similaruuid.clone()).collect::<Vec<_>>())
+        .bind(similar.iter().map(|s| s.score).collect::<Vec<_>>())
+        .bind(similar.iter().map(|s| s.update_time).collect::<Vec<_>>())
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_similar(&self, stationuuids: &[String]) -> Result<Vec<Similar>> {
+        let similar = sqlx::query_as::<_, Similar>(
+            r#"select stationuuid, similaruuid, score, update_time
+            from hiqradio_similar where stationuuid = any($1)
+            order by score desc"#,
+        )
+        .bind(stationuuids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(similar)
+    }
+
+    async fn query_station_listens(&self, since: i64, until: i64) -> Result<Vec<StationListen>> {
+        let listens = sqlx::query_as::<_, StationListen>(
+            r#"select stationuuid, user_id,
+            sum(case when end_time > start_time then end_time - start_time else 0 end)::bigint as listen_time
+            from hiqradio_recently where start_time >= $1 and start_time <= $2
+            group by stationuuid, user_id"#,
+        )
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(listens)
+    }
+
+    async fn query_new_favorites(&self, since: i64, until: i64) -> Result<Vec<UserStation>> {
+        let favorites = sqlx::query_as::<_, UserStation>(
+            r#"select distinct user_id, stationuuid, 1::bigint as favorite
+            from hiqradio_favorite where create_time >= $1 and create_time <= $2"#,
+        )
+        .bind(since)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(favorites)
+    }
+
+    async fn save_trending(&self, period: &str, stations: &[TrendingStation]) -> Result {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(r#"delete from hiqradio_trending where period = $1"#)
+            .bind(period)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        for s in stations {
+            if let Err(e) = sqlx::query(
+                r#"insert into hiqradio_trending(
+                period, stationuuid, listeners, listen_time, favorites, score, update_time)
+                values ($1, $2, $3, $4, $5, $6, $7)"#,
+            )
+            .bind(period)
+            .bind(&s.stationuuid)
+            .bind(s.listeners)
+            .bind(s.listen_time)
+            .bind(s.favorites)
+            .bind(s.score)
+            .bind(s.update_time)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn query_trending(&self, period: &str, limit: i64) -> Result<Vec<TrendingStation>> {
+        let stations = sqlx::query_as::<_, TrendingStation>(
+            r#"select period, stationuuid, listeners, listen_time, favorites, score, update_time
+            from hiqradio_trending where period = $1
+            order by score desc, stationuuid limit $2"#,
+        )
+        .bind(period)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn save_review(&self, review: &Review) -> Result<Review> {
+        let mut txn = self.begin().await?;
+        let id = match sqlx::query_scalar::<_, i64>(
+            r#"insert into hiqradio_review(
+            user_id, stationuuid, rating, content, status, reports, create_time, update_time)
+            values ($1, $2, $3, $4, $5, 0, $6, $7)
+            on conflict(user_id, stationuuid) do update set
+            rating = excluded.rating,
+            content = excluded.content,
+            status = excluded.status,
+            reports = 0,
+            update_time = excluded.update_time
+            returning id"#,
+        )
+        .bind(review.user_id)
+        .bind(&review.stationuuid)
+        .bind(review.rating)
+        .bind(&review.content)
+        .bind(&review.status)
+        .bind(review.create_time)
+        .bind(review.update_time)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(id) => id,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        // 修改后的评论重新接受举报
+        if let Err(e) = sqlx::query(r#"delete from hiqradio_review_report where review_id = $1"#)
+            .bind(id)
+            .execute(&mut *txn)
+            .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let review = match sqlx::query_as::<_, Review>(&format!("{} where a.id = $1", REVIEW_SELECT))
+            .bind(id)
+            .fetch_one(&mut *txn)
+            .await
+        {
+            Ok(review) => review,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(review)
+    }
+
+    async fn delete_review(&self, user_id: i64, stationuuid: &str) -> Result<bool> {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(
+            r#"delete from hiqradio_review_report where review_id in (
+            select id from hiqradio_review where user_id = $1 and stationuuid = $2)"#,
+        )
+        .bind(user_id)
+        .bind(stationuuid)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let deleted = match sqlx::query(
+            r#"delete from hiqradio_review where user_id = $1 and stationuuid = $2"#,
+        )
+        .bind(user_id)
+        .bind(stationuuid)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(res) => res.rows_affected() > 0,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(deleted)
+    }
+
+    async fn query_review(&self, id: i64) -> Result<Option<Review>> {
+        let review = sqlx::query_as::<_, Review>(&format!("{} where a.id = $1", REVIEW_SELECT))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(review)
+    }
+
+    async fn query_user_reviews(&self, user_id: i64) -> Result<Vec<Review>> {
+        let reviews = sqlx::query_as::<_, Review>(&format!(
+            "{} where a.user_id = $1 order by a.update_time desc, a.id desc",
+            REVIEW_SELECT
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(reviews)
+    }
+
+    async fn query_station_reviews(
+        &self,
+        stationuuid: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Review>> {
+        let reviews = sqlx::query_as::<_, Review>(&format!(
+            r#"{} where a.stationuuid = $1 and a.status = 'approved'
+            and exists (select 1 from "user" c, user_product d, product e
+                where c.id = a.user_id and c.status = '00' and d.user_id = c.id
+                and d.product_id = e.id and d.status = '00' and e.product = $4)
+            order by a.update_time desc, a.id desc limit $2 offset $3"#,
+            REVIEW_SELECT
+        ))
+        .bind(stationuuid)
+        .bind(limit)
+        .bind(offset)
+        .bind(PRODUCT_HIQRADIO)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(reviews)
+    }
+
+    async fn query_pending_reviews(&self, offset: i64, limit: i64) -> Result<Vec<Review>> {
+        let reviews = sqlx::query_as::<_, Review>(&format!(
+            "{} where a.status = 'pending'
+            order by a.reports desc, a.update_time, a.id limit $1 offset $2",
+            REVIEW_SELECT
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(reviews)
+    }
+
+    async fn moderate_review(&self, id: i64, status: &str) -> Result<bool> {
+        let res = sqlx::query(r#"update hiqradio_review set status = $1, reports = 0 where id = $2"#)
+            .bind(status)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn report_review(
+        &self,
+        id: i64,
+        user_id: i64,
+        reason: &str,
+        time: i64,
+        pending_reports: i64,
+    ) -> Result<i64> {
+        let mut txn = self.begin().await?;
+        let reported = match sqlx::query(
+            r#"insert into hiqradio_review_report(review_id, user_id, reason, create_time)
+            values ($1, $2, $3, $4)
+            on conflict(review_id, user_id) do nothing"#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(reason)
+        .bind(time)
+        .execute(&mut *txn)
+        .await
+        {
+            Ok(res) => res.rows_affected() > 0,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        if reported {
+            if let Err(e) = sqlx::query(
+                r#"update hiqradio_review set
+                status = case when status = 'approved' and reports + 1 >= $1 then 'pending' else status end,
+                reports = reports + 1
+                where id = $2"#,
+            )
+            .bind(pending_reports)
+            .bind(id)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        let reports = match sqlx::query_scalar::<_, i64>(
+            r#"select reports from hiqradio_review where id = $1"#,
+        )
+        .bind(id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(reports) => reports,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(reports)
+    }
+
+    async fn query_ratings(&self, stationuuids: &[String]) -> Result<Vec<Rating>> {
+        let ratings = sqlx::query_as::<_, Rating>(
+            r#"select a.stationuuid, avg(a.rating)::float8 as rating, count(*) as ratings
+            from hiqradio_review a where a.status = 'approved' and a.stationuuid = any($1)
+            and exists (select 1 from "user" c, user_product d, product e
+            where c.id = a.user_id and c.status = '00' and d.user_id = c.id
+            and d.product_id = e.id and d.status = '00' and e.product = $2)
+            group by a.stationuuid"#,
+        )
+        .bind(stationuuids)
+        .bind(PRODUCT_HIQRADIO)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(ratings)
+    }
+
+    async fn save_presence(&self, presence: &Presence) -> Result<Presence> {
+        let mut txn = self.begin().await?;
+        if let Err(e) = sqlx::query(
+            r#"insert into hiqradio_presence(
+            user_id, device_id, device_name, token, stationuuid, start_time, update_time, expire)
+            values ($1, $2, $3, $4, $5, $6, $7, $8)
+            on conflict(user_id, device_id) do update set
+            start_time = case when hiqradio_presence.stationuuid = excluded.stationuuid
+                then hiqradio_presence.start_time else excluded.start_time end,
+            device_name = excluded.device_name,
+            token = excluded.token,
+            stationuuid = excluded.stationuuid,
+            update_time = excluded.update_time,
+            expire = excluded.expire"#,
+        )
+        .bind(presence.user_id)
+        .bind(&presence.device_id)
+        .bind(&presence.device_name)
+        .bind(&presence.token)
+        .bind(&presence.stationuuid)
+        .bind(presence.start_time)
+        .bind(presence.update_time)
+        .bind(presence.expire)
+        .execute(&mut *txn)
+        .await
+        {
+            self.rollback(txn).await?;
+            return Err(Error::DatabaseException(e.to_string()));
+        }
+
+        let presence = match sqlx::query_as::<_, Presence>(
+            r#"select user_id, device_id, device_name, token, stationuuid, start_time, update_time, expire
+            from hiqradio_presence where user_id = $1 and device_id = $2"#,
+        )
+        .bind(presence.user_id)
+        .bind(&presence.device_id)
+        .fetch_one(&mut *txn)
+        .await
+        {
+            Ok(presence) => presence,
+            Err(e) => {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        };
+
+        self.commit(txn).await?;
+        Ok(presence)
+    }
+
+    async fn delete_presence(&self, user_id: i64, device_id: &str) -> Result<bool> {
+        let res = sqlx::query(r#"delete from hiqradio_presence where user_id = $1 and device_id = $2"#)
+            .bind(user_id)
+            .bind(device_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn query_presences(&self, user_id: i64, now: i64) -> Result<Vec<Presence>> {
+        let presences = sqlx::query_as::<_, Presence>(
+            r#"select user_id, device_id, device_name, token, stationuuid, start_time, update_time, expire
+            from hiqradio_presence where user_id = $1 and expire > $2
+            order by update_time desc, device_id"#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(presences)
+    }
+
+    async fn clean_presence(&self, now: i64) -> Result<usize> {
+        let res = sqlx::query(r#"delete from hiqradio_presence where expire <= $1"#)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(res.rows_affected() as usize)
+    }
+
+    async fn save_stations(&self, stations: &[Station]) -> Result {
+        let mut txn = self.begin().await?;
+        for s in stations {
+            if let Err(e) = sqlx::query(
+                r#"insert into hiqradio_station(
+                stationuuid, name, url, url_resolved, homepage, favicon, tags,
+                country, countrycode, state, language, codec, bitrate, hls,
+                lastcheckok, votes, clickcount, update_time)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+                on conflict(stationuuid) do update set
+                name = excluded.name,
+                url = excluded.url,
+                url_resolved = excluded.url_resolved,
+                homepage = excluded.homepage,
+                favicon = excluded.favicon,
+                tags = excluded.tags,
+                country = excluded.country,
+                countrycode = excluded.countrycode,
+                state = excluded.state,
+                language = excluded.language,
+                codec = excluded.codec,
+                bitrate = excluded.bitrate,
+                hls = excluded.hls,
+                lastcheckok = excluded.lastcheckok,
+                votes = excluded.votes,
+                clickcount = excluded.clickcount,
+                update_time = excluded.update_time,
+                deleted = 0"#,
+            )
+            .bind(&s.stationuuid)
+            .bind(&s.name)
+            .bind(&s.url)
+            .bind(&s.url_resolved)
+            .bind(&s.homepage)
+            .bind(&s.favicon)
+            .bind(&s.tags)
+            .bind(&s.country)
+            .bind(&s.countrycode)
+            .bind(&s.state)
+            .bind(&s.language)
+            .bind(&s.codec)
+            .bind(s.bitrate)
+            .bind(s.hls)
+            .bind(s.lastcheckok)
+            .bind(s.votes)
+            .bind(s.clickcount)
+            .bind(s.update_time)
+            .execute(&mut *txn)
+            .await
+            {
+                self.rollback(txn).await?;
+                return Err(Error::DatabaseException(e.to_string()));
+            }
+        }
+
+        self.commit(txn).await?;
+        Ok(())
+    }
+
+    async fn delete_stations_before(&self, before: i64) -> Result<usize> {
+        let count = sqlx::query(
+            r#"update hiqradio_station set deleted = 1 where deleted = 0 and update_time < $1"#,
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?
+        .rows_affected();
+
+        Ok(count as usize)
+    }
+
+    async fn query_stations(&self, stationuuids: &[String]) -> Result<Vec<Station>> {
+        let stations = sqlx::query_as::<_, Station>(
+            r#"select stationuuid, name, url, url_resolved, homepage, favicon,
+            tags, country, countrycode, state, language, codec,
+            bitrate, hls, lastcheckok, votes, clickcount, deleted,
+            update_time
+            from hiqradio_station where stationuuid = any($1)"#,
+        )
+        .bind(stationuuids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn query_stations_by_url(&self, urls: &[String]) -> Result<Vec<Station>> {
+        let stations = sqlx::query_as::<_, Station>(
+            r#"select stationuuid, name, url, url_resolved, homepage, favicon,
+            tags, country, countrycode, state, language, codec,
+            bitrate, hls, lastcheckok, votes, clickcount, deleted,
+            update_time
+            from hiqradio_station where url = any($1) or url_resolved = any($1)"#,
+        )
+        .bind(urls)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn query_stations_by_name(&self, names: &[String]) -> Result<Vec<Station>> {
+        let stations = sqlx::query_as::<_, Station>(
+            r#"select stationuuid, name, url, url_resolved, homepage, favicon,
+            tags, country, countrycode, state, language, codec,
+            bitrate, hls, lastcheckok, votes, clickcount, deleted,
+            update_time
+            from hiqradio_station where name = any($1)"#,
+        )
+        .bind(names)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+
+    async fn search_stations(&self, filter: &StationFilter) -> Result<Vec<Station>> {
+        let mut params = 2;
+        let mut param = || {
+            params += 1;
+            format!("${}", params)
+        };
+        let mut from = String::from("hiqradio_station s");
+        let mut rank = "1.0";
+        let mut conds = vec![String::from("s.deleted = 0"), String::from("s.bitrate >= $2")];
+        if !filter.terms.is_empty() {
+            from.push_str(&format!(", to_tsquery('simple', {}) q", param()));
+            rank = "ts_rank(s.search, q)";
+            conds.push(String::from("s.search @@ q"));
+        }
+        if filter.only_ok {
+            conds.push(String::from("s.lastcheckok = 1"));
+        }
+        if filter.tag.is_some() {
+            conds.push(format!("strpos(',' || lower(s.tags) || ',', {}) > 0", param()));
+        }
+        if filter.language.is_some() {
+            conds.push(format!(
+                "strpos(',' || lower(s.language) || ',', {}) > 0",
+                param()
+            ));
+        }
+        if filter.countrycode.is_some() {
+            conds.push(format!("upper(s.countrycode) = {}", param()));
+        }
+        if filter.codec.is_some() {
+            conds.push(format!("lower(s.codec) = {}", param()));
+        }
+        let sql = format!(
+            r#"select s.stationuuid, s.name, s.url, s.url_resolved, s.homepage, s.favicon,
+            s.tags, s.country, s.countrycode, s.state, s.language, s.codec,
+            s.bitrate, s.hls, s.lastcheckok, s.votes, s.clickcount, s.deleted,
+            s.update_time,
+            {} * (case when s.stationuuid = any($1) then {:.1} else 1.0 end) as score
+            from {} where {}
+            order by score desc, s.votes desc, s.stationuuid limit {} offset {}"#,
+            rank,
+            SEARCH_BOOST,
+            from,
+            conds.join(" and "),
+            param(),
+            param()
+        );
+
+        let mut query = sqlx::query_as::<_, Station>(&sql)
+            .bind(&filter.boost)
+            .bind(filter.bitrate_min);
+        if !filter.terms.is_empty() {
+            // 关键词只含字母和数字，按前缀匹配
+            let terms: Vec<_> = filter.terms.iter().map(|t| format!("{}:*", t)).collect();
+            query = query.bind(terms.join(" & "));
+        }
+        for item in [&filter.tag, &filter.language].into_iter().flatten() {
+            query = query.bind(format!(",{},", item));
+        }
+        for item in [&filter.countrycode, &filter.codec].into_iter().flatten() {
+            query = query.bind(item);
+        }
+        let stations = query
+            .bind(filter.limit)
+            .bind(filter.offset)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::DatabaseException(e.to_string()))?;
+
+        Ok(stations)
+    }
+}
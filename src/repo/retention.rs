@@ -0,0 +1,109 @@
+//! 最近播放的保留策略
+//!
+//! 每个用户算出一个截止时间，删除开始时间早于截止时间的记录，并用同一时间写入清空记录，
+//! 其他设备同步时按清空记录删除本地的记录，离线同步的更早记录也不再写入。
+
+/// 用户最近播放的时间范围和设置
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct RecentlyRetention {
+    pub user_id: i64,
+    /// 最早一条的开始时间
+    pub oldest: i64,
+    /// 最新一条的开始时间
+    pub newest: i64,
+    /// 按条数保留时保留的最早一条的开始时间，条数不超过时为None
+    pub kept_oldest: Option<i64>,
+    /// 没有设置时为None
+    pub recently_enabled: Option<i64>,
+    pub recently_days: Option<i64>,
+}
+
+impl RecentlyRetention {
+    /// 截止时间，没有需要删除的记录时返回None；max_days为0表示不按天数清理
+    ///
+    /// 按条数保留时开始时间相同的记录一起保留，可能略多于max_rows条
+    pub fn cutoff(&self, now: i64, max_days: i64) -> Option<i64> {
+        let days = |days: i64| (days > 0).then(|| now - days * 24 * 3600);
+        let disabled = (self.recently_enabled == Some(0)).then_some(self.newest + 1);
+        let cutoff = [
+            self.kept_oldest,
+            days(max_days),
+            self.recently_days.and_then(days),
+            disabled,
+        ]
+        .into_iter()
+        .flatten()
+        .max()?;
+        (cutoff > self.oldest).then_some(cutoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 3600;
+
+    fn retention(oldest: i64, newest: i64) -> RecentlyRetention {
+        RecentlyRetention {
+            user_id: 1,
+            oldest,
+            newest,
+            kept_oldest: None,
+            recently_enabled: None,
+            recently_days: None,
+        }
+    }
+
+    #[test]
+    fn no_policy() {
+        assert_eq!(retention(0, 100).cutoff(100 * DAY, 0), None);
+    }
+
+    #[test]
+    fn by_days() {
+        let now = 100 * DAY;
+        assert_eq!(retention(0, now).cutoff(now, 30), Some(70 * DAY));
+        // 没有超过天数的记录
+        assert_eq!(retention(80 * DAY, now).cutoff(now, 30), None);
+
+        // 用户设置更短时按用户设置，更长时按服务端配置
+        let short = RecentlyRetention {
+            recently_days: Some(10),
+            ..retention(0, now)
+        };
+        assert_eq!(short.cutoff(now, 30), Some(90 * DAY));
+        assert_eq!(short.cutoff(now, 5), Some(95 * DAY));
+        assert_eq!(short.cutoff(now, 0), Some(90 * DAY));
+    }
+
+    #[test]
+    fn by_rows() {
+        let kept = RecentlyRetention {
+            kept_oldest: Some(50),
+            ..retention(10, 100)
+        };
+        assert_eq!(kept.cutoff(1000, 0), Some(50));
+        // 保留的最早一条和最早一条开始时间相同
+        let same = RecentlyRetention {
+            kept_oldest: Some(10),
+            ..retention(10, 100)
+        };
+        assert_eq!(same.cutoff(1000, 0), None);
+    }
+
+    #[test]
+    fn disabled() {
+        let disabled = RecentlyRetention {
+            recently_enabled: Some(0),
+            ..retention(10, 100)
+        };
+        // 包括开始时间晚于当前时间的记录
+        assert_eq!(disabled.cutoff(50, 0), Some(101));
+        let enabled = RecentlyRetention {
+            recently_enabled: Some(1),
+            ..retention(10, 100)
+        };
+        assert_eq!(enabled.cutoff(50, 0), None);
+    }
+}
@@ -0,0 +1,197 @@
+//! 最近播放统计
+//!
+//! 统计start_time在[since, until)内的播放记录，时长为end_time - start_time，
+//! 未结束的记录只计播放次数；按天和按小时统计时整条记录算在开始的时间上。
+//! 数据库按电台和按本地时间的小时汇总，这里只合并汇总结果。
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{
+    proto::{DayStats, HourStats, ListenStats, StationStats},
+    Result,
+};
+
+use super::DynAppServRepo;
+
+/// 一个小时内开始的播放，hour为本地时间1970-01-01 00:00起的小时数
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct HourPlays {
+    pub hour: i64,
+    pub plays: i64,
+    pub time: i64,
+}
+
+/// 小时数对应的日期(2024-01-01)和当天的小时
+fn day_hour(hour: i64) -> (String, u32) {
+    let day = DateTime::from_timestamp(hour.div_euclid(24) * 24 * 3600, 0)
+        .map(|day| day.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    (day, hour.rem_euclid(24) as u32)
+}
+
+/// 按天和当天的小时合并，天按日期升序，小时固定24项
+fn days_hours(plays: &[HourPlays]) -> (Vec<DayStats>, Vec<HourStats>) {
+    let mut days: Vec<DayStats> = Vec::new();
+    let mut hours: Vec<_> = (0..24)
+        .map(|hour| HourStats {
+            hour,
+            plays: 0,
+            time: 0,
+        })
+        .collect();
+    for p in plays {
+        let (day, hour) = day_hour(p.hour);
+        match days.last_mut() {
+            Some(last) if last.day == day => {
+                last.plays += p.plays;
+                last.time += p.time;
+            }
+            _ => days.push(DayStats {
+                day,
+                plays: p.plays,
+                time: p.time,
+            }),
+        }
+        let hour = &mut hours[hour as usize];
+        hour.plays += p.plays;
+        hour.time += p.time;
+    }
+    (days, hours)
+}
+
+/// 取排在前面的top个电台，排序相同时按stationuuid
+fn top_stations(
+    stations: &[StationStats],
+    top: usize,
+    key: impl Fn(&StationStats) -> (i64, i64),
+) -> Vec<StationStats> {
+    let mut stations = stations.to_vec();
+    stations.sort_by(|a, b| {
+        key(b)
+            .cmp(&key(a))
+            .then_with(|| a.stationuuid.cmp(&b.stationuuid))
+    });
+    stations.truncate(top);
+    stations
+}
+
+/// 统计用户的收听情况，top为排行的条数
+pub async fn listening_stats(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    since: Option<i64>,
+    until: Option<i64>,
+    offset: FixedOffset,
+    top: usize,
+) -> Result<ListenStats> {
+    let since = since.unwrap_or(i64::MIN);
+    let until = until.unwrap_or(i64::MAX);
+
+    let stations = repo.query_recently_stations(user_id, since, until).await?;
+    let plays = repo
+        .query_recently_hours(user_id, since, until, offset.local_minus_utc() as i64)
+        .await?;
+    let (days, hours) = days_hours(&plays);
+
+    Ok(ListenStats {
+        plays: stations.iter().map(|s| s.plays).sum(),
+        time: stations.iter().map(|s| s.time).sum(),
+        stations: stations.len() as i64,
+        top_by_time: top_stations(&stations, top, |s| (s.time, s.plays)),
+        top_by_plays: top_stations(&stations, top, |s| (s.plays, s.time)),
+        days,
+        hours,
+        longest: repo
+            .query_recently_longest(user_id, since, until, top as i64)
+            .await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn station(stationuuid: &str, plays: i64, time: i64) -> StationStats {
+        StationStats {
+            stationuuid: stationuuid.to_string(),
+            plays,
+            time,
+        }
+    }
+
+    fn hour_plays(hour: i64, plays: i64, time: i64) -> HourPlays {
+        HourPlays { hour, plays, time }
+    }
+
+    #[test]
+    fn day_and_hour() {
+        assert_eq!(day_hour(0), (String::from("1970-01-01"), 0));
+        assert_eq!(day_hour(25), (String::from("1970-01-02"), 1));
+        // 1970年之前
+        assert_eq!(day_hour(-1), (String::from("1969-12-31"), 23));
+        assert_eq!(day_hour(-24), (String::from("1969-12-31"), 0));
+    }
+
+    #[test]
+    fn merge_days_and_hours() {
+        let (days, hours) = days_hours(&[
+            hour_plays(1, 1, 10),
+            hour_plays(23, 2, 0),
+            hour_plays(25, 1, 30),
+            hour_plays(47, 1, 5),
+        ]);
+        assert_eq!(
+            days,
+            vec![
+                DayStats {
+                    day: String::from("1970-01-01"),
+                    plays: 3,
+                    time: 10,
+                },
+                DayStats {
+                    day: String::from("1970-01-02"),
+                    plays: 2,
+                    time: 35,
+                },
+            ]
+        );
+        assert_eq!(hours.len(), 24);
+        assert_eq!((hours[1].plays, hours[1].time), (2, 40));
+        assert_eq!((hours[23].plays, hours[23].time), (3, 5));
+        assert_eq!((hours[0].plays, hours[0].time), (0, 0));
+    }
+
+    #[test]
+    fn no_plays() {
+        let (days, hours) = days_hours(&[]);
+        assert!(days.is_empty());
+        assert_eq!(hours.len(), 24);
+        assert!(hours.iter().all(|h| h.plays == 0 && h.time == 0));
+    }
+
+    #[test]
+    fn top() {
+        let stations = [
+            station("b", 1, 100),
+            station("a", 3, 100),
+            station("c", 3, 50),
+        ];
+        let names = |stats: Vec<StationStats>| -> Vec<String> {
+            stats.into_iter().map(|s| s.stationuuid).collect()
+        };
+        assert_eq!(
+            names(top_stations(&stations, 10, |s| (s.time, s.plays))),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            names(top_stations(&stations, 2, |s| (s.plays, s.time))),
+            vec!["a", "c"]
+        );
+        // 完全相同时按stationuuid
+        let same = [station("y", 1, 1), station("x", 1, 1)];
+        assert_eq!(
+            names(top_stations(&same, 1, |s| (s.time, s.plays))),
+            vec!["x"]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! 电台的评分和评论
+//!
+//! 每个用户对每个电台一条评论，重复评论覆盖原来的。只评分的直接通过，带内容的评论审核通过后才公开，
+//! 平均评分只统计审核通过的评论。已通过的评论被`PENDING_REPORTS`个用户举报后重新进入待审核。
+//! 评论者注销或关闭hiqradio后，其评论不再公开，也不计入平均评分。
+
+use crate::{
+    config::CONFIG,
+    errors::Error,
+    model::{
+        hiqradio::{
+            PublicReview, Rating, Review, REVIEW_STATUS_APPROVED, REVIEW_STATUS_PENDING,
+            REVIEW_STATUS_REJECTED,
+        },
+        user::User,
+    },
+    proto::{
+        ReviewsReq, REVIEWS_PAGE_MAX, REVIEWS_PAGE_SIZE, REVIEW_CONTENT_MAX, REVIEW_REASON_MAX,
+    },
+    repo::DynAppServRepo,
+    Result,
+};
+
+/// 已通过的评论被这么多用户举报后重新待审核
+pub const PENDING_REPORTS: i64 = 3;
+/// 分页的最大offset
+const OFFSET_MAX: i64 = 10000;
+
+/// 分页参数，返回offset和limit
+pub fn page(req: &ReviewsReq) -> Result<(i64, i64)> {
+    let offset = req.offset.unwrap_or_default();
+    if !(0..=OFFSET_MAX).contains(&offset) {
+        return Err(Error::Parse(format!("offset {}", offset)));
+    }
+    let limit = req
+        .limit
+        .unwrap_or(REVIEWS_PAGE_SIZE)
+        .clamp(1, REVIEWS_PAGE_MAX);
+    Ok((offset, limit))
+}
+
+/// 多查的一条用来判断是否还有下一页
+fn next_page(reviews: &mut Vec<Review>, offset: i64, limit: i64) -> Option<i64> {
+    if reviews.len() as i64 > limit {
+        reviews.truncate(limit as usize);
+        Some(offset + limit)
+    } else {
+        None
+    }
+}
+
+/// 评分和评论电台，返回保存后的评论
+pub async fn review(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    stationuuid: &str,
+    rating: i64,
+    content: &str,
+    now: i64,
+) -> Result<Review> {
+    let stationuuid = stationuuid.trim().to_ascii_lowercase();
+    if stationuuid.is_empty() {
+        return Err(Error::Parse(String::from("stationuuid")));
+    }
+    if !(1..=5).contains(&rating) {
+        return Err(Error::Parse(format!("rating {}", rating)));
+    }
+    let content = content.trim();
+    if content.chars().count() > REVIEW_CONTENT_MAX {
+        return Err(Error::Parse(format!(
+            "content longer than {}",
+            REVIEW_CONTENT_MAX
+        )));
+    }
+
+    let status = if content.is_empty() {
+        REVIEW_STATUS_APPROVED
+    } else {
+        REVIEW_STATUS_PENDING
+    };
+    repo.save_review(&Review {
+        id: None,
+        user_id,
+        user_name: String::new(),
+        stationuuid,
+        rating,
+        content: content.to_string(),
+        status: status.to_string(),
+        reports: 0,
+        create_time: now,
+        update_time: now,
+    })
+    .await
+}
+
+/// 电台的评分汇总和一页审核通过的评论，返回评分、评论和下一页的offset；
+/// 不需要登录，只返回公开的字段
+pub async fn reviews(
+    repo: &DynAppServRepo,
+    stationuuid: &str,
+    offset: i64,
+    limit: i64,
+) -> Result<(Rating, Vec<PublicReview>, Option<i64>)> {
+    let stationuuid = stationuuid.trim().to_ascii_lowercase();
+    let rating = repo
+        .query_ratings(std::slice::from_ref(&stationuuid))
+        .await?
+        .pop()
+        .unwrap_or(Rating {
+            stationuuid: stationuuid.clone(),
+            rating: 0.0,
+            ratings: 0,
+        });
+    let mut reviews = repo
+        .query_station_reviews(&stationuuid, offset, limit + 1)
+        .await?;
+    let next = next_page(&mut reviews, offset, limit);
+    Ok((
+        rating,
+        reviews.into_iter().map(PublicReview::from).collect(),
+        next,
+    ))
+}
+
+/// 举报审核通过的评论，不能举报自己的评论，返回评论的举报数
+pub async fn report(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    id: i64,
+    reason: &str,
+    now: i64,
+) -> Result<i64> {
+    let reason = reason.trim();
+    if reason.chars().count() > REVIEW_REASON_MAX {
+        return Err(Error::Parse(format!(
+            "reason longer than {}",
+            REVIEW_REASON_MAX
+        )));
+    }
+    match repo.query_review(id).await? {
+        Some(r) if r.status == REVIEW_STATUS_APPROVED && r.user_id != user_id => {}
+        Some(r) if r.user_id == user_id => {
+            return Err(Error::Custom(String::from("can not report own review")))
+        }
+        _ => return Err(Error::Custom(format!("review {} not exists", id))),
+    }
+    repo.report_review(id, user_id, reason, now, PENDING_REPORTS)
+        .await
+}
+
+/// 是否是评论审核员
+pub fn check_moderator(user: &User) -> Result {
+    if CONFIG
+        .review_moderators
+        .iter()
+        .any(|email| email.eq_ignore_ascii_case(&user.email))
+    {
+        Ok(())
+    } else {
+        Err(Error::Custom(String::from("permission denied")))
+    }
+}
+
+/// 一页待审核的评论，返回评论和下一页的offset
+pub async fn queue(
+    repo: &DynAppServRepo,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<Review>, Option<i64>)> {
+    let mut reviews = repo.query_pending_reviews(offset, limit + 1).await?;
+    let next = next_page(&mut reviews, offset, limit);
+    Ok((reviews, next))
+}
+
+/// 审核评论，status为approved或rejected
+pub async fn moderate(repo: &DynAppServRepo, id: i64, status: &str) -> Result {
+    if status != REVIEW_STATUS_APPROVED && status != REVIEW_STATUS_REJECTED {
+        return Err(Error::Parse(format!("status {}", status)));
+    }
+    if !repo.moderate_review(id, status).await? {
+        return Err(Error::Custom(format!("review {} not exists", id)));
+    }
+    Ok(())
+}
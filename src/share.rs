@@ -0,0 +1,104 @@
+//! 收藏分组的公开分享
+//!
+//! 分享使用不可猜测的token，持有token即可不登录只读访问分组，不返回分享者的信息。
+//! 分享跟随分组，分组改名后仍然有效，分组删除或取消分享后失效；分享者注销或关闭hiqradio后不再公开。
+
+use crate::{
+    catalog,
+    errors::Error,
+    model::hiqradio::{Share, Station},
+    playlist::{self, Entry, Imported},
+    repo::DynAppServRepo,
+    Result,
+};
+
+/// 分享的分组内容
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Shared {
+    pub name: String,
+    pub desc: String,
+    /// 按收藏顺序的stationuuid
+    pub favorites: Vec<String>,
+    /// 电台目录中有的电台
+    pub stations: Vec<Station>,
+}
+
+/// 分享分组，已分享的返回原来的token
+pub async fn share(repo: &DynAppServRepo, user_id: i64, group: &str) -> Result<Share> {
+    if !repo
+        .query_groups(user_id)
+        .await?
+        .iter()
+        .any(|g| g.name == group)
+    {
+        return Err(Error::Custom(format!("group {} not exists", group)));
+    }
+
+    repo.share_group(user_id, group, &Share::new_token()).await
+}
+
+/// 按token查询分享，token无效或已失效时报错
+async fn find(repo: &DynAppServRepo, token: &str) -> Result<Share> {
+    repo.query_share(token)
+        .await?
+        .ok_or_else(|| Error::Custom(String::from("share not exists")))
+}
+
+/// 查询分享的分组
+pub async fn shared(repo: &DynAppServRepo, token: &str) -> Result<Shared> {
+    let share = find(repo, token).await?;
+
+    let desc = repo
+        .query_groups(share.user_id)
+        .await?
+        .into_iter()
+        .find(|g| g.name == share.group_name)
+        .map(|g| g.desc)
+        .unwrap_or_default();
+    let favorites: Vec<_> = repo
+        .query_favorites(share.user_id)
+        .await?
+        .into_iter()
+        .filter(|f| f.group_name == share.group_name)
+        .map(|f| f.stationuuid)
+        .collect();
+    let stations = catalog::stations(repo, &favorites).await?;
+
+    Ok(Shared {
+        name: share.group_name,
+        desc,
+        favorites,
+        stations,
+    })
+}
+
+/// 复制分享的分组到用户的收藏，返回复制到的分组和导入结果
+pub async fn copy(
+    repo: &DynAppServRepo,
+    user_id: i64,
+    token: &str,
+    group: Option<&str>,
+    desc: Option<&str>,
+) -> Result<(String, Imported)> {
+    let shared = shared(repo, token).await?;
+
+    let group = group.unwrap_or(&shared.name).to_string();
+    let entries: Vec<_> = shared
+        .favorites
+        .into_iter()
+        .map(|stationuuid| Entry {
+            stationuuid: Some(stationuuid),
+            ..Default::default()
+        })
+        .collect();
+    let imported = playlist::import(
+        repo,
+        user_id,
+        &group,
+        desc.unwrap_or(&shared.desc),
+        &entries,
+    )
+    .await?;
+
+    Ok((group, imported))
+}
@@ -0,0 +1,225 @@
+//! 热门电台，反映本站用户的收听，不使用radio-browser的点击数
+//!
+//! 后台任务定期按日、周、月重建：统计周期内播放过电台的用户数、播放时长和新增收藏的用户数，
+//! 每个用户在一个电台上计入的时长最多`LISTEN_TIME_MAX`，避免少数用户长时间播放影响排名。
+
+use std::collections::HashMap;
+
+use crate::{
+    catalog,
+    errors::Error,
+    model::hiqradio::{Station, StationListen, TrendingStation, UserStation},
+    repo::DynAppServRepo,
+    Result,
+};
+
+/// 统计周期和时长(秒)
+pub const PERIODS: [(&str, i64); 3] = [("day", 86400), ("week", 7 * 86400), ("month", 30 * 86400)];
+
+/// 每个周期保留的热门电台数
+const TRENDING_MAX: usize = 200;
+/// 每个用户在一个电台上计入的播放时长(秒)
+const LISTEN_TIME_MAX: i64 = 4 * 3600;
+/// 每小时播放时长的得分，每个用户计1分
+const LISTEN_HOUR_WEIGHT: f64 = 0.5;
+/// 每个新增收藏的得分
+const FAVORITE_WEIGHT: f64 = 2.0;
+
+/// 周期的时长(秒)，不支持的周期报错
+pub fn period_duration(period: &str) -> Result<i64> {
+    PERIODS
+        .iter()
+        .find(|(name, _)| *name == period)
+        .map(|(_, duration)| *duration)
+        .ok_or_else(|| Error::Parse(format!("period {}", period)))
+}
+
+fn new_station(period: &str, stationuuid: &str, now: i64) -> TrendingStation {
+    TrendingStation {
+        period: period.to_string(),
+        stationuuid: stationuuid.to_string(),
+        listeners: 0,
+        listen_time: 0,
+        favorites: 0,
+        score: 0.0,
+        update_time: now,
+    }
+}
+
+/// 按用户的收听和新增收藏给电台打分，按得分从高到低保留前`TRENDING_MAX`个
+fn rank(
+    period: &str,
+    now: i64,
+    listens: &[StationListen],
+    favorites: &[UserStation],
+) -> Vec<TrendingStation> {
+    let mut stations: HashMap<String, TrendingStation> = HashMap::new();
+    for listen in listens {
+        let s = stations
+            .entry(listen.stationuuid.clone())
+            .or_insert_with(|| new_station(period, &listen.stationuuid, now));
+        s.listeners += 1;
+        s.listen_time += listen.listen_time.min(LISTEN_TIME_MAX);
+    }
+    for favorite in favorites {
+        stations
+            .entry(favorite.stationuuid.clone())
+            .or_insert_with(|| new_station(period, &favorite.stationuuid, now))
+            .favorites += 1;
+    }
+
+    let mut stations: Vec<_> = stations
+        .into_values()
+        .map(|mut s| {
+            s.score = s.listeners as f64
+                + s.listen_time as f64 / 3600.0 * LISTEN_HOUR_WEIGHT
+                + s.favorites as f64 * FAVORITE_WEIGHT;
+            s
+        })
+        .collect();
+    stations.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.stationuuid.cmp(&b.stationuuid))
+    });
+    stations.truncate(TRENDING_MAX);
+    stations
+}
+
+/// 重建各周期的热门电台，统计到now为止，返回各周期的电台数
+pub async fn build(repo: &DynAppServRepo, now: i64) -> Result<Vec<(&'static str, usize)>> {
+    let mut counts = Vec::new();
+    for (period, duration) in PERIODS {
+        let since = now - duration;
+        let stations = rank(
+            period,
+            now,
+            &repo.query_station_listens(since, now).await?,
+            &repo.query_new_favorites(since, now).await?,
+        );
+
+        repo.save_trending(period, &stations).await?;
+        counts.push((period, stations.len()));
+    }
+    Ok(counts)
+}
+
+/// 查询热门电台和电台目录中有的电台信息
+pub async fn query(
+    repo: &DynAppServRepo,
+    period: &str,
+    limit: i64,
+) -> Result<(Vec<TrendingStation>, Vec<Station>)> {
+    period_duration(period)?;
+    let trending = repo.query_trending(period, limit).await?;
+    let stations = catalog::stations(repo, trending.iter().map(|s| &s.stationuuid)).await?;
+    Ok((trending, stations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen(stationuuid: &str, user_id: i64, listen_time: i64) -> StationListen {
+        StationListen {
+            stationuuid: stationuuid.to_string(),
+            user_id,
+            listen_time,
+        }
+    }
+
+    fn favorite(stationuuid: &str, user_id: i64) -> UserStation {
+        UserStation {
+            user_id,
+            stationuuid: stationuuid.to_string(),
+            favorite: 1,
+        }
+    }
+
+    fn scores(stations: &[TrendingStation]) -> Vec<(&str, i64, i64, i64, f64)> {
+        stations
+            .iter()
+            .map(|s| {
+                (
+                    s.stationuuid.as_str(),
+                    s.listeners,
+                    s.listen_time,
+                    s.favorites,
+                    s.score,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn score() {
+        let stations = rank(
+            "day",
+            100,
+            &[
+                listen("a", 1, 3600),
+                listen("a", 2, 7200),
+                listen("b", 1, 0),
+            ],
+            &[favorite("b", 1), favorite("c", 2)],
+        );
+        // a: 2个用户 + 3小时 * 0.5；b: 1个用户 + 1个收藏 * 2；c: 1个收藏 * 2
+        assert_eq!(
+            scores(&stations),
+            vec![
+                ("a", 2, 10800, 0, 3.5),
+                ("b", 1, 0, 1, 3.0),
+                ("c", 0, 0, 1, 2.0),
+            ]
+        );
+        assert!(stations
+            .iter()
+            .all(|s| s.period == "day" && s.update_time == 100));
+    }
+
+    #[test]
+    fn listen_time_capped() {
+        // 一个用户长时间播放，计入的时长不超过LISTEN_TIME_MAX
+        let stations = rank(
+            "week",
+            0,
+            &[
+                listen("a", 1, 100 * 3600),
+                listen("b", 1, 3600),
+                listen("b", 2, 3600),
+            ],
+            &[],
+        );
+        assert_eq!(
+            scores(&stations),
+            vec![("a", 1, LISTEN_TIME_MAX, 0, 3.0), ("b", 2, 7200, 0, 3.0)]
+        );
+    }
+
+    #[test]
+    fn ties_and_limit() {
+        // 得分相同时按stationuuid
+        let stations = rank("day", 0, &[], &[favorite("b", 1), favorite("a", 1)]);
+        assert_eq!(
+            stations
+                .iter()
+                .map(|s| s.stationuuid.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+
+        let favorites: Vec<_> = (0..TRENDING_MAX + 10)
+            .map(|i| favorite(&format!("s{:04}", i), 1))
+            .collect();
+        let stations = rank("day", 0, &[], &favorites);
+        assert_eq!(stations.len(), TRENDING_MAX);
+        assert_eq!(stations[0].stationuuid, "s0000");
+        assert!(rank("day", 0, &[], &[]).is_empty());
+    }
+
+    #[test]
+    fn periods() {
+        assert_eq!(period_duration("week").unwrap(), 7 * 86400);
+        assert!(matches!(period_duration("year"), Err(Error::Parse(_))));
+    }
+}
@@ -13,7 +13,7 @@ pub fn send_email(receiver: String, subject: String, body: String) -> Result {
         return Err(Error::Internal("smtp configuration error".to_string()));
     }
     let sender_email = CONFIG.smtp_sender.clone().unwrap();
-    let name = sender_email.split("@").nth(0).unwrap().to_string();
+    let name = sender_email.split("@").next().unwrap().to_string();
     let sender = format!("{name} <{sender_email}>");
 
     let email = Message::builder()
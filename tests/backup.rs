@@ -0,0 +1,213 @@
+//! sqlite备份保留数量和恢复前的版本校验
+
+use std::{fs, path::Path, sync::Once};
+
+use appserv::{
+    backup,
+    errors::Error,
+    proto::SignUpReq,
+    repo::{self, migrate::SCHEMA_VERSION, DynAppServRepo},
+};
+
+static INIT: Once = Once::new();
+
+/// 使用测试专用配置，避免按命令行参数加载并改写工作目录下的config.toml
+fn init() {
+    INIT.call_once(|| {
+        std::env::set_var(
+            "APPSERV_CONFIG",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/conformance/config.toml"),
+        );
+    });
+}
+
+/// 每个用例使用独立的目录
+fn test_dir() -> String {
+    let dir = format!(
+        "{}/backup-{}",
+        env!("CARGO_TARGET_TMPDIR"),
+        nanoid::nanoid!(10)
+    );
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn open(db_url: &str) -> DynAppServRepo {
+    init();
+    repo::new(db_url).await.unwrap()
+}
+
+async fn signup(repo: &DynAppServRepo, email: &str) -> i64 {
+    repo.create_user(&SignUpReq {
+        product: String::from("hiqradio"),
+        email: email.to_string(),
+        passwd: String::from("passwd123"),
+        captcha: String::new(),
+        code: String::new(),
+    })
+    .await
+    .unwrap()
+    .id
+    .unwrap()
+}
+
+fn files(dir: &str) -> Vec<String> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn backup_keeps_latest() {
+    let dir = test_dir();
+    let db_url = format!("sqlite://{}/appserv.db", dir);
+    let repo = open(&db_url).await;
+    let user_id = signup(&repo, "backup@backup.test").await;
+
+    let backup_dir = format!("{}/backup", dir);
+    let mut paths = Vec::new();
+    for _ in 0..3 {
+        paths.push(
+            backup::backup(&repo, &db_url, &backup_dir, 2)
+                .await
+                .unwrap(),
+        );
+    }
+
+    assert_eq!(files(&backup_dir).len(), 2);
+    assert!(!paths[0].exists());
+    let latest = repo::connect(&format!("sqlite://{}", paths[2].display()))
+        .await
+        .unwrap();
+    assert_eq!(latest.schema_version().await.unwrap(), SCHEMA_VERSION);
+    assert_eq!(
+        latest.query_user(user_id).await.unwrap().email,
+        "backup@backup.test"
+    );
+
+    assert!(matches!(
+        backup::backup(&repo, "memory://", &backup_dir, 2).await,
+        Err(Error::Internal(_))
+    ));
+}
+
+/// `repo::connect`也接受`sqlite:`开头的连接串，备份和恢复使用同样的解析规则
+#[tokio::test]
+async fn backup_sqlite_colon_url() {
+    let dir = test_dir();
+    let db_url = format!("sqlite:{}/appserv.db", dir);
+    let repo = open(&db_url).await;
+    let user_id = signup(&repo, "colon@backup.test").await;
+
+    let backup_dir = format!("{}/backup", dir);
+    let path = backup::backup(&repo, &db_url, &backup_dir, 1)
+        .await
+        .unwrap();
+    assert!(path.starts_with(&backup_dir));
+    assert!(path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("appserv-"));
+    drop(repo);
+
+    assert_eq!(
+        backup::restore(&db_url, &path.to_string_lossy())
+            .await
+            .unwrap(),
+        SCHEMA_VERSION
+    );
+    let repo = open(&db_url).await;
+    assert_eq!(
+        repo.query_user(user_id).await.unwrap().email,
+        "colon@backup.test"
+    );
+    assert!(files(&dir).iter().any(|f| f.ends_with(".bak")));
+}
+
+#[tokio::test]
+async fn restore_swaps_database() {
+    let dir = test_dir();
+    let db_url = format!("sqlite://{}/appserv.db", dir);
+    let repo = open(&db_url).await;
+    let first = signup(&repo, "first@backup.test").await;
+    let path = backup::backup(&repo, &db_url, &format!("{}/backup", dir), 0)
+        .await
+        .unwrap();
+    let second = signup(&repo, "second@backup.test").await;
+    drop(repo);
+
+    assert_eq!(
+        backup::restore(&db_url, &path.to_string_lossy())
+            .await
+            .unwrap(),
+        SCHEMA_VERSION
+    );
+
+    let repo = open(&db_url).await;
+    assert_eq!(
+        repo.query_user(first).await.unwrap().email,
+        "first@backup.test"
+    );
+    assert!(repo.query_user(second).await.is_err());
+    assert!(path.exists());
+    assert!(files(&dir).iter().any(|f| f.ends_with(".bak")));
+}
+
+#[tokio::test]
+async fn restore_rejects_invalid_backup() {
+    let dir = test_dir();
+    let db_url = format!("sqlite://{}/appserv.db", dir);
+    let repo = open(&db_url).await;
+    let user_id = signup(&repo, "keep@backup.test").await;
+    let path = backup::backup(&repo, &db_url, &format!("{}/backup", dir), 0)
+        .await
+        .unwrap();
+
+    // 比程序新的数据库版本
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", path.display()))
+        .await
+        .unwrap();
+    sqlx::query(
+        "insert into schema_version(version, description, applied_time) values (?, 'future', 0)",
+    )
+    .bind(SCHEMA_VERSION + 1)
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+    assert!(matches!(
+        backup::restore(&db_url, &path.to_string_lossy()).await,
+        Err(Error::DatabaseException(_))
+    ));
+
+    // 不是appserv的数据库
+    let other = format!("{}/other.db", dir);
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}?mode=rwc", other))
+        .await
+        .unwrap();
+    sqlx::query("create table t(id integer)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+    assert!(backup::restore(&db_url, &other).await.is_err());
+
+    assert!(backup::restore(&db_url, &format!("{}/missing.db", dir))
+        .await
+        .is_err());
+
+    // 校验失败时数据库保持不变
+    assert_eq!(
+        repo.query_user(user_id).await.unwrap().email,
+        "keep@backup.test"
+    );
+    assert!(!files(&dir)
+        .iter()
+        .any(|f| f.contains(".restore") || f.ends_with(".bak")));
+    assert!(Path::new(&format!("{}/appserv.db", dir)).exists());
+}
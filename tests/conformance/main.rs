@@ -0,0 +1,119 @@
+//! AppServRepo后端一致性测试
+//!
+//! 同一组场景依次跑在每个后端上：memory和sqlite总是执行，
+//! postgres/mysql需设置`APPSERV_TEST_POSTGRES_URL`/`APPSERV_TEST_MYSQL_URL`，
+//! 未设置时跳过。新增后端只需在文件末尾加一行`conformance!`。
+
+mod scenarios;
+
+use std::sync::Once;
+
+use appserv::repo::{
+    self,
+    migrate::{MYSQL_MIGRATIONS, SCHEMA_VERSION},
+    DynAppServRepo,
+};
+use sqlx::Executor;
+
+static INIT: Once = Once::new();
+
+/// 使用测试专用配置，避免按命令行参数加载并改写工作目录下的config.toml
+fn init() {
+    INIT.call_once(|| {
+        std::env::set_var(
+            "APPSERV_CONFIG",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/conformance/config.toml"),
+        );
+    });
+}
+
+/// 每个sqlite用例使用独立的数据库文件
+fn sqlite_url() -> Option<String> {
+    Some(format!(
+        "sqlite://{}/conformance-{}.db",
+        env!("CARGO_TARGET_TMPDIR"),
+        nanoid::nanoid!(10)
+    ))
+}
+
+async fn open(url: Option<String>) -> Option<DynAppServRepo> {
+    init();
+    let url = url?;
+    Some(repo::new(&url).await.unwrap())
+}
+
+macro_rules! conformance {
+    ($backend:ident, $url:expr, [$($scenario:ident),* $(,)?]) => {
+        mod $backend {
+            $(
+                #[tokio::test]
+                async fn $scenario() {
+                    if let Some(repo) = super::open($url).await {
+                        super::scenarios::$scenario(repo).await;
+                    }
+                }
+            )*
+        }
+    };
+    ($backend:ident, $url:expr) => {
+        conformance!(
+            $backend,
+            $url,
+            [
+                schema,
+                signup_signin,
+                reset_and_update_user,
+                session_refresh_and_expiry,
+                recently,
+                groups,
+                group_delete_cascades_to_favorites,
+                favorites,
+                modify_favorite_regroups,
+                query_sync_filters,
+                clean_avatar_path,
+                tombstones_record_removals,
+                apply_sync_is_atomic,
+                push_sync_resolves_conflicts,
+                archive_export_import,
+                cancel_user_purges_after_grace,
+                close_and_reopen_product,
+                recently_page_and_filter,
+                recently_retention,
+                listening_stats,
+                playlist_export_import,
+                station_catalog,
+                station_search,
+                reorder_groups_and_favorites,
+                share_group_copy_revoke,
+                share_hidden_after_cancel_and_close,
+                station_recommendations,
+                trending_stations,
+                station_reviews,
+                presence_heartbeat,
+            ]
+        );
+    };
+}
+
+conformance!(memory, Some(String::from("memory://")));
+conformance!(sqlite, super::sqlite_url());
+conformance!(postgres, std::env::var("APPSERV_TEST_POSTGRES_URL").ok());
+conformance!(mysql, std::env::var("APPSERV_TEST_MYSQL_URL").ok());
+
+/// mysql的ddl会隐式提交，迁移中途失败后会重新执行整个迁移，已执行过的迁移再次执行不能报错
+#[tokio::test]
+async fn mysql_migrations_rerun() {
+    let Some(url) = std::env::var("APPSERV_TEST_MYSQL_URL").ok() else {
+        return;
+    };
+    let repo = open(Some(url.clone())).await.unwrap();
+    repo.migrate().await.unwrap();
+
+    let pool = sqlx::MySqlPool::connect(&url).await.unwrap();
+    for m in MYSQL_MIGRATIONS {
+        if let Err(e) = pool.execute(m.sql).await {
+            panic!("rerun migration {} error: {}", m.version, e);
+        }
+    }
+    assert_eq!(repo.schema_version().await.unwrap(), SCHEMA_VERSION);
+}
@@ -0,0 +1,3098 @@
+//! 与后端无关的场景，只通过AppServRepo接口读写
+
+use std::time::Duration;
+
+use appserv::{
+    catalog,
+    config::CONFIG,
+    errors::Error,
+    model::{
+        hiqradio::{
+            FavGroup, Presence, PublicReview, Recently, Review, Setting, Station, StationGroup,
+            Tombstone, TrendingStation, REVIEW_STATUS_APPROVED, REVIEW_STATUS_PENDING,
+            REVIEW_STATUS_REJECTED, TOMBSTONE_FAVORITE_DELETE, TOMBSTONE_GROUP_DELETE,
+            TOMBSTONE_GROUP_MODIFY, TOMBSTONE_RECENTLY_CLEAR,
+        },
+        user::{User, USER_STATUS_NORMAL},
+        user_product::USER_PRODUCT_STATUS_NORMAL,
+    },
+    playlist::{self, Format},
+    presence,
+    proto::{
+        Archive, GroupNew, PresenceReq, RecentlyFilter, RecentlyNew, RecentlyReq, Recommendation,
+        ReorderReq, ResetPasswdReq, SearchReq, SignInReq, SignUpReq, StationFilter, StationStats,
+        SyncOp, ARCHIVE_FORMAT, ARCHIVE_VERSION, PRESENCE_DEVICE_MAX, RECENTLY_PAGE_MAX,
+        RECENTLY_PAGE_SIZE, REVIEW_CONTENT_MAX, SEARCH_OFFSET_MAX, SEARCH_PAGE_MAX,
+        SEARCH_TERMS_MAX, SYNC_OP_APPLIED, SYNC_OP_CONFLICT, SYNC_OP_IGNORED,
+    },
+    recommend,
+    repo::{
+        archive::{self, Imported},
+        merge,
+        migrate::SCHEMA_VERSION,
+        order, stats, DynAppServRepo,
+    },
+    review, share, trending,
+    util::gen_passwd,
+};
+use chrono::{FixedOffset, Local};
+
+const PRODUCT: &str = "hiqradio";
+const PASSWD: &str = "passwd123";
+
+fn new_email() -> String {
+    format!("{}@conformance.test", nanoid::nanoid!(12))
+}
+
+fn signup_req(product: &str, email: &str) -> SignUpReq {
+    SignUpReq {
+        product: product.to_string(),
+        email: email.to_string(),
+        passwd: PASSWD.to_string(),
+        captcha: String::new(),
+        code: String::new(),
+    }
+}
+
+fn signin_req(product: &str, email: &str, passwd: &str, product_open_flag: bool) -> SignInReq {
+    SignInReq {
+        product: product.to_string(),
+        email: email.to_string(),
+        passwd: passwd.to_string(),
+        captcha: String::new(),
+        product_open_flag,
+    }
+}
+
+async fn new_user(repo: &DynAppServRepo) -> User {
+    repo.create_user(&signup_req(PRODUCT, &new_email()))
+        .await
+        .unwrap()
+}
+
+fn group(name: &str, create_time: i64, is_def: i64) -> GroupNew {
+    GroupNew {
+        create_time,
+        name: name.to_string(),
+        desc: format!("{} desc", name),
+        is_def,
+    }
+}
+
+fn station(group_name: &str, stationuuid: &str, create_time: i64) -> StationGroup {
+    StationGroup {
+        group_name: group_name.to_string(),
+        stationuuid: stationuuid.to_string(),
+        create_time,
+        position: 0,
+    }
+}
+
+fn recently_new(stationuuid: &str, start_time: i64, end_time: Option<i64>) -> RecentlyNew {
+    RecentlyNew {
+        stationuuid: stationuuid.to_string(),
+        start_time,
+        end_time,
+    }
+}
+
+fn group_names(groups: &[FavGroup]) -> Vec<String> {
+    let mut names: Vec<_> = groups.iter().map(|g| g.name.clone()).collect();
+    names.sort();
+    names
+}
+
+/// (分组, 电台)，已排序
+fn favorite_pairs(favorites: &[StationGroup]) -> Vec<(String, String)> {
+    let mut pairs: Vec<_> = favorites
+        .iter()
+        .map(|f| (f.group_name.clone(), f.stationuuid.clone()))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(g, s)| (g.to_string(), s.to_string()))
+        .collect()
+}
+
+/// (类型, 分组, 电台, 新分组名)，按记录顺序
+fn tombstone_list(tombstones: &[Tombstone]) -> Vec<(String, String, String, String)> {
+    tombstones
+        .iter()
+        .map(|t| {
+            (
+                t.kind.clone(),
+                t.group_name.clone(),
+                t.stationuuid.clone(),
+                t.new_name.clone(),
+            )
+        })
+        .collect()
+}
+
+fn tombstones(tombstones: &[(&str, &str, &str, &str)]) -> Vec<(String, String, String, String)> {
+    tombstones
+        .iter()
+        .map(|(k, g, s, n)| (k.to_string(), g.to_string(), s.to_string(), n.to_string()))
+        .collect()
+}
+
+pub async fn schema(repo: DynAppServRepo) {
+    assert_eq!(repo.schema_version().await.unwrap(), SCHEMA_VERSION);
+    // 重复迁移无副作用
+    repo.migrate().await.unwrap();
+    assert_eq!(repo.schema_version().await.unwrap(), SCHEMA_VERSION);
+}
+
+pub async fn signup_signin(repo: DynAppServRepo) {
+    let email = new_email();
+    let user = repo
+        .create_user(&signup_req(PRODUCT, &email))
+        .await
+        .unwrap();
+    let user_id = user.id.unwrap();
+    assert_eq!(user.email, email);
+    assert_eq!(user.user_name, email.split('@').next().unwrap());
+    assert_eq!(user.passwd, gen_passwd(&email, PASSWD));
+    assert_eq!(user.status, USER_STATUS_NORMAL);
+
+    assert!(matches!(
+        repo.create_user(&signup_req(PRODUCT, &email)).await,
+        Err(Error::UserExists(_))
+    ));
+    assert!(matches!(
+        repo.create_user(&signup_req("no-such-product", &new_email()))
+            .await,
+        Err(Error::ProductNotExists)
+    ));
+
+    assert!(matches!(
+        repo.signin_user(&signin_req(PRODUCT, &new_email(), PASSWD, false))
+            .await,
+        Err(Error::UserNotExists)
+    ));
+    assert!(matches!(
+        repo.signin_user(&signin_req(PRODUCT, &email, "wrong", false))
+            .await,
+        Err(Error::UserPasswdError)
+    ));
+
+    let (signin_user, product, session) = repo
+        .signin_user(&signin_req(PRODUCT, &email, PASSWD, false))
+        .await
+        .unwrap();
+    let product_id = product.id.unwrap();
+    assert_eq!(signin_user.id, Some(user_id));
+    assert_eq!(product.product, PRODUCT);
+    assert_eq!(session.user_id, user_id);
+    assert_eq!(session.product_id, product_id);
+    assert!(session.expire > Local::now().timestamp_millis());
+
+    // 已开通的产品带open标志登录不会重复开通
+    let (_, product2, session2) = repo
+        .signin_user(&signin_req(PRODUCT, &email, PASSWD, true))
+        .await
+        .unwrap();
+    assert_eq!(product2.id, Some(product_id));
+    assert_ne!(session2.token, session.token);
+    assert_eq!(repo.query_user_products(user_id).await.unwrap().len(), 1);
+
+    assert!(matches!(
+        repo.signin_user(&signin_req("no-such-product", &email, PASSWD, false))
+            .await,
+        Err(Error::ProductNotOpen)
+    ));
+    assert!(matches!(
+        repo.signin_user(&signin_req("no-such-product", &email, PASSWD, true))
+            .await,
+        Err(Error::ProductNotExists)
+    ));
+
+    // 开通产品
+    repo.open_product(user_id, PRODUCT).await.unwrap();
+    assert_eq!(repo.query_user_products(user_id).await.unwrap().len(), 1);
+    assert!(matches!(
+        repo.open_product(user_id, "no-such-product").await,
+        Err(Error::ProductNotExists)
+    ));
+
+    // 查询
+    assert_eq!(repo.query_user(user_id).await.unwrap().email, email);
+    assert!(repo
+        .query_products()
+        .await
+        .unwrap()
+        .iter()
+        .any(|p| p.product == PRODUCT));
+    assert_eq!(
+        repo.query_product(product_id).await.unwrap().product,
+        PRODUCT
+    );
+    let user_product = repo.query_user_product(user_id, product_id).await.unwrap();
+    assert_eq!(user_product.status, USER_PRODUCT_STATUS_NORMAL);
+    assert!(matches!(
+        repo.query_user(-1).await,
+        Err(Error::UserNotExists)
+    ));
+    assert!(matches!(
+        repo.query_product(-1).await,
+        Err(Error::ProductNotExists)
+    ));
+    assert!(matches!(
+        repo.query_user_product(user_id, -1).await,
+        Err(Error::ProductNotOpen)
+    ));
+}
+
+pub async fn reset_and_update_user(repo: DynAppServRepo) {
+    let user = new_user(&repo).await;
+    let user_id = user.id.unwrap();
+
+    repo.reset_user_passwd(&ResetPasswdReq {
+        email: user.email.clone(),
+        passwd: String::from("newpasswd"),
+        captcha: String::new(),
+        code: String::new(),
+    })
+    .await
+    .unwrap();
+    assert!(matches!(
+        repo.signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+            .await,
+        Err(Error::UserPasswdError)
+    ));
+    let (_, product, _) = repo
+        .signin_user(&signin_req(PRODUCT, &user.email, "newpasswd", false))
+        .await
+        .unwrap();
+    let product_id = product.id.unwrap();
+
+    assert!(matches!(
+        repo.reset_user_passwd(&ResetPasswdReq {
+            email: new_email(),
+            passwd: String::from("newpasswd"),
+            captcha: String::new(),
+            code: String::new(),
+        })
+        .await,
+        Err(Error::UserNotExists)
+    ));
+
+    let passwd = gen_passwd(&user.email, "another");
+    repo.update_user_info(
+        user_id,
+        product_id,
+        Some(String::from("alice")),
+        Some(passwd.clone()),
+        Some(String::from("alice.png")),
+    )
+    .await
+    .unwrap();
+    let updated = repo.query_user(user_id).await.unwrap();
+    assert_eq!(updated.user_name, "alice");
+    assert_eq!(updated.passwd, passwd);
+    assert_eq!(
+        repo.query_user_product(user_id, product_id)
+            .await
+            .unwrap()
+            .avatar,
+        "alice.png"
+    );
+
+    // 只改部分字段
+    repo.update_user_info(user_id, product_id, Some(String::from("bob")), None, None)
+        .await
+        .unwrap();
+    let updated = repo.query_user(user_id).await.unwrap();
+    assert_eq!(updated.user_name, "bob");
+    assert_eq!(updated.passwd, passwd);
+    assert_eq!(
+        repo.query_user_product(user_id, product_id)
+            .await
+            .unwrap()
+            .avatar,
+        "alice.png"
+    );
+}
+
+pub async fn session_refresh_and_expiry(repo: DynAppServRepo) {
+    let user = new_user(&repo).await;
+    let signin = signin_req(PRODUCT, &user.email, PASSWD, false);
+    let (_, _, session) = repo.signin_user(&signin).await.unwrap();
+    let (_, _, idle) = repo.signin_user(&signin).await.unwrap();
+    let (_, _, deleted) = repo.signin_user(&signin).await.unwrap();
+
+    assert!(matches!(
+        repo.get_session("no-such-token").await,
+        Err(Error::TokenInvalid)
+    ));
+
+    repo.delete_session(&deleted.token).await.unwrap();
+    assert!(matches!(
+        repo.get_session(&deleted.token).await,
+        Err(Error::TokenInvalid)
+    ));
+
+    // 远未过期时不刷新
+    let got = repo.get_session(&session.token).await.unwrap();
+    assert_eq!(got.expire, session.expire);
+    assert_eq!(got.user_id, user.id.unwrap());
+
+    // 进入刷新窗口后延长过期时间
+    let refresh = CONFIG.token_expire * 1000 - CONFIG.token_refresh * 1000;
+    tokio::time::sleep(Duration::from_millis(refresh as u64 + 200)).await;
+    let refreshed = repo.get_session(&session.token).await.unwrap();
+    assert!(refreshed.expire > session.expire);
+    let got = repo.get_session(&session.token).await.unwrap();
+    assert_eq!(got.expire, refreshed.expire);
+
+    // 过期后失效
+    tokio::time::sleep(Duration::from_millis(
+        CONFIG.token_expire as u64 * 1000 + 200,
+    ))
+    .await;
+    assert!(matches!(
+        repo.get_session(&session.token).await,
+        Err(Error::TokenInvalid)
+    ));
+    assert!(matches!(
+        repo.get_session(&session.token).await,
+        Err(Error::TokenInvalid)
+    ));
+
+    // 定时清理过期会话
+    repo.clean_session().await.unwrap();
+    assert!(matches!(
+        repo.get_session(&idle.token).await,
+        Err(Error::TokenInvalid)
+    ));
+}
+
+pub async fn recently(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other_id = new_user(&repo).await.id.unwrap();
+
+    repo.new_recently(
+        user_id,
+        &[
+            recently_new("s1", 100, None),
+            recently_new("s2", 200, Some(250)),
+            recently_new("s1", 300, None),
+        ],
+    )
+    .await
+    .unwrap();
+    repo.new_recently(other_id, &[recently_new("s1", 100, None)])
+        .await
+        .unwrap();
+
+    let recently = repo.query_recently(user_id).await.unwrap();
+    let start_times: Vec<_> = recently.iter().map(|r| r.start_time).collect();
+    assert_eq!(start_times, vec![300, 200, 100]);
+    assert!(recently.iter().all(|r| r.user_id == user_id));
+    assert_eq!(recently[1].end_time, Some(250));
+
+    repo.modify_recently(user_id, "s1", 300, 360).await.unwrap();
+    let recently = repo.query_recently(user_id).await.unwrap();
+    assert_eq!(recently[0].end_time, Some(360));
+    assert_eq!(recently[2].end_time, None);
+
+    repo.delete_recently(user_id).await.unwrap();
+    assert!(repo.query_recently(user_id).await.unwrap().is_empty());
+    assert_eq!(repo.query_recently(other_id).await.unwrap().len(), 1);
+}
+
+pub async fn groups(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other_id = new_user(&repo).await.id.unwrap();
+
+    repo.new_groups(user_id, &[group("默认", 100, 1), group("rock", 110, 0)])
+        .await
+        .unwrap();
+    repo.new_groups(other_id, &[group("rock", 110, 0)])
+        .await
+        .unwrap();
+    let groups = repo.query_groups(user_id).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["rock", "默认"]);
+    assert!(groups.iter().all(|g| g.user_id == user_id));
+
+    // 同名分组忽略
+    repo.new_groups(
+        user_id,
+        &[GroupNew {
+            desc: String::from("ignored"),
+            ..group("rock", 120, 0)
+        }],
+    )
+    .await
+    .unwrap();
+    let groups = repo.query_groups(user_id).await.unwrap();
+    assert_eq!(groups.len(), 2);
+    let rock = groups.iter().find(|g| g.name == "rock").unwrap();
+    assert_eq!(rock.desc, "rock desc");
+    assert_eq!(rock.create_time, 110);
+
+    // 更早的默认分组忽略，更新的默认分组替换原有默认分组
+    repo.new_groups(user_id, &[group("old default", 50, 1)])
+        .await
+        .unwrap();
+    assert_eq!(
+        group_names(&repo.query_groups(user_id).await.unwrap()),
+        vec!["rock", "默认"]
+    );
+    repo.new_groups(user_id, &[group("new default", 200, 1)])
+        .await
+        .unwrap();
+    let groups = repo.query_groups(user_id).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["new default", "rock"]);
+    assert_eq!(groups.iter().filter(|g| g.is_def == 1).count(), 1);
+
+    repo.modify_group(user_id, "rock", "metal", "heavy")
+        .await
+        .unwrap();
+    let groups = repo.query_groups(user_id).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["metal", "new default"]);
+    assert_eq!(
+        groups.iter().find(|g| g.name == "metal").unwrap().desc,
+        "heavy"
+    );
+    assert_eq!(
+        group_names(&repo.query_groups(other_id).await.unwrap()),
+        vec!["rock"]
+    );
+}
+
+pub async fn group_delete_cascades_to_favorites(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other_id = new_user(&repo).await.id.unwrap();
+
+    repo.new_groups(user_id, &[group("a", 100, 0), group("b", 100, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(
+        user_id,
+        &[
+            station("a", "s1", 100),
+            station("a", "s2", 100),
+            station("b", "s3", 100),
+        ],
+    )
+    .await
+    .unwrap();
+    repo.new_groups(other_id, &[group("a", 100, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(other_id, &[station("a", "s1", 100)])
+        .await
+        .unwrap();
+
+    repo.delete_groups(user_id, &[String::from("a")])
+        .await
+        .unwrap();
+    assert_eq!(
+        group_names(&repo.query_groups(user_id).await.unwrap()),
+        vec!["b"]
+    );
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(user_id).await.unwrap()),
+        pairs(&[("b", "s3")])
+    );
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(other_id).await.unwrap()),
+        pairs(&[("a", "s1")])
+    );
+
+    assert!(repo
+        .delete_groups(user_id, &[String::from("missing")])
+        .await
+        .is_err());
+}
+
+pub async fn favorites(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other_id = new_user(&repo).await.id.unwrap();
+
+    repo.new_groups(user_id, &[group("a", 100, 0), group("b", 100, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("a", "s1", 10), station("b", "s2", 20)])
+        .await
+        .unwrap();
+    // 重复收藏忽略
+    repo.new_favorite(user_id, &[station("a", "s1", 30)])
+        .await
+        .unwrap();
+    assert!(repo
+        .new_favorite(user_id, &[station("missing", "s9", 30)])
+        .await
+        .is_err());
+    repo.new_groups(other_id, &[group("a", 100, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(other_id, &[station("a", "s1", 10)])
+        .await
+        .unwrap();
+
+    let favorites = repo.query_favorites(user_id).await.unwrap();
+    assert_eq!(
+        favorite_pairs(&favorites),
+        pairs(&[("a", "s1"), ("b", "s2")])
+    );
+    let s1 = favorites.iter().find(|f| f.stationuuid == "s1").unwrap();
+    assert_eq!(s1.create_time, 10);
+
+    repo.delete_favorite(user_id, &Some(vec![String::from("s1")]), &None)
+        .await
+        .unwrap();
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(user_id).await.unwrap()),
+        pairs(&[("b", "s2")])
+    );
+
+    repo.new_favorite(user_id, &[station("a", "s3", 40)])
+        .await
+        .unwrap();
+    repo.delete_favorite(user_id, &None, &Some(vec![String::from("b")]))
+        .await
+        .unwrap();
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(user_id).await.unwrap()),
+        pairs(&[("a", "s3")])
+    );
+    // 分组本身保留
+    assert_eq!(
+        group_names(&repo.query_groups(user_id).await.unwrap()),
+        vec!["a", "b"]
+    );
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(other_id).await.unwrap()),
+        pairs(&[("a", "s1")])
+    );
+}
+
+pub async fn modify_favorite_regroups(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+
+    repo.new_groups(
+        user_id,
+        &[group("a", 100, 0), group("b", 100, 0), group("c", 100, 0)],
+    )
+    .await
+    .unwrap();
+    repo.new_favorite(user_id, &[station("a", "s1", 10), station("a", "s2", 10)])
+        .await
+        .unwrap();
+
+    let now = Local::now().timestamp();
+    repo.modify_favorite(user_id, "s1", &[String::from("b"), String::from("c")])
+        .await
+        .unwrap();
+    let favorites = repo.query_favorites(user_id).await.unwrap();
+    assert_eq!(
+        favorite_pairs(&favorites),
+        pairs(&[("a", "s2"), ("b", "s1"), ("c", "s1")])
+    );
+    assert!(favorites
+        .iter()
+        .filter(|f| f.stationuuid == "s1")
+        .all(|f| f.create_time >= now));
+
+    assert!(repo
+        .modify_favorite(user_id, "missing", &[String::from("a")])
+        .await
+        .is_err());
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(user_id).await.unwrap()),
+        pairs(&[("a", "s2"), ("b", "s1"), ("c", "s1")])
+    );
+}
+
+pub async fn query_sync_filters(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other_id = new_user(&repo).await.id.unwrap();
+    assert_eq!(repo.query_revision(user_id).await.unwrap(), 0);
+
+    for id in [user_id, other_id] {
+        repo.new_groups(id, &[group("old", 100, 0)]).await.unwrap();
+        repo.new_favorite(id, &[station("old", "s1", 100)])
+            .await
+            .unwrap();
+        repo.new_recently(id, &[recently_new("s1", 100, None)])
+            .await
+            .unwrap();
+    }
+    let r1 = repo.query_revision(user_id).await.unwrap();
+    assert!(r1 > 0);
+
+    repo.new_groups(user_id, &[group("new", 50, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("new", "s2", 50)])
+        .await
+        .unwrap();
+    repo.modify_recently(user_id, "s1", 100, 150).await.unwrap();
+    let r2 = repo.query_revision(user_id).await.unwrap();
+    assert!(r2 > r1);
+    // 其他用户的修改不影响版本号
+    repo.new_groups(other_id, &[group("other", 300, 0)])
+        .await
+        .unwrap();
+    assert_eq!(repo.query_revision(user_id).await.unwrap(), r2);
+
+    // 按版本号过滤，与客户端时间无关
+    let (groups, recently, favorites) = repo.query_sync(user_id, r1, r2).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["new"]);
+    assert!(groups.iter().all(|g| g.user_id == user_id));
+    assert_eq!(recently.len(), 1);
+    assert_eq!(recently[0].stationuuid, "s1");
+    assert_eq!(recently[0].end_time, Some(150));
+    assert_eq!(recently[0].user_id, user_id);
+    assert_eq!(favorite_pairs(&favorites), pairs(&[("new", "s2")]));
+
+    let (groups, recently, favorites) = repo.query_sync(user_id, 0, r2).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["new", "old"]);
+    assert_eq!(recently.len(), 1);
+    assert!(recently.iter().all(|r| r.user_id == user_id));
+    assert_eq!(
+        favorite_pairs(&favorites),
+        pairs(&[("new", "s2"), ("old", "s1")])
+    );
+
+    let (groups, recently, favorites) = repo.query_sync(user_id, r2, r2).await.unwrap();
+    assert!(groups.is_empty() && recently.is_empty() && favorites.is_empty());
+
+    // 修改分组后进入新的版本区间
+    repo.modify_group(user_id, "old", "renamed", "desc")
+        .await
+        .unwrap();
+    let r3 = repo.query_revision(user_id).await.unwrap();
+    assert!(r3 > r2);
+    let (groups, recently, _) = repo.query_sync(user_id, r2, r3).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["renamed"]);
+    assert!(recently.is_empty());
+}
+
+pub async fn clean_avatar_path(repo: DynAppServRepo) {
+    let user = new_user(&repo).await;
+    let (_, product, _) = repo
+        .signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+        .await
+        .unwrap();
+
+    let used = format!("{}.png", nanoid::nanoid!(12));
+    let unused = format!("{}.png", nanoid::nanoid!(12));
+    for name in [&used, &unused] {
+        std::fs::write(format!("{}/{}", CONFIG.avatar_path, name), b"png").unwrap();
+    }
+    repo.update_user_info(
+        user.id.unwrap(),
+        product.id.unwrap(),
+        None,
+        None,
+        Some(used.clone()),
+    )
+    .await
+    .unwrap();
+
+    repo.clean_avatar_path(&used).await.unwrap();
+    repo.clean_avatar_path(&unused).await.unwrap();
+    assert!(std::path::Path::new(&format!("{}/{}", CONFIG.avatar_path, used)).exists());
+    assert!(!std::path::Path::new(&format!("{}/{}", CONFIG.avatar_path, unused)).exists());
+}
+
+pub async fn tombstones_record_removals(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other_id = new_user(&repo).await.id.unwrap();
+
+    repo.new_groups(
+        user_id,
+        &[
+            group("def", 100, 1),
+            group("a", 100, 0),
+            group("b", 100, 0),
+            group("c", 100, 0),
+        ],
+    )
+    .await
+    .unwrap();
+    repo.new_favorite(
+        user_id,
+        &[
+            station("a", "s1", 100),
+            station("a", "s2", 100),
+            station("b", "s3", 100),
+        ],
+    )
+    .await
+    .unwrap();
+    repo.new_recently(user_id, &[recently_new("s1", 100, None)])
+        .await
+        .unwrap();
+    let start = repo.query_revision(user_id).await.unwrap();
+    assert!(repo
+        .query_tombstones(user_id, 0, start)
+        .await
+        .unwrap()
+        .is_empty());
+
+    repo.delete_favorite(user_id, &Some(vec![String::from("s1")]), &None)
+        .await
+        .unwrap();
+    repo.delete_favorite(user_id, &None, &Some(vec![String::from("b")]))
+        .await
+        .unwrap();
+    repo.modify_favorite(user_id, "s2", &[String::from("c")])
+        .await
+        .unwrap();
+    repo.modify_group(user_id, "c", "d", "d desc")
+        .await
+        .unwrap();
+    repo.modify_group(user_id, "missing", "e", "")
+        .await
+        .unwrap();
+    repo.delete_groups(user_id, &[String::from("a")])
+        .await
+        .unwrap();
+    repo.new_groups(user_id, &[group("new def", 200, 1)])
+        .await
+        .unwrap();
+    repo.delete_recently(user_id).await.unwrap();
+
+    let expected = tombstones(&[
+        (TOMBSTONE_FAVORITE_DELETE, "a", "s1", ""),
+        (TOMBSTONE_FAVORITE_DELETE, "b", "s3", ""),
+        (TOMBSTONE_FAVORITE_DELETE, "a", "s2", ""),
+        (TOMBSTONE_GROUP_MODIFY, "c", "", "d"),
+        (TOMBSTONE_GROUP_DELETE, "a", "", ""),
+        (TOMBSTONE_GROUP_DELETE, "def", "", ""),
+        (TOMBSTONE_RECENTLY_CLEAR, "", "", ""),
+    ]);
+    let end = repo.query_revision(user_id).await.unwrap();
+    let got = repo.query_tombstones(user_id, 0, end).await.unwrap();
+    assert_eq!(tombstone_list(&got), expected);
+    assert!(got
+        .iter()
+        .all(|t| t.user_id == user_id && t.revision > start && t.revision <= end));
+    assert_eq!(got[3].new_desc, "d desc");
+
+    assert_eq!(
+        tombstone_list(&repo.query_tombstones(user_id, start, end).await.unwrap()),
+        expected
+    );
+    assert!(repo
+        .query_tombstones(user_id, end, end)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(repo
+        .query_tombstones(other_id, 0, end)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // 重新创建后删除记录作废，清空最近播放只保留最后一次
+    repo.new_favorite(user_id, &[station("b", "s3", 300)])
+        .await
+        .unwrap();
+    repo.new_groups(user_id, &[group("a", 300, 0)])
+        .await
+        .unwrap();
+    repo.delete_recently(user_id).await.unwrap();
+    let end = repo.query_revision(user_id).await.unwrap();
+    assert_eq!(
+        tombstone_list(&repo.query_tombstones(user_id, 0, end).await.unwrap()),
+        tombstones(&[
+            (TOMBSTONE_FAVORITE_DELETE, "a", "s1", ""),
+            (TOMBSTONE_FAVORITE_DELETE, "a", "s2", ""),
+            (TOMBSTONE_GROUP_MODIFY, "c", "", "d"),
+            (TOMBSTONE_GROUP_DELETE, "def", "", ""),
+            (TOMBSTONE_RECENTLY_CLEAR, "", "", ""),
+        ])
+    );
+}
+
+fn op_group_new(time: i64, name: &str) -> SyncOp {
+    SyncOp::GroupNew {
+        time,
+        name: name.to_string(),
+        desc: String::new(),
+        is_def: 0,
+    }
+}
+
+fn op_favorite_new(time: i64, group_name: &str, stationuuid: &str) -> SyncOp {
+    SyncOp::FavoriteNew {
+        time,
+        group_name: group_name.to_string(),
+        stationuuid: stationuuid.to_string(),
+    }
+}
+
+pub async fn apply_sync_is_atomic(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_groups(user_id, &[group("a", 100, 0)])
+        .await
+        .unwrap();
+    let revision = repo.query_revision(user_id).await.unwrap();
+
+    // 版本号已变化
+    assert_eq!(
+        repo.apply_sync(user_id, revision - 1, &[op_group_new(200, "b")])
+            .await
+            .unwrap(),
+        None
+    );
+    // 中途失败整批回滚
+    assert!(repo
+        .apply_sync(
+            user_id,
+            revision,
+            &[
+                op_group_new(200, "b"),
+                op_favorite_new(200, "missing", "s1"),
+            ],
+        )
+        .await
+        .is_err());
+    assert_eq!(repo.query_revision(user_id).await.unwrap(), revision);
+    assert_eq!(
+        group_names(&repo.query_groups(user_id).await.unwrap()),
+        vec!["a"]
+    );
+
+    let ops = [
+        op_group_new(200, "b"),
+        op_favorite_new(200, "b", "s1"),
+        op_favorite_new(200, "a", "s1"),
+        SyncOp::FavoriteModify {
+            time: 300,
+            stationuuid: String::from("s1"),
+            group_names: vec![String::from("b")],
+        },
+        SyncOp::GroupModify {
+            time: 300,
+            old_name: String::from("b"),
+            name: String::from("c"),
+            desc: String::from("c desc"),
+        },
+        SyncOp::RecentlyNew {
+            stationuuid: String::from("s1"),
+            start_time: 300,
+            end_time: None,
+        },
+        SyncOp::RecentlyModify {
+            stationuuid: String::from("s1"),
+            start_time: 300,
+            end_time: 360,
+        },
+        SyncOp::GroupDelete {
+            time: 400,
+            name: String::from("a"),
+        },
+    ];
+    let new_revision = repo
+        .apply_sync(user_id, revision, &ops)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(new_revision, revision + 1);
+    assert_eq!(repo.query_revision(user_id).await.unwrap(), new_revision);
+
+    let (groups, recently, favorites) = repo
+        .query_sync(user_id, revision, new_revision)
+        .await
+        .unwrap();
+    assert_eq!(group_names(&groups), vec!["c"]);
+    assert_eq!(favorite_pairs(&favorites), pairs(&[("c", "s1")]));
+    assert_eq!(recently.len(), 1);
+    assert_eq!(recently[0].end_time, Some(360));
+
+    // 删除记录使用客户端操作时间
+    let got = repo
+        .query_tombstones(user_id, revision, new_revision)
+        .await
+        .unwrap();
+    assert_eq!(
+        tombstone_list(&got),
+        tombstones(&[
+            (TOMBSTONE_FAVORITE_DELETE, "a", "s1", ""),
+            (TOMBSTONE_GROUP_MODIFY, "b", "", "c"),
+            (TOMBSTONE_GROUP_DELETE, "a", "", ""),
+        ])
+    );
+    assert_eq!(
+        got.iter().map(|t| t.create_time).collect::<Vec<_>>(),
+        vec![300, 300, 400]
+    );
+    assert!(got.iter().all(|t| t.revision == new_revision));
+}
+
+pub async fn push_sync_resolves_conflicts(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_groups(user_id, &[group("a", 100, 0), group("b", 100, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("a", "s1", 100), station("b", "s2", 100)])
+        .await
+        .unwrap();
+    let base = repo.query_revision(user_id).await.unwrap();
+
+    // 客户端离线期间其他设备的修改
+    let now = Local::now().timestamp();
+    repo.delete_favorite(user_id, &Some(vec![String::from("s1")]), &None)
+        .await
+        .unwrap();
+    repo.modify_group(user_id, "b", "c", "").await.unwrap();
+
+    let ops = vec![
+        op_favorite_new(now - 100, "a", "s1"),
+        op_favorite_new(now + 100, "a", "s3"),
+        SyncOp::GroupModify {
+            time: now - 100,
+            old_name: String::from("b"),
+            name: String::from("x"),
+            desc: String::new(),
+        },
+        op_group_new(now + 100, "d"),
+        op_favorite_new(now + 100, "d", "s4"),
+        SyncOp::GroupDelete {
+            time: now + 100,
+            name: String::from("missing"),
+        },
+        SyncOp::RecentlyNew {
+            stationuuid: String::from("s4"),
+            start_time: now + 100,
+            end_time: None,
+        },
+        SyncOp::RecentlyNew {
+            stationuuid: String::from("s4"),
+            start_time: now + 100,
+            end_time: None,
+        },
+    ];
+    let before = repo.query_revision(user_id).await.unwrap();
+    let (revision, results) = merge::push_sync(&repo, user_id, base, &ops).await.unwrap();
+    assert_eq!(
+        results,
+        vec![
+            SYNC_OP_CONFLICT,
+            SYNC_OP_APPLIED,
+            SYNC_OP_CONFLICT,
+            SYNC_OP_APPLIED,
+            SYNC_OP_APPLIED,
+            SYNC_OP_IGNORED,
+            SYNC_OP_APPLIED,
+            SYNC_OP_IGNORED,
+        ]
+    );
+    assert_eq!(revision, before + 1);
+
+    assert_eq!(
+        group_names(&repo.query_groups(user_id).await.unwrap()),
+        vec!["a", "c", "d"]
+    );
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(user_id).await.unwrap()),
+        pairs(&[("a", "s3"), ("c", "s2"), ("d", "s4")])
+    );
+    assert_eq!(repo.query_recently(user_id).await.unwrap().len(), 1);
+
+    // 后写者胜：更晚的操作覆盖服务端的修改
+    let (_, results) = merge::push_sync(
+        &repo,
+        user_id,
+        base,
+        &[op_favorite_new(now + 100, "a", "s1")],
+    )
+    .await
+    .unwrap();
+    assert_eq!(results, vec![SYNC_OP_APPLIED]);
+
+    // 重复提交不产生新版本
+    let current = repo.query_revision(user_id).await.unwrap();
+    let (revision, results) = merge::push_sync(&repo, user_id, current, &ops[1..2])
+        .await
+        .unwrap();
+    assert_eq!(results, vec![SYNC_OP_IGNORED]);
+    assert_eq!(revision, current);
+}
+
+pub async fn archive_export_import(repo: DynAppServRepo) {
+    let source = new_user(&repo).await;
+    let source_id = source.id.unwrap();
+    let (_, product, _) = repo
+        .signin_user(&signin_req(PRODUCT, &source.email, PASSWD, false))
+        .await
+        .unwrap();
+    let product_id = product.id.unwrap();
+    let avatar = format!("{}.png", nanoid::nanoid!(12));
+    std::fs::write(format!("{}/{}", CONFIG.avatar_path, avatar), b"avatar png").unwrap();
+    repo.update_user_info(source_id, product_id, None, None, Some(avatar.clone()))
+        .await
+        .unwrap();
+    repo.new_groups(source_id, &[group("def", 1000, 1), group("rock", 1001, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(
+        source_id,
+        &[station("def", "s1", 1002), station("rock", "s2", 1003)],
+    )
+    .await
+    .unwrap();
+    repo.new_recently(
+        source_id,
+        &[
+            recently_new("s1", 2000, Some(2100)),
+            recently_new("s2", 3000, None),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let exported = archive::export(&repo, source_id).await.unwrap();
+    assert_eq!(exported.format, ARCHIVE_FORMAT);
+    assert_eq!(exported.version, ARCHIVE_VERSION);
+    assert_eq!(exported.user.email, source.email);
+    assert_eq!(exported.products.len(), 1);
+    assert_eq!(exported.products[0].product, PRODUCT);
+    assert_eq!(exported.products[0].avatar.as_ref().unwrap().name, avatar);
+    assert_eq!(exported.hiqradio.groups.len(), 2);
+    assert_eq!(exported.hiqradio.favorites.len(), 2);
+    assert_eq!(exported.hiqradio.recently.len(), 2);
+
+    // 经过JSON往返，与下载后再上传一致
+    let exported: Archive =
+        serde_json::from_str(&serde_json::to_string(&exported).unwrap()).unwrap();
+
+    // 目标账号已有默认分组、同名分组和相同收藏
+    let target_id = new_user(&repo).await.id.unwrap();
+    repo.new_groups(target_id, &[group("mine", 500, 1), group("rock", 600, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(target_id, &[station("rock", "s2", 700)])
+        .await
+        .unwrap();
+
+    let imported = archive::import(&repo, target_id, &exported).await.unwrap();
+    assert_eq!(
+        imported,
+        Imported {
+            products: 0,
+            avatars: 1,
+            groups: 1,
+            favorites: 1,
+            recently: 2,
+        }
+    );
+
+    let groups = repo.query_groups(target_id).await.unwrap();
+    assert_eq!(group_names(&groups), vec!["def", "mine", "rock"]);
+    let defaults: Vec<_> = groups
+        .iter()
+        .filter(|g| g.is_def == 1)
+        .map(|g| g.name.as_str())
+        .collect();
+    assert_eq!(defaults, vec!["mine"]);
+    assert_eq!(
+        favorite_pairs(&repo.query_favorites(target_id).await.unwrap()),
+        pairs(&[("def", "s1"), ("rock", "s2")])
+    );
+    assert_eq!(repo.query_recently(target_id).await.unwrap().len(), 2);
+
+    let target_avatar = repo
+        .query_user_product(target_id, product_id)
+        .await
+        .unwrap()
+        .avatar;
+    assert_ne!(target_avatar, avatar);
+    assert!(target_avatar.ends_with(".png"));
+    assert_eq!(
+        std::fs::read(format!("{}/{}", CONFIG.avatar_path, target_avatar)).unwrap(),
+        b"avatar png"
+    );
+
+    // 重复导入不产生重复数据
+    assert_eq!(
+        archive::import(&repo, target_id, &exported).await.unwrap(),
+        Imported::default()
+    );
+
+    let mut newer = exported;
+    newer.version = ARCHIVE_VERSION + 1;
+    assert!(matches!(
+        archive::import(&repo, target_id, &newer).await,
+        Err(Error::Parse(_))
+    ));
+}
+
+pub async fn cancel_user_purges_after_grace(repo: DynAppServRepo) {
+    let user = new_user(&repo).await;
+    let user_id = user.id.unwrap();
+    let (_, product, session) = repo
+        .signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+        .await
+        .unwrap();
+    let product_id = product.id.unwrap();
+    let avatar = format!("{}.png", nanoid::nanoid!(12));
+    std::fs::write(format!("{}/{}", CONFIG.avatar_path, avatar), b"png").unwrap();
+    repo.update_user_info(user_id, product_id, None, None, Some(avatar.clone()))
+        .await
+        .unwrap();
+    repo.new_groups(user_id, &[group("def", 1000, 1)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("def", "s1", 1001)])
+        .await
+        .unwrap();
+    repo.new_recently(user_id, &[recently_new("s1", 2000, None)])
+        .await
+        .unwrap();
+    repo.modify_setting(&Setting {
+        recently_days: 7,
+        ..Setting::new(user_id)
+    })
+    .await
+    .unwrap();
+    let other = new_user(&repo).await.id.unwrap();
+    repo.new_groups(other, &[group("other", 1000, 1)])
+        .await
+        .unwrap();
+    let token = share::share(&repo, user_id, "def").await.unwrap().token;
+    let reviewed = dump_uuid();
+    repo.save_stations(&[catalog_station(&reviewed, "cancelled radio", "", 0)])
+        .await
+        .unwrap();
+    review::review(&repo, user_id, &reviewed, 5, "", 1000)
+        .await
+        .unwrap();
+    review::review(&repo, other, &reviewed, 1, "", 1001)
+        .await
+        .unwrap();
+    let (rating, reviews, _) = review::reviews(&repo, &reviewed, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings, reviews.len()), (3.0, 2, 2));
+
+    repo.cancel_user(user_id).await.unwrap();
+    assert!(matches!(
+        repo.query_user(user_id).await,
+        Err(Error::UserNotExists)
+    ));
+    assert!(matches!(
+        repo.signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+            .await,
+        Err(Error::UserNotExists)
+    ));
+    assert!(repo.get_session(&session.token).await.is_err());
+    assert!(matches!(
+        repo.cancel_user(user_id).await,
+        Err(Error::UserNotExists)
+    ));
+    assert!(matches!(
+        repo.create_user(&signup_req(PRODUCT, &user.email)).await,
+        Err(Error::UserExists(_))
+    ));
+    assert!(matches!(
+        repo.reset_user_passwd(&ResetPasswdReq {
+            email: user.email.clone(),
+            passwd: String::from("new-passwd"),
+            captcha: String::new(),
+            code: String::new(),
+        })
+        .await,
+        Err(Error::UserNotExists)
+    ));
+
+    // 注销后分享和评论立即不再公开，评论不计入评分
+    assert!(repo.query_share(&token).await.unwrap().is_none());
+    assert!(matches!(
+        share::shared(&repo, &token).await,
+        Err(Error::Custom(_))
+    ));
+    let (rating, reviews, _) = review::reviews(&repo, &reviewed, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (1.0, 1));
+    assert_eq!(
+        reviews.iter().map(|r| r.rating).collect::<Vec<_>>(),
+        vec![1]
+    );
+    let stations = catalog::stations(&repo, [&reviewed]).await.unwrap();
+    assert_eq!((stations[0].rating, stations[0].ratings), (1.0, 1));
+
+    // 宽限期内数据保留，共享数据库中可能有其他用例注销的用户，不检查数量
+    let now = Local::now().timestamp();
+    repo.purge_cancelled_users(now - 3600).await.unwrap();
+    assert_eq!(repo.query_groups(user_id).await.unwrap().len(), 1);
+    assert_eq!(repo.query_recently(user_id).await.unwrap().len(), 1);
+
+    assert!(repo.purge_cancelled_users(now + 1).await.unwrap() >= 1);
+    assert!(repo.query_groups(user_id).await.unwrap().is_empty());
+    assert!(repo.query_favorites(user_id).await.unwrap().is_empty());
+    assert!(repo.query_recently(user_id).await.unwrap().is_empty());
+    assert_eq!(repo.query_revision(user_id).await.unwrap(), 0);
+    assert_eq!(
+        repo.query_setting(user_id).await.unwrap(),
+        Setting::new(user_id)
+    );
+    assert_eq!(
+        group_names(&repo.query_groups(other).await.unwrap()),
+        vec!["other"]
+    );
+    assert!(repo
+        .query_user_product(user_id, product_id)
+        .await
+        .unwrap()
+        .avatar
+        .is_empty());
+    repo.clean_avatar_path(&avatar).await.unwrap();
+    assert!(!std::path::Path::new(&format!("{}/{}", CONFIG.avatar_path, avatar)).exists());
+
+    // 匿名化后邮箱可以重新注册
+    let again = repo
+        .create_user(&signup_req(PRODUCT, &user.email))
+        .await
+        .unwrap();
+    assert_ne!(again.id, user.id);
+    repo.signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+        .await
+        .unwrap();
+}
+
+pub async fn close_and_reopen_product(repo: DynAppServRepo) {
+    let user = new_user(&repo).await;
+    let user_id = user.id.unwrap();
+    let (_, product, session) = repo
+        .signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+        .await
+        .unwrap();
+    let product_id = product.id.unwrap();
+    let user_product_id = repo
+        .query_user_product(user_id, product_id)
+        .await
+        .unwrap()
+        .id;
+    repo.update_user_info(user_id, product_id, None, None, Some(String::from("a.png")))
+        .await
+        .unwrap();
+    repo.new_groups(user_id, &[group("def", 1000, 1)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("def", "s1", 1001)])
+        .await
+        .unwrap();
+    repo.new_recently(user_id, &[recently_new("s1", 2000, None)])
+        .await
+        .unwrap();
+
+    // 保留数据关闭
+    repo.close_product(user_id, PRODUCT, false).await.unwrap();
+    assert!(repo.query_user_products(user_id).await.unwrap().is_empty());
+    assert!(matches!(
+        repo.query_user_product(user_id, product_id).await,
+        Err(Error::ProductNotOpen)
+    ));
+    assert!(repo.get_session(&session.token).await.is_err());
+    assert!(matches!(
+        repo.signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+            .await,
+        Err(Error::ProductNotOpen)
+    ));
+    assert!(matches!(
+        repo.close_product(user_id, PRODUCT, false).await,
+        Err(Error::ProductNotOpen)
+    ));
+    assert_eq!(repo.query_groups(user_id).await.unwrap().len(), 1);
+
+    // 重新开通恢复原记录
+    repo.open_product(user_id, PRODUCT).await.unwrap();
+    let user_product = repo.query_user_product(user_id, product_id).await.unwrap();
+    assert_eq!(user_product.id, user_product_id);
+    assert_eq!(user_product.avatar, "a.png");
+    assert_eq!(repo.query_user_products(user_id).await.unwrap().len(), 1);
+
+    // 清理数据关闭，关闭后不再同步，删除记录和版本号一并删除
+    repo.delete_favorite(user_id, &Some(vec![String::from("s1")]), &None)
+        .await
+        .unwrap();
+    repo.modify_setting(&Setting {
+        recently_days: 7,
+        ..Setting::new(user_id)
+    })
+    .await
+    .unwrap();
+    repo.share_group(user_id, "def", "close-product-share")
+        .await
+        .unwrap();
+    review::review(&repo, user_id, UUID_A, 5, "", 1000)
+        .await
+        .unwrap();
+    assert!(repo.query_revision(user_id).await.unwrap() > 0);
+    repo.close_product(user_id, PRODUCT, true).await.unwrap();
+    assert!(repo.query_groups(user_id).await.unwrap().is_empty());
+    assert!(repo.query_favorites(user_id).await.unwrap().is_empty());
+    assert!(repo.query_recently(user_id).await.unwrap().is_empty());
+    assert_eq!(
+        repo.query_setting(user_id).await.unwrap(),
+        Setting::new(user_id)
+    );
+    assert!(repo.query_shares(user_id).await.unwrap().is_empty());
+    assert!(repo.query_user_reviews(user_id).await.unwrap().is_empty());
+    assert_eq!(repo.query_revision(user_id).await.unwrap(), 0);
+    assert!(repo
+        .query_tombstones(user_id, 0, i64::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // 登录时开通也恢复原记录
+    repo.signin_user(&signin_req(PRODUCT, &user.email, PASSWD, true))
+        .await
+        .unwrap();
+    let user_product = repo.query_user_product(user_id, product_id).await.unwrap();
+    assert_eq!(user_product.id, user_product_id);
+    assert!(user_product.avatar.is_empty());
+}
+
+fn recently_filter(after: Option<(i64, i64)>, limit: i64) -> RecentlyFilter {
+    RecentlyFilter {
+        after,
+        since: None,
+        until: None,
+        stationuuid: None,
+        limit,
+    }
+}
+
+fn recently_keys(recently: &[Recently]) -> Vec<(String, i64)> {
+    recently
+        .iter()
+        .map(|r| (r.stationuuid.clone(), r.start_time))
+        .collect()
+}
+
+pub async fn recently_page_and_filter(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_recently(
+        user_id,
+        &[
+            recently_new("s1", 100, Some(150)),
+            recently_new("s2", 200, Some(250)),
+            recently_new("s1", 300, None),
+            recently_new("s2", 300, None),
+            recently_new("s1", 400, None),
+        ],
+    )
+    .await
+    .unwrap();
+    let all = repo.query_recently(user_id).await.unwrap();
+
+    // 按页读取，start_time相同的记录也不会重复或遗漏
+    let mut pages = Vec::new();
+    let mut after = None;
+    loop {
+        let page = repo
+            .query_recently_page(user_id, &recently_filter(after, 2))
+            .await
+            .unwrap();
+        assert!(page.len() <= 2);
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = RecentlyFilter::parse_cursor(&RecentlyFilter::cursor(last));
+        pages.extend(page);
+    }
+    assert_eq!(pages.len(), 5);
+    let times: Vec<_> = pages.iter().map(|r| r.start_time).collect();
+    assert_eq!(times, vec![400, 300, 300, 200, 100]);
+    let mut ids: Vec<_> = pages.iter().map(|r| r.id.unwrap()).collect();
+    let mut all_ids: Vec<_> = all.iter().map(|r| r.id.unwrap()).collect();
+    ids.sort();
+    all_ids.sort();
+    assert_eq!(ids, all_ids);
+
+    // 时间范围为[since, until)
+    let mut filter = recently_filter(None, 100);
+    filter.since = Some(200);
+    filter.until = Some(400);
+    let page = repo.query_recently_page(user_id, &filter).await.unwrap();
+    assert_eq!(page.len(), 3);
+    assert!(page.iter().all(|r| (200..400).contains(&r.start_time)));
+
+    // 按电台过滤
+    filter.stationuuid = Some(String::from("s1"));
+    let page = repo.query_recently_page(user_id, &filter).await.unwrap();
+    assert_eq!(recently_keys(&page), vec![(String::from("s1"), 300)]);
+    filter.since = None;
+    filter.until = None;
+    let page = repo.query_recently_page(user_id, &filter).await.unwrap();
+    assert_eq!(
+        recently_keys(&page),
+        vec![
+            (String::from("s1"), 400),
+            (String::from("s1"), 300),
+            (String::from("s1"), 100)
+        ]
+    );
+    assert_eq!(page[2].end_time, Some(150));
+
+    // 其他用户的记录不会返回
+    let other = new_user(&repo).await.id.unwrap();
+    assert!(repo
+        .query_recently_page(other, &recently_filter(None, 100))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // 请求参数转换
+    let filter = RecentlyFilter::try_from(&RecentlyReq {
+        cursor: Some(String::from("300-12")),
+        limit: Some(100000),
+        stationuuid: Some(String::new()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(filter.after, Some((300, 12)));
+    assert_eq!(filter.limit, RECENTLY_PAGE_MAX);
+    assert_eq!(filter.stationuuid, None);
+    assert_eq!(
+        RecentlyFilter::try_from(&RecentlyReq::default())
+            .unwrap()
+            .limit,
+        RECENTLY_PAGE_SIZE
+    );
+    assert!(matches!(
+        RecentlyFilter::try_from(&RecentlyReq {
+            cursor: Some(String::from("bad")),
+            ..Default::default()
+        }),
+        Err(Error::Parse(_))
+    ));
+}
+
+fn recently_times(recently: &[Recently]) -> Vec<i64> {
+    recently.iter().map(|r| r.start_time).collect()
+}
+
+/// 清空最近播放的时间
+async fn recently_cleared(repo: &DynAppServRepo, user_id: i64) -> Vec<i64> {
+    let revision = repo.query_revision(user_id).await.unwrap();
+    repo.query_tombstones(user_id, 0, revision)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|t| t.kind == TOMBSTONE_RECENTLY_CLEAR)
+        .map(|t| t.create_time)
+        .collect()
+}
+
+pub async fn recently_retention(repo: DynAppServRepo) {
+    // 时间都取负数，按时间清理时不影响共享数据库中其他场景的记录
+    const DAY: i64 = 24 * 3600;
+    let now = -1000 * DAY;
+
+    let user_id = new_user(&repo).await.id.unwrap();
+    let setting = repo.query_setting(user_id).await.unwrap();
+    assert_eq!(setting, Setting::new(user_id));
+    repo.new_recently(
+        user_id,
+        &[
+            recently_new("s1", now - 400 * DAY, None),
+            recently_new("s1", now - 100 * DAY, None),
+            recently_new("s1", now - 10 * DAY, None),
+            recently_new("s1", now, None),
+        ],
+    )
+    .await
+    .unwrap();
+
+    // 用户设置更短的保留天数
+    let short = new_user(&repo).await.id.unwrap();
+    assert!(!repo
+        .modify_setting(&Setting {
+            recently_days: 30,
+            ..Setting::new(short)
+        })
+        .await
+        .unwrap());
+    let setting = repo.query_setting(short).await.unwrap();
+    assert_eq!(setting.recently_days, 30);
+    assert!(setting.update_time > 0);
+    repo.new_recently(
+        short,
+        &[
+            recently_new("s1", now - 100 * DAY, None),
+            recently_new("s1", now - 10 * DAY, None),
+        ],
+    )
+    .await
+    .unwrap();
+
+    // 用户关闭了最近播放
+    let disabled = new_user(&repo).await.id.unwrap();
+    repo.new_recently(disabled, &[recently_new("s1", now, None)])
+        .await
+        .unwrap();
+    let off = Setting {
+        recently_enabled: 0,
+        ..Setting::new(disabled)
+    };
+    // 关闭时同时清空并记录清空
+    assert!(repo.modify_setting(&off).await.unwrap());
+    assert!(repo.query_recently(disabled).await.unwrap().is_empty());
+    let cleared = recently_cleared(&repo, disabled).await;
+    assert_eq!(cleared.len(), 1);
+    assert!(cleared[0] > 0);
+    assert!(!repo.modify_setting(&off).await.unwrap());
+    // 关闭前写入的记录
+    repo.new_recently(disabled, &[recently_new("s1", now, None)])
+        .await
+        .unwrap();
+
+    let count = repo.clean_recently(now, 365, 0).await.unwrap();
+    assert!(count >= 3);
+    // 清空时间为截止时间，已有更晚的清空记录时沿用
+    assert_eq!(
+        recently_cleared(&repo, user_id).await,
+        vec![now - 365 * DAY]
+    );
+    assert_eq!(recently_cleared(&repo, short).await, vec![now - 30 * DAY]);
+    assert_eq!(recently_cleared(&repo, disabled).await, cleared);
+    assert_eq!(
+        recently_times(&repo.query_recently(user_id).await.unwrap()),
+        vec![now, now - 10 * DAY, now - 100 * DAY]
+    );
+    assert_eq!(
+        recently_times(&repo.query_recently(short).await.unwrap()),
+        vec![now - 10 * DAY]
+    );
+    assert!(repo.query_recently(disabled).await.unwrap().is_empty());
+
+    // 天数为0不按时间清理
+    repo.clean_recently(now + 1000 * DAY, 0, 0).await.unwrap();
+    assert_eq!(repo.query_recently(user_id).await.unwrap().len(), 3);
+
+    // 每个用户只保留最新的条数，其他场景每个用户的记录都少于20条
+    let many = new_user(&repo).await.id.unwrap();
+    let recently: Vec<_> = (0..25).map(|i| recently_new("s2", now + i, None)).collect();
+    repo.new_recently(many, &recently).await.unwrap();
+    assert!(repo.clean_recently(now, 0, 20).await.unwrap() >= 5);
+    let times = recently_times(&repo.query_recently(many).await.unwrap());
+    assert_eq!(times, (5..25).rev().map(|i| now + i).collect::<Vec<_>>());
+    assert_eq!(recently_cleared(&repo, many).await, vec![now + 5]);
+    assert_eq!(repo.query_recently(user_id).await.unwrap().len(), 3);
+
+    // 关闭后离线同步的播放记录不写入
+    let revision = repo.query_revision(disabled).await.unwrap();
+    let ops = vec![SyncOp::RecentlyNew {
+        stationuuid: String::from("s1"),
+        start_time: Local::now().timestamp(),
+        end_time: None,
+    }];
+    let (_, results) = merge::push_sync(&repo, disabled, revision, &ops)
+        .await
+        .unwrap();
+    assert_eq!(results, vec![SYNC_OP_IGNORED]);
+    assert!(repo.query_recently(disabled).await.unwrap().is_empty());
+}
+
+fn station_stats(stats: &[StationStats]) -> Vec<(&str, i64, i64)> {
+    stats
+        .iter()
+        .map(|s| (s.stationuuid.as_str(), s.plays, s.time))
+        .collect()
+}
+
+pub async fn listening_stats(repo: DynAppServRepo) {
+    // 2024-01-01 00:00:00 UTC
+    const BASE: i64 = 1704067200;
+    const DAY: i64 = 24 * 3600;
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_recently(
+        user_id,
+        &[
+            recently_new("s1", BASE + 3600, Some(BASE + 3600 + 1800)),
+            recently_new("s2", BASE + 7200, Some(BASE + 7200 + 3000)),
+            recently_new("s1", BASE + DAY + 7200, Some(BASE + DAY + 7200 + 600)),
+            recently_new("s3", BASE + DAY + 3600, None),
+            recently_new("s3", BASE + 2 * DAY, Some(BASE + 2 * DAY + 9000)),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let stats = stats::listening_stats(&repo, user_id, Some(BASE), Some(BASE + 2 * DAY), utc, 2)
+        .await
+        .unwrap();
+    assert_eq!(stats.plays, 4);
+    assert_eq!(stats.time, 5400);
+    assert_eq!(stats.stations, 3);
+    assert_eq!(
+        station_stats(&stats.top_by_time),
+        vec![("s2", 1, 3000), ("s1", 2, 2400)]
+    );
+    assert_eq!(
+        station_stats(&stats.top_by_plays),
+        vec![("s1", 2, 2400), ("s2", 1, 3000)]
+    );
+    let days: Vec<_> = stats
+        .days
+        .iter()
+        .map(|d| (d.day.as_str(), d.plays, d.time))
+        .collect();
+    assert_eq!(days, vec![("2024-01-01", 2, 4800), ("2024-01-02", 2, 600)]);
+    assert_eq!(stats.hours.len(), 24);
+    let hours: Vec<_> = stats
+        .hours
+        .iter()
+        .filter(|h| h.plays > 0)
+        .map(|h| (h.hour, h.plays, h.time))
+        .collect();
+    assert_eq!(hours, vec![(1, 2, 1800), (2, 2, 3600)]);
+    assert_eq!(
+        recently_keys(&stats.longest),
+        vec![
+            (String::from("s2"), BASE + 7200),
+            (String::from("s1"), BASE + 3600)
+        ]
+    );
+
+    // 按客户端时区统计
+    let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+    let stats = stats::listening_stats(&repo, user_id, None, None, east8, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.plays, 5);
+    assert_eq!(stats.longest.len(), 4);
+    assert_eq!(stats.days.last().unwrap().day, "2024-01-03");
+    assert_eq!(stats.hours[9].plays, 2);
+    assert_eq!(stats.hours[8].time, 9000);
+
+    // 时区偏移不是整小时，本地时间在前一天
+    let west = FixedOffset::west_opt(9 * 3600 + 1800).unwrap();
+    let stats = stats::listening_stats(&repo, user_id, Some(BASE), Some(BASE + DAY), west, 10)
+        .await
+        .unwrap();
+    let days: Vec<_> = stats
+        .days
+        .iter()
+        .map(|d| (d.day.as_str(), d.plays, d.time))
+        .collect();
+    assert_eq!(days, vec![("2023-12-31", 2, 4800)]);
+    assert_eq!((stats.hours[15].plays, stats.hours[15].time), (1, 1800));
+    assert_eq!((stats.hours[16].plays, stats.hours[16].time), (1, 3000));
+
+    let other = new_user(&repo).await.id.unwrap();
+    let stats = stats::listening_stats(&repo, other, None, None, utc, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.plays, 0);
+    assert!(stats.days.is_empty() && stats.longest.is_empty());
+    assert!(stats.hours.iter().all(|h| h.plays == 0));
+}
+
+const UUID_A: &str = "960c0d8f-0601-11e8-ae97-52543be04c81";
+const UUID_B: &str = "96202f73-0601-11e8-ae97-52543be04c81";
+const UUID_C: &str = "a1e9d3b5-1d3c-4b6e-9f3e-6c1b5a6e2d10";
+
+async fn playlist_stations(repo: &DynAppServRepo, entries: &[playlist::Entry]) -> Vec<String> {
+    playlist::stations(repo, entries)
+        .await
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+pub async fn playlist_export_import(repo: DynAppServRepo) {
+    // a和b在电台目录中，c不在
+    let (a, b, c) = (dump_uuid(), dump_uuid(), dump_uuid());
+    let name_a = format!("playlist {}", &a[..8]);
+    let url_b = format!("http://stream.example.com/{}", &b[..8]);
+    repo.save_stations(&[
+        catalog_station(&a, &name_a, "", 0),
+        Station {
+            url: format!("{}/redirect", url_b),
+            url_resolved: url_b.clone(),
+            ..catalog_station(&b, "", "", 0)
+        },
+    ])
+    .await
+    .unwrap();
+
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_groups(
+        user_id,
+        &[group("def", 1000, 1), group("rock & roll", 1001, 0)],
+    )
+    .await
+    .unwrap();
+    repo.new_favorite(
+        user_id,
+        &[
+            station("def", &a, 1002),
+            station("def", &c, 1003),
+            station("rock & roll", &b, 1004),
+            station("rock & roll", &a, 1005),
+        ],
+    )
+    .await
+    .unwrap();
+
+    // 标题取目录中的名称，没有名称时用stationuuid
+    let m3u = playlist::export(&repo, user_id, Format::M3u, None)
+        .await
+        .unwrap();
+    assert!(m3u.contains(&format!(",{}\n", name_a)));
+    assert!(m3u.contains(&format!(",{}\n", b)));
+    assert!(m3u.contains("#EXTGRP:rock & roll\n"));
+
+    // 导出后能重新解析出电台，PLS不带stationuuid，地址中的stationuuid要目录中有
+    for format in [Format::M3u, Format::Pls, Format::Opml] {
+        let content = playlist::export(&repo, user_id, format, None)
+            .await
+            .unwrap();
+        assert_eq!(Format::detect(&content), format);
+        let entries = playlist::parse(None, &content).unwrap();
+        let expected = if format == Format::Pls {
+            vec![a.clone(), b.clone(), a.clone()]
+        } else {
+            vec![a.clone(), c.clone(), b.clone(), a.clone()]
+        };
+        assert_eq!(playlist_stations(&repo, &entries).await, expected);
+
+        let content = playlist::export(&repo, user_id, format, Some("rock & roll"))
+            .await
+            .unwrap();
+        let entries = playlist::parse(Some(format), &content).unwrap();
+        assert_eq!(
+            playlist_stations(&repo, &entries).await,
+            vec![b.clone(), a.clone()]
+        );
+    }
+    assert!(
+        playlist::export(&repo, user_id, Format::M3u, Some("missing"))
+            .await
+            .is_err()
+    );
+
+    // 其他应用的m3u按目录的播放地址和名称匹配，找不到电台的条目返回
+    let m3u = format!(
+        "#EXTM3U\n#EXTINF:-1,{}\nhttp://other.example.com/a\n\
+         #EXTINF:-1,B\n{}\n\
+         #EXTINF:-1 tvg-id=\"{}\",C\nhttp://stream.example.com/c\n\
+         #EXTINF:-1,Unknown\nhttps://de1.api.radio-browser.info/m3u/url/{}\n",
+        name_a,
+        url_b,
+        c,
+        dump_uuid().to_uppercase()
+    );
+    let entries = playlist::parse(None, &m3u).unwrap();
+    let imported = playlist::import(&repo, user_id, "imported", "from m3u", &entries)
+        .await
+        .unwrap();
+    assert!(imported.group_created);
+    assert_eq!(imported.favorites, 3);
+    assert_eq!(imported.unmatched, vec!["Unknown"]);
+    let favorites = favorite_pairs(&repo.query_favorites(user_id).await.unwrap());
+    for s in [&a, &b, &c] {
+        assert!(favorites.contains(&(String::from("imported"), s.clone())));
+    }
+    let groups = repo.query_groups(user_id).await.unwrap();
+    let imported_group = groups.iter().find(|g| g.name == "imported").unwrap();
+    assert_eq!(imported_group.desc, "from m3u");
+    assert_eq!(imported_group.is_def, 0);
+
+    // 导入已有分组时已收藏的电台不重复
+    let pls = format!(
+        "[playlist]\nFile1={}\nTitle1=B\nFile2=http://stream.example.com/x\nNumberOfEntries=2\nVersion=2\n",
+        url_b
+    );
+    let entries = playlist::parse(Some(Format::Pls), &pls).unwrap();
+    let imported = playlist::import(&repo, user_id, "rock & roll", "", &entries)
+        .await
+        .unwrap();
+    assert!(!imported.group_created);
+    assert_eq!(imported.favorites, 0);
+    assert_eq!(imported.unmatched, vec!["http://stream.example.com/x"]);
+
+    // opml导入到其他账号
+    let opml = playlist::export(&repo, user_id, Format::Opml, None)
+        .await
+        .unwrap();
+    let other = new_user(&repo).await.id.unwrap();
+    let entries = playlist::parse(Some(Format::Opml), &opml).unwrap();
+    let imported = playlist::import(&repo, other, "all", "", &entries)
+        .await
+        .unwrap();
+    assert_eq!(imported.favorites, 3);
+    assert!(imported.unmatched.is_empty());
+}
+
+fn dump_uuid() -> String {
+    const HEX: [char; 16] = [
+        '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
+    ];
+    let hex = nanoid::nanoid!(32, &HEX);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+pub async fn station_catalog(repo: DynAppServRepo) {
+    let (a, b, c) = (dump_uuid(), dump_uuid(), dump_uuid());
+    let time = Local::now().timestamp();
+
+    // json中null字段取默认值，大写uuid转小写，去掉重复的和没有uuid的
+    let json = format!(
+        r#"[
+            {{"stationuuid": "{}", "name": "Radio A", "url": "http://a", "tags": "rock,pop", "bitrate": 128, "hls": 0, "lastcheckok": 1, "votes": 10, "clickcount": 3}},
+            {{"stationuuid": "{}", "name": "Radio B", "url": null, "favicon": null, "bitrate": null, "extra": "x"}},
+            {{"stationuuid": "{}", "name": "Radio A again"}},
+            {{"name": "no uuid"}}
+        ]"#,
+        a.to_uppercase(),
+        b,
+        a
+    );
+    let stations = catalog::parse(&json, time).unwrap();
+    assert_eq!(stations.len(), 2);
+    assert_eq!(stations[0].stationuuid, a);
+    assert_eq!(stations[0].name, "Radio A");
+    assert_eq!(stations[0].tags, "rock,pop");
+    assert_eq!(stations[0].bitrate, 128);
+    assert_eq!(stations[0].votes, 10);
+    assert_eq!(stations[0].update_time, time);
+    assert_eq!(stations[1].url, "");
+    assert_eq!(stations[1].bitrate, 0);
+
+    // csv第一行为字段名，字段顺序不限
+    let csv = format!(
+        "\u{feff}name,stationuuid,url,bitrate,country\nRadio C,{},http://c,64,\"Germany, DE\"\n,,,,\n",
+        c
+    );
+    let csv_stations = catalog::parse(&csv, time).unwrap();
+    assert_eq!(csv_stations.len(), 1);
+    assert_eq!(csv_stations[0].stationuuid, c);
+    assert_eq!(csv_stations[0].country, "Germany, DE");
+    assert_eq!(csv_stations[0].bitrate, 64);
+    assert!(matches!(
+        catalog::parse("[{\"stationuuid\": 1", time),
+        Err(Error::Parse(_))
+    ));
+
+    let mut all = stations.clone();
+    all.extend(csv_stations.clone());
+    let imported = catalog::import(&repo, &all).await.unwrap();
+    assert_eq!(imported.stations, 3);
+
+    let found = repo
+        .query_stations(&[a.clone(), c.clone(), dump_uuid()])
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+    let found_a = found.iter().find(|s| s.stationuuid == a).unwrap();
+    assert_eq!(found_a, &stations[0]);
+
+    // 重复的stationuuid只查一次
+    let found = catalog::stations(&repo, [&a, &b, &a]).await.unwrap();
+    assert_eq!(found.len(), 2);
+    assert!(catalog::stations(&repo, []).await.unwrap().is_empty());
+
+    // 新一次导入中没有的电台标记为已删除，信息仍可查
+    let mut again = catalog::parse(&json, time + 1).unwrap();
+    again[0].name = String::from("Radio A renamed");
+    let imported = catalog::import(&repo, &again).await.unwrap();
+    assert_eq!(imported.stations, 2);
+    assert!(imported.deleted >= 1);
+    let found = catalog::stations(&repo, [&a, &b, &c]).await.unwrap();
+    let by_uuid = |uuid: &str| found.iter().find(|s| s.stationuuid == uuid).unwrap();
+    assert_eq!(by_uuid(&a).name, "Radio A renamed");
+    assert_eq!(by_uuid(&a).deleted, 0);
+    assert_eq!(by_uuid(&b).update_time, time + 1);
+    assert_eq!(by_uuid(&c).deleted, 1);
+    assert_eq!(by_uuid(&c).update_time, time);
+
+    // 重新出现的电台取消删除
+    let mut again = csv_stations.clone();
+    again[0].update_time = time + 2;
+    again.extend(catalog::parse(&json, time + 2).unwrap());
+    catalog::import(&repo, &again).await.unwrap();
+    let found = repo.query_stations(std::slice::from_ref(&c)).await.unwrap();
+    assert_eq!(found[0].deleted, 0);
+    assert_eq!(found[0].update_time, time + 2);
+
+    // 空数据不导入
+    assert!(matches!(
+        catalog::import(&repo, &[]).await,
+        Err(Error::Parse(_))
+    ));
+    assert!(catalog::parse("[]", time).unwrap().is_empty());
+}
+
+fn catalog_station(stationuuid: &str, name: &str, tags: &str, votes: i64) -> Station {
+    Station {
+        stationuuid: stationuuid.to_string(),
+        name: name.to_string(),
+        tags: tags.to_string(),
+        votes,
+        // 晚于其他场景导入的时间，不会被标记为已删除
+        update_time: Local::now().timestamp() + 100 * 365 * 86400,
+        ..Default::default()
+    }
+}
+
+fn search_names(stations: &[Station]) -> Vec<&str> {
+    stations.iter().map(|s| s.name.as_str()).collect()
+}
+
+pub async fn station_search(repo: DynAppServRepo) {
+    const LOWER: [char; 26] = [
+        'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
+        's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
+    ];
+    // 每次运行使用不同的关键词，不受其他场景的电台影响
+    let word = nanoid::nanoid!(12, &LOWER);
+    let tag = format!("t{}", word);
+    let a = Station {
+        country: String::from("Germany"),
+        countrycode: String::from("DE"),
+        language: String::from("german"),
+        codec: String::from("MP3"),
+        bitrate: 128,
+        lastcheckok: 1,
+        ..catalog_station(
+            &dump_uuid(),
+            &format!("{} Jazz FM", word.to_uppercase()),
+            &format!("jazz,{},{}", word, tag),
+            5,
+        )
+    };
+    let b = Station {
+        country: String::from("France"),
+        countrycode: String::from("FR"),
+        language: String::from("french,english"),
+        codec: String::from("AAC"),
+        bitrate: 64,
+        ..catalog_station(
+            &dump_uuid(),
+            &format!("Radio {}", word),
+            &format!("rock,{}", tag),
+            50,
+        )
+    };
+    let c = Station {
+        country: String::from("Germany"),
+        countrycode: String::from("de"),
+        language: String::from("german"),
+        codec: String::from("mp3"),
+        bitrate: 320,
+        lastcheckok: 1,
+        ..catalog_station(
+            &dump_uuid(),
+            "Other Station",
+            &format!("{},{}", word, tag),
+            1,
+        )
+    };
+    repo.save_stations(&[a.clone(), b.clone(), c.clone()])
+        .await
+        .unwrap();
+
+    let search = |req: SearchReq| {
+        let repo = repo.clone();
+        async move {
+            let filter = StationFilter::try_from(&req).unwrap();
+            repo.search_stations(&filter).await.unwrap()
+        }
+    };
+    let query = |query: &str| SearchReq {
+        query: Some(query.to_string()),
+        ..Default::default()
+    };
+
+    // 名称比标签相关，大小写不敏感，关键词按前缀匹配
+    let found = search(query(&word)).await;
+    assert_eq!(
+        search_names(&found),
+        vec![a.name.as_str(), b.name.as_str(), c.name.as_str()]
+    );
+    assert_eq!(found[0], a);
+    let found = search(query(&format!("{} jaz", &word[..8]))).await;
+    assert_eq!(search_names(&found), vec![a.name.as_str()]);
+    let found = search(query(&format!("{}, rock!", word.to_uppercase()))).await;
+    assert_eq!(search_names(&found), vec![b.name.as_str()]);
+    assert!(search(query(&format!("{} pop", word))).await.is_empty());
+
+    // 过滤条件
+    let found = search(SearchReq {
+        countrycode: Some(String::from("de")),
+        codec: Some(String::from("Mp3")),
+        ..query(&word)
+    })
+    .await;
+    assert_eq!(search_names(&found), vec![a.name.as_str(), c.name.as_str()]);
+    let found = search(SearchReq {
+        bitrate_min: Some(200),
+        ..query(&word)
+    })
+    .await;
+    assert_eq!(search_names(&found), vec![c.name.as_str()]);
+    let found = search(SearchReq {
+        language: Some(String::from("English")),
+        ..query(&word)
+    })
+    .await;
+    assert_eq!(search_names(&found), vec![b.name.as_str()]);
+    let found = search(SearchReq {
+        only_ok: true,
+        ..query(&word)
+    })
+    .await;
+    assert_eq!(search_names(&found), vec![a.name.as_str(), c.name.as_str()]);
+    // 标签匹配逗号分隔中的一项
+    let found = search(SearchReq {
+        tag: Some(String::from("jazz")),
+        ..query(&word)
+    })
+    .await;
+    assert_eq!(search_names(&found), vec![a.name.as_str()]);
+    assert!(search(SearchReq {
+        tag: Some(word[..8].to_string()),
+        ..Default::default()
+    })
+    .await
+    .is_empty());
+
+    // 不带关键词时按votes排序，收藏和最近播放过的电台排在前面
+    let by_tag = StationFilter::try_from(&SearchReq {
+        tag: Some(tag.clone()),
+        query: Some(String::from(" ,, ")),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(by_tag.terms.is_empty());
+    let user_id = new_user(&repo).await.id.unwrap();
+    let (found, next) = catalog::search(&repo, user_id, &by_tag).await.unwrap();
+    assert_eq!(
+        search_names(&found),
+        vec![b.name.as_str(), a.name.as_str(), c.name.as_str()]
+    );
+    assert_eq!(next, None);
+
+    repo.new_groups(user_id, &[group("def", 1000, 1)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("def", &c.stationuuid, 1001)])
+        .await
+        .unwrap();
+    repo.new_recently(user_id, &[recently_new(&a.stationuuid, 1002, None)])
+        .await
+        .unwrap();
+    let (found, _) = catalog::search(&repo, user_id, &by_tag).await.unwrap();
+    assert_eq!(
+        search_names(&found),
+        vec![a.name.as_str(), c.name.as_str(), b.name.as_str()]
+    );
+
+    // 分页
+    let page = StationFilter {
+        limit: 2,
+        ..by_tag.clone()
+    };
+    let (found, next) = catalog::search(&repo, user_id, &page).await.unwrap();
+    assert_eq!(search_names(&found), vec![a.name.as_str(), c.name.as_str()]);
+    assert_eq!(next, Some(2));
+    let page = StationFilter { offset: 2, ..page };
+    let (found, next) = catalog::search(&repo, user_id, &page).await.unwrap();
+    assert_eq!(search_names(&found), vec![b.name.as_str()]);
+    assert_eq!(next, None);
+
+    // 请求参数
+    assert_eq!(
+        StationFilter::terms("Jazz-FM, 24/7! ü"),
+        vec!["jazz", "fm", "24", "7", "ü"]
+    );
+    assert_eq!(
+        StationFilter::terms(&"a ".repeat(20)).len(),
+        SEARCH_TERMS_MAX
+    );
+    for offset in [-1, SEARCH_OFFSET_MAX + 1] {
+        assert!(matches!(
+            StationFilter::try_from(&SearchReq {
+                offset: Some(offset),
+                ..Default::default()
+            }),
+            Err(Error::Parse(_))
+        ));
+    }
+    let filter = StationFilter::try_from(&SearchReq {
+        countrycode: Some(String::from(" de ")),
+        codec: Some(String::new()),
+        limit: Some(100000),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(filter.countrycode.as_deref(), Some("DE"));
+    assert_eq!(filter.codec, None);
+    assert_eq!(filter.limit, SEARCH_PAGE_MAX);
+}
+
+fn ordered_favorites(favorites: &[StationGroup]) -> Vec<(&str, &str, i64)> {
+    favorites
+        .iter()
+        .map(|f| (f.group_name.as_str(), f.stationuuid.as_str(), f.position))
+        .collect()
+}
+
+fn reorder_req(
+    group: Option<&str>,
+    order: Option<&[&str]>,
+    moving: Option<(&str, i64)>,
+) -> ReorderReq {
+    ReorderReq {
+        group: group.map(String::from),
+        order: order.map(|o| o.iter().map(|s| s.to_string()).collect()),
+        moving: moving.map(|(item, _)| item.to_string()),
+        to: moving.map(|(_, to)| to),
+    }
+}
+
+pub async fn reorder_groups_and_favorites(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_groups(
+        user_id,
+        &[
+            group("a", 1000, 1),
+            group("b", 1001, 0),
+            group("c", 1002, 0),
+        ],
+    )
+    .await
+    .unwrap();
+    repo.new_favorite(
+        user_id,
+        &[
+            station("b", UUID_A, 1003),
+            station("b", UUID_B, 1004),
+            station("b", UUID_C, 1005),
+            station("c", UUID_A, 1006),
+        ],
+    )
+    .await
+    .unwrap();
+
+    // 新建的排在最后
+    let groups = repo.query_groups(user_id).await.unwrap();
+    let positions: Vec<_> = groups
+        .iter()
+        .map(|g| (g.name.as_str(), g.position))
+        .collect();
+    assert_eq!(positions, vec![("a", 0), ("b", 1), ("c", 2)]);
+    assert_eq!(
+        ordered_favorites(&repo.query_favorites(user_id).await.unwrap()),
+        vec![
+            ("b", UUID_A, 0),
+            ("b", UUID_B, 1),
+            ("b", UUID_C, 2),
+            ("c", UUID_A, 0)
+        ]
+    );
+
+    // 完整的顺序，不存在和重复的忽略，缺少的排在后面
+    let revision = repo.query_revision(user_id).await.unwrap();
+    let order = order::reorder(
+        &repo,
+        user_id,
+        &reorder_req(None, Some(&["c", "missing", "a", "c"]), None),
+    )
+    .await
+    .unwrap();
+    assert_eq!(order, vec!["c", "a", "b"]);
+    let groups = repo.query_groups(user_id).await.unwrap();
+    let positions: Vec<_> = groups
+        .iter()
+        .map(|g| (g.name.as_str(), g.position))
+        .collect();
+    assert_eq!(positions, vec![("c", 0), ("a", 1), ("b", 2)]);
+    let current = repo.query_revision(user_id).await.unwrap();
+    let (groups, _, favorites) = repo.query_sync(user_id, revision, current).await.unwrap();
+    let names: Vec<_> = groups.iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(names, vec!["c", "a", "b"]);
+    assert!(favorites.is_empty());
+
+    // 移动分组中的收藏，收藏按分组的顺序返回
+    let revision = current;
+    let order = order::reorder(
+        &repo,
+        user_id,
+        &reorder_req(Some("b"), None, Some((UUID_C, 0))),
+    )
+    .await
+    .unwrap();
+    assert_eq!(order, vec![UUID_C, UUID_A, UUID_B]);
+    assert_eq!(
+        ordered_favorites(&repo.query_favorites(user_id).await.unwrap()),
+        vec![
+            ("c", UUID_A, 0),
+            ("b", UUID_C, 0),
+            ("b", UUID_A, 1),
+            ("b", UUID_B, 2)
+        ]
+    );
+    let current = repo.query_revision(user_id).await.unwrap();
+    let (groups, _, favorites) = repo.query_sync(user_id, revision, current).await.unwrap();
+    assert!(groups.is_empty());
+    assert_eq!(
+        ordered_favorites(&favorites),
+        vec![("b", UUID_C, 0), ("b", UUID_A, 1), ("b", UUID_B, 2)]
+    );
+
+    // 位置没有变化的不会同步
+    let revision = current;
+    let order = order::reorder(
+        &repo,
+        user_id,
+        &reorder_req(Some("b"), None, Some((UUID_B, 100))),
+    )
+    .await
+    .unwrap();
+    assert_eq!(order, vec![UUID_C, UUID_A, UUID_B]);
+    let current = repo.query_revision(user_id).await.unwrap();
+    let (_, _, favorites) = repo.query_sync(user_id, revision, current).await.unwrap();
+    assert!(favorites.is_empty());
+
+    // 移动到其他分组时排在最后，仍在原分组中的保持位置
+    repo.modify_favorite(user_id, UUID_A, &[String::from("b"), String::from("a")])
+        .await
+        .unwrap();
+    assert_eq!(
+        ordered_favorites(&repo.query_favorites(user_id).await.unwrap()),
+        vec![
+            ("a", UUID_A, 0),
+            ("b", UUID_C, 0),
+            ("b", UUID_A, 1),
+            ("b", UUID_B, 2)
+        ]
+    );
+    repo.new_favorite(
+        user_id,
+        &[station("b", UUID_C, 1007), station("a", UUID_B, 1008)],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        ordered_favorites(&repo.query_favorites(user_id).await.unwrap()),
+        vec![
+            ("a", UUID_A, 0),
+            ("a", UUID_B, 1),
+            ("b", UUID_C, 0),
+            ("b", UUID_A, 1),
+            ("b", UUID_B, 2)
+        ]
+    );
+
+    // 错误的请求
+    assert!(matches!(
+        order::reorder(
+            &repo,
+            user_id,
+            &reorder_req(Some("b"), None, Some(("missing", 0)))
+        )
+        .await,
+        Err(Error::Custom(_))
+    ));
+    assert!(matches!(
+        order::reorder(
+            &repo,
+            user_id,
+            &reorder_req(Some("missing"), Some(&[]), None)
+        )
+        .await,
+        Err(Error::Custom(_))
+    ));
+    assert!(matches!(
+        order::reorder(&repo, user_id, &reorder_req(None, None, None)).await,
+        Err(Error::Parse(_))
+    ));
+    assert!(matches!(
+        order::reorder(
+            &repo,
+            user_id,
+            &reorder_req(None, Some(&["a"]), Some(("a", 0)))
+        )
+        .await,
+        Err(Error::Parse(_))
+    ));
+    assert!(repo
+        .reorder_favorites(user_id, "missing", &[String::from(UUID_A)])
+        .await
+        .is_err());
+}
+
+pub async fn share_group_copy_revoke(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    let other = new_user(&repo).await.id.unwrap();
+    let (in_catalog, not_in_catalog) = (dump_uuid(), dump_uuid());
+    repo.save_stations(&[catalog_station(&in_catalog, "shared radio", "", 0)])
+        .await
+        .unwrap();
+    repo.new_groups(user_id, &[group("a", 1000, 1), group("b", 1001, 0)])
+        .await
+        .unwrap();
+    repo.new_favorite(
+        user_id,
+        &[
+            station("b", &not_in_catalog, 1002),
+            station("b", &in_catalog, 1003),
+            station("a", UUID_A, 1004),
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert!(matches!(
+        share::share(&repo, user_id, "missing").await,
+        Err(Error::Custom(_))
+    ));
+    assert!(repo.share_group(user_id, "missing", "token").await.is_err());
+
+    // 重复分享返回原来的token
+    let shared_b = share::share(&repo, user_id, "b").await.unwrap();
+    assert_eq!(shared_b.token.len(), 32);
+    assert_eq!(shared_b.group_name, "b");
+    assert_eq!(share::share(&repo, user_id, "b").await.unwrap(), shared_b);
+    assert_eq!(
+        repo.query_shares(user_id).await.unwrap(),
+        vec![shared_b.clone()]
+    );
+    assert!(repo.query_shares(other).await.unwrap().is_empty());
+
+    // 不登录查询，收藏按顺序返回，电台只返回目录中有的
+    let shared = share::shared(&repo, &shared_b.token).await.unwrap();
+    assert_eq!(shared.name, "b");
+    assert_eq!(shared.desc, "b desc");
+    assert_eq!(
+        shared.favorites,
+        vec![not_in_catalog.clone(), in_catalog.clone()]
+    );
+    let stations: Vec<_> = shared
+        .stations
+        .iter()
+        .map(|s| s.stationuuid.as_str())
+        .collect();
+    assert_eq!(stations, vec![in_catalog.as_str()]);
+    assert!(matches!(
+        share::shared(&repo, "missing").await,
+        Err(Error::Custom(_))
+    ));
+
+    // 分组改名后分享仍然有效
+    repo.modify_group(user_id, "b", "b2", "b2 desc")
+        .await
+        .unwrap();
+    let shared = share::shared(&repo, &shared_b.token).await.unwrap();
+    assert_eq!(
+        (shared.name.as_str(), shared.desc.as_str()),
+        ("b2", "b2 desc")
+    );
+
+    // 复制到其他用户，重复复制不会新增
+    let (group_name, imported) = share::copy(&repo, other, &shared_b.token, None, None)
+        .await
+        .unwrap();
+    assert_eq!(group_name, "b2");
+    assert!(imported.group_created);
+    assert_eq!(imported.favorites, 2);
+    let groups = repo.query_groups(other).await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(
+        (groups[0].name.as_str(), groups[0].desc.as_str()),
+        ("b2", "b2 desc")
+    );
+    assert_eq!(
+        ordered_favorites(&repo.query_favorites(other).await.unwrap()),
+        vec![
+            ("b2", not_in_catalog.as_str(), 0),
+            ("b2", in_catalog.as_str(), 1)
+        ]
+    );
+    let (_, imported) = share::copy(&repo, other, &shared_b.token, None, None)
+        .await
+        .unwrap();
+    assert!(!imported.group_created);
+    assert_eq!(imported.favorites, 0);
+    let (group_name, imported) =
+        share::copy(&repo, other, &shared_b.token, Some("mine"), Some("copied"))
+            .await
+            .unwrap();
+    assert_eq!(group_name, "mine");
+    assert!(imported.group_created);
+    assert_eq!(imported.favorites, 2);
+    let groups = repo.query_groups(other).await.unwrap();
+    assert_eq!(groups[1].desc, "copied");
+
+    // 取消分享后token失效，再次分享使用新的token
+    assert!(repo.delete_share(user_id, "b2").await.unwrap());
+    assert!(!repo.delete_share(user_id, "b2").await.unwrap());
+    assert!(repo.query_share(&shared_b.token).await.unwrap().is_none());
+    assert!(repo.query_shares(user_id).await.unwrap().is_empty());
+    assert!(matches!(
+        share::copy(&repo, other, &shared_b.token, None, None).await,
+        Err(Error::Custom(_))
+    ));
+    let shared_b2 = share::share(&repo, user_id, "b2").await.unwrap();
+    assert_ne!(shared_b2.token, shared_b.token);
+
+    // 分组删除后分享失效
+    let shared_a = share::share(&repo, user_id, "a").await.unwrap();
+    assert_eq!(
+        repo.query_shares(user_id).await.unwrap(),
+        vec![shared_a.clone(), shared_b2.clone()]
+    );
+    repo.delete_groups(user_id, &[String::from("b2")])
+        .await
+        .unwrap();
+    assert!(repo.query_share(&shared_b2.token).await.unwrap().is_none());
+    assert_eq!(repo.query_shares(user_id).await.unwrap(), vec![shared_a]);
+}
+
+pub async fn share_hidden_after_cancel_and_close(repo: DynAppServRepo) {
+    let user_id = new_user(&repo).await.id.unwrap();
+    repo.new_groups(user_id, &[group("a", 1000, 1)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("a", UUID_A, 1001)])
+        .await
+        .unwrap();
+    let token = share::share(&repo, user_id, "a").await.unwrap().token;
+    assert!(share::shared(&repo, &token).await.is_ok());
+
+    // 关闭hiqradio但不清理数据，分享不再公开，重新开通后恢复
+    repo.close_product(user_id, PRODUCT, false).await.unwrap();
+    assert!(repo.query_share(&token).await.unwrap().is_none());
+    assert!(matches!(
+        share::shared(&repo, &token).await,
+        Err(Error::Custom(_))
+    ));
+    repo.open_product(user_id, PRODUCT).await.unwrap();
+    assert_eq!(
+        share::shared(&repo, &token).await.unwrap().favorites,
+        vec![UUID_A]
+    );
+
+    // 注销后宽限期内数据还在，分享不再公开
+    repo.cancel_user(user_id).await.unwrap();
+    assert!(repo.query_share(&token).await.unwrap().is_none());
+    assert!(matches!(
+        share::shared(&repo, &token).await,
+        Err(Error::Custom(_))
+    ));
+    let other = new_user(&repo).await.id.unwrap();
+    assert!(share::copy(&repo, other, &token, None, None).await.is_err());
+}
+
+fn recommended(recommendations: &[Recommendation]) -> Vec<(&str, f64)> {
+    recommendations
+        .iter()
+        .map(|r| (r.stationuuid.as_str(), r.score))
+        .collect()
+}
+
+fn assert_scores(scores: &[(&str, f64)], expected: &[(&str, f64)]) {
+    assert_eq!(scores.len(), expected.len(), "{:?}", scores);
+    for ((a, score), (b, expected)) in scores.iter().zip(expected) {
+        assert_eq!(a, b);
+        assert!((score - expected).abs() < 1e-9, "{} != {}", score, expected);
+    }
+}
+
+pub async fn station_recommendations(repo: DynAppServRepo) {
+    let (x, y, z, w) = (dump_uuid(), dump_uuid(), dump_uuid(), dump_uuid());
+    // 余弦相似度，收藏的权重为1，播放的为0.5：x为(1, 1, 1)，y为(1, 1, 0.5)，z为(1, 1)
+    let xy = 2.0 / (3.0f64 * 2.25).sqrt();
+    let yz = 1.5 / (2.25f64 * 2.0).sqrt();
+    let now = Local::now().timestamp();
+    let since = now - 86400;
+
+    // x和y有两个用户同时收藏；y和z有一个用户同时收藏，一个用户播放y收藏z；
+    // x和z、x和w只有一个用户，不算相似
+    let mut users = Vec::new();
+    for stations in [vec![&x, &y, &z], vec![&x, &y], vec![&x, &w], vec![&z]] {
+        let user_id = new_user(&repo).await.id.unwrap();
+        repo.new_groups(user_id, &[group("a", 1000, 1)])
+            .await
+            .unwrap();
+        let favorites: Vec<_> = stations
+            .into_iter()
+            .map(|s| station("a", s, 1001))
+            .collect();
+        repo.new_favorite(user_id, &favorites).await.unwrap();
+        users.push(user_id);
+    }
+    repo.new_recently(users[3], &[recently_new(&y, now - 60, Some(now))])
+        .await
+        .unwrap();
+    // since之前的播放不参与计算
+    repo.new_recently(users[2], &[recently_new(&z, since - 60, Some(since))])
+        .await
+        .unwrap();
+
+    // 按user_id和stationuuid分页，同时收藏和播放过的favorite为1
+    let mut xw = [x.clone(), w.clone()];
+    xw.sort();
+    let page = repo
+        .query_user_stations(since, users[2], "", 2)
+        .await
+        .unwrap();
+    let page: Vec<_> = page
+        .iter()
+        .map(|s| (s.user_id, s.stationuuid.as_str(), s.favorite))
+        .collect();
+    assert_eq!(
+        page,
+        vec![(users[2], xw[0].as_str(), 1), (users[2], xw[1].as_str(), 1)]
+    );
+    repo.new_recently(users[3], &[recently_new(&z, now - 30, Some(now))])
+        .await
+        .unwrap();
+    let mut yz_page = vec![(users[3], y.as_str(), 0), (users[3], z.as_str(), 1)];
+    yz_page.sort();
+    let page = repo
+        .query_user_stations(since, users[2], &xw[1], 10)
+        .await
+        .unwrap();
+    let page: Vec<_> = page
+        .iter()
+        .map(|s| (s.user_id, s.stationuuid.as_str(), s.favorite))
+        .take(2)
+        .collect();
+    assert_eq!(page, yz_page);
+
+    // 共享数据库中有其他用例的数据，不检查总数
+    assert!(recommend::build(&repo, since, now).await.unwrap() >= 4);
+    let similar = repo.query_similar(std::slice::from_ref(&y)).await.unwrap();
+    let scores: Vec<_> = similar
+        .iter()
+        .map(|s| {
+            assert_eq!(s.update_time, now);
+            (s.similaruuid.as_str(), s.score)
+        })
+        .collect();
+    assert_scores(&scores, &[(&x, xy), (&z, yz)]);
+    let similar = repo.query_similar(std::slice::from_ref(&w)).await.unwrap();
+    assert!(similar.is_empty());
+
+    // 收藏x、播放过y的用户，y从x得到推荐，z从y得到推荐且播放的权重较低
+    let user_id = new_user(&repo).await.id.unwrap();
+    let (recommendations, stations) = recommend::recommend(&repo, user_id, since, 20)
+        .await
+        .unwrap();
+    assert!(recommendations.is_empty() && stations.is_empty());
+    repo.new_groups(user_id, &[group("a", 1000, 1)])
+        .await
+        .unwrap();
+    repo.new_favorite(user_id, &[station("a", &x, 1001)])
+        .await
+        .unwrap();
+    repo.new_recently(user_id, &[recently_new(&y, now - 60, Some(now))])
+        .await
+        .unwrap();
+    repo.save_stations(&[catalog_station(&y, "similar radio", "", 0)])
+        .await
+        .unwrap();
+    let (recommendations, stations) = recommend::recommend(&repo, user_id, since, 20)
+        .await
+        .unwrap();
+    assert_scores(&recommended(&recommendations), &[(&y, xy), (&z, 0.5 * yz)]);
+    let stations: Vec<_> = stations.iter().map(|s| s.stationuuid.as_str()).collect();
+    assert_eq!(stations, vec![y.as_str()]);
+    let (recommendations, _) = recommend::recommend(&repo, user_id, since, 1)
+        .await
+        .unwrap();
+    assert_scores(&recommended(&recommendations), &[(&y, xy)]);
+
+    // 已收藏的不推荐
+    repo.new_favorite(user_id, &[station("a", &y, 1002)])
+        .await
+        .unwrap();
+    let (recommendations, stations) = recommend::recommend(&repo, user_id, since, 20)
+        .await
+        .unwrap();
+    assert_scores(&recommended(&recommendations), &[(&z, yz)]);
+    assert!(stations.is_empty());
+}
+
+fn trending_stats(trending: &[TrendingStation]) -> Vec<(&str, i64, i64, i64)> {
+    trending
+        .iter()
+        .map(|s| {
+            (
+                s.stationuuid.as_str(),
+                s.listeners,
+                s.listen_time,
+                s.favorites,
+            )
+        })
+        .collect()
+}
+
+pub async fn trending_stations(repo: DynAppServRepo) {
+    // 以微秒数作为统计时间，远在其他用例和之前运行的数据之后，统计周期内只有本用例的数据
+    let now = Local::now().timestamp_micros();
+    let (p, q, r, s) = (dump_uuid(), dump_uuid(), dump_uuid(), dump_uuid());
+    let mut users = Vec::new();
+    for _ in 0..5 {
+        let user_id = new_user(&repo).await.id.unwrap();
+        repo.new_groups(user_id, &[group("a", now, 1), group("b", now, 0)])
+            .await
+            .unwrap();
+        users.push(user_id);
+    }
+
+    // p有3个用户各播放10分钟；q有1个用户播放10小时，按4小时计，1个用户收藏在两个分组中
+    for user_id in &users[..3] {
+        repo.new_recently(*user_id, &[recently_new(&p, now - 3600, Some(now - 3000))])
+            .await
+            .unwrap();
+    }
+    repo.new_recently(users[3], &[recently_new(&q, now - 36000, Some(now))])
+        .await
+        .unwrap();
+    repo.new_favorite(
+        users[4],
+        &[station("a", &q, now - 100), station("b", &q, now - 100)],
+    )
+    .await
+    .unwrap();
+    // 统计时间之后的不计入
+    repo.new_recently(users[3], &[recently_new(&p, now + 10, None)])
+        .await
+        .unwrap();
+    // r两天前有2个用户播放，s十天前被收藏
+    for user_id in &users[..2] {
+        repo.new_recently(
+            *user_id,
+            &[recently_new(
+                &r,
+                now - 2 * 86400,
+                Some(now - 2 * 86400 + 60),
+            )],
+        )
+        .await
+        .unwrap();
+    }
+    repo.new_favorite(users[4], &[station("a", &s, now - 10 * 86400)])
+        .await
+        .unwrap();
+    repo.save_stations(&[catalog_station(&p, "trending radio", "", 0)])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        trending::build(&repo, now).await.unwrap(),
+        vec![("day", 2), ("week", 3), ("month", 4)]
+    );
+
+    let (day, stations) = trending::query(&repo, "day", 50).await.unwrap();
+    assert_eq!(
+        trending_stats(&day),
+        vec![(q.as_str(), 1, 4 * 3600, 1), (p.as_str(), 3, 1800, 0)]
+    );
+    assert!(day.iter().all(|s| s.update_time == now));
+    assert!(day[0].score > day[1].score);
+    let stations: Vec<_> = stations.iter().map(|s| s.stationuuid.as_str()).collect();
+    assert_eq!(stations, vec![p.as_str()]);
+
+    let (week, _) = trending::query(&repo, "week", 50).await.unwrap();
+    assert_eq!(
+        trending_stats(&week),
+        vec![
+            (q.as_str(), 1, 4 * 3600, 1),
+            (p.as_str(), 3, 1800, 0),
+            (r.as_str(), 2, 120, 0)
+        ]
+    );
+    let (month, _) = trending::query(&repo, "month", 50).await.unwrap();
+    let month: Vec<_> = month.iter().map(|t| t.stationuuid.as_str()).collect();
+    assert_eq!(month, vec![q.as_str(), p.as_str(), r.as_str(), s.as_str()]);
+
+    let (day, _) = trending::query(&repo, "day", 1).await.unwrap();
+    assert_eq!(day.len(), 1);
+    assert!(matches!(
+        trending::query(&repo, "year", 50).await,
+        Err(Error::Parse(_))
+    ));
+
+    // 重建时替换原来的数据
+    assert_eq!(
+        trending::build(&repo, now + 30 * 86400).await.unwrap(),
+        vec![("day", 0), ("week", 0), ("month", 1)]
+    );
+    let (day, _) = trending::query(&repo, "day", 50).await.unwrap();
+    assert!(day.is_empty());
+}
+
+fn review_summary(reviews: &[Review]) -> Vec<(&str, i64, &str)> {
+    reviews
+        .iter()
+        .map(|r| (r.stationuuid.as_str(), r.rating, r.status.as_str()))
+        .collect()
+}
+
+fn public_summary(reviews: &[PublicReview]) -> Vec<(&str, i64, &str)> {
+    reviews
+        .iter()
+        .map(|r| (r.stationuuid.as_str(), r.rating, r.user_name.as_str()))
+        .collect()
+}
+
+pub async fn station_reviews(repo: DynAppServRepo) {
+    let (p, q) = (dump_uuid(), dump_uuid());
+    let now = Local::now().timestamp();
+    let mut users = Vec::new();
+    for _ in 0..5 {
+        users.push(new_user(&repo).await);
+    }
+    let ids: Vec<_> = users.iter().map(|u| u.id.unwrap()).collect();
+    repo.save_stations(&[catalog_station(&p, "reviewed radio", "", 0)])
+        .await
+        .unwrap();
+
+    // 只评分的直接通过，带内容的待审核，大写uuid转小写
+    let a = review::review(&repo, ids[0], &p.to_uppercase(), 5, "  great  ", now)
+        .await
+        .unwrap();
+    assert_eq!(a.stationuuid, p);
+    assert_eq!(a.content, "great");
+    assert_eq!(a.status, REVIEW_STATUS_PENDING);
+    // 注册时默认的用户名是邮箱前缀，不返回
+    assert_eq!(a.user_name, "");
+    let b = review::review(&repo, ids[1], &p, 2, "", now + 1)
+        .await
+        .unwrap();
+    assert_eq!(b.status, REVIEW_STATUS_APPROVED);
+    review::review(&repo, ids[1], &q, 4, "", now + 1)
+        .await
+        .unwrap();
+
+    for (rating, content) in [(0, ""), (6, ""), (3, &"x".repeat(REVIEW_CONTENT_MAX + 1))] {
+        assert!(matches!(
+            review::review(&repo, ids[2], &p, rating, content, now).await,
+            Err(Error::Parse(_))
+        ));
+    }
+    assert!(matches!(
+        review::review(&repo, ids[2], " ", 3, "", now).await,
+        Err(Error::Parse(_))
+    ));
+
+    // 平均评分只统计审核通过的评论
+    let (rating, reviews, next) = review::reviews(&repo, &p, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (2.0, 1));
+    assert_eq!(public_summary(&reviews), vec![(p.as_str(), 2, "")]);
+    assert_eq!(next, None);
+    let (rating, reviews, _) = review::reviews(&repo, &dump_uuid(), 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (0.0, 0));
+    assert!(reviews.is_empty());
+
+    let queue = |reviews: Vec<Review>| -> Vec<i64> {
+        reviews
+            .into_iter()
+            .filter(|r| r.stationuuid == p)
+            .map(|r| r.id.unwrap())
+            .collect()
+    };
+    let (pending, _) = review::queue(&repo, 0, 100).await.unwrap();
+    assert!(queue(pending).contains(&a.id.unwrap()));
+
+    review::moderate(&repo, a.id.unwrap(), REVIEW_STATUS_APPROVED)
+        .await
+        .unwrap();
+    assert!(matches!(
+        review::moderate(&repo, a.id.unwrap(), REVIEW_STATUS_PENDING).await,
+        Err(Error::Parse(_))
+    ));
+    assert!(matches!(
+        review::moderate(&repo, -1, REVIEW_STATUS_APPROVED).await,
+        Err(Error::Custom(_))
+    ));
+
+    // 修改过的用户名公开
+    let product_id = repo.query_user_products(ids[1]).await.unwrap()[0]
+        .id
+        .unwrap();
+    repo.update_user_info(ids[1], product_id, Some(String::from("bob")), None, None)
+        .await
+        .unwrap();
+    let (rating, reviews, next) = review::reviews(&repo, &p, 0, 1).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (3.5, 2));
+    assert_eq!(public_summary(&reviews), vec![(p.as_str(), 2, "bob")]);
+    assert_eq!(next, Some(1));
+    let (_, reviews, next) = review::reviews(&repo, &p, 1, 1).await.unwrap();
+    assert_eq!(reviews[0].id, a.id);
+    assert_eq!(next, None);
+
+    // 返回的电台带评分
+    let stations = catalog::stations(&repo, [&p, &q]).await.unwrap();
+    assert_eq!(stations.len(), 1);
+    assert_eq!((stations[0].rating, stations[0].ratings), (3.5, 2));
+
+    // 不能举报自己的和未通过的评论，重复举报只计一次，达到阈值后重新待审核
+    let id = a.id.unwrap();
+    assert!(matches!(
+        review::report(&repo, ids[0], id, "", now).await,
+        Err(Error::Custom(_))
+    ));
+    assert!(matches!(
+        review::report(&repo, ids[1], -1, "", now).await,
+        Err(Error::Custom(_))
+    ));
+    assert_eq!(
+        review::report(&repo, ids[1], id, "spam", now)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        review::report(&repo, ids[1], id, "spam", now)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(review::report(&repo, ids[2], id, "", now).await.unwrap(), 2);
+    assert_eq!(
+        repo.query_review(id).await.unwrap().unwrap().status,
+        REVIEW_STATUS_APPROVED
+    );
+    assert_eq!(
+        review::report(&repo, ids[3], id, "", now).await.unwrap(),
+        review::PENDING_REPORTS
+    );
+    let reported = repo.query_review(id).await.unwrap().unwrap();
+    assert_eq!(reported.status, REVIEW_STATUS_PENDING);
+    assert!(matches!(
+        review::report(&repo, ids[4], id, "", now).await,
+        Err(Error::Custom(_))
+    ));
+    let (rating, _, _) = review::reviews(&repo, &p, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (2.0, 1));
+
+    // 拒绝后清空举报；修改评论后重新审核，保留创建时间
+    review::moderate(&repo, id, REVIEW_STATUS_REJECTED)
+        .await
+        .unwrap();
+    let rejected = repo.query_review(id).await.unwrap().unwrap();
+    assert_eq!(
+        (rejected.status.as_str(), rejected.reports),
+        ("rejected", 0)
+    );
+    let a = review::review(&repo, ids[0], &p, 4, "fine", now + 10)
+        .await
+        .unwrap();
+    assert_eq!(a.id, Some(id));
+    assert_eq!((a.create_time, a.update_time), (now, now + 10));
+    assert_eq!(a.status, REVIEW_STATUS_PENDING);
+    review::moderate(&repo, id, REVIEW_STATUS_APPROVED)
+        .await
+        .unwrap();
+    assert_eq!(review::report(&repo, ids[1], id, "", now).await.unwrap(), 1);
+
+    assert_eq!(
+        review_summary(&repo.query_user_reviews(ids[1]).await.unwrap()),
+        vec![(q.as_str(), 4, "approved"), (p.as_str(), 2, "approved")]
+    );
+
+    // 删除评论
+    assert!(repo.delete_review(ids[1], &q).await.unwrap());
+    assert!(!repo.delete_review(ids[1], &q).await.unwrap());
+    assert_eq!(repo.query_user_reviews(ids[1]).await.unwrap().len(), 1);
+
+    // 清理数据关闭产品时删除评论
+    repo.close_product(ids[0], PRODUCT, true).await.unwrap();
+    assert!(repo.query_user_reviews(ids[0]).await.unwrap().is_empty());
+    assert!(repo.query_review(id).await.unwrap().is_none());
+    let (rating, _, _) = review::reviews(&repo, &p, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (2.0, 1));
+
+    // 关闭hiqradio但不清理数据，评论不再公开也不计入评分，重新开通后恢复
+    repo.close_product(ids[1], PRODUCT, false).await.unwrap();
+    let (rating, reviews, _) = review::reviews(&repo, &p, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (0.0, 0));
+    assert!(reviews.is_empty());
+    assert!(repo
+        .query_ratings(std::slice::from_ref(&p))
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(repo.query_user_reviews(ids[1]).await.unwrap().len(), 1);
+    repo.open_product(ids[1], PRODUCT).await.unwrap();
+    let (rating, reviews, _) = review::reviews(&repo, &p, 0, 20).await.unwrap();
+    assert_eq!((rating.rating, rating.ratings), (2.0, 1));
+    assert_eq!(public_summary(&reviews), vec![(p.as_str(), 2, "bob")]);
+}
+
+fn presence_req(device_id: &str, device_name: &str, stationuuid: Option<&str>) -> PresenceReq {
+    PresenceReq {
+        device_id: device_id.to_string(),
+        device_name: Some(device_name.to_string()),
+        stationuuid: stationuuid.map(|s| s.to_string()),
+    }
+}
+
+fn presence_devices(presences: &[Presence]) -> Vec<(&str, &str, bool)> {
+    presences
+        .iter()
+        .map(|p| (p.device_id.as_str(), p.stationuuid.as_str(), p.current))
+        .collect()
+}
+
+pub async fn presence_heartbeat(repo: DynAppServRepo) {
+    let (p, q) = (dump_uuid(), dump_uuid());
+    let now = Local::now().timestamp();
+    let user = new_user(&repo).await;
+    let user_id = user.id.unwrap();
+    let (_, _, phone) = repo
+        .signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+        .await
+        .unwrap();
+    let (_, _, desktop) = repo
+        .signin_user(&signin_req(PRODUCT, &user.email, PASSWD, false))
+        .await
+        .unwrap();
+    repo.save_stations(&[catalog_station(&p, "playing radio", "", 0)])
+        .await
+        .unwrap();
+    let heartbeat = |token: &str, req: PresenceReq, time: i64| {
+        let repo = repo.clone();
+        let token = token.to_string();
+        async move { presence::heartbeat(&repo, user_id, &token, &req, time, 90).await }
+    };
+
+    // 同一电台的心跳保留开始时间，大写uuid转小写
+    let saved = heartbeat(
+        &phone.token,
+        presence_req("phone", "My Phone", Some(&p.to_uppercase())),
+        now,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(saved.stationuuid, p);
+    assert_eq!((saved.start_time, saved.expire), (now, now + 90));
+    assert!(saved.current);
+    let saved = heartbeat(
+        &phone.token,
+        presence_req("phone", "My Phone", Some(&p)),
+        now + 30,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        (saved.start_time, saved.update_time, saved.expire),
+        (now, now + 30, now + 120)
+    );
+    heartbeat(
+        &desktop.token,
+        presence_req("desktop", "", Some(&q)),
+        now + 40,
+    )
+    .await
+    .unwrap();
+
+    for req in [
+        presence_req(" ", "", Some(&p)),
+        presence_req(&"d".repeat(PRESENCE_DEVICE_MAX + 1), "", Some(&p)),
+        presence_req("phone", &"n".repeat(PRESENCE_DEVICE_MAX + 1), Some(&p)),
+    ] {
+        assert!(matches!(
+            heartbeat(&phone.token, req, now).await,
+            Err(Error::Parse(_))
+        ));
+    }
+
+    // 最近心跳的在前，当前会话的设备标记为current，电台目录中没有的电台不返回信息
+    let (presences, stations) = presence::presences(&repo, user_id, &phone.token, now + 50)
+        .await
+        .unwrap();
+    assert_eq!(
+        presence_devices(&presences),
+        vec![("desktop", q.as_str(), false), ("phone", p.as_str(), true)]
+    );
+    assert_eq!(presences[1].device_name, "My Phone");
+    let stations: Vec<_> = stations.iter().map(|s| s.stationuuid.as_str()).collect();
+    assert_eq!(stations, vec![p.as_str()]);
+    let (presences, _) = presence::presences(&repo, user_id, &desktop.token, now + 50)
+        .await
+        .unwrap();
+    assert_eq!(
+        presence_devices(&presences),
+        vec![("desktop", q.as_str(), true), ("phone", p.as_str(), false)]
+    );
+    assert!(repo
+        .query_presences(new_user(&repo).await.id.unwrap(), now)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // 换电台时重新计算开始时间，过期的设备不返回
+    let saved = heartbeat(
+        &phone.token,
+        presence_req("phone", "My Phone", Some(&q)),
+        now + 60,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(saved.start_time, now + 60);
+    let (presences, _) = presence::presences(&repo, user_id, &phone.token, now + 130)
+        .await
+        .unwrap();
+    assert_eq!(
+        presence_devices(&presences),
+        vec![("phone", q.as_str(), true)]
+    );
+
+    // 停止播放时删除设备的状态
+    assert!(
+        heartbeat(&phone.token, presence_req("phone", "", None), now + 70)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let (presences, _) = presence::presences(&repo, user_id, &phone.token, now + 70)
+        .await
+        .unwrap();
+    assert_eq!(
+        presence_devices(&presences),
+        vec![("desktop", q.as_str(), false)]
+    );
+
+    // 清理过期的状态
+    assert!(repo.clean_presence(now + 130).await.unwrap() >= 1);
+    assert!(repo.query_presences(user_id, now).await.unwrap().is_empty());
+
+    // 退出登录时删除会话的状态
+    for (token, device_id) in [(&phone.token, "phone"), (&desktop.token, "desktop")] {
+        heartbeat(token, presence_req(device_id, "", Some(&p)), now)
+            .await
+            .unwrap();
+    }
+    repo.delete_session(&phone.token).await.unwrap();
+    let presences = repo.query_presences(user_id, now).await.unwrap();
+    assert_eq!(
+        presence_devices(&presences),
+        vec![("desktop", p.as_str(), false)]
+    );
+
+    // 清理数据关闭产品时删除状态
+    repo.close_product(user_id, PRODUCT, true).await.unwrap();
+    assert!(repo.query_presences(user_id, now).await.unwrap().is_empty());
+}
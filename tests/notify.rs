@@ -0,0 +1,107 @@
+//! 数据修改通知只发给同一用户的其他会话
+
+use std::time::Duration;
+
+use appserv::notify::{LibraryChanged, LibraryEvent, Notifier};
+use tokio_stream::{Stream, StreamExt};
+
+fn changed(user_id: i64, revision: i64, token: &str) -> LibraryChanged {
+    LibraryChanged {
+        user_id,
+        revision,
+        token: token.to_string(),
+    }
+}
+
+/// 等待下一条通知，没有则返回None
+async fn next(stream: &mut (impl Stream<Item = LibraryEvent> + Unpin)) -> Option<LibraryEvent> {
+    tokio::time::timeout(Duration::from_millis(50), stream.next())
+        .await
+        .ok()
+        .flatten()
+}
+
+#[tokio::test]
+async fn notify_other_sessions_of_same_user() {
+    let notifier = Notifier::new();
+    let mut phone = Box::pin(notifier.subscribe(1, "phone"));
+    let mut desktop = Box::pin(notifier.subscribe(1, "desktop"));
+
+    notifier.send(changed(1, 1, "phone"));
+    notifier.send(changed(2, 5, "other"));
+    notifier.send(changed(1, 2, "desktop"));
+
+    assert_eq!(next(&mut desktop).await, Some(LibraryEvent::Changed(1)));
+    assert_eq!(next(&mut desktop).await, None);
+    assert_eq!(next(&mut phone).await, Some(LibraryEvent::Changed(2)));
+    assert_eq!(next(&mut phone).await, None);
+}
+
+#[tokio::test]
+async fn lagged_subscriber_is_told_to_sync() {
+    let notifier = Notifier::new();
+    let mut desktop = Box::pin(notifier.subscribe(1, "desktop"));
+
+    for revision in 1..=2000 {
+        notifier.send(changed(1, revision, "phone"));
+    }
+
+    assert_eq!(next(&mut desktop).await, Some(LibraryEvent::Lagged));
+    assert!(matches!(
+        next(&mut desktop).await,
+        Some(LibraryEvent::Changed(_))
+    ));
+}
+
+#[tokio::test]
+async fn busy_user_does_not_lag_others() {
+    let notifier = Notifier::new();
+    let mut other = Box::pin(notifier.subscribe(2, "other"));
+    let mut desktop = Box::pin(notifier.subscribe(1, "desktop"));
+
+    for revision in 1..=2000 {
+        notifier.send(changed(1, revision, "phone"));
+    }
+    notifier.send(changed(2, 7, "phone"));
+
+    assert_eq!(next(&mut other).await, Some(LibraryEvent::Changed(7)));
+    assert_eq!(next(&mut other).await, None);
+    assert_eq!(next(&mut desktop).await, Some(LibraryEvent::Lagged));
+}
+
+#[tokio::test]
+async fn closed_session_ends_stream() {
+    let notifier = Notifier::new();
+    let mut phone = Box::pin(notifier.subscribe(1, "phone"));
+    let mut desktop = Box::pin(notifier.subscribe(1, "desktop"));
+
+    // 退出登录只结束自己的订阅
+    notifier.close(1, Some("phone"));
+    notifier.send(changed(1, 1, "desktop"));
+    assert_eq!(next(&mut phone).await, Some(LibraryEvent::Closed));
+    assert_eq!(phone.next().await, None);
+    assert_eq!(next(&mut desktop).await, None);
+
+    // 关闭产品或注销结束全部订阅
+    notifier.close(1, None);
+    assert_eq!(next(&mut desktop).await, Some(LibraryEvent::Closed));
+    assert_eq!(desktop.next().await, None);
+}
+
+#[tokio::test]
+async fn channel_removed_with_last_subscriber() {
+    let notifier = Notifier::new();
+    let phone = notifier.subscribe(1, "phone");
+    let desktop = notifier.subscribe(1, "desktop");
+    let mut other = Box::pin(notifier.subscribe(2, "other"));
+    assert_eq!(notifier.users(), 2);
+
+    drop(phone);
+    assert_eq!(notifier.users(), 2);
+    drop(desktop);
+    assert_eq!(notifier.users(), 1);
+
+    notifier.close(2, None);
+    assert_eq!(next(&mut other).await, Some(LibraryEvent::Closed));
+    assert_eq!(notifier.users(), 0);
+}